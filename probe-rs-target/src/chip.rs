@@ -1,5 +1,8 @@
 use super::memory::MemoryRegion;
-use crate::{serialize::hex_option, CoreType};
+use crate::{
+    serialize::{hex_option, hex_u_int},
+    CoreType,
+};
 use serde::{Deserialize, Serialize};
 
 /// Represents a DAP scan chain element.
@@ -71,6 +74,9 @@ pub struct Chip {
     pub scan_chain: Option<Vec<ScanChainElement>>,
     /// The default binary format for this chip
     pub default_binary_format: Option<BinaryFormat>,
+    /// The register reporting the cause of the last reset, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reset_cause: Option<ResetCauseRegister>,
 }
 
 impl Chip {
@@ -91,6 +97,83 @@ impl Chip {
             rtt_scan_ranges: None,
             scan_chain: Some(vec![]),
             default_binary_format: Some(BinaryFormat::Raw),
+            reset_cause: None,
+        }
+    }
+}
+
+/// The cause of a reset, as reported by the reset status register of a chip
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResetCause {
+    /// The chip was powered on.
+    PowerOn,
+    /// The supply voltage dropped below the brown-out threshold.
+    Brownout,
+    /// The reset pin was asserted.
+    Pin,
+    /// A watchdog expired.
+    Watchdog,
+    /// The firmware or the debugger requested a reset, e.g. with `AIRCR.SYSRESETREQ`.
+    Software,
+    /// The core entered the lockup state.
+    Lockup,
+    /// The chip woke up from a low power mode.
+    LowPower,
+    /// None of the known reset flags is set.
+    Unknown,
+}
+
+/// A register with one flag per reset cause, e.g. `RCC_CSR` on STM32 or `RESETREAS` on nRF52
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResetCauseRegister {
+    /// The address of the 32-bit register.
+    #[serde(serialize_with = "hex_u_int")]
+    pub address: u64,
+    /// The flags of the register, in order of priority.
+    ///
+    /// Several flags can be set at once, e.g. a power-on reset may set the pin reset flag as
+    /// well. The cause of the first flag which is set is reported.
+    pub flags: Vec<ResetCauseFlag>,
+    /// The cause reported when none of the flags is set, e.g. a power-on reset on parts which
+    /// have no flag for it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_flag_set: Option<ResetCause>,
+    /// A bit which clears all flags when it is set, e.g. `RMVF` in the STM32 `RCC_CSR`.
+    ///
+    /// Without it, the flags are cleared by writing ones to them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clear_bit: Option<u8>,
+}
+
+/// A single flag of a [ResetCauseRegister]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResetCauseFlag {
+    /// The bit position of the flag
+    pub bit: u8,
+    /// The cause of the reset when the flag is set
+    pub cause: ResetCause,
+}
+
+impl ResetCauseRegister {
+    /// Decode the value of the register.
+    pub fn decode(&self, value: u32) -> ResetCause {
+        self.flags
+            .iter()
+            .find(|flag| value & (1 << flag.bit) != 0)
+            .map(|flag| flag.cause)
+            .or(self.no_flag_set)
+            .unwrap_or(ResetCause::Unknown)
+    }
+
+    /// The value to write to the register to clear all flags, given its current `value`.
+    pub fn clear_value(&self, value: u32) -> u32 {
+        match self.clear_bit {
+            Some(bit) => value | (1 << bit),
+            None => self
+                .flags
+                .iter()
+                .fold(0, |clear, flag| clear | (1 << flag.bit)),
         }
     }
 }
@@ -134,6 +217,22 @@ pub struct ArmCoreAccessOptions {
     /// Required in ARMv8-A
     #[serde(serialize_with = "hex_option")]
     pub cti_base: Option<u64>,
+    /// The base address of the Flash Patch and Breakpoint unit (FPB) of a Cortex-M core.
+    /// Only needed if the part doesn't use the architectural address `0xE000_2000`.
+    #[serde(
+        default,
+        serialize_with = "hex_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub fpb_base: Option<u64>,
+    /// The base address of the Data Watchpoint and Trace unit (DWT) of a Cortex-M core.
+    /// Only needed if the part doesn't use the architectural address `0xE000_1000`.
+    #[serde(
+        default,
+        serialize_with = "hex_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub dwt_base: Option<u64>,
 }
 
 /// The data required to access a Risc-V core
@@ -19,8 +19,8 @@ mod memory;
 pub(crate) mod serialize;
 
 pub use chip::{
-    get_ir_lengths, ArmCoreAccessOptions, BinaryFormat, Chip, Core, CoreAccessOptions,
-    RiscvCoreAccessOptions, ScanChainElement,
+    get_ir_lengths, ArmCoreAccessOptions, BinaryFormat, Chip, Core, CoreAccessOptions, ResetCause,
+    ResetCauseFlag, ResetCauseRegister, RiscvCoreAccessOptions, ScanChainElement,
 };
 pub use chip_family::{
     Architecture, ChipFamily, CoreType, InstructionSet, TargetDescriptionSource,
@@ -28,6 +28,6 @@ pub use chip_family::{
 pub use flash_algorithm::{RawFlashAlgorithm, TransferEncoding};
 pub use flash_properties::FlashProperties;
 pub use memory::{
-    GenericRegion, MemoryRange, MemoryRegion, NvmRegion, PageInfo, RamRegion, SectorDescription,
-    SectorInfo,
+    AccessWidth, GenericRegion, MemoryRange, MemoryRegion, NvmRegion, PageInfo, RamRegion,
+    SectorDescription, SectorInfo,
 };
@@ -51,6 +51,33 @@ pub struct GenericRegion {
     pub range: Range<u64>,
     /// List of cores that can access this region
     pub cores: Vec<String>,
+    /// Access width which has to be used for this region, e.g. for device memory
+    /// which doesn't accept accesses of other sizes
+    #[serde(default)]
+    pub access_width: Option<AccessWidth>,
+}
+
+/// Width of a single memory access.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessWidth {
+    /// 8-bit accesses
+    U8,
+    /// 16-bit accesses
+    U16,
+    /// 32-bit accesses
+    U32,
+}
+
+impl AccessWidth {
+    /// Size of a single access in bytes.
+    pub fn bytes(&self) -> usize {
+        match self {
+            AccessWidth::U8 => 1,
+            AccessWidth::U16 => 2,
+            AccessWidth::U32 => 4,
+        }
+    }
 }
 
 /// Holds information about a specific, individual flash
@@ -0,0 +1,66 @@
+use probe_rs::architecture::arm::{
+    component::TraceSink,
+    swo::{
+        itm::{ItmDecoder, ItmPacket},
+        SwoConfig,
+    },
+};
+use probe_rs::{Error, Lister, Permissions};
+
+use std::io::Write;
+use std::thread::sleep;
+use std::time::Duration;
+
+fn main() -> Result<(), Error> {
+    pretty_env_logger::init();
+
+    let lister = Lister::new();
+
+    // Get a list of all available debug probes.
+    let probes = lister.list_all();
+
+    // Use the first probe found.
+    let probe = probes[0].open(&lister)?;
+
+    // Attach to a chip.
+    let mut session = probe.attach("stm32f407", Permissions::default())?;
+
+    // Create a new SwoConfig with a system clock frequency of 16MHz
+    let cfg = SwoConfig::new(16_000_000)
+        .set_baud(2_000_000)
+        .set_continuous_formatting(false);
+
+    session.setup_tracing(0, TraceSink::Swo(cfg))?;
+
+    let mut decoder = ItmDecoder::new();
+    let mut data = Vec::new();
+    let mut stdout = std::io::stdout();
+
+    println!("Printing stimulus port 0 ...");
+
+    loop {
+        data.clear();
+
+        if session.read_swo(&mut data)? {
+            eprintln!("<SWO data lost>");
+        }
+
+        decoder.feed(&data);
+
+        while let Some(packet) = decoder.next_packet() {
+            match packet {
+                ItmPacket::Instrumentation { port: 0, payload } => {
+                    stdout.write_all(&payload).unwrap();
+                }
+                ItmPacket::Overflow => eprintln!("<ITM overflow>"),
+                _ => {}
+            }
+        }
+
+        stdout.flush().unwrap();
+
+        if data.is_empty() {
+            sleep(Duration::from_millis(10));
+        }
+    }
+}
@@ -7,6 +7,7 @@ pub(crate) mod memory_ap;
 
 use crate::architecture::arm::dp::DebugPortError;
 use crate::DebugProbeError;
+use num_traits::FromPrimitive;
 
 pub use generic_ap::{ApClass, ApType, GenericAp, IDR};
 pub use memory_ap::{
@@ -14,8 +15,8 @@ pub use memory_ap::{
 };
 
 use super::{
-    communication_interface::RegisterParseError, ApAddress, ArmError, DapAccess, DpAddress,
-    Register,
+    communication_interface::RegisterParseError, ApAddress, ArmError, DapAccess, DapTransfer,
+    DpAddress, Register,
 };
 
 /// Some error during AP handling occurred.
@@ -133,6 +134,18 @@ pub trait ApAccess {
     where
         PORT: AccessPort,
         R: ApRegister<PORT>;
+
+    /// Execute a batch of register reads and writes on the access port `ap`,
+    /// see [`DapAccess::transfer_batch`].
+    ///
+    /// Returns `false` if batches are not supported, in this case nothing was transferred.
+    fn ap_transfer_batch(
+        &mut self,
+        _ap: ApAddress,
+        _transfers: &mut [DapTransfer],
+    ) -> Result<bool, ArmError> {
+        Ok(false)
+    }
 }
 
 impl<T: DapAccess> ApAccess for T {
@@ -200,6 +213,15 @@ impl<T: DapAccess> ApAccess for T {
 
         self.read_raw_ap_register_repeated(port.into().ap_address(), R::ADDRESS, values)
     }
+
+    fn ap_transfer_batch(
+        &mut self,
+        ap: ApAddress,
+        transfers: &mut [DapTransfer],
+    ) -> Result<bool, ArmError> {
+        self.transfer_batch(ap, transfers)?;
+        Ok(true)
+    }
 }
 
 /// Determine if an AP exists with the given AP number.
@@ -239,6 +261,143 @@ where
         .collect::<Vec<GenericAp>>()
 }
 
+/// Number of consecutive access ports without an IDR after which [`scan_access_ports`] stops.
+const SCAN_EMPTY_RUN: usize = 8;
+
+/// The JEP106 designer code of Nordic Semiconductor, as encoded in the IDR register.
+const DESIGNER_NORDIC: u16 = 0x144;
+
+/// The kind of an access port, decoded from its IDR register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApKind {
+    /// A MEM-AP on an AHB bus.
+    AhbMemoryAp,
+    /// A MEM-AP on an APB bus.
+    ApbMemoryAp,
+    /// A MEM-AP on an AXI bus.
+    AxiMemoryAp,
+    /// A MEM-AP on an unknown bus.
+    MemoryAp,
+    /// A JTAG-AP.
+    JtagAp,
+    /// The CTRL-AP of Nordic devices, used for erasing and unlocking the device.
+    NordicCtrlAp,
+    /// A vendor-specific access port.
+    VendorSpecific,
+}
+
+impl std::fmt::Display for ApKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            ApKind::AhbMemoryAp => "MEM-AP (AHB)",
+            ApKind::ApbMemoryAp => "MEM-AP (APB)",
+            ApKind::AxiMemoryAp => "MEM-AP (AXI)",
+            ApKind::MemoryAp => "MEM-AP",
+            ApKind::JtagAp => "JTAG-AP",
+            ApKind::NordicCtrlAp => "Nordic CTRL-AP",
+            ApKind::VendorSpecific => "Vendor-specific",
+        })
+    }
+}
+
+/// An access port found by [`scan_access_ports`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApInfo {
+    /// The address of the access port.
+    pub address: ApAddress,
+    /// The raw value of the IDR register.
+    pub idr: u32,
+}
+
+impl ApInfo {
+    /// The JEP106 code of the designer of the access port.
+    pub fn designer(&self) -> jep106::JEP106Code {
+        let designer = self.designer_raw();
+        jep106::JEP106Code::new((designer >> 7) as u8, (designer & 0x7f) as u8)
+    }
+
+    /// The revision of the access port.
+    pub fn revision(&self) -> u8 {
+        (self.idr >> 28) as u8
+    }
+
+    /// The class of the access port, `None` if it is reserved.
+    pub fn class(&self) -> Option<ApClass> {
+        ApClass::from_u8(((self.idr >> 13) & 0xF) as u8)
+    }
+
+    /// The variant of the access port.
+    pub fn variant(&self) -> u8 {
+        ((self.idr >> 4) & 0xF) as u8
+    }
+
+    /// The raw type field of the access port, see [`ApType`].
+    pub fn ap_type(&self) -> u8 {
+        (self.idr & 0xF) as u8
+    }
+
+    /// Returns `true` if the access port is a MEM-AP, which can be used to access memory.
+    pub fn is_memory_ap(&self) -> bool {
+        self.class() == Some(ApClass::MemAp)
+    }
+
+    /// The kind of the access port.
+    pub fn kind(&self) -> ApKind {
+        match self.class() {
+            Some(ApClass::MemAp) => match self.ap_type() {
+                0x1 | 0x5 | 0x8 => ApKind::AhbMemoryAp,
+                0x2 | 0x6 => ApKind::ApbMemoryAp,
+                0x4 | 0x7 => ApKind::AxiMemoryAp,
+                _ => ApKind::MemoryAp,
+            },
+            Some(ApClass::ComAp) if self.ap_type() == 0 => ApKind::JtagAp,
+            _ if self.designer_raw() == DESIGNER_NORDIC => ApKind::NordicCtrlAp,
+            _ => ApKind::VendorSpecific,
+        }
+    }
+
+    fn designer_raw(&self) -> u16 {
+        ((self.idr >> 17) & 0x7FF) as u16
+    }
+}
+
+/// Read the IDR register of all access ports of the debug port `dp`, and return the ones which exist.
+///
+/// Unlike [`valid_access_ports`], gaps in the AP numbers are skipped. The scan stops after
+/// [`SCAN_EMPTY_RUN`] consecutive access ports which don't exist.
+pub fn scan_access_ports<P>(debug_port: &mut P, dp: DpAddress) -> Vec<ApInfo>
+where
+    P: DapAccess + ?Sized,
+{
+    let mut access_ports = Vec::new();
+    let mut empty_run = 0;
+
+    for ap in 0..=255 {
+        let address = ApAddress { dp, ap };
+
+        let idr = match debug_port.read_raw_ap_register(address, IDR::ADDRESS) {
+            Ok(idr) => idr,
+            Err(e) => {
+                tracing::debug!("Error reading IDR register from AP {}: {}", ap, e);
+                0
+            }
+        };
+
+        if idr == 0 {
+            empty_run += 1;
+            if empty_run == SCAN_EMPTY_RUN {
+                break;
+            }
+            continue;
+        }
+
+        empty_run = 0;
+        access_ports.push(ApInfo { address, idr });
+    }
+
+    access_ports
+}
+
 /// Tries to find the first AP with the given idr value, returns `None` if there isn't any
 pub fn get_ap_by_idr<AP, P>(debug_port: &mut AP, dp: DpAddress, f: P) -> Option<GenericAp>
 where
@@ -255,3 +414,34 @@ where
             }
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ap_info(idr: u32) -> ApInfo {
+        ApInfo {
+            address: ApAddress {
+                dp: DpAddress::Default,
+                ap: 0,
+            },
+            idr,
+        }
+    }
+
+    #[test]
+    fn decode_ap_kind() {
+        let ahb_ap = ap_info(0x2477_0011);
+        assert_eq!(ahb_ap.kind(), ApKind::AhbMemoryAp);
+        assert!(ahb_ap.is_memory_ap());
+        assert_eq!(ahb_ap.designer().get(), Some("ARM Ltd"));
+        assert_eq!(ahb_ap.revision(), 2);
+
+        assert_eq!(ap_info(0x5477_0002).kind(), ApKind::ApbMemoryAp);
+        assert_eq!(ap_info(0x0477_0004).kind(), ApKind::AxiMemoryAp);
+
+        let ctrl_ap = ap_info(0x0288_0000);
+        assert_eq!(ctrl_ap.kind(), ApKind::NordicCtrlAp);
+        assert!(!ctrl_ap.is_memory_ap());
+    }
+}
@@ -1,15 +1,16 @@
 use super::{
     ap::{
-        valid_access_ports, AccessPort, ApAccess, ApClass, BaseaddrFormat, GenericAp, MemoryAp,
-        BASE, BASE2, CFG, CSW, IDR,
+        scan_access_ports, valid_access_ports, AccessPort, ApAccess, ApClass, BaseaddrFormat,
+        GenericAp, MemoryAp, BASE, BASE2, CFG, CSW, IDR,
     },
-    dp::{Abort, Ctrl, DebugPortVersion, DpAccess, Select, DPIDR},
+    dp::{Abort, Ctrl, DebugPortVersion, DpAccess, Select, StickyErrors, DPIDR},
     memory::{
         adi_v5_memory_interface::{ADIMemoryInterface, ArmProbe},
         Component,
     },
     sequences::{ArmDebugSequence, DefaultArmSequence},
-    ApAddress, ArmError, DapAccess, DpAddress, PortType, RawDapAccess, SwoAccess, SwoConfig,
+    ApAddress, ArmError, DapAccess, DapTransfer, DpAddress, PortType, RawDapAccess, SwoAccess,
+    SwoConfig,
 };
 use crate::{
     architecture::arm::ap::DataSize, CoreStatus, DebugProbe, DebugProbeError,
@@ -43,6 +44,17 @@ pub enum DapError {
     /// The parity bit on the read request was incorrect.
     #[error("Incorrect parity on READ request.")]
     IncorrectParity,
+    /// A transaction failed and set the sticky error flag in the CTRL/STAT register,
+    /// e.g. because of an access to invalid memory.
+    ///
+    /// The error flags have already been cleared, so the following transactions can succeed.
+    #[error("The transaction to {port:?} register {address:#04x} failed with a sticky error.")]
+    StickyError {
+        /// The port of the failed transaction.
+        port: PortType,
+        /// The register address of the failed transaction.
+        address: u8,
+    },
 }
 
 /// A trait to be implemented on register types for typed device access.
@@ -91,6 +103,15 @@ pub trait ArmProbeInterface: DapAccess + SwdSequence + SwoAccess + Send {
         dp: DpAddress,
     ) -> Result<Option<ArmChipInfo>, ArmError>;
 
+    /// Get the maximum speed of the debug protocol in kHz, see [`Probe::speed_khz`].
+    fn speed_khz(&self) -> u32;
+
+    /// Returns how often transfers were retried or recovered from errors,
+    /// if the interface keeps track of it.
+    fn dap_statistics(&self) -> Option<DapStatistics> {
+        None
+    }
+
     /// Closes the interface and returns back the generic probe it consumed.
     fn close(self: Box<Self>) -> Probe;
 }
@@ -107,6 +128,15 @@ pub trait SwdSequence {
         pin_select: u32,
         pin_wait: u32,
     ) -> Result<u32, DebugProbeError>;
+
+    /// Perform an SWD line reset, i.e. more than 50 clock cycles with SWDIO high followed by
+    /// idle cycles.
+    fn swd_line_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.swj_sequence(
+            crate::probe::SWD_LINE_RESET_BITS as u8,
+            crate::probe::SWD_LINE_RESET,
+        )
+    }
 }
 
 pub trait UninitializedArmProbe: SwdSequence + Debug {
@@ -140,6 +170,9 @@ pub struct Initialized {
     dps: HashMap<DpAddress, DpState>,
     use_overrun_detect: bool,
     sequence: Arc<dyn ArmDebugSequence>,
+    statistics: DapStatistics,
+    /// Set while recovering from an error, to avoid recursive recovery attempts.
+    recovering: bool,
 }
 
 impl Initialized {
@@ -149,10 +182,23 @@ impl Initialized {
             dps: HashMap::new(),
             use_overrun_detect,
             sequence,
+            statistics: DapStatistics::default(),
+            recovering: false,
         }
     }
 }
 
+/// Counters for the error recovery of an [`ArmCommunicationInterface`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DapStatistics {
+    /// Number of transfers which were retried after a WAIT response.
+    pub wait_retries: usize,
+    /// Number of FAULT responses after which the sticky error flags were cleared.
+    pub fault_recoveries: usize,
+    /// Number of protocol errors after which the debug port was reset and initialized again.
+    pub protocol_recoveries: usize,
+}
+
 impl ArmDebugState for Uninitialized {}
 
 impl ArmDebugState for Initialized {}
@@ -354,6 +400,14 @@ impl ArmProbeInterface for ArmCommunicationInterface<Initialized> {
         ArmCommunicationInterface::num_access_ports(self, dp)
     }
 
+    fn speed_khz(&self) -> u32 {
+        self.probe.speed_khz()
+    }
+
+    fn dap_statistics(&self) -> Option<DapStatistics> {
+        Some(self.state.statistics)
+    }
+
     fn close(self: Box<Self>) -> Probe {
         Probe::from_attached_probe(RawDapAccess::into_probe(self.probe))
     }
@@ -658,13 +712,23 @@ impl SwoAccess for ArmCommunicationInterface<Initialized> {
             None => Err(ArmError::ArchitectureRequired(&["ARMv7", "ARMv8"])),
         }
     }
+
+    fn take_swo_overrun(&mut self) -> bool {
+        self.probe
+            .get_swo_interface_mut()
+            .map_or(false, |interface| interface.take_swo_overrun())
+    }
 }
 
 impl DapAccess for ArmCommunicationInterface<Initialized> {
     fn read_raw_dp_register(&mut self, dp: DpAddress, address: u8) -> Result<u32, ArmError> {
-        self.select_dp_and_dp_bank(dp, address)?;
-        let result = self.probe.raw_read_register(PortType::DebugPort, address)?;
-        Ok(result)
+        self.with_recovery(dp, None, true, |interface| {
+            interface.select_dp_and_dp_bank(dp, address)?;
+            let result = interface
+                .probe
+                .raw_read_register(PortType::DebugPort, address)?;
+            Ok(result)
+        })
     }
 
     fn write_raw_dp_register(
@@ -673,10 +737,25 @@ impl DapAccess for ArmCommunicationInterface<Initialized> {
         address: u8,
         value: u32,
     ) -> Result<(), ArmError> {
-        self.select_dp_and_dp_bank(dp, address)?;
-        self.probe
-            .raw_write_register(PortType::DebugPort, address, value)?;
-        Ok(())
+        self.with_recovery(dp, None, true, |interface| {
+            interface.select_dp_and_dp_bank(dp, address)?;
+            interface
+                .probe
+                .raw_write_register(PortType::DebugPort, address, value)?;
+
+            // SELECT can also be written directly, keep the cached banks in sync with it
+            if address & 0xF == Select::ADDRESS {
+                if let Some(dp_state) = interface.state.dps.get_mut(&dp) {
+                    let select = Select(value);
+
+                    dp_state.current_apsel = select.ap_sel();
+                    dp_state.current_apbanksel = select.ap_bank_sel();
+                    dp_state.current_dpbanksel = select.dp_bank_sel();
+                }
+            }
+
+            Ok(())
+        })
     }
 
     fn read_raw_ap_register(
@@ -684,13 +763,15 @@ impl DapAccess for ArmCommunicationInterface<Initialized> {
         ap: ApAddress,
         address: u8,
     ) -> std::result::Result<u32, ArmError> {
-        self.select_ap_and_ap_bank(ap, address)?;
+        self.with_recovery(ap.dp, Some((ap, address)), true, |interface| {
+            interface.select_ap_and_ap_bank(ap, address)?;
 
-        let result = self
-            .probe
-            .raw_read_register(PortType::AccessPort, address)?;
+            let result = interface
+                .probe
+                .raw_read_register(PortType::AccessPort, address)?;
 
-        Ok(result)
+            Ok(result)
+        })
     }
 
     fn read_raw_ap_register_repeated(
@@ -699,11 +780,16 @@ impl DapAccess for ArmCommunicationInterface<Initialized> {
         address: u8,
         values: &mut [u32],
     ) -> Result<(), ArmError> {
-        self.select_ap_and_ap_bank(ap, address)?;
-
-        self.probe
-            .raw_read_block(PortType::AccessPort, address, values)?;
-        Ok(())
+        // Part of the block may already have been transferred when an error occurs,
+        // so the transfer is not retried.
+        self.with_recovery(ap.dp, Some((ap, address)), false, |interface| {
+            interface.select_ap_and_ap_bank(ap, address)?;
+
+            interface
+                .probe
+                .raw_read_block(PortType::AccessPort, address, values)?;
+            Ok(())
+        })
     }
 
     fn write_raw_ap_register(
@@ -712,12 +798,15 @@ impl DapAccess for ArmCommunicationInterface<Initialized> {
         address: u8,
         value: u32,
     ) -> Result<(), ArmError> {
-        self.select_ap_and_ap_bank(ap, address)?;
+        self.with_recovery(ap.dp, Some((ap, address)), true, |interface| {
+            interface.select_ap_and_ap_bank(ap, address)?;
 
-        self.probe
-            .raw_write_register(PortType::AccessPort, address, value)?;
+            interface
+                .probe
+                .raw_write_register(PortType::AccessPort, address, value)?;
 
-        Ok(())
+            Ok(())
+        })
     }
 
     fn write_raw_ap_register_repeated(
@@ -726,12 +815,289 @@ impl DapAccess for ArmCommunicationInterface<Initialized> {
         address: u8,
         values: &[u32],
     ) -> Result<(), ArmError> {
-        self.select_ap_and_ap_bank(ap, address)?;
+        self.with_recovery(ap.dp, Some((ap, address)), false, |interface| {
+            interface.select_ap_and_ap_bank(ap, address)?;
+
+            interface
+                .probe
+                .raw_write_block(PortType::AccessPort, address, values)?;
+            Ok(())
+        })
+    }
+
+    fn transfer_batch(
+        &mut self,
+        ap: ApAddress,
+        transfers: &mut [DapTransfer],
+    ) -> Result<(), ArmError> {
+        let result = self.transfer_batch_runs(ap, transfers);
+
+        // Transfers before the failed one have already been executed, so the batch
+        // is not retried. The debug port still has to be recovered.
+        if let Err(ArmError::BatchTransfer { index, source }) = result {
+            let DapTransfer { port, address, .. } = transfers[index];
+            let ap_register = (port == PortType::AccessPort).then_some((ap, address));
+
+            let source = match *source {
+                error @ ArmError::Dap(DapError::FaultResponse | DapError::StickyError { .. }) => {
+                    self.recover_from_fault(ap.dp, ap_register, error)
+                }
+                error @ ArmError::Dap(DapError::SwdProtocol) => {
+                    self.recover_from_protocol_error(ap.dp)?;
+                    error
+                }
+                error => error,
+            };
+
+            return Err(ArmError::BatchTransfer {
+                index,
+                source: Box::new(source),
+            });
+        }
+
+        result
+    }
+}
+
+/// Maximum number of times a transfer is retried after a WAIT response.
+const MAX_WAIT_RETRIES: u32 = 8;
+
+impl ArmCommunicationInterface<Initialized> {
+    /// Runs the transfer `op`, and recovers from errors reported by the debug port `dp`.
+    ///
+    /// - After a WAIT response, the transfer is retried up to [`MAX_WAIT_RETRIES`] times,
+    ///   with an increasing delay.
+    /// - After a FAULT response, see [`Self::recover_from_fault`].
+    /// - After a protocol error, the debug port is reset and initialized again,
+    ///   and the transfer is retried once.
+    ///
+    /// A missing acknowledge is returned to the caller, e.g. the target may be powered off or
+    /// held in reset, so reconnecting would fail as well.
+    ///
+    /// Transfers which can't be repeated safely pass `retry = false`,
+    /// the debug port is only recovered for them.
+    fn with_recovery<T>(
+        &mut self,
+        dp: DpAddress,
+        ap_register: Option<(ApAddress, u8)>,
+        retry: bool,
+        mut op: impl FnMut(&mut Self) -> Result<T, ArmError>,
+    ) -> Result<T, ArmError> {
+        let mut wait_retries = 0;
+        let mut reconnected = false;
+
+        loop {
+            let error = match op(self) {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+
+            if self.state.recovering {
+                return Err(error);
+            }
+
+            match error {
+                ArmError::Dap(DapError::WaitResponse)
+                    if retry && wait_retries < MAX_WAIT_RETRIES =>
+                {
+                    self.state.statistics.wait_retries += 1;
+                    std::thread::sleep(Duration::from_micros(100 << wait_retries));
+                    wait_retries += 1;
+                }
+                ArmError::Dap(DapError::FaultResponse | DapError::StickyError { .. }) => {
+                    return Err(self.recover_from_fault(dp, ap_register, error));
+                }
+                ArmError::Dap(DapError::SwdProtocol) if !reconnected => {
+                    self.recover_from_protocol_error(dp)?;
+                    reconnected = true;
+
+                    if !retry {
+                        return Err(error);
+                    }
+                }
+                error => return Err(error),
+            }
+        }
+    }
+
+    /// Clears the sticky error flags of the debug port `dp` after a FAULT response,
+    /// and returns the error to report.
+    ///
+    /// For access port transfers, given by `ap_register`, this is an
+    /// [`ArmError::ApTransferFault`] with the flags which were set, otherwise `error`.
+    fn recover_from_fault(
+        &mut self,
+        dp: DpAddress,
+        ap_register: Option<(ApAddress, u8)>,
+        error: ArmError,
+    ) -> ArmError {
+        if self.state.recovering {
+            return error;
+        }
+
+        self.state.statistics.fault_recoveries += 1;
+
+        let mut sticky_errors = match self.recover(|interface| interface.clear_sticky_errors(dp)) {
+            Ok(sticky_errors) => sticky_errors,
+            Err(clear_error) => {
+                tracing::warn!("Failed to clear sticky errors: {}", clear_error);
+                return error;
+            }
+        };
+
+        // Some probes clear the flags on their own, before reporting the error.
+        if matches!(error, ArmError::Dap(DapError::StickyError { .. })) {
+            sticky_errors.sticky_err = true;
+        }
+
+        match ap_register {
+            Some((address, register)) => ArmError::ApTransferFault {
+                address,
+                register,
+                sticky_errors,
+            },
+            None => error,
+        }
+    }
+
+    /// Resets the connection to the debug port `dp` after a protocol error, and initializes it again.
+    fn recover_from_protocol_error(&mut self, dp: DpAddress) -> Result<(), ArmError> {
+        if self.state.recovering {
+            return Ok(());
+        }
+
+        self.state.statistics.protocol_recoveries += 1;
+
+        self.recover(|interface| interface.reconnect_debug_port(dp))
+    }
+
+    /// Runs `op` with the error recovery disabled.
+    fn recover<T>(
+        &mut self,
+        op: impl FnOnce(&mut Self) -> Result<T, ArmError>,
+    ) -> Result<T, ArmError> {
+        self.state.recovering = true;
+        let result = op(self);
+        self.state.recovering = false;
+        result
+    }
+
+    /// Reads the sticky error flags of the debug port `dp`, and clears them.
+    fn clear_sticky_errors(&mut self, dp: DpAddress) -> Result<StickyErrors, ArmError> {
+        let ctrl: Ctrl = self.read_dp_register(dp)?;
+        let sticky_errors = StickyErrors::from(ctrl);
+
+        tracing::debug!("Clearing sticky errors of {:x?}: {}", dp, sticky_errors);
+
+        if sticky_errors.any() {
+            self.write_dp_register(dp, sticky_errors.abort())?;
+            self.probe.raw_flush()?;
+        }
+
+        Ok(sticky_errors)
+    }
+
+    /// Resets the connection to the debug port `dp` and initializes it again.
+    fn reconnect_debug_port(&mut self, dp: DpAddress) -> Result<(), ArmError> {
+        tracing::warn!("Protocol error, reconnecting to debug port {:x?}", dp);
+
+        let sequence = self.state.sequence.clone();
+
+        if dp == DpAddress::Default {
+            sequence.debug_port_setup(&mut *self.probe)?;
+        }
+
+        // Selecting the debug port again also runs the multidrop connection sequence.
+        self.state.current_dp = None;
+        self.select_dp(dp)?;
+
+        sequence.debug_port_start(self, dp)?;
+
+        // DebugPortStart writes zero to SELECT.
+        if let Some(dp_state) = self.state.dps.get_mut(&dp) {
+            dp_state.current_dpbanksel = 0;
+            dp_state.current_apsel = 0;
+            dp_state.current_apbanksel = 0;
+        }
+
+        let mut ctrl_reg: Ctrl = self.read_dp_register(dp)?;
+        ctrl_reg.set_orun_detect(self.state.use_overrun_detect);
+        self.write_dp_register(dp, ctrl_reg)?;
 
-        self.probe
-            .raw_write_block(PortType::AccessPort, address, values)?;
         Ok(())
     }
+
+    /// Sends the batch `transfers` to the probe, in runs which don't need a bank switch.
+    /// The SELECT writes are issued in between.
+    fn transfer_batch_runs(
+        &mut self,
+        ap: ApAddress,
+        transfers: &mut [DapTransfer],
+    ) -> Result<(), ArmError> {
+        let mut start = 0;
+
+        for index in 0..transfers.len() {
+            let DapTransfer { port, address, .. } = transfers[index];
+
+            if self.is_bank_selected(ap, port, address) {
+                continue;
+            }
+
+            self.probe
+                .raw_transfer_batch(&mut transfers[start..index])
+                .map_err(|error| offset_batch_error(error, start))?;
+            start = index;
+
+            match port {
+                PortType::DebugPort => self.select_dp_and_dp_bank(ap.dp, address),
+                PortType::AccessPort => self.select_ap_and_ap_bank(ap, address),
+            }
+            .map_err(|source| ArmError::BatchTransfer {
+                index,
+                source: Box::new(source),
+            })?;
+        }
+
+        self.probe
+            .raw_transfer_batch(&mut transfers[start..])
+            .map_err(|error| offset_batch_error(error, start))
+    }
+}
+
+impl ArmCommunicationInterface<Initialized> {
+    /// Returns `true` if the register at `address` can be accessed without changing the selected
+    /// debug port or bank.
+    fn is_bank_selected(&self, ap: ApAddress, port: PortType, address: u8) -> bool {
+        if self.state.current_dp != Some(ap.dp) {
+            return false;
+        }
+
+        let Some(dp_state) = self.state.dps.get(&ap.dp) else {
+            return false;
+        };
+
+        let bank = address >> 4;
+
+        match port {
+            // Only DP address 0x4 is banked.
+            PortType::DebugPort => address & 0xF != 4 || bank == dp_state.current_dpbanksel,
+            PortType::AccessPort => {
+                dp_state.current_apsel == ap.ap && dp_state.current_apbanksel == bank
+            }
+        }
+    }
+}
+
+/// Shift the index of a [`ArmError::BatchTransfer`] error by `offset`,
+/// for errors of a batch which started at `offset` in a larger batch.
+fn offset_batch_error(error: ArmError, offset: usize) -> ArmError {
+    match error {
+        ArmError::BatchTransfer { index, source } => ArmError::BatchTransfer {
+            index: index + offset,
+            source,
+        },
+        other => other,
+    }
 }
 
 /// Information about the chip target we are currently attached to.
@@ -755,7 +1121,7 @@ impl ArmCommunicationInterface<Initialized> {
         dp: DpAddress,
     ) -> Result<Option<ArmChipInfo>, ArmError> {
         // faults on some chips need to be cleaned up.
-        let aps = valid_access_ports(self, dp);
+        let aps = scan_access_ports(self, dp);
 
         // Check sticky error and cleanup if necessary
         let ctrl_reg: crate::architecture::arm::dp::Ctrl = self.read_dp_register(dp)?;
@@ -767,11 +1133,15 @@ impl ArmCommunicationInterface<Initialized> {
             self.write_dp_register(dp, abort)?;
         }
         for access_port in aps {
-            let idr: IDR = self.read_ap_register(access_port)?;
-            tracing::debug!("{:#x?}", idr);
+            tracing::debug!(
+                "AP {}: {:#010x}, {}",
+                access_port.address.ap,
+                access_port.idr,
+                access_port.kind()
+            );
 
-            if idr.CLASS == ApClass::MemAp {
-                let access_port: MemoryAp = access_port.into();
+            if access_port.is_memory_ap() {
+                let access_port = MemoryAp::new(access_port.address);
 
                 let baseaddr = access_port.base_address(self)?;
 
@@ -824,3 +1194,251 @@ pub trait FlushableArmAccess {
         &mut self,
     ) -> Result<&mut ArmCommunicationInterface<Initialized>, DebugProbeError>;
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    use super::{
+        ArmCommunicationInterface, DapError, DapStatistics, Initialized, StickyErrors,
+        MAX_WAIT_RETRIES,
+    };
+    use crate::{
+        architecture::arm::{
+            sequences::DefaultArmSequence, ApAddress, ArmError, ArmProbeInterface, DapAccess,
+            DapTransfer, DpAddress, PortType,
+        },
+        probe::fake_probe::FakeProbe,
+    };
+
+    /// CSYSPWRUPACK and CDBGPWRUPACK of CTRL/STAT
+    const POWER_UP_ACKS: u32 = (1 << 31) | (1 << 29);
+    /// STICKYERR of CTRL/STAT
+    const STICKYERR: u32 = 1 << 5;
+    /// STKERRCLR of ABORT
+    const STKERRCLR: u32 = 1 << 2;
+
+    /// A powered up debug port without access ports, answering the AP register
+    /// reads with the queued responses
+    #[derive(Default)]
+    struct MockDp {
+        sticky_errors: u32,
+        dpidr_reads: usize,
+        abort_writes: Vec<u32>,
+        ap_responses: VecDeque<Result<u32, ArmError>>,
+        /// All register accesses, with the written value for writes
+        accesses: Vec<(PortType, u8, Option<u32>)>,
+    }
+
+    fn interface_with_mock_dp() -> (ArmCommunicationInterface<Initialized>, Arc<Mutex<MockDp>>) {
+        let dp = Arc::new(Mutex::new(MockDp::default()));
+        let mut probe = FakeProbe::new();
+
+        let read_dp = dp.clone();
+        probe.set_dap_register_read_handler(Box::new(move |port, address| {
+            let mut dp = read_dp.lock().unwrap();
+            dp.accesses.push((port, address, None));
+
+            match (port, address) {
+                (PortType::DebugPort, 0x0) => {
+                    dp.dpidr_reads += 1;
+                    Ok(0x2ba0_1477)
+                }
+                (PortType::DebugPort, 0x4) => Ok(POWER_UP_ACKS | dp.sticky_errors),
+                (PortType::DebugPort, _) => Ok(0),
+                // No access ports are found while the debug port is started
+                (PortType::AccessPort, 0xFC) => Ok(0),
+                (PortType::AccessPort, _) => dp.ap_responses.pop_front().unwrap_or(Ok(0)),
+            }
+        }));
+
+        let write_dp = dp.clone();
+        probe.set_dap_register_write_handler(Box::new(move |port, address, value| {
+            let mut dp = write_dp.lock().unwrap();
+            dp.accesses.push((port, address, Some(value)));
+
+            if (port, address) == (PortType::DebugPort, 0x0) {
+                dp.abort_writes.push(value);
+
+                if value & STKERRCLR != 0 {
+                    dp.sticky_errors &= !STICKYERR;
+                }
+            }
+
+            Ok(())
+        }));
+
+        let interface = ArmCommunicationInterface::from_uninitialized(
+            ArmCommunicationInterface::new(Box::new(probe), false),
+            DefaultArmSequence::create(),
+            false,
+        )
+        .unwrap();
+
+        (interface, dp)
+    }
+
+    /// Start the debug port, and queue the responses to the following AP register reads
+    fn start_with_responses(
+        interface: &mut ArmCommunicationInterface<Initialized>,
+        dp: &Mutex<MockDp>,
+        responses: impl IntoIterator<Item = Result<u32, ArmError>>,
+    ) {
+        interface.select_dp(DpAddress::Default).unwrap();
+
+        let mut dp = dp.lock().unwrap();
+        dp.dpidr_reads = 0;
+        dp.abort_writes.clear();
+        dp.accesses.clear();
+        dp.ap_responses.extend(responses);
+    }
+
+    const AP: ApAddress = ApAddress {
+        dp: DpAddress::Default,
+        ap: 0,
+    };
+
+    #[test]
+    fn wait_response_is_retried() {
+        let (mut interface, dp) = interface_with_mock_dp();
+        start_with_responses(
+            &mut interface,
+            &dp,
+            [
+                Err(ArmError::Dap(DapError::WaitResponse)),
+                Err(ArmError::Dap(DapError::WaitResponse)),
+                Ok(0x1234),
+            ],
+        );
+
+        assert_eq!(interface.read_raw_ap_register(AP, 0x0C).unwrap(), 0x1234);
+        assert_eq!(
+            interface.dap_statistics(),
+            Some(DapStatistics {
+                wait_retries: 2,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn wait_response_is_returned_after_the_last_retry() {
+        let (mut interface, dp) = interface_with_mock_dp();
+        start_with_responses(
+            &mut interface,
+            &dp,
+            std::iter::repeat_with(|| Err(ArmError::Dap(DapError::WaitResponse)))
+                .take(MAX_WAIT_RETRIES as usize + 1),
+        );
+
+        assert!(matches!(
+            interface.read_raw_ap_register(AP, 0x0C),
+            Err(ArmError::Dap(DapError::WaitResponse))
+        ));
+        assert_eq!(
+            interface.dap_statistics().unwrap().wait_retries,
+            MAX_WAIT_RETRIES as usize
+        );
+        assert!(dp.lock().unwrap().ap_responses.is_empty());
+    }
+
+    #[test]
+    fn fault_response_clears_sticky_errors() {
+        let (mut interface, dp) = interface_with_mock_dp();
+        start_with_responses(
+            &mut interface,
+            &dp,
+            [Err(ArmError::Dap(DapError::FaultResponse)), Ok(0x1234)],
+        );
+        dp.lock().unwrap().sticky_errors = STICKYERR;
+
+        let error = interface.read_raw_ap_register(AP, 0x0C).unwrap_err();
+
+        assert!(matches!(
+            error,
+            ArmError::ApTransferFault {
+                address: AP,
+                register: 0x0C,
+                sticky_errors: StickyErrors {
+                    sticky_err: true,
+                    ..
+                },
+            }
+        ));
+        assert_eq!(dp.lock().unwrap().abort_writes, vec![STKERRCLR]);
+        assert_eq!(interface.dap_statistics().unwrap().fault_recoveries, 1);
+
+        // The next transfer is not affected by the fault
+        assert_eq!(interface.read_raw_ap_register(AP, 0x0C).unwrap(), 0x1234);
+    }
+
+    #[test]
+    fn protocol_error_reconnects_and_retries() {
+        let (mut interface, dp) = interface_with_mock_dp();
+        start_with_responses(
+            &mut interface,
+            &dp,
+            [Err(ArmError::Dap(DapError::SwdProtocol)), Ok(0x1234)],
+        );
+
+        assert_eq!(interface.read_raw_ap_register(AP, 0x0C).unwrap(), 0x1234);
+        assert_eq!(interface.dap_statistics().unwrap().protocol_recoveries, 1);
+
+        // The line reset is followed by a DPIDR read, and the debug port is started again
+        let dp = dp.lock().unwrap();
+        assert_eq!(dp.dpidr_reads, 1);
+        assert!(!dp.abort_writes.is_empty());
+    }
+
+    #[test]
+    fn no_acknowledge_is_returned_without_reconnecting() {
+        let (mut interface, dp) = interface_with_mock_dp();
+        start_with_responses(
+            &mut interface,
+            &dp,
+            [Err(ArmError::Dap(DapError::NoAcknowledge)), Ok(0x1234)],
+        );
+
+        assert!(matches!(
+            interface.read_raw_ap_register(AP, 0x0C),
+            Err(ArmError::Dap(DapError::NoAcknowledge))
+        ));
+        assert_eq!(interface.dap_statistics(), Some(DapStatistics::default()));
+        assert_eq!(dp.lock().unwrap().dpidr_reads, 0);
+    }
+
+    #[test]
+    fn transfer_batch_selects_banks_between_runs() {
+        let (mut interface, dp) = interface_with_mock_dp();
+        start_with_responses(&mut interface, &dp, [Ok(0x1111), Ok(0x2222)]);
+
+        // CSW, TAR and DRW are in bank 0, IDR in bank 0xF
+        let mut transfers = [
+            DapTransfer::write(PortType::AccessPort, 0x00, 0x2300_0052),
+            DapTransfer::write(PortType::AccessPort, 0x04, 0x2000_0000),
+            DapTransfer::read(PortType::AccessPort, 0x0C),
+            DapTransfer::read(PortType::AccessPort, 0xFC),
+            DapTransfer::read(PortType::AccessPort, 0x0C),
+        ];
+        interface.transfer_batch(AP, &mut transfers).unwrap();
+
+        assert_eq!(transfers[2].value, 0x1111);
+        assert_eq!(transfers[4].value, 0x2222);
+
+        // The AP discovery left bank 0xF selected
+        assert_eq!(
+            dp.lock().unwrap().accesses,
+            [
+                (PortType::DebugPort, 0x08, Some(0x00)),
+                (PortType::AccessPort, 0x00, Some(0x2300_0052)),
+                (PortType::AccessPort, 0x04, Some(0x2000_0000)),
+                (PortType::AccessPort, 0x0C, None),
+                (PortType::DebugPort, 0x08, Some(0xF0)),
+                (PortType::AccessPort, 0xFC, None),
+                (PortType::DebugPort, 0x08, Some(0x00)),
+                (PortType::AccessPort, 0x0C, None),
+            ]
+        );
+    }
+}
@@ -360,6 +360,22 @@ pub(crate) fn add_swv_data_trace(
     dwt.enable_data_trace(unit, address)
 }
 
+/// Enables or disables the exception trace of the DWT, `DWT_CTRL.EXCTRCENA`.
+///
+/// Expects to be given a list of all ROM table `components` as the second argument.
+pub(crate) fn set_swv_exception_trace(
+    interface: &mut dyn ArmProbeInterface,
+    components: &[CoresightComponent],
+    enabled: bool,
+) -> Result<(), ArmError> {
+    let mut dwt = Dwt::new(interface, find_component(components, PeripheralType::Dwt)?);
+    if enabled {
+        dwt.enable_exception_trace()
+    } else {
+        dwt.disable_exception_trace()
+    }
+}
+
 /// Configures DWT trace unit `unit` to stop tracing `address`.
 ///
 ///
@@ -1,13 +1,13 @@
 //! Register types and the core interface for armv6-M
 
-use super::{registers::cortex_m::*, CortexMState, Dfsr};
+use super::{registers::cortex_m::*, CortexMState, Dfsr, DwtComparator, MpuConfig};
 use crate::{
     architecture::arm::{
         memory::adi_v5_memory_interface::ArmProbe, sequences::ArmDebugSequence, ArmError,
     },
-    core::{CoreRegisters, RegisterId, RegisterValue, VectorCatchCondition},
+    core::{CoreRegisters, RegisterId, RegisterValue, VectorCatchCondition, WatchpointKind},
     error::Error,
-    memory::valid_32bit_address,
+    memory::{valid_32bit_address, MemoryTransfer},
     Architecture, CoreInformation, CoreInterface, CoreRegister, CoreStatus, CoreType,
     DebugProbeError, HaltReason, InstructionSet, MemoryInterface, MemoryMappedRegister,
 };
@@ -465,6 +465,11 @@ impl<'probe> Armv6m<'probe> {
 }
 
 impl<'probe> CoreInterface for Armv6m<'probe> {
+    fn set_reset_catch_timeout(&mut self, timeout: Duration) -> Result<(), Error> {
+        self.state.reset_catch_timeout = timeout;
+        Ok(())
+    }
+
     fn wait_for_core_halted(&mut self, timeout: Duration) -> Result<(), Error> {
         // Wait until halted state is active again.
         let start = Instant::now();
@@ -475,7 +480,14 @@ impl<'probe> CoreInterface for Armv6m<'probe> {
             }
             std::thread::sleep(Duration::from_millis(1));
         }
-        Err(Error::Arm(ArmError::Timeout))
+        Err(Error::Arm(super::cortex_m::halt_timeout_error(
+            &self.state.current_state,
+        )))
+    }
+
+    fn ping(&mut self) -> Result<(), Error> {
+        self.memory.read_word_32(Dhcsr::get_mmio_address())?;
+        Ok(())
     }
 
     fn core_halted(&mut self) -> Result<bool, Error> {
@@ -580,6 +592,8 @@ impl<'probe> CoreInterface for Armv6m<'probe> {
         // get pc
         Ok(CoreInformation {
             pc: pc_value.try_into()?,
+            sp: None,
+            lr: None,
         })
     }
 
@@ -604,6 +618,10 @@ impl<'probe> CoreInterface for Armv6m<'probe> {
     fn reset(&mut self) -> Result<(), Error> {
         self.sequence
             .reset_system(&mut *self.memory, crate::CoreType::Armv6m, None)?;
+
+        // A reset may disable the FPB, it is enabled again when the next breakpoint is set
+        self.state.hw_breakpoints_enabled = false;
+
         Ok(())
     }
 
@@ -613,8 +631,11 @@ impl<'probe> CoreInterface for Armv6m<'probe> {
         self.sequence
             .reset_system(&mut *self.memory, crate::CoreType::Armv6m, None)?;
 
-        // Update core status
-        let _ = self.status()?;
+        // A reset may disable the FPB, it is enabled again when the next breakpoint is set
+        self.state.hw_breakpoints_enabled = false;
+
+        // Coming out of reset can take much longer than halting a running core
+        self.wait_for_core_halted(self.state.reset_catch_timeout)?;
 
         const XPSR_THUMB: u32 = 1 << 24;
 
@@ -631,6 +652,8 @@ impl<'probe> CoreInterface for Armv6m<'probe> {
         // get pc
         Ok(CoreInformation {
             pc: pc_value.try_into()?,
+            sp: None,
+            lr: None,
         })
     }
 
@@ -683,6 +706,8 @@ impl<'probe> CoreInterface for Armv6m<'probe> {
 
         Ok(CoreInformation {
             pc: pc_after_step.try_into()?,
+            sp: None,
+            lr: None,
         })
     }
 
@@ -704,7 +729,9 @@ impl<'probe> CoreInterface for Armv6m<'probe> {
     }
 
     fn available_breakpoint_units(&mut self) -> Result<u32, Error> {
-        let result = self.memory.read_word_32(BpCtrl::get_mmio_address())?;
+        let result = self
+            .memory
+            .read_word_32(self.state.components.fpb_register::<BpCtrl>())?;
 
         let register = BpCtrl::from(result);
 
@@ -716,7 +743,8 @@ impl<'probe> CoreInterface for Armv6m<'probe> {
         let mut breakpoints = vec![];
         let num_hw_breakpoints = self.available_breakpoint_units()? as usize;
         for bp_unit_index in 0..num_hw_breakpoints {
-            let reg_addr = BpCompx::get_mmio_address() + (bp_unit_index * size_of::<u32>()) as u64;
+            let reg_addr = self.state.components.fpb_register::<BpCompx>()
+                + (bp_unit_index * size_of::<u32>()) as u64;
             // The raw breakpoint address as read from memory
             let register_value = self.memory.read_word_32(reg_addr)?;
             if BpCompx::from(register_value).enable() {
@@ -736,7 +764,7 @@ impl<'probe> CoreInterface for Armv6m<'probe> {
         value.set_enable(state);
 
         self.memory
-            .write_word_32(BpCtrl::get_mmio_address(), value.into())?;
+            .write_word_32(self.state.components.fpb_register::<BpCtrl>(), value.into())?;
         self.memory.flush()?;
 
         self.state.hw_breakpoints_enabled = state;
@@ -766,8 +794,8 @@ impl<'probe> CoreInterface for Armv6m<'probe> {
         value.set_comp((addr >> 2) & 0x07FF_FFFF);
         value.set_enable(true);
 
-        let register_addr =
-            BpCompx::get_mmio_address() + (bp_register_index * size_of::<u32>()) as u64;
+        let register_addr = self.state.components.fpb_register::<BpCompx>()
+            + (bp_register_index * size_of::<u32>()) as u64;
 
         self.memory.write_word_32(register_addr, value.into())?;
 
@@ -775,7 +803,8 @@ impl<'probe> CoreInterface for Armv6m<'probe> {
     }
 
     fn clear_hw_breakpoint(&mut self, bp_unit_index: usize) -> Result<(), Error> {
-        let register_addr = BpCompx::get_mmio_address() + (bp_unit_index * size_of::<u32>()) as u64;
+        let register_addr = self.state.components.fpb_register::<BpCompx>()
+            + (bp_unit_index * size_of::<u32>()) as u64;
 
         let mut value = BpCompx::from(0);
         value.set_enable(false);
@@ -785,8 +814,50 @@ impl<'probe> CoreInterface for Armv6m<'probe> {
         Ok(())
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        super::cortex_m::available_watchpoint_units(&mut *self.memory, self.state.components)
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<u64>>, Error> {
+        super::cortex_m::hw_watchpoints(&mut *self.memory, self.state.components)
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        addr: u64,
+        len: u64,
+        kind: WatchpointKind,
+    ) -> Result<(), Error> {
+        super::cortex_m::set_hw_watchpoint(
+            &mut *self.memory,
+            self.state.components,
+            false,
+            unit_index,
+            addr,
+            len,
+            kind,
+        )
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        super::cortex_m::clear_hw_watchpoint(&mut *self.memory, self.state.components, unit_index)
+    }
+
+    fn triggered_hw_watchpoint(&mut self) -> Result<Option<usize>, Error> {
+        super::cortex_m::triggered_hw_watchpoint(&mut *self.memory, self.state.components)
+    }
+
+    fn read_dwt_comparators(&mut self) -> Result<Vec<DwtComparator>, Error> {
+        super::cortex_m::read_dwt_comparators(&mut *self.memory, self.state.components, false)
+    }
+
+    fn read_mpu_config(&mut self) -> Result<MpuConfig, Error> {
+        super::cortex_m::read_mpu_config(&mut *self.memory, false)
+    }
+
     fn registers(&self) -> &'static CoreRegisters {
-        &CORTEX_M_CORE_REGISTERS
+        self.state.registers
     }
 
     fn program_counter(&self) -> &'static CoreRegister {
@@ -851,6 +922,14 @@ impl<'probe> CoreInterface for Armv6m<'probe> {
         Ok(())
     }
 
+    #[tracing::instrument(skip(self))]
+    fn reset_init(&mut self) -> Result<(), Error> {
+        self.sequence
+            .reset_init(&mut *self.memory, CoreType::Armv6m, None)?;
+
+        Ok(())
+    }
+
     #[tracing::instrument(skip(self))]
     fn debug_core_stop(&mut self) -> Result<(), Error> {
         self.sequence
@@ -945,6 +1024,11 @@ impl<'probe> MemoryInterface for Armv6m<'probe> {
         Ok(())
     }
 
+    fn write_word_16(&mut self, address: u64, data: u16) -> Result<(), Error> {
+        self.memory.write_word_16(address, data)?;
+        Ok(())
+    }
+
     fn write_word_8(&mut self, address: u64, data: u8) -> Result<(), Error> {
         self.memory.write_word_8(address, data)?;
         Ok(())
@@ -970,6 +1054,11 @@ impl<'probe> MemoryInterface for Armv6m<'probe> {
         Ok(())
     }
 
+    fn transfer_batch_32(&mut self, transfers: &mut [MemoryTransfer]) -> Result<(), Error> {
+        self.memory.transfer_batch_32(transfers)?;
+        Ok(())
+    }
+
     fn supports_8bit_transfers(&self) -> Result<bool, Error> {
         let value = self.memory.supports_8bit_transfers()?;
         Ok(value)
@@ -376,6 +376,8 @@ impl<'probe> CoreInterface for Armv7a<'probe> {
         // get pc
         Ok(CoreInformation {
             pc: pc_value.try_into()?,
+            sp: None,
+            lr: None,
         })
     }
     fn run(&mut self) -> Result<(), Error> {
@@ -462,6 +464,8 @@ impl<'probe> CoreInterface for Armv7a<'probe> {
         // get pc
         Ok(CoreInformation {
             pc: pc_value.try_into()?,
+            sp: None,
+            lr: None,
         })
     }
 
@@ -513,6 +517,8 @@ impl<'probe> CoreInterface for Armv7a<'probe> {
         // get pc
         Ok(CoreInformation {
             pc: pc_value.try_into()?,
+            sp: None,
+            lr: None,
         })
     }
 
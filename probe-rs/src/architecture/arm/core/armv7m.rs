@@ -1,11 +1,9 @@
 //! Register types and the core interface for armv7-M
 
 use super::{
-    cortex_m::Mvfr0,
-    registers::cortex_m::{
-        CORTEX_M_CORE_REGISTERS, CORTEX_M_WITH_FP_CORE_REGISTERS, FP, PC, RA, SP,
-    },
-    CortexMState, Dfsr,
+    cortex_m::{dcache_maintenance, CacheMaintenance, Cpuid, Mvfr0, CORTEX_M7_PARTNO},
+    registers::cortex_m::{cortex_m_core_registers, FP, PC, RA, SP},
+    CortexMState, Dfsr, DwtComparator, MpuConfig,
 };
 use crate::{
     architecture::arm::{
@@ -14,10 +12,10 @@ use crate::{
     },
     core::{
         Architecture, CoreInformation, CoreInterface, CoreRegisters, CoreStatus, HaltReason,
-        MemoryMappedRegister, RegisterId, RegisterValue, VectorCatchCondition,
+        MemoryMappedRegister, RegisterId, RegisterValue, VectorCatchCondition, WatchpointKind,
     },
     error::Error,
-    memory::valid_32bit_address,
+    memory::{valid_32bit_address, MemoryTransfer},
     CoreRegister, CoreType, DebugProbeError, InstructionSet, MemoryInterface,
 };
 use anyhow::{anyhow, Result};
@@ -630,6 +628,7 @@ impl<'probe> Armv7m<'probe> {
 
             state.current_state = core_state;
             state.fp_present = Mvfr0(memory.read_word_32(Mvfr0::get_mmio_address())?).fp_present();
+            state.registers = cortex_m_core_registers(state.fp_present, false);
 
             state.initialize();
         }
@@ -648,6 +647,11 @@ impl<'probe> Armv7m<'probe> {
 }
 
 impl<'probe> CoreInterface for Armv7m<'probe> {
+    fn set_reset_catch_timeout(&mut self, timeout: Duration) -> Result<(), Error> {
+        self.state.reset_catch_timeout = timeout;
+        Ok(())
+    }
+
     fn wait_for_core_halted(&mut self, timeout: Duration) -> Result<(), Error> {
         // Wait until halted state is active again.
         let start = Instant::now();
@@ -657,12 +661,19 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
                 // Wait a bit before polling again.
                 std::thread::sleep(Duration::from_millis(1));
             } else {
-                return Err(Error::Arm(ArmError::Timeout));
+                return Err(Error::Arm(super::cortex_m::halt_timeout_error(
+                    &self.state.current_state,
+                )));
             }
         }
         Ok(())
     }
 
+    fn ping(&mut self) -> Result<(), Error> {
+        self.memory.read_word_32(Dhcsr::get_mmio_address())?;
+        Ok(())
+    }
+
     fn core_halted(&mut self) -> Result<bool, Error> {
         Ok(self.status()?.is_halted())
     }
@@ -759,6 +770,8 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
         // get pc
         Ok(CoreInformation {
             pc: pc_value.try_into()?,
+            sp: None,
+            lr: None,
         })
     }
 
@@ -794,6 +807,10 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
     fn reset(&mut self) -> Result<(), Error> {
         self.sequence
             .reset_system(&mut *self.memory, crate::CoreType::Armv7m, None)?;
+
+        // A reset may disable the FPB, it is enabled again when the next breakpoint is set
+        self.state.hw_breakpoints_enabled = false;
+
         Ok(())
     }
 
@@ -805,8 +822,11 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
         self.sequence
             .reset_system(&mut *self.memory, crate::CoreType::Armv7m, None)?;
 
-        // Update core status
-        let _ = self.status()?;
+        // A reset may disable the FPB, it is enabled again when the next breakpoint is set
+        self.state.hw_breakpoints_enabled = false;
+
+        // Coming out of reset can take much longer than halting a running core
+        self.wait_for_core_halted(self.state.reset_catch_timeout)?;
 
         const XPSR_THUMB: u32 = 1 << 24;
 
@@ -823,6 +843,8 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
         // get pc
         Ok(CoreInformation {
             pc: pc_value.try_into()?,
+            sp: None,
+            lr: None,
         })
     }
 
@@ -885,6 +907,8 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
 
         Ok(CoreInformation {
             pc: pc_after_step.try_into()?,
+            sp: None,
+            lr: None,
         })
     }
 
@@ -906,17 +930,16 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
     }
 
     fn available_breakpoint_units(&mut self) -> Result<u32, Error> {
-        let raw_val = self.memory.read_word_32(FpCtrl::get_mmio_address())?;
+        let raw_val = self
+            .memory
+            .read_word_32(self.state.components.fpb_register::<FpCtrl>())?;
 
         let reg = FpCtrl::from(raw_val);
 
         if reg.rev() == 0 || reg.rev() == 1 {
             Ok(reg.num_code())
         } else {
-            tracing::warn!("This chip uses FPBU revision {}, which is not yet supported. HW breakpoints are not available.", reg.rev());
-            Err(Error::Probe(DebugProbeError::CommandNotSupportedByProbe(
-                "get_available_breakpoint_units",
-            )))
+            Err(Error::Arm(ArmError::UnsupportedFpbRevision(reg.rev())))
         }
     }
 
@@ -925,14 +948,17 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
         let mut breakpoints = vec![];
         let num_hw_breakpoints = self.available_breakpoint_units()? as usize;
         { 0..num_hw_breakpoints }.try_for_each(|bp_unit_index| {
-            let raw_val = self.memory.read_word_32(FpCtrl::get_mmio_address())?;
+            let raw_val = self
+                .memory
+                .read_word_32(self.state.components.fpb_register::<FpCtrl>())?;
             let ctrl_reg = FpCtrl::from(raw_val);
             // FpRev1 and FpRev2 needs different decoding of the register value, but the location where we read from is the same ...
-            let reg_addr = FpRev1CompX::get_mmio_address() + (bp_unit_index * size_of::<u32>()) as u64;
+            let reg_addr = self.state.components.fpb_register::<FpRev1CompX>()
+                + (bp_unit_index * size_of::<u32>()) as u64;
             // The raw breakpoint address as read from memory.
             let register_value = self.memory.read_word_32(reg_addr)?;
             // The breakpoint address after it has been adjusted for FpRev 1 or 2.
-            let breakpoint:u32;
+            let breakpoint: u32;
             if register_value & 0b1 == 0b1 {
                 // We only care about `enabled` breakpoints.
                 if ctrl_reg.rev() == 0 {
@@ -940,8 +966,7 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
                 } else if ctrl_reg.rev() == 1 {
                     breakpoint = FpRev2CompX::from(register_value).bpaddr() << 1;
                 } else {
-                    tracing::warn!("This chip uses FPBU revision {}, which is not yet supported. HW breakpoints are not available.", ctrl_reg.rev());
-                    return Err(Error::Other(anyhow!("This chip uses FPBU revision {}, which is not yet supported. HW breakpoints are not available.", ctrl_reg.rev())));
+                    return Err(Error::Arm(ArmError::UnsupportedFpbRevision(ctrl_reg.rev())));
                 }
                 breakpoints.push(Some(breakpoint as u64));
             } else {
@@ -958,7 +983,7 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
         val.set_enable(state);
 
         self.memory
-            .write_word_32(FpCtrl::get_mmio_address(), val.into())?;
+            .write_word_32(self.state.components.fpb_register::<FpCtrl>(), val.into())?;
         self.memory.flush()?;
 
         self.state.hw_breakpoints_enabled = state;
@@ -977,7 +1002,9 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
             )));
         }
 
-        let raw_val = self.memory.read_word_32(FpCtrl::get_mmio_address())?;
+        let raw_val = self
+            .memory
+            .read_word_32(self.state.components.fpb_register::<FpCtrl>())?;
         let ctrl_reg = FpCtrl::from(raw_val);
 
         let val: u32;
@@ -986,14 +1013,14 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
         } else if ctrl_reg.rev() == 1 {
             val = FpRev2CompX::breakpoint_configuration(addr).into();
         } else {
-            tracing::warn!("This chip uses FPBU revision {}, which is not yet supported. HW breakpoints are not available.", ctrl_reg.rev());
-            return Err(Error::Other(anyhow!("This chip uses FPBU revision {}, which is not yet supported. HW breakpoints are not available.", ctrl_reg.rev())));
+            return Err(Error::Arm(ArmError::UnsupportedFpbRevision(ctrl_reg.rev())));
         }
 
         // This is fine as FpRev1CompX and Rev2CompX are just two different
         // interpretations of the same memory region as Rev2 can handle bigger
         // address spaces than Rev1.
-        let reg_addr = FpRev1CompX::get_mmio_address() + (bp_unit_index * size_of::<u32>()) as u64;
+        let reg_addr = self.state.components.fpb_register::<FpRev1CompX>()
+            + (bp_unit_index * size_of::<u32>()) as u64;
 
         self.memory.write_word_32(reg_addr, val)?;
 
@@ -1004,19 +1031,58 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
         let mut val = FpRev1CompX::from(0);
         val.set_enable(false);
 
-        let reg_addr = FpRev1CompX::get_mmio_address() + (bp_unit_index * size_of::<u32>()) as u64;
+        let reg_addr = self.state.components.fpb_register::<FpRev1CompX>()
+            + (bp_unit_index * size_of::<u32>()) as u64;
 
         self.memory.write_word_32(reg_addr, val.into())?;
 
         Ok(())
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        super::cortex_m::available_watchpoint_units(&mut *self.memory, self.state.components)
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<u64>>, Error> {
+        super::cortex_m::hw_watchpoints(&mut *self.memory, self.state.components)
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        addr: u64,
+        len: u64,
+        kind: WatchpointKind,
+    ) -> Result<(), Error> {
+        super::cortex_m::set_hw_watchpoint(
+            &mut *self.memory,
+            self.state.components,
+            false,
+            unit_index,
+            addr,
+            len,
+            kind,
+        )
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        super::cortex_m::clear_hw_watchpoint(&mut *self.memory, self.state.components, unit_index)
+    }
+
+    fn triggered_hw_watchpoint(&mut self) -> Result<Option<usize>, Error> {
+        super::cortex_m::triggered_hw_watchpoint(&mut *self.memory, self.state.components)
+    }
+
+    fn read_dwt_comparators(&mut self) -> Result<Vec<DwtComparator>, Error> {
+        super::cortex_m::read_dwt_comparators(&mut *self.memory, self.state.components, false)
+    }
+
+    fn read_mpu_config(&mut self) -> Result<MpuConfig, Error> {
+        super::cortex_m::read_mpu_config(&mut *self.memory, false)
+    }
+
     fn registers(&self) -> &'static CoreRegisters {
-        if self.state.fp_present {
-            &CORTEX_M_WITH_FP_CORE_REGISTERS
-        } else {
-            &CORTEX_M_CORE_REGISTERS
-        }
+        self.state.registers
     }
 
     fn program_counter(&self) -> &'static CoreRegister {
@@ -1079,6 +1145,14 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
         Ok(())
     }
 
+    #[tracing::instrument(skip(self))]
+    fn reset_init(&mut self) -> Result<(), Error> {
+        self.sequence
+            .reset_init(&mut *self.memory, CoreType::Armv7m, None)?;
+
+        Ok(())
+    }
+
     #[tracing::instrument(skip(self))]
     fn debug_core_stop(&mut self) -> Result<(), Error> {
         self.sequence
@@ -1129,6 +1203,47 @@ impl<'probe> CoreInterface for Armv7m<'probe> {
             .write_word_32(Demcr::get_mmio_address(), demcr.into())?;
         Ok(())
     }
+
+    fn set_cache_coherent(&mut self, enabled: bool) -> Result<(), Error> {
+        if enabled {
+            let cpuid = Cpuid(self.memory.read_word_32(Cpuid::get_mmio_address())?);
+
+            // Only the Cortex-M7 has a data cache in the ARMv7-M profile
+            if cpuid.partno() != CORTEX_M7_PARTNO {
+                tracing::debug!("Core has no data cache, nothing to keep coherent");
+                return Ok(());
+            }
+        }
+
+        self.state.cache_coherent = enabled;
+
+        Ok(())
+    }
+}
+
+impl<'probe> Armv7m<'probe> {
+    /// Write back the data cache lines covering `len` bytes at `address` before they are accessed
+    fn clean_dcache(&mut self, address: u64, len: usize) -> Result<(), ArmError> {
+        if !self.state.cache_coherent {
+            return Ok(());
+        }
+
+        dcache_maintenance(&mut *self.memory, CacheMaintenance::Clean, address, len)
+    }
+
+    /// Discard the data cache lines covering `len` bytes at `address` after they were written
+    fn invalidate_dcache(&mut self, address: u64, len: usize) -> Result<(), ArmError> {
+        if !self.state.cache_coherent {
+            return Ok(());
+        }
+
+        dcache_maintenance(
+            &mut *self.memory,
+            CacheMaintenance::Invalidate,
+            address,
+            len,
+        )
+    }
 }
 
 impl<'probe> MemoryInterface for Armv7m<'probe> {
@@ -1137,81 +1252,130 @@ impl<'probe> MemoryInterface for Armv7m<'probe> {
     }
 
     fn read_word_64(&mut self, address: u64) -> Result<u64, crate::error::Error> {
+        self.clean_dcache(address, 8)?;
+
         self.memory
             .read_word_64(address)
             .map_err(From::<ArmError>::from)
     }
 
     fn read_word_32(&mut self, address: u64) -> Result<u32, Error> {
+        self.clean_dcache(address, 4)?;
+
         self.memory
             .read_word_32(address)
             .map_err(From::<ArmError>::from)
     }
 
     fn read_word_8(&mut self, address: u64) -> Result<u8, Error> {
+        self.clean_dcache(address, 1)?;
+
         self.memory
             .read_word_8(address)
             .map_err(From::<ArmError>::from)
     }
 
     fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), crate::error::Error> {
+        self.clean_dcache(address, data.len() * 8)?;
+
         self.memory
             .read_64(address, data)
             .map_err(From::<ArmError>::from)
     }
 
     fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), Error> {
+        self.clean_dcache(address, data.len() * 4)?;
+
         self.memory
             .read_32(address, data)
             .map_err(From::<ArmError>::from)
     }
 
     fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), Error> {
+        self.clean_dcache(address, data.len())?;
+
         self.memory
             .read_8(address, data)
             .map_err(From::<ArmError>::from)
     }
 
     fn write_word_64(&mut self, address: u64, data: u64) -> Result<(), crate::error::Error> {
-        self.memory
-            .write_word_64(address, data)
-            .map_err(From::<ArmError>::from)
+        self.clean_dcache(address, 8)?;
+        self.memory.write_word_64(address, data)?;
+        self.invalidate_dcache(address, 8)?;
+
+        Ok(())
     }
 
     fn write_word_32(&mut self, address: u64, data: u32) -> Result<(), Error> {
-        self.memory
-            .write_word_32(address, data)
-            .map_err(From::<ArmError>::from)
+        self.clean_dcache(address, 4)?;
+        self.memory.write_word_32(address, data)?;
+        self.invalidate_dcache(address, 4)?;
+
+        Ok(())
+    }
+
+    fn write_word_16(&mut self, address: u64, data: u16) -> Result<(), Error> {
+        self.clean_dcache(address, 2)?;
+        self.memory.write_word_16(address, data)?;
+        self.invalidate_dcache(address, 2)?;
+
+        Ok(())
     }
 
     fn write_word_8(&mut self, address: u64, data: u8) -> Result<(), Error> {
-        self.memory
-            .write_word_8(address, data)
-            .map_err(From::<ArmError>::from)
+        self.clean_dcache(address, 1)?;
+        self.memory.write_word_8(address, data)?;
+        self.invalidate_dcache(address, 1)?;
+
+        Ok(())
     }
 
     fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), crate::error::Error> {
-        self.memory
-            .write_64(address, data)
-            .map_err(From::<ArmError>::from)
+        self.clean_dcache(address, data.len() * 8)?;
+        self.memory.write_64(address, data)?;
+        self.invalidate_dcache(address, data.len() * 8)?;
+
+        Ok(())
     }
 
     fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), Error> {
-        self.memory
-            .write_32(address, data)
-            .map_err(From::<ArmError>::from)
+        self.clean_dcache(address, data.len() * 4)?;
+        self.memory.write_32(address, data)?;
+        self.invalidate_dcache(address, data.len() * 4)?;
+
+        Ok(())
     }
 
     fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), Error> {
-        self.memory
-            .write_8(address, data)
-            .map_err(From::<ArmError>::from)
+        self.clean_dcache(address, data.len())?;
+        self.memory.write_8(address, data)?;
+        self.invalidate_dcache(address, data.len())?;
+
+        Ok(())
     }
 
     fn write(&mut self, address: u64, data: &[u8]) -> Result<(), Error> {
-        self.memory
-            .write(address, data)
-            .map_err(From::<ArmError>::from)
+        self.clean_dcache(address, data.len())?;
+        self.memory.write(address, data)?;
+        self.invalidate_dcache(address, data.len())?;
+
+        Ok(())
+    }
+
+    fn transfer_batch_32(&mut self, transfers: &mut [MemoryTransfer]) -> Result<(), Error> {
+        // Transfers to the system region, e.g. of core registers, don't need any cache maintenance
+        for transfer in transfers.iter() {
+            self.clean_dcache(transfer.address, 4)?;
+        }
+
+        self.memory.transfer_batch_32(transfers)?;
+
+        for transfer in transfers.iter().filter(|transfer| transfer.write) {
+            self.invalidate_dcache(transfer.address, 4)?;
+        }
+
+        Ok(())
     }
 
     fn supports_8bit_transfers(&self) -> Result<bool, Error> {
@@ -1246,3 +1410,111 @@ fn unsupported_breakpoint_address() {
 
     FpRev1CompX::breakpoint_configuration(address).unwrap_err();
 }
+
+#[test]
+fn reset_and_halt_transactions() {
+    use super::replay::{Op::*, ReplayMemory};
+    use crate::architecture::arm::sequences::DefaultArmSequence;
+
+    const DHCSR: u64 = 0xE000_EDF0;
+    const DCRSR: u64 = 0xE000_EDF4;
+    const DCRDR: u64 = 0xE000_EDF8;
+    const DEMCR: u64 = 0xE000_EDFC;
+    const AIRCR: u64 = 0xE000_ED0C;
+    const DFSR: u64 = 0xE000_ED30;
+    const MVFR0: u64 = 0xE000_EF40;
+
+    // Reset of a running Cortex-M3 without FPU, whose xPSR lacks the Thumb bit after the reset.
+    let mut memory = ReplayMemory::new([
+        // Attach: determine the core status and the FPU
+        (Read, DHCSR, 0x0000_0001),
+        (Write, DFSR, 0x0000_001f),
+        (Read, MVFR0, 0x0000_0000),
+        // Reset catch set
+        (Read, DEMCR, 0x0100_0000),
+        (Write, DEMCR, 0x0100_0001),
+        (Read, DHCSR, 0x0003_0003),
+        // Reset, and wait for S_RESET_ST to confirm the reset
+        (Write, AIRCR, 0x05fa_0004),
+        (Read, DHCSR, 0x0000_0003),
+        (Read, DHCSR, 0x0203_0003),
+        // Wait for the halt: halted by the reset vector catch
+        (Read, DHCSR, 0x0003_0003),
+        (Read, DFSR, 0x0000_0008),
+        (Write, DFSR, 0x0000_001f),
+        // Set the Thumb bit in xPSR
+        (Write, DCRSR, 0x0000_0010),
+        (Read, DHCSR, 0x0003_0003),
+        (Read, DCRDR, 0x0000_0000),
+        (Write, DCRDR, 0x0100_0000),
+        (Write, DCRSR, 0x0001_0010),
+        (Read, DHCSR, 0x0003_0003),
+        // Reset catch clear
+        (Read, DEMCR, 0x0100_0001),
+        (Write, DEMCR, 0x0100_0000),
+        // Read the PC
+        (Write, DCRSR, 0x0000_000f),
+        (Read, DHCSR, 0x0003_0003),
+        (Read, DCRDR, 0x0000_02e4),
+    ]);
+
+    let mut state = CortexMState::new();
+
+    let mut core = Armv7m::new(
+        Box::new(&mut memory),
+        &mut state,
+        DefaultArmSequence::create(),
+        0,
+    )
+    .unwrap();
+
+    let info = core.reset_and_halt(Duration::from_millis(100)).unwrap();
+    assert_eq!(info.pc, 0x2e4);
+
+    drop(core);
+    memory.assert_finished();
+}
+
+#[test]
+fn relocated_component_transactions() {
+    use super::cortex_m::ComponentBases;
+    use super::replay::{Op::*, ReplayMemory};
+    use crate::architecture::arm::sequences::DefaultArmSequence;
+
+    const DHCSR: u64 = 0xE000_EDF0;
+    const DFSR: u64 = 0xE000_ED30;
+    const MVFR0: u64 = 0xE000_EF40;
+
+    // A part with the FPB at 0xE004_2000 and the DWT at 0xE004_1000
+    let mut memory = ReplayMemory::new([
+        // Attach: determine the core status and the FPU
+        (Read, DHCSR, 0x0000_0001),
+        (Write, DFSR, 0x0000_001f),
+        (Read, MVFR0, 0x0000_0000),
+        // Enable the FPB
+        (Write, 0xE004_2000, 0x0000_0003),
+        // Set the second comparator of a revision 2 FPB
+        (Read, 0xE004_2000, 0x1000_0041),
+        (Write, 0xE004_200C, 0x0000_1001),
+        // Four DWT comparators
+        (Read, 0xE004_1000, 0x4000_0000),
+    ]);
+
+    let mut state = CortexMState::new();
+    state.set_component_bases(ComponentBases::new(Some(0xE004_2000), Some(0xE004_1000)));
+
+    let mut core = Armv7m::new(
+        Box::new(&mut memory),
+        &mut state,
+        DefaultArmSequence::create(),
+        0,
+    )
+    .unwrap();
+
+    core.enable_breakpoints(true).unwrap();
+    core.set_hw_breakpoint(1, 0x1000).unwrap();
+    assert_eq!(core.available_watchpoint_units().unwrap(), 4);
+
+    drop(core);
+    memory.assert_finished();
+}
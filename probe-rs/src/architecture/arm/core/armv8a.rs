@@ -809,6 +809,8 @@ impl<'probe> CoreInterface for Armv8a<'probe> {
         // get pc
         Ok(CoreInformation {
             pc: pc_value.try_into()?,
+            sp: None,
+            lr: None,
         })
     }
 
@@ -905,6 +907,8 @@ impl<'probe> CoreInterface for Armv8a<'probe> {
         // get pc
         Ok(CoreInformation {
             pc: pc_value.try_into()?,
+            sp: None,
+            lr: None,
         })
     }
 
@@ -932,6 +936,8 @@ impl<'probe> CoreInterface for Armv8a<'probe> {
         // get pc
         Ok(CoreInformation {
             pc: pc_value.try_into()?,
+            sp: None,
+            lr: None,
         })
     }
 
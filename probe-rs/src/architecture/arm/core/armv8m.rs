@@ -2,19 +2,17 @@
 
 use super::{
     cortex_m::{IdPfr1, Mvfr0},
-    registers::cortex_m::{
-        CORTEX_M_CORE_REGISTERS, CORTEX_M_WITH_FP_CORE_REGISTERS, FP, PC, RA, SP,
-    },
-    CortexMState, Dfsr,
+    registers::cortex_m::{cortex_m_core_registers, FP, PC, RA, SP},
+    CortexMState, Dfsr, DwtComparator, MpuConfig,
 };
 use crate::{
     architecture::arm::{
         core::registers::cortex_m::XPSR, memory::adi_v5_memory_interface::ArmProbe,
         sequences::ArmDebugSequence, ArmError,
     },
-    core::{CoreRegisters, RegisterId, RegisterValue, VectorCatchCondition},
+    core::{CoreRegisters, RegisterId, RegisterValue, VectorCatchCondition, WatchpointKind},
     error::Error,
-    memory::valid_32bit_address,
+    memory::{valid_32bit_address, MemoryTransfer},
     Architecture, CoreInformation, CoreInterface, CoreRegister, CoreStatus, CoreType, HaltReason,
     InstructionSet, MemoryInterface, MemoryMappedRegister,
 };
@@ -73,6 +71,11 @@ impl<'probe> Armv8m<'probe> {
             state.current_state = core_state;
             state.fp_present = Mvfr0(memory.read_word_32(Mvfr0::get_mmio_address())?).fp_present();
 
+            // The Security Extension adds banked stack pointers and stack limits
+            let security_present =
+                IdPfr1(memory.read_word_32(IdPfr1::get_mmio_address())?).security_present();
+            state.registers = cortex_m_core_registers(state.fp_present, security_present);
+
             state.initialize();
         }
 
@@ -90,6 +93,11 @@ impl<'probe> Armv8m<'probe> {
 }
 
 impl<'probe> CoreInterface for Armv8m<'probe> {
+    fn set_reset_catch_timeout(&mut self, timeout: Duration) -> Result<(), Error> {
+        self.state.reset_catch_timeout = timeout;
+        Ok(())
+    }
+
     fn wait_for_core_halted(&mut self, timeout: Duration) -> Result<(), Error> {
         // Wait until halted state is active again.
         let start = Instant::now();
@@ -101,7 +109,14 @@ impl<'probe> CoreInterface for Armv8m<'probe> {
 
             std::thread::sleep(Duration::from_millis(1));
         }
-        Err(Error::Arm(ArmError::Timeout))
+        Err(Error::Arm(super::cortex_m::halt_timeout_error(
+            &self.state.current_state,
+        )))
+    }
+
+    fn ping(&mut self) -> Result<(), Error> {
+        self.memory.read_word_32(Dhcsr::get_mmio_address())?;
+        Ok(())
     }
 
     fn core_halted(&mut self) -> Result<bool, Error> {
@@ -204,6 +219,8 @@ impl<'probe> CoreInterface for Armv8m<'probe> {
         // get pc
         Ok(CoreInformation {
             pc: pc_value.try_into()?,
+            sp: None,
+            lr: None,
         })
     }
     fn run(&mut self) -> Result<(), Error> {
@@ -228,6 +245,10 @@ impl<'probe> CoreInterface for Armv8m<'probe> {
     fn reset(&mut self) -> Result<(), Error> {
         self.sequence
             .reset_system(&mut *self.memory, crate::CoreType::Armv8m, None)?;
+
+        // A reset may disable the FPB, it is enabled again when the next breakpoint is set
+        self.state.hw_breakpoints_enabled = false;
+
         Ok(())
     }
 
@@ -239,8 +260,11 @@ impl<'probe> CoreInterface for Armv8m<'probe> {
         self.sequence
             .reset_system(&mut *self.memory, crate::CoreType::Armv8m, None)?;
 
-        // Update core status
-        let _ = self.status()?;
+        // A reset may disable the FPB, it is enabled again when the next breakpoint is set
+        self.state.hw_breakpoints_enabled = false;
+
+        // Coming out of reset can take much longer than halting a running core
+        self.wait_for_core_halted(self.state.reset_catch_timeout)?;
 
         const XPSR_THUMB: u32 = 1 << 24;
 
@@ -257,6 +281,8 @@ impl<'probe> CoreInterface for Armv8m<'probe> {
         // get pc
         Ok(CoreInformation {
             pc: pc_value.try_into()?,
+            sp: None,
+            lr: None,
         })
     }
 
@@ -309,6 +335,8 @@ impl<'probe> CoreInterface for Armv8m<'probe> {
 
         Ok(CoreInformation {
             pc: pc_after_step.try_into()?,
+            sp: None,
+            lr: None,
         })
     }
 
@@ -330,7 +358,9 @@ impl<'probe> CoreInterface for Armv8m<'probe> {
     }
 
     fn available_breakpoint_units(&mut self) -> Result<u32, Error> {
-        let raw_val = self.memory.read_word_32(FpCtrl::get_mmio_address())?;
+        let raw_val = self
+            .memory
+            .read_word_32(self.state.components.fpb_register::<FpCtrl>())?;
 
         let reg = FpCtrl::from(raw_val);
 
@@ -342,7 +372,8 @@ impl<'probe> CoreInterface for Armv8m<'probe> {
         let mut breakpoints = vec![];
         let num_hw_breakpoints = self.available_breakpoint_units()? as usize;
         for bp_unit_index in 0..num_hw_breakpoints {
-            let reg_addr = FpCompN::get_mmio_address() + (bp_unit_index * size_of::<u32>()) as u64;
+            let reg_addr = self.state.components.fpb_register::<FpCompN>()
+                + (bp_unit_index * size_of::<u32>()) as u64;
             // The raw breakpoint address as read from memory
             let register_value = self.memory.read_word_32(reg_addr)?;
             // The breakpoint address after it has been adjusted for FpRev 1 or 2
@@ -362,7 +393,7 @@ impl<'probe> CoreInterface for Armv8m<'probe> {
         val.set_enable(state);
 
         self.memory
-            .write_word_32(FpCtrl::get_mmio_address(), val.into())?;
+            .write_word_32(self.state.components.fpb_register::<FpCtrl>(), val.into())?;
         self.memory.flush()?;
 
         self.state.hw_breakpoints_enabled = state;
@@ -381,7 +412,8 @@ impl<'probe> CoreInterface for Armv8m<'probe> {
         val.set_bp_addr(comp_val);
         val.set_enable(true);
 
-        let reg_addr = FpCompN::get_mmio_address() + (bp_unit_index * size_of::<u32>()) as u64;
+        let reg_addr = self.state.components.fpb_register::<FpCompN>()
+            + (bp_unit_index * size_of::<u32>()) as u64;
 
         self.memory.write_word_32(reg_addr, val.into())?;
 
@@ -393,19 +425,58 @@ impl<'probe> CoreInterface for Armv8m<'probe> {
         val.set_enable(false);
         val.set_bp_addr(0);
 
-        let reg_addr = FpCompN::get_mmio_address() + (bp_unit_index * size_of::<u32>()) as u64;
+        let reg_addr = self.state.components.fpb_register::<FpCompN>()
+            + (bp_unit_index * size_of::<u32>()) as u64;
 
         self.memory.write_word_32(reg_addr, val.into())?;
 
         Ok(())
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        super::cortex_m::available_watchpoint_units(&mut *self.memory, self.state.components)
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<u64>>, Error> {
+        super::cortex_m::hw_watchpoints(&mut *self.memory, self.state.components)
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        addr: u64,
+        len: u64,
+        kind: WatchpointKind,
+    ) -> Result<(), Error> {
+        super::cortex_m::set_hw_watchpoint(
+            &mut *self.memory,
+            self.state.components,
+            true,
+            unit_index,
+            addr,
+            len,
+            kind,
+        )
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        super::cortex_m::clear_hw_watchpoint(&mut *self.memory, self.state.components, unit_index)
+    }
+
+    fn triggered_hw_watchpoint(&mut self) -> Result<Option<usize>, Error> {
+        super::cortex_m::triggered_hw_watchpoint(&mut *self.memory, self.state.components)
+    }
+
+    fn read_dwt_comparators(&mut self) -> Result<Vec<DwtComparator>, Error> {
+        super::cortex_m::read_dwt_comparators(&mut *self.memory, self.state.components, true)
+    }
+
+    fn read_mpu_config(&mut self) -> Result<MpuConfig, Error> {
+        super::cortex_m::read_mpu_config(&mut *self.memory, true)
+    }
+
     fn registers(&self) -> &'static CoreRegisters {
-        if self.state.fp_present {
-            &CORTEX_M_WITH_FP_CORE_REGISTERS
-        } else {
-            &CORTEX_M_CORE_REGISTERS
-        }
+        self.state.registers
     }
 
     fn program_counter(&self) -> &'static CoreRegister {
@@ -468,6 +539,14 @@ impl<'probe> CoreInterface for Armv8m<'probe> {
         Ok(())
     }
 
+    #[tracing::instrument(skip(self))]
+    fn reset_init(&mut self) -> Result<(), Error> {
+        self.sequence
+            .reset_init(&mut *self.memory, CoreType::Armv8m, None)?;
+
+        Ok(())
+    }
+
     #[tracing::instrument(skip(self))]
     fn debug_core_stop(&mut self) -> Result<(), Error> {
         self.sequence
@@ -586,6 +665,12 @@ impl<'probe> MemoryInterface for Armv8m<'probe> {
             .map_err(From::<ArmError>::from)
     }
 
+    fn write_word_16(&mut self, address: u64, data: u16) -> Result<(), Error> {
+        self.memory
+            .write_word_16(address, data)
+            .map_err(From::<ArmError>::from)
+    }
+
     fn write_word_8(&mut self, address: u64, data: u8) -> Result<(), Error> {
         self.memory
             .write_word_8(address, data)
@@ -616,6 +701,12 @@ impl<'probe> MemoryInterface for Armv8m<'probe> {
             .map_err(From::<ArmError>::from)
     }
 
+    fn transfer_batch_32(&mut self, transfers: &mut [MemoryTransfer]) -> Result<(), Error> {
+        self.memory
+            .transfer_batch_32(transfers)
+            .map_err(From::<ArmError>::from)
+    }
+
     fn supports_8bit_transfers(&self) -> Result<bool, Error> {
         self.memory
             .supports_8bit_transfers()
@@ -1,10 +1,14 @@
 //! Common functions and data types for Cortex-M core variants
 
+use super::armv7m::Demcr;
+use super::registers::cortex_m::ARMV8M_SECURITY_WITH_FP_CORE_REGISTERS;
+use super::{DwtComparator, MpuAccess, MpuConfig, MpuRegion};
 use crate::{
     architecture::arm::{memory::adi_v5_memory_interface::ArmProbe, ArmError},
     core::RegisterId,
-    memory_mapped_bitfield_register, BreakpointCause, CoreInterface, Error, HaltReason,
-    MemoryMappedRegister,
+    memory::MemoryTransfer,
+    memory_mapped_bitfield_register, BreakpointCause, CoreInterface, CoreStatus, Error, HaltReason,
+    MemoryMappedRegister, WatchpointKind,
 };
 use std::time::{Duration, Instant};
 
@@ -142,17 +146,490 @@ impl IdPfr1 {
     }
 }
 
+/// Architectural base address of the Flash Patch and Breakpoint unit
+const FPB_BASE: u64 = 0xE000_2000;
+/// Architectural base address of the Data Watchpoint and Trace unit
+const DWT_BASE: u64 = 0xE000_1000;
+
+/// Base addresses of the debug components of a Cortex-M core
+///
+/// The registers of these components are defined at their architectural addresses, parts which
+/// relocate a component specify its base address in the target description instead. Only the FPB
+/// and DWT can be relocated, the core debug registers and the DCRSR register selectors are always
+/// the architectural ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ComponentBases {
+    /// Base address of the Flash Patch and Breakpoint unit
+    fpb: u64,
+    /// Base address of the Data Watchpoint and Trace unit
+    dwt: u64,
+}
+
+impl Default for ComponentBases {
+    fn default() -> Self {
+        Self {
+            fpb: FPB_BASE,
+            dwt: DWT_BASE,
+        }
+    }
+}
+
+impl ComponentBases {
+    /// Use the given base addresses, the architectural ones are used for components which are `None`
+    pub fn new(fpb: Option<u64>, dwt: Option<u64>) -> Self {
+        Self {
+            fpb: fpb.unwrap_or(FPB_BASE),
+            dwt: dwt.unwrap_or(DWT_BASE),
+        }
+    }
+
+    /// Address of the FPB register `R`
+    pub fn fpb_register<R: MemoryMappedRegister<u32>>(&self) -> u64 {
+        R::get_mmio_address() - FPB_BASE + self.fpb
+    }
+
+    /// Address of the DWT register `R`
+    pub fn dwt_register<R: MemoryMappedRegister<u32>>(&self) -> u64 {
+        R::get_mmio_address() - DWT_BASE + self.dwt
+    }
+}
+
+memory_mapped_bitfield_register! {
+    /// DWT Control Register
+    pub struct DwtCtrl(u32);
+    0xE000_1000, "DWT_CTRL",
+    impl From;
+    /// Number of comparators implemented
+    pub u8, numcomp, _: 31, 28;
+}
+
+memory_mapped_bitfield_register! {
+    /// DWT Comparator Register, the registers of unit `n` are located `16 * n` bytes after unit 0
+    pub struct DwtComp(u32);
+    0xE000_1020, "DWT_COMP0",
+    impl From;
+}
+
+memory_mapped_bitfield_register! {
+    /// DWT Mask Register, not present on ARMv8-M
+    pub struct DwtMask(u32);
+    0xE000_1024, "DWT_MASK0",
+    impl From;
+    /// Number of least significant address bits ignored by the comparison
+    pub u8, mask, set_mask: 4, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// DWT Function Register
+    pub struct DwtFunction(u32);
+    0xE000_1028, "DWT_FUNCTION0",
+    impl From;
+    /// The comparator matched since this register was last read
+    pub matched, _: 24;
+    /// Size of the watched access on ARMv8-M, as a power of two
+    pub u8, datavsize, set_datavsize: 11, 10;
+    /// Action on a match on ARMv8-M, `0b01` generates a debug event
+    pub u8, action, set_action: 5, 4;
+    /// Type of comparison (MATCH on ARMv8-M), `0` disables the comparator
+    pub u8, function, set_function: 3, 0;
+}
+
+/// Address of the register `R` for the DWT comparator `unit_index`
+fn dwt_register_address<R: MemoryMappedRegister<u32>>(
+    bases: ComponentBases,
+    unit_index: usize,
+) -> u64 {
+    bases.dwt_register::<R>() + (unit_index as u64) * 16
+}
+
+/// Returns the number of DWT comparators, which can be used as data watchpoints.
+///
+/// Works on ARMv6-M, ARMv7-M and ARMv8-M.
+pub(crate) fn available_watchpoint_units(
+    memory: &mut dyn ArmProbe,
+    bases: ComponentBases,
+) -> Result<u32, Error> {
+    let ctrl = DwtCtrl(memory.read_word_32(bases.dwt_register::<DwtCtrl>())?);
+
+    Ok(ctrl.numcomp() as u32)
+}
+
+/// Read the address of every DWT comparator which is in use.
+///
+/// Comparators used for other purposes, e.g. data tracing, are reported as well, so they are not
+/// overwritten by a watchpoint.
+pub(crate) fn hw_watchpoints(
+    memory: &mut dyn ArmProbe,
+    bases: ComponentBases,
+) -> Result<Vec<Option<u64>>, Error> {
+    let num_units = available_watchpoint_units(memory, bases)? as usize;
+
+    // The function and comparator registers of all units are read in a single batch.
+    let mut transfers = (0..num_units)
+        .flat_map(|unit_index| {
+            [
+                MemoryTransfer::read(dwt_register_address::<DwtFunction>(bases, unit_index)),
+                MemoryTransfer::read(dwt_register_address::<DwtComp>(bases, unit_index)),
+            ]
+        })
+        .collect::<Vec<_>>();
+    memory.transfer_batch_32(&mut transfers)?;
+
+    let watchpoints = transfers
+        .chunks_exact(2)
+        .map(|unit| {
+            let function = DwtFunction(unit[0].value);
+
+            (function.function() != 0).then_some(unit[1].value as u64)
+        })
+        .collect();
+
+    Ok(watchpoints)
+}
+
+/// Configure the DWT comparator `unit_index` to halt the core on an access to `len` bytes at `address`.
+///
+/// `len` has to be a power of two and `address` aligned to it. ARMv8-M comparators without
+/// linked comparators can watch at most four bytes.
+pub(crate) fn set_hw_watchpoint(
+    memory: &mut dyn ArmProbe,
+    bases: ComponentBases,
+    armv8m: bool,
+    unit_index: usize,
+    address: u64,
+    len: u64,
+    kind: WatchpointKind,
+) -> Result<(), Error> {
+    let address = u32::try_from(address)
+        .map_err(|_| Error::Other(anyhow::anyhow!("Address {address:#x} is out of range")))?;
+
+    if !len.is_power_of_two() || address as u64 % len != 0 || (armv8m && len > 4) {
+        return Err(Error::Other(anyhow::anyhow!(
+            "Unsupported watchpoint for {len} bytes at {address:#010x}, the length must be a power of two and the address aligned to it"
+        )));
+    }
+
+    // The DWT is only enabled if TRCENA is set
+    let mut demcr = Demcr::from(memory.read_word_32(Demcr::get_mmio_address())?);
+    if !demcr.trcena() {
+        demcr.set_trcena(true);
+        memory.write_word_32(Demcr::get_mmio_address(), demcr.into())?;
+    }
+
+    memory.write_word_32(dwt_register_address::<DwtComp>(bases, unit_index), address)?;
+
+    let size = len.trailing_zeros() as u8;
+    let mut function = DwtFunction(0);
+
+    if armv8m {
+        function.set_datavsize(size);
+        function.set_action(0b01);
+        function.set_function(match kind {
+            WatchpointKind::ReadWrite => 0b0100,
+            WatchpointKind::Write => 0b0101,
+            WatchpointKind::Read => 0b0110,
+        });
+    } else {
+        let mut mask = DwtMask(0);
+        mask.set_mask(size);
+        memory.write_word_32(
+            dwt_register_address::<DwtMask>(bases, unit_index),
+            mask.into(),
+        )?;
+
+        // The number of mask bits is implementation defined, unsupported bits read as zero
+        let mask =
+            DwtMask(memory.read_word_32(dwt_register_address::<DwtMask>(bases, unit_index))?);
+        if mask.mask() != size {
+            return Err(Error::Other(anyhow::anyhow!(
+                "Watchpoints for {len} bytes are not supported by this core"
+            )));
+        }
+
+        function.set_function(match kind {
+            WatchpointKind::Read => 0b0101,
+            WatchpointKind::Write => 0b0110,
+            WatchpointKind::ReadWrite => 0b0111,
+        });
+    }
+
+    memory.write_word_32(
+        dwt_register_address::<DwtFunction>(bases, unit_index),
+        function.into(),
+    )?;
+    memory.flush()?;
+
+    Ok(())
+}
+
+/// Disable the DWT comparator `unit_index`.
+pub(crate) fn clear_hw_watchpoint(
+    memory: &mut dyn ArmProbe,
+    bases: ComponentBases,
+    unit_index: usize,
+) -> Result<(), Error> {
+    memory.write_word_32(dwt_register_address::<DwtFunction>(bases, unit_index), 0)?;
+    memory.flush()?;
+
+    Ok(())
+}
+
+/// Returns the DWT comparator which matched, if any.
+///
+/// Reading the function register clears its `MATCHED` flag, so this only reports a match once.
+pub(crate) fn triggered_hw_watchpoint(
+    memory: &mut dyn ArmProbe,
+    bases: ComponentBases,
+) -> Result<Option<usize>, Error> {
+    let num_units = available_watchpoint_units(memory, bases)? as usize;
+
+    for unit_index in 0..num_units {
+        let function = DwtFunction(
+            memory.read_word_32(dwt_register_address::<DwtFunction>(bases, unit_index))?,
+        );
+
+        if function.function() != 0 && function.matched() {
+            return Ok(Some(unit_index));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Read the state of every DWT comparator.
+///
+/// Reading the function register clears its `MATCHED` flag, so a match is only reported once,
+/// including to [triggered_hw_watchpoint].
+pub(crate) fn read_dwt_comparators(
+    memory: &mut dyn ArmProbe,
+    bases: ComponentBases,
+    armv8m: bool,
+) -> Result<Vec<DwtComparator>, Error> {
+    let num_units = available_watchpoint_units(memory, bases)? as usize;
+
+    // The registers of all units are read in a single batch, ARMv8-M has no mask register.
+    let registers_per_unit = if armv8m { 2 } else { 3 };
+
+    let mut transfers = Vec::with_capacity(num_units * registers_per_unit);
+    for unit_index in 0..num_units {
+        transfers.push(MemoryTransfer::read(dwt_register_address::<DwtComp>(
+            bases, unit_index,
+        )));
+        if !armv8m {
+            transfers.push(MemoryTransfer::read(dwt_register_address::<DwtMask>(
+                bases, unit_index,
+            )));
+        }
+        transfers.push(MemoryTransfer::read(dwt_register_address::<DwtFunction>(
+            bases, unit_index,
+        )));
+    }
+    memory.transfer_batch_32(&mut transfers)?;
+
+    let comparators = transfers
+        .chunks_exact(registers_per_unit)
+        .map(|unit| {
+            let function = DwtFunction(unit[registers_per_unit - 1].value);
+
+            DwtComparator {
+                address: unit[0].value,
+                mask: (!armv8m).then(|| DwtMask(unit[1].value).mask()),
+                function: function.function(),
+                matched: function.matched(),
+            }
+        })
+        .collect();
+
+    Ok(comparators)
+}
+
+memory_mapped_bitfield_register! {
+    /// MPU Type Register
+    pub struct MpuType(u32);
+    0xE000_ED90, "MPU_TYPE",
+    impl From;
+    /// Number of regions supported by the MPU, `0` if there is no MPU
+    pub u8, dregion, _: 15, 8;
+}
+
+memory_mapped_bitfield_register! {
+    /// MPU Control Register
+    pub struct MpuCtrl(u32);
+    0xE000_ED94, "MPU_CTRL",
+    impl From;
+    /// Use the default memory map for privileged accesses outside of all regions
+    pub privdefena, _: 2;
+    /// Keep the MPU enabled in HardFault and NMI handlers
+    pub hfnmiena, _: 1;
+    pub enable, _: 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// MPU Region Number Register, selects the region accessed by `MPU_RBAR` and `MPU_RASR`
+    pub struct MpuRnr(u32);
+    0xE000_ED98, "MPU_RNR",
+    impl From;
+    pub u8, region, set_region: 7, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// MPU Region Base Address Register
+    pub struct MpuRbar(u32);
+    0xE000_ED9C, "MPU_RBAR",
+    impl From;
+    /// Access permissions on ARMv8-M
+    pub u8, ap_v8m, _: 2, 1;
+    /// Execute never on ARMv8-M
+    pub xn_v8m, _: 0;
+}
+
+impl MpuRbar {
+    /// The base address of the region
+    pub fn base(&self) -> u32 {
+        self.0 & !0x1f
+    }
+}
+
+memory_mapped_bitfield_register! {
+    /// MPU Region Attribute and Size Register, the Region Limit Address Register (`MPU_RLAR`)
+    /// on ARMv8-M
+    pub struct MpuRasr(u32);
+    0xE000_EDA0, "MPU_RASR",
+    impl From;
+    pub xn, _: 28;
+    pub u8, ap, _: 26, 24;
+    /// Subregion disable bits
+    pub u8, srd, _: 15, 8;
+    /// The region size is `2^(SIZE + 1)` bytes
+    pub u8, size, _: 5, 1;
+    pub enable, _: 0;
+}
+
+/// Decode the `AP` field of `MPU_RASR` of ARMv6-M and ARMv7-M
+fn mpu_access_pmsav7(ap: u8) -> MpuAccess {
+    match ap {
+        0b000 => MpuAccess::NoAccess,
+        0b001 => MpuAccess::PrivilegedReadWrite,
+        0b010 => MpuAccess::PrivilegedReadWriteUnprivilegedReadOnly,
+        0b011 => MpuAccess::ReadWrite,
+        0b101 => MpuAccess::PrivilegedReadOnly,
+        0b110 | 0b111 => MpuAccess::ReadOnly,
+        ap => MpuAccess::Reserved(ap),
+    }
+}
+
+/// Decode the `AP` field of `MPU_RBAR` of ARMv8-M
+fn mpu_access_pmsav8(ap: u8) -> MpuAccess {
+    match ap {
+        0b00 => MpuAccess::PrivilegedReadWrite,
+        0b01 => MpuAccess::ReadWrite,
+        0b10 => MpuAccess::PrivilegedReadOnly,
+        _ => MpuAccess::ReadOnly,
+    }
+}
+
+/// Read the MPU configuration and the settings of all its regions.
+///
+/// The regions are selected with `MPU_RNR`, which is restored afterwards.
+pub(crate) fn read_mpu_config(memory: &mut dyn ArmProbe, armv8m: bool) -> Result<MpuConfig, Error> {
+    let mut transfers = [
+        MemoryTransfer::read(MpuType::get_mmio_address()),
+        MemoryTransfer::read(MpuCtrl::get_mmio_address()),
+        MemoryTransfer::read(MpuRnr::get_mmio_address()),
+    ];
+    memory.transfer_batch_32(&mut transfers)?;
+
+    let mpu_type = MpuType(transfers[0].value);
+    let ctrl = MpuCtrl(transfers[1].value);
+    let rnr = transfers[2].value;
+
+    let mut config = MpuConfig {
+        enabled: ctrl.enable(),
+        enabled_in_fault_handlers: ctrl.hfnmiena(),
+        privileged_default_map: ctrl.privdefena(),
+        regions: Vec::with_capacity(mpu_type.dregion() as usize),
+    };
+
+    if mpu_type.dregion() == 0 {
+        return Ok(config);
+    }
+
+    // All regions are selected and read in a single batch, which restores MPU_RNR at the end.
+    let mut transfers = (0..mpu_type.dregion())
+        .flat_map(|number| {
+            let mut select = MpuRnr(0);
+            select.set_region(number);
+
+            [
+                MemoryTransfer::write(MpuRnr::get_mmio_address(), select.into()),
+                MemoryTransfer::read(MpuRbar::get_mmio_address()),
+                MemoryTransfer::read(MpuRasr::get_mmio_address()),
+            ]
+        })
+        .chain([MemoryTransfer::write(MpuRnr::get_mmio_address(), rnr)])
+        .collect::<Vec<_>>();
+    memory.transfer_batch_32(&mut transfers)?;
+
+    for (number, registers) in (0..mpu_type.dregion()).zip(transfers.chunks_exact(3)) {
+        let rbar = MpuRbar(registers[1].value);
+        let rasr = MpuRasr(registers[2].value);
+
+        let region = if armv8m {
+            // On ARMv8-M the second register is MPU_RLAR, which holds the inclusive limit
+            let limit = rasr.0 | 0x1f;
+
+            MpuRegion {
+                number,
+                base: rbar.base(),
+                size: u64::from(limit).saturating_sub(u64::from(rbar.base())) + 1,
+                disabled_subregions: 0,
+                access: mpu_access_pmsav8(rbar.ap_v8m()),
+                execute_never: rbar.xn_v8m(),
+                enabled: rasr.enable(),
+            }
+        } else {
+            MpuRegion {
+                number,
+                base: rbar.base(),
+                size: 1 << (rasr.size() + 1),
+                disabled_subregions: rasr.srd(),
+                access: mpu_access_pmsav7(rasr.ap()),
+                execute_never: rasr.xn(),
+                enabled: rasr.enable(),
+            }
+        };
+
+        config.regions.push(region);
+    }
+
+    Ok(config)
+}
+
 pub(crate) fn read_core_reg(memory: &mut dyn ArmProbe, addr: RegisterId) -> Result<u32, Error> {
-    // Write the DCRSR value to select the register we want to read.
-    let mut dcrsr_val = Dcrsr(0);
-    dcrsr_val.set_regwnr(false); // Perform a read.
-    dcrsr_val.set_regsel(addr.into()); // The address of the register to read.
+    let value = retry_register_transfer(|| {
+        // Write the DCRSR value to select the register we want to read.
+        let mut dcrsr_val = Dcrsr(0);
+        dcrsr_val.set_regwnr(false); // Perform a read.
+        dcrsr_val.set_regsel(addr.into()); // The address of the register to read.
+
+        // Most of the time the register is ready when DHCSR is checked for the first time,
+        // so DCRDR is read in the same batch, saving two round trips.
+        let mut transfers = [
+            MemoryTransfer::write(Dcrsr::get_mmio_address(), dcrsr_val.into()),
+            MemoryTransfer::read(Dhcsr::get_mmio_address()),
+            MemoryTransfer::read(Dcrdr::get_mmio_address()),
+        ];
 
-    memory.write_word_32(Dcrsr::get_mmio_address(), dcrsr_val.into())?;
+        memory.transfer_batch_32(&mut transfers)?;
 
-    wait_for_core_register_transfer(memory, Duration::from_millis(100))?;
+        if Dhcsr(transfers[1].value).s_regrdy() {
+            return Ok(transfers[2].value);
+        }
 
-    let value = memory.read_word_32(Dcrdr::get_mmio_address())?;
+        wait_for_core_register_transfer(memory, addr, Duration::from_millis(100))?;
+
+        memory.read_word_32(Dcrdr::get_mmio_address())
+    })?;
 
     Ok(value)
 }
@@ -162,20 +639,53 @@ pub(crate) fn write_core_reg(
     addr: RegisterId,
     value: u32,
 ) -> Result<(), Error> {
-    memory.write_word_32(Dcrdr::get_mmio_address(), value)?;
+    retry_register_transfer(|| {
+        // write the DCRSR value to select the register we want to write.
+        let mut dcrsr_val = Dcrsr(0);
+        dcrsr_val.set_regwnr(true); // Perform a write.
+        dcrsr_val.set_regsel(addr.into()); // The address of the register to write.
 
-    // write the DCRSR value to select the register we want to write.
-    let mut dcrsr_val = Dcrsr(0);
-    dcrsr_val.set_regwnr(true); // Perform a write.
-    dcrsr_val.set_regsel(addr.into()); // The address of the register to write.
+        // The batch is executed before its result is returned, so the transfer is issued before
+        // polling for its completion, and a following resume can't use the old register value.
+        let mut transfers = [
+            MemoryTransfer::write(Dcrdr::get_mmio_address(), value),
+            MemoryTransfer::write(Dcrsr::get_mmio_address(), dcrsr_val.into()),
+            MemoryTransfer::read(Dhcsr::get_mmio_address()),
+        ];
 
-    memory.write_word_32(Dcrsr::get_mmio_address(), dcrsr_val.into())?;
+        memory.transfer_batch_32(&mut transfers)?;
 
-    wait_for_core_register_transfer(memory, Duration::from_millis(100))?;
+        if Dhcsr(transfers[2].value).s_regrdy() {
+            return Ok(());
+        }
+
+        wait_for_core_register_transfer(memory, addr, Duration::from_millis(100))
+    })?;
 
     Ok(())
 }
 
+/// Run a core register transfer, and run it again once if the core doesn't complete it in time
+///
+/// Some probes intermittently fail to observe S_REGRDY, so the whole DCRSR sequence is repeated
+/// before the timeout is reported.
+fn retry_register_transfer<T>(
+    mut transfer: impl FnMut() -> Result<T, ArmError>,
+) -> Result<T, ArmError> {
+    match transfer() {
+        Err(ArmError::RegisterTransferTimeout { name, selector }) => {
+            tracing::debug!(
+                "Transfer of core register {} ({:#04x}) timed out, retrying",
+                name,
+                selector
+            );
+
+            transfer()
+        }
+        result => result,
+    }
+}
+
 /// Check if the current breakpoint is a semihosting call. Does nothing unless feature rtt is enabled.
 ///
 /// Call this if you get some kind of breakpoint. Works on ARMv6-M, ARMv7-M and ARMv8-M.
@@ -222,8 +732,102 @@ pub(crate) fn check_for_semihosting(
     Ok(reason)
 }
 
+memory_mapped_bitfield_register! {
+    /// CPUID Base Register
+    pub struct Cpuid(u32);
+    0xE000_ED00, "CPUID",
+    impl From;
+    pub implementer, _: 31, 24;
+    pub partno, _: 15, 4;
+}
+
+/// Part number of the Cortex-M7 in [Cpuid]
+pub(crate) const CORTEX_M7_PARTNO: u32 = 0xC27;
+
+memory_mapped_bitfield_register! {
+    /// Configuration and Control Register
+    pub struct Ccr(u32);
+    0xE000_ED14, "CCR",
+    impl From;
+    /// Data cache enabled
+    pub dc, _: 16;
+}
+
+memory_mapped_bitfield_register! {
+    /// Cache Type Register
+    pub struct Ctr(u32);
+    0xE000_ED7C, "CTR",
+    impl From;
+    /// Log2 of the number of words in the smallest data cache line
+    pub dminline, _: 19, 16;
+}
+
+/// Data cache clean by address to the point of coherency, DCCMVAC
+const DCCMVAC: u64 = 0xE000_EF68;
+/// Data cache invalidate by address to the point of coherency, DCIMVAC
+const DCIMVAC: u64 = 0xE000_EF5C;
+
+/// Data cache maintenance by address
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CacheMaintenance {
+    /// Write dirty cache lines back to memory, so the debugger reads the current values
+    Clean,
+    /// Discard cache lines, so the core sees the values written by the debugger
+    Invalidate,
+}
+
+/// Perform data cache maintenance on all cache lines covering `len` bytes at `address`
+///
+/// Nothing is done if the data cache is disabled, or for the system region at `0xE000_0000`
+/// and above, which is never cached.
+pub(crate) fn dcache_maintenance(
+    memory: &mut dyn ArmProbe,
+    operation: CacheMaintenance,
+    address: u64,
+    len: usize,
+) -> Result<(), ArmError> {
+    if len == 0 || address >= 0xE000_0000 {
+        return Ok(());
+    }
+
+    let ccr = Ccr(memory.read_word_32(Ccr::get_mmio_address())?);
+    if !ccr.dc() {
+        return Ok(());
+    }
+
+    let ctr = Ctr(memory.read_word_32(Ctr::get_mmio_address())?);
+    let line_size = 4u64 << ctr.dminline();
+
+    let register = match operation {
+        CacheMaintenance::Clean => DCCMVAC,
+        CacheMaintenance::Invalidate => DCIMVAC,
+    };
+
+    let end = (address + len as u64).min(0xE000_0000);
+    let mut line = address & !(line_size - 1);
+    while line < end {
+        memory.write_word_32(register, line as u32)?;
+        line += line_size;
+    }
+
+    memory.flush()
+}
+
+/// The error returned when a core didn't halt in time
+///
+/// A locked up core doesn't halt on its own, e.g. after a step, so this is reported
+/// instead of a plain timeout.
+pub(crate) fn halt_timeout_error(current_state: &CoreStatus) -> ArmError {
+    if matches!(current_state, CoreStatus::LockedUp) {
+        ArmError::CoreLockedUp
+    } else {
+        ArmError::Timeout
+    }
+}
+
 fn wait_for_core_register_transfer(
     memory: &mut dyn ArmProbe,
+    addr: RegisterId,
     timeout: Duration,
 ) -> Result<(), ArmError> {
     // now we have to poll the dhcsr register, until the dhcsr.s_regrdy bit is set
@@ -237,5 +841,224 @@ fn wait_for_core_register_transfer(
             return Ok(());
         }
     }
-    Err(ArmError::Timeout)
+
+    let name = ARMV8M_SECURITY_WITH_FP_CORE_REGISTERS
+        .all_registers()
+        .find(|register| register.id() == addr)
+        .map_or("<unknown>", |register| register.name());
+
+    Err(ArmError::RegisterTransferTimeout {
+        name,
+        selector: addr.0,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn register_transfer_is_retried_once() {
+        let mut attempts = 0;
+        let result = retry_register_transfer(|| {
+            attempts += 1;
+
+            if attempts == 1 {
+                Err(ArmError::RegisterTransferTimeout {
+                    name: "R0",
+                    selector: 0,
+                })
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert!(matches!(result, Ok(2)));
+
+        let mut attempts = 0;
+        let result: Result<(), _> = retry_register_transfer(|| {
+            attempts += 1;
+
+            Err(ArmError::RegisterTransferTimeout {
+                name: "R0",
+                selector: 0,
+            })
+        });
+        assert!(matches!(
+            result,
+            Err(ArmError::RegisterTransferTimeout { name: "R0", .. })
+        ));
+        assert_eq!(attempts, 2);
+
+        let mut attempts = 0;
+        let result: Result<(), _> = retry_register_transfer(|| {
+            attempts += 1;
+
+            Err(ArmError::CoreNotHalted)
+        });
+        assert!(matches!(result, Err(ArmError::CoreNotHalted)));
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn read_core_register_after_polling() {
+        use super::super::replay::{Op::*, ReplayMemory};
+
+        let mut memory = ReplayMemory::new([
+            (Write, 0xE000_EDF4, 0x0000_0002),
+            // S_REGRDY is not set yet, so the DCRDR value of the batch is ignored
+            (Read, 0xE000_EDF0, 0x0002_0000),
+            (Read, 0xE000_EDF8, 0xDEAD_BEEF),
+            (Read, 0xE000_EDF0, 0x0003_0000),
+            (Read, 0xE000_EDF8, 0x1234_5678),
+        ]);
+
+        let value = read_core_reg(&mut &mut memory, RegisterId(2)).unwrap();
+
+        assert_eq!(value, 0x1234_5678);
+        memory.assert_finished();
+    }
+
+    #[test]
+    fn write_core_register_completes_before_returning() {
+        use super::super::replay::{Op::*, ReplayMemory};
+
+        let mut memory = ReplayMemory::new([
+            // Write the PC, the value has to be in DCRDR before the write is started
+            (Write, 0xE000_EDF8, 0x0000_1234),
+            (Write, 0xE000_EDF4, 0x0001_000F),
+            // S_REGRDY is not set yet, so the write is still in progress
+            (Read, 0xE000_EDF0, 0x0002_0000),
+            (Read, 0xE000_EDF0, 0x0003_0000),
+        ]);
+
+        write_core_reg(&mut &mut memory, RegisterId(15), 0x1234).unwrap();
+
+        // Nothing is left to be flushed, a following resume uses the new value
+        memory.assert_finished();
+    }
+
+    #[test]
+    fn halt_timeout_of_locked_up_core() {
+        assert!(matches!(
+            halt_timeout_error(&CoreStatus::LockedUp),
+            ArmError::CoreLockedUp
+        ));
+        assert!(matches!(
+            halt_timeout_error(&CoreStatus::Running),
+            ArmError::Timeout
+        ));
+    }
+
+    #[test]
+    fn read_dwt_comparator_state() {
+        use super::super::replay::{Op::*, ReplayMemory};
+
+        let mut memory = ReplayMemory::new([
+            (Read, 0xE000_1000, 0x2000_0000),
+            // Comparator 0 watches writes to 4 bytes and matched
+            (Read, 0xE000_1020, 0x2000_0100),
+            (Read, 0xE000_1024, 0x0000_0002),
+            (Read, 0xE000_1028, 0x0100_0006),
+            // Comparator 1 is disabled
+            (Read, 0xE000_1030, 0x0000_0000),
+            (Read, 0xE000_1034, 0x0000_0000),
+            (Read, 0xE000_1038, 0x0000_0000),
+        ]);
+
+        let comparators =
+            read_dwt_comparators(&mut &mut memory, ComponentBases::default(), false).unwrap();
+        memory.assert_finished();
+
+        assert_eq!(
+            comparators,
+            [
+                DwtComparator {
+                    address: 0x2000_0100,
+                    mask: Some(2),
+                    function: 0b0110,
+                    matched: true,
+                },
+                DwtComparator {
+                    address: 0,
+                    mask: Some(0),
+                    function: 0,
+                    matched: false,
+                },
+            ]
+        );
+        assert!(comparators[0].is_enabled());
+        assert!(!comparators[1].is_enabled());
+    }
+
+    #[test]
+    fn mpu_config() {
+        use super::super::replay::{Op::*, ReplayMemory};
+
+        let mut memory = ReplayMemory::new([
+            // Two regions, MPU and default map enabled
+            (Read, 0xE000_ED90, 0x0000_0200),
+            (Read, 0xE000_ED94, 0x0000_0005),
+            (Read, 0xE000_ED98, 0x0000_0001),
+            // Region 0: 256 KiB of flash at 0, read-only for all code
+            (Write, 0xE000_ED98, 0x0000_0000),
+            (Read, 0xE000_ED9C, 0x0000_0000),
+            (Read, 0xE000_EDA0, 0x0602_0023),
+            // Region 1: 32 bytes at 0x2000_0000, no access, execute never (stack guard)
+            (Write, 0xE000_ED98, 0x0000_0001),
+            (Read, 0xE000_ED9C, 0x2000_0001),
+            (Read, 0xE000_EDA0, 0x1000_0009),
+            (Write, 0xE000_ED98, 0x0000_0001),
+        ]);
+
+        let config = read_mpu_config(&mut &mut memory, false).unwrap();
+        memory.assert_finished();
+
+        assert!(config.enabled);
+        assert!(!config.enabled_in_fault_handlers);
+        assert!(config.privileged_default_map);
+        assert_eq!(
+            config.regions,
+            [
+                MpuRegion {
+                    number: 0,
+                    base: 0,
+                    size: 0x4_0000,
+                    disabled_subregions: 0,
+                    access: MpuAccess::ReadOnly,
+                    execute_never: false,
+                    enabled: true,
+                },
+                MpuRegion {
+                    number: 1,
+                    base: 0x2000_0000,
+                    size: 32,
+                    disabled_subregions: 0,
+                    access: MpuAccess::NoAccess,
+                    execute_never: true,
+                    enabled: true,
+                },
+            ]
+        );
+
+        assert_eq!(config.region_for(0x1000).map(|r| r.number), Some(0));
+        assert_eq!(config.region_for(0x2000_0010).map(|r| r.number), Some(1));
+        assert_eq!(config.region_for(0x2000_0020), None);
+    }
+
+    #[test]
+    fn architectural_component_registers() {
+        let bases = ComponentBases::default();
+
+        assert_eq!(bases.dwt_register::<DwtCtrl>(), 0xE000_1000);
+        assert_eq!(dwt_register_address::<DwtFunction>(bases, 1), 0xE000_1038);
+    }
+
+    #[test]
+    fn relocated_component_registers() {
+        let bases = ComponentBases::new(None, Some(0xE004_1000));
+
+        assert_eq!(bases.fpb, FPB_BASE);
+        assert_eq!(bases.dwt_register::<DwtCtrl>(), 0xE004_1000);
+        assert_eq!(dwt_register_address::<DwtComp>(bases, 2), 0xE004_1040);
+    }
 }
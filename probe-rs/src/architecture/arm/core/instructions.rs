@@ -1,4 +1,51 @@
 //! Contains helpers to build instructions for debugger use
+pub(crate) mod thumb {
+    /// Returns the length of the instruction in `code` if it is a function call.
+    ///
+    /// `code` holds the first two halfwords of the instruction, in target byte order (little
+    /// endian). The calls are `BL` and `BLX <label>`, which are 4 bytes long, and `BLX <Rm>`,
+    /// which is 2 bytes long. The call returns to the address after the instruction.
+    pub(crate) fn call_length(code: [u8; 4]) -> Option<u64> {
+        let hw1 = u16::from_le_bytes([code[0], code[1]]);
+        let hw2 = u16::from_le_bytes([code[2], code[3]]);
+
+        if hw1 & 0xff87 == 0x4780 {
+            // BLX <Rm>
+            Some(2)
+        } else if hw1 & 0xf800 == 0xf000 && hw2 & 0xc000 == 0xc000 {
+            // BL <label> has bit 12 of the second halfword set, BLX <label> has it cleared
+            Some(4)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn detect_calls() {
+            // BL #0x1c4
+            assert_eq!(call_length([0x00, 0xf0, 0xe2, 0xf8]), Some(4));
+            // BLX #0x8
+            assert_eq!(call_length([0x00, 0xf0, 0x04, 0xe8]), Some(4));
+            // BLX r3
+            assert_eq!(call_length([0x98, 0x47, 0x00, 0xbf]), Some(2));
+        }
+
+        #[test]
+        fn ignore_other_instructions() {
+            // BX lr
+            assert_eq!(call_length([0x70, 0x47, 0x00, 0xbf]), None);
+            // B.W #0x1c4
+            assert_eq!(call_length([0x00, 0xf0, 0xe2, 0xb8]), None);
+            // MOVS r0, #1; NOP
+            assert_eq!(call_length([0x01, 0x20, 0x00, 0xbf]), None);
+        }
+    }
+}
+
 pub(crate) mod aarch32 {
     /// Build a MOV instruction
     pub(crate) fn build_mov(rd: u16, rm: u16) -> u32 {
@@ -1,9 +1,11 @@
 //! The different ARM core implementations with all constants and custom handling.
 
+use self::cortex_m::ComponentBases;
 use crate::{
     core::{BreakpointCause, RegisterValue},
-    memory_mapped_bitfield_register, CoreStatus, HaltReason,
+    memory_mapped_bitfield_register, CoreRegisters, CoreStatus, HaltReason,
 };
+use std::time::Duration;
 
 pub mod armv6m;
 pub mod armv7a;
@@ -18,6 +20,130 @@ pub(crate) mod exception_handling;
 pub(crate) mod instructions;
 pub(crate) mod registers;
 
+#[cfg(test)]
+pub(crate) mod replay;
+
+/// The state of a comparator of the Data Watchpoint and Trace (DWT) unit of a Cortex-M core.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DwtComparator {
+    /// The address compared against, from the `DWT_COMPn` register.
+    pub address: u32,
+    /// The number of least significant address bits ignored by the comparison, from the
+    /// `DWT_MASKn` register. ARMv8-M cores have no mask register, so this is `None` for them.
+    pub mask: Option<u8>,
+    /// The `FUNCTION` (or `MATCH` on ARMv8-M) field of the `DWT_FUNCTIONn` register.
+    /// `0` means the comparator is disabled.
+    pub function: u8,
+    /// The comparator matched since its function register was last read.
+    pub matched: bool,
+}
+
+impl DwtComparator {
+    /// Returns `true` if the comparator is in use.
+    pub fn is_enabled(&self) -> bool {
+        self.function != 0
+    }
+}
+
+/// The configuration of the Memory Protection Unit (MPU) of a Cortex-M core.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MpuConfig {
+    /// The MPU is enabled, from the `ENABLE` bit of `MPU_CTRL`.
+    pub enabled: bool,
+    /// The MPU stays enabled in HardFault and NMI handlers, from the `HFNMIENA` bit of `MPU_CTRL`.
+    pub enabled_in_fault_handlers: bool,
+    /// Privileged code can access memory outside of all regions with the default memory map,
+    /// from the `PRIVDEFENA` bit of `MPU_CTRL`.
+    pub privileged_default_map: bool,
+    /// All regions of the MPU, including the disabled ones. The number of regions is read from
+    /// `MPU_TYPE`, a core without an MPU has none.
+    pub regions: Vec<MpuRegion>,
+}
+
+impl MpuConfig {
+    /// Returns the enabled region which determines the access permissions for `address`.
+    ///
+    /// If regions overlap, the one with the highest number takes precedence. `None` means that
+    /// no region covers the address, so an access by unprivileged code faults.
+    pub fn region_for(&self, address: u32) -> Option<&MpuRegion> {
+        self.regions
+            .iter()
+            .filter(|region| region.enabled && region.contains(address))
+            .last()
+    }
+}
+
+/// A region of the Memory Protection Unit of a Cortex-M core.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MpuRegion {
+    /// The number of the region.
+    pub number: u8,
+    /// The first address of the region.
+    pub base: u32,
+    /// The size of the region in bytes.
+    pub size: u64,
+    /// Bitmask of the disabled subregions, from the `SRD` field of `MPU_RASR`.
+    ///
+    /// Bit `n` disables the `n`th eighth of the region. ARMv8-M has no subregions,
+    /// so this is always `0` there.
+    pub disabled_subregions: u8,
+    /// The access permissions of privileged and unprivileged code.
+    pub access: MpuAccess,
+    /// Instructions can't be fetched from the region.
+    pub execute_never: bool,
+    /// The region is enabled.
+    pub enabled: bool,
+}
+
+impl MpuRegion {
+    /// Returns `true` if `address` is part of the region and not in a disabled subregion.
+    pub fn contains(&self, address: u32) -> bool {
+        let (address, base) = (u64::from(address), u64::from(self.base));
+
+        if address < base || address - base >= self.size {
+            return false;
+        }
+
+        let subregion = (address - base) / (self.size / 8).max(1);
+        self.disabled_subregions & (1 << subregion) == 0
+    }
+}
+
+/// Access permissions of an MPU region, decoded from its `AP` field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MpuAccess {
+    /// No access for any code.
+    NoAccess,
+    /// Read and write access for privileged code, no access for unprivileged code.
+    PrivilegedReadWrite,
+    /// Read and write access for privileged code, read only for unprivileged code.
+    PrivilegedReadWriteUnprivilegedReadOnly,
+    /// Read and write access for all code.
+    ReadWrite,
+    /// Read only for privileged code, no access for unprivileged code.
+    PrivilegedReadOnly,
+    /// Read only for all code.
+    ReadOnly,
+    /// A reserved encoding of the `AP` field.
+    Reserved(u8),
+}
+
+impl std::fmt::Display for MpuAccess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MpuAccess::NoAccess => write!(f, "no access"),
+            MpuAccess::PrivilegedReadWrite => write!(f, "privileged read/write"),
+            MpuAccess::PrivilegedReadWriteUnprivilegedReadOnly => {
+                write!(f, "privileged read/write, unprivileged read-only")
+            }
+            MpuAccess::ReadWrite => write!(f, "read/write"),
+            MpuAccess::PrivilegedReadOnly => write!(f, "privileged read-only"),
+            MpuAccess::ReadOnly => write!(f, "read-only"),
+            MpuAccess::Reserved(ap) => write!(f, "reserved ({ap:#05b})"),
+        }
+    }
+}
+
 /// Core information data which is downloaded from the target, represents its state and can be used for debugging.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Dump {
@@ -123,6 +249,12 @@ impl From<Dfsr> for u32 {
     }
 }
 
+/// Default time to wait for a Cortex-M core to halt after a reset
+///
+/// This is much longer than halting a running core, as some parts take a while to come out of
+/// reset, e.g. to lock a PLL or to run a boot ROM.
+const DEFAULT_RESET_CATCH_TIMEOUT: Duration = Duration::from_secs(1);
+
 /// The state cache of a Cortex-M core.
 ///
 /// This state is used internally to not having to poll the core constantly.
@@ -135,6 +267,18 @@ pub struct CortexMState {
     current_state: CoreStatus,
 
     fp_present: bool,
+
+    /// Registers of this core, depending on the implemented extensions
+    registers: &'static CoreRegisters,
+
+    /// Keep debugger memory accesses coherent with the data cache
+    cache_coherent: bool,
+
+    /// Base addresses of the FPB and DWT
+    components: ComponentBases,
+
+    /// How long to wait for the core to halt after a reset with the reset vector catch
+    reset_catch_timeout: Duration,
 }
 
 impl CortexMState {
@@ -144,9 +288,18 @@ impl CortexMState {
             hw_breakpoints_enabled: false,
             current_state: CoreStatus::Unknown,
             fp_present: false,
+            registers: registers::cortex_m::cortex_m_core_registers(false, false),
+            cache_coherent: false,
+            components: ComponentBases::default(),
+            reset_catch_timeout: DEFAULT_RESET_CATCH_TIMEOUT,
         }
     }
 
+    /// Use the FPB and DWT at the given base addresses, e.g. for parts which relocate them
+    pub(crate) fn set_component_bases(&mut self, bases: ComponentBases) {
+        self.components = bases;
+    }
+
     fn initialize(&mut self) {
         self.initialized = true;
     }
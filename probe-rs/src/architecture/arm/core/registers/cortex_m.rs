@@ -61,6 +61,43 @@ pub(crate) static CORTEX_M_WITH_FP_CORE_REGISTERS: Lazy<CoreRegisters> = Lazy::n
     )
 });
 
+/// All of the ARMv8-M core registers, for cores which implement the Security Extension.
+pub(crate) static ARMV8M_SECURITY_CORE_REGISTERS: Lazy<CoreRegisters> = Lazy::new(|| {
+    CoreRegisters::new(
+        ARM32_COMMON_REGS_SET
+            .iter()
+            .chain(CORTEX_M_COMMON_REGS_SET)
+            .chain(ARMV8M_SECURITY_REGS_SET)
+            .collect(),
+    )
+});
+
+pub(crate) static ARMV8M_SECURITY_WITH_FP_CORE_REGISTERS: Lazy<CoreRegisters> = Lazy::new(|| {
+    CoreRegisters::new(
+        ARM32_COMMON_REGS_SET
+            .iter()
+            .chain(CORTEX_M_COMMON_REGS_SET)
+            .chain(ARMV8M_SECURITY_REGS_SET)
+            .chain(CORTEX_M_WITH_FP_REGS_SET)
+            .collect(),
+    )
+});
+
+/// Select the registers of a Cortex-M core, depending on the extensions it implements.
+///
+/// The register set is determined once per core, when the core is first attached.
+pub(crate) fn cortex_m_core_registers(
+    fp_present: bool,
+    security_present: bool,
+) -> &'static CoreRegisters {
+    match (fp_present, security_present) {
+        (false, false) => &CORTEX_M_CORE_REGISTERS,
+        (true, false) => &CORTEX_M_WITH_FP_CORE_REGISTERS,
+        (false, true) => &ARMV8M_SECURITY_CORE_REGISTERS,
+        (true, true) => &ARMV8M_SECURITY_WITH_FP_CORE_REGISTERS,
+    }
+}
+
 pub(super) static ARM32_COMMON_REGS_SET: &[CoreRegister] = &[
     CoreRegister {
         roles: &[
@@ -172,6 +209,59 @@ static CORTEX_M_COMMON_REGS_SET: &[CoreRegister] = &[
     },
 ];
 
+/// Banked stack pointers and stack limit registers of the Security Extension,
+/// see the DCRSR register in the ARMv8-M Architecture Reference Manual.
+static ARMV8M_SECURITY_REGS_SET: &[CoreRegister] = &[
+    CoreRegister {
+        roles: &[RegisterRole::Core("MSP_NS")],
+        id: RegisterId(0b11000),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Preserve,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("PSP_NS")],
+        id: RegisterId(0b11001),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Preserve,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("MSP_S")],
+        id: RegisterId(0b11010),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Preserve,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("PSP_S")],
+        id: RegisterId(0b11011),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Preserve,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("MSPLIM_S")],
+        id: RegisterId(0b11100),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Preserve,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("PSPLIM_S")],
+        id: RegisterId(0b11101),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Preserve,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("MSPLIM_NS")],
+        id: RegisterId(0b11110),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Preserve,
+    },
+    CoreRegister {
+        roles: &[RegisterRole::Core("PSPLIM_NS")],
+        id: RegisterId(0b11111),
+        data_type: RegisterDataType::UnsignedInteger(32),
+        unwind_rule: UnwindRule::Preserve,
+    },
+];
+
 static CORTEX_M_WITH_FP_REGS_SET: &[CoreRegister] = &[
     CoreRegister {
         roles: &[
@@ -0,0 +1,156 @@
+//! Replay of recorded memory transactions, to test the exact register accesses of the core
+//! implementations without hardware.
+
+use std::collections::VecDeque;
+
+use crate::{
+    architecture::arm::{
+        ap::MemoryAp,
+        communication_interface::{Initialized, SwdSequence},
+        memory::adi_v5_memory_interface::ArmProbe,
+        ArmCommunicationInterface, ArmError,
+    },
+    CoreStatus, DebugProbeError,
+};
+
+/// The kind of a recorded memory transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Read,
+    Write,
+}
+
+/// A memory interface which replays a recorded list of 32-bit transactions.
+///
+/// Every access is checked against the next `(op, address, value)` entry of the recording.
+/// Reads return the recorded value, writes have to match it. Any other access panics, so a
+/// test fails as soon as the order of the accesses changes.
+#[derive(Debug)]
+pub struct ReplayMemory {
+    transactions: VecDeque<(Op, u64, u32)>,
+    /// Number of transactions replayed so far, used for the panic messages
+    position: usize,
+}
+
+impl ReplayMemory {
+    pub fn new(transactions: impl IntoIterator<Item = (Op, u64, u32)>) -> Self {
+        Self {
+            transactions: transactions.into_iter().collect(),
+            position: 0,
+        }
+    }
+
+    /// Panic if the recording contains transactions which were not replayed.
+    pub fn assert_finished(&self) {
+        assert!(
+            self.transactions.is_empty(),
+            "{} recorded transactions were not replayed, next one is {:x?}",
+            self.transactions.len(),
+            self.transactions.front()
+        );
+    }
+
+    fn replay(&mut self, op: Op, address: u64, value: Option<u32>) -> u32 {
+        let Some((expected_op, expected_address, expected_value)) = self.transactions.pop_front()
+        else {
+            panic!(
+                "Unexpected transaction #{}: {:?} {:#010x}, the recording is finished",
+                self.position, op, address
+            );
+        };
+
+        assert_eq!(
+            (expected_op, expected_address),
+            (op, address),
+            "Transaction #{} doesn't match the recording",
+            self.position
+        );
+
+        if let Some(value) = value {
+            assert_eq!(
+                expected_value, value,
+                "Transaction #{}: value {:#010x} written to {:#010x} doesn't match the recording",
+                self.position, value, address
+            );
+        }
+
+        self.position += 1;
+
+        expected_value
+    }
+}
+
+impl ArmProbe for &mut ReplayMemory {
+    fn read_8(&mut self, address: u64, _data: &mut [u8]) -> Result<(), ArmError> {
+        panic!("Unexpected 8-bit read from {address:#010x}")
+    }
+
+    fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+        for (i, word) in data.iter_mut().enumerate() {
+            *word = self.replay(Op::Read, address + i as u64 * 4, None);
+        }
+
+        Ok(())
+    }
+
+    fn read_64(&mut self, address: u64, _data: &mut [u64]) -> Result<(), ArmError> {
+        panic!("Unexpected 64-bit read from {address:#010x}")
+    }
+
+    fn write_8(&mut self, address: u64, _data: &[u8]) -> Result<(), ArmError> {
+        panic!("Unexpected 8-bit write to {address:#010x}")
+    }
+
+    fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+        for (i, word) in data.iter().enumerate() {
+            self.replay(Op::Write, address + i as u64 * 4, Some(*word));
+        }
+
+        Ok(())
+    }
+
+    fn write_64(&mut self, address: u64, _data: &[u64]) -> Result<(), ArmError> {
+        panic!("Unexpected 64-bit write to {address:#010x}")
+    }
+
+    fn flush(&mut self) -> Result<(), ArmError> {
+        Ok(())
+    }
+
+    fn supports_native_64bit_access(&mut self) -> bool {
+        false
+    }
+
+    fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
+        Ok(false)
+    }
+
+    fn ap(&mut self) -> MemoryAp {
+        todo!()
+    }
+
+    fn get_arm_communication_interface(
+        &mut self,
+    ) -> Result<&mut ArmCommunicationInterface<Initialized>, DebugProbeError> {
+        Err(DebugProbeError::NotImplemented(
+            "get_arm_communication_interface",
+        ))
+    }
+
+    fn update_core_status(&mut self, _state: CoreStatus) {}
+}
+
+impl SwdSequence for &mut ReplayMemory {
+    fn swj_sequence(&mut self, _bit_len: u8, _bits: u64) -> Result<(), DebugProbeError> {
+        todo!()
+    }
+
+    fn swj_pins(
+        &mut self,
+        _pin_out: u32,
+        _pin_select: u32,
+        _pin_wait: u32,
+    ) -> Result<u32, DebugProbeError> {
+        todo!()
+    }
+}
@@ -34,9 +34,12 @@ pub enum DebugPortError {
     #[error("Timeout occurred")]
     Timeout,
 
-    /// Powerup of the target device failed.
-    #[error("Target power-up failed.")]
-    TargetPowerUpFailed,
+    /// The debug and system power-up requests were not acknowledged by the target.
+    #[error("Debug power-up failed, CTRL/STAT is {ctrl_stat:#010x}.")]
+    DebugPowerUpFailed {
+        /// The last value read from the CTRL/STAT register.
+        ctrl_stat: u32,
+    },
 
     /// The debug port is not supported.
     #[error("Debug port not supported: {0}")]
@@ -305,6 +308,68 @@ impl Register for Ctrl {
     const NAME: &'static str = "CTRL/STAT";
 }
 
+/// The sticky error flags of the [`Ctrl`] register.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StickyErrors {
+    /// An AP transaction failed, `STICKYERR`.
+    pub sticky_err: bool,
+    /// A pushed compare or verify matched, `STICKYCMP`.
+    pub sticky_cmp: bool,
+    /// A write data parity or framing error occurred, `WDATAERR`.
+    pub write_data_err: bool,
+    /// An overrun occurred, `STICKYORUN`.
+    pub sticky_orun: bool,
+}
+
+impl StickyErrors {
+    /// Returns `true` if any of the flags is set.
+    pub fn any(&self) -> bool {
+        self.sticky_err || self.sticky_cmp || self.write_data_err || self.sticky_orun
+    }
+
+    /// The [`Abort`] register value which clears the set flags.
+    pub fn abort(&self) -> Abort {
+        let mut abort = Abort(0);
+        abort.set_stkerrclr(self.sticky_err);
+        abort.set_stkcmpclr(self.sticky_cmp);
+        abort.set_wderrclr(self.write_data_err);
+        abort.set_orunerrclr(self.sticky_orun);
+        abort
+    }
+}
+
+impl From<Ctrl> for StickyErrors {
+    fn from(ctrl: Ctrl) -> Self {
+        Self {
+            sticky_err: ctrl.sticky_err(),
+            sticky_cmp: ctrl.stick_cmp(),
+            write_data_err: ctrl.w_data_err(),
+            sticky_orun: ctrl.sticky_orun(),
+        }
+    }
+}
+
+impl Display for StickyErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let flags = [
+            (self.sticky_err, "STICKYERR"),
+            (self.sticky_cmp, "STICKYCMP"),
+            (self.write_data_err, "WDATAERR"),
+            (self.sticky_orun, "STICKYORUN"),
+        ];
+
+        let mut names = flags.iter().filter(|(set, _)| *set).map(|(_, name)| *name);
+
+        match names.next() {
+            Some(first) => {
+                write!(f, "{first}")?;
+                names.try_for_each(|name| write!(f, ", {name}"))
+            }
+            None => write!(f, "none"),
+        }
+    }
+}
+
 bitfield! {
     /// SELECT, AP Select register (see ADI v5.2 B2.2.9)
     #[derive(Clone)]
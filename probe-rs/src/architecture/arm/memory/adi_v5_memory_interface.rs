@@ -6,7 +6,10 @@ use crate::architecture::arm::communication_interface::{FlushableArmAccess, SwdS
 use crate::architecture::arm::{
     communication_interface::Initialized, dp::DpAccess, MemoryApInformation,
 };
-use crate::architecture::arm::{ArmCommunicationInterface, ArmError};
+use crate::architecture::arm::{
+    ArmCommunicationInterface, ArmError, DapTransfer, PortType, Register,
+};
+use crate::memory::MemoryTransfer;
 use crate::{CoreStatus, DebugProbeError};
 use std::convert::TryInto;
 use std::ops::Range;
@@ -84,6 +87,13 @@ pub trait ArmProbe: SwdSequence {
         self.write_32(address, &[data])
     }
 
+    /// Writes a 16 bit word to `address`.
+    ///
+    /// Probes which can't do 16 bit accesses return [`ArmError::UnsupportedTransferWidth`].
+    fn write_word_16(&mut self, _address: u64, _data: u16) -> Result<(), ArmError> {
+        Err(ArmError::UnsupportedTransferWidth(16))
+    }
+
     /// Writes a 8 bit word to `address`.
     fn write_word_8(&mut self, address: u64, data: u8) -> Result<(), ArmError> {
         self.write_8(address, &[data])
@@ -128,6 +138,29 @@ pub trait ArmProbe: SwdSequence {
         Ok(())
     }
 
+    /// Execute a batch of 32 bit reads and writes, in order.
+    ///
+    /// The read values are stored in the transfers. Probes which support it send the whole batch
+    /// at once, instead of waiting for the result of each read. If a transfer fails,
+    /// [`ArmError::BatchTransfer`] contains its index.
+    fn transfer_batch_32(&mut self, transfers: &mut [MemoryTransfer]) -> Result<(), ArmError> {
+        for (index, transfer) in transfers.iter_mut().enumerate() {
+            let result = if transfer.write {
+                self.write_word_32(transfer.address, transfer.value)
+            } else {
+                self.read_word_32(transfer.address)
+                    .map(|value| transfer.value = value)
+            };
+
+            result.map_err(|source| ArmError::BatchTransfer {
+                index,
+                source: Box::new(source),
+            })?;
+        }
+
+        Ok(())
+    }
+
     fn flush(&mut self) -> Result<(), ArmError>;
 
     fn supports_native_64bit_access(&mut self) -> bool;
@@ -332,10 +365,14 @@ where
         }
 
         if !self.ap_information.has_large_data_extension {
-            let mut ret: u64 = self.read_word_32(access_port, address)? as u64;
-            ret |= (self.read_word_32(access_port, address + 4)? as u64) << 32;
-
-            Ok(ret)
+            let mut transfers = [
+                MemoryTransfer::read(address),
+                MemoryTransfer::read(address + 4),
+            ];
+            self.transfer_batch_32(access_port, &mut transfers)
+                .map_err(single_transfer_error)?;
+
+            Ok(transfers[0].value as u64 | (transfers[1].value as u64) << 32)
         } else {
             let csw = self.build_csw_register(DataSize::U64);
 
@@ -357,10 +394,19 @@ where
     /// The address where the read should be performed at has to be word aligned.
     /// Returns `ArmError::MemoryNotAligned` if this does not hold true.
     pub fn read_word_32(&mut self, access_port: MemoryAp, address: u64) -> Result<u32, ArmError> {
-        if (address % 4) != 0 {
-            return Err(ArmError::alignment_error(address, 4));
-        }
+        let mut transfers = [MemoryTransfer::read(address)];
+        self.transfer_batch_32(access_port, &mut transfers)
+            .map_err(single_transfer_error)?;
+
+        Ok(transfers[0].value)
+    }
 
+    /// Read a 32bit word at the aligned `address`, with a separate access for each AP register.
+    fn read_word_32_single(
+        &mut self,
+        access_port: MemoryAp,
+        address: u64,
+    ) -> Result<u32, ArmError> {
         let csw = self.build_csw_register(DataSize::U32);
 
         self.write_csw_register(access_port, csw)?;
@@ -598,8 +644,12 @@ where
         let high_word = (data >> 32) as u32;
 
         if !self.ap_information.has_large_data_extension {
-            self.write_word_32(access_port, address, low_word)?;
-            self.write_word_32(access_port, address + 4, high_word)
+            let mut transfers = [
+                MemoryTransfer::write(address, low_word),
+                MemoryTransfer::write(address + 4, high_word),
+            ];
+            self.transfer_batch_32(access_port, &mut transfers)
+                .map_err(single_transfer_error)
         } else {
             let csw = self.build_csw_register(DataSize::U64);
             let drw = DRW { data: low_word };
@@ -626,10 +676,18 @@ where
         address: u64,
         data: u32,
     ) -> Result<(), ArmError> {
-        if (address % 4) != 0 {
-            return Err(ArmError::alignment_error(address, 4));
-        }
+        let mut transfers = [MemoryTransfer::write(address, data)];
+        self.transfer_batch_32(access_port, &mut transfers)
+            .map_err(single_transfer_error)
+    }
 
+    /// Write a 32bit word at the aligned `address`, with a separate access for each AP register.
+    fn write_word_32_single(
+        &mut self,
+        access_port: MemoryAp,
+        address: u64,
+        data: u32,
+    ) -> Result<(), ArmError> {
         let csw = self.build_csw_register(DataSize::U32);
         let drw = DRW { data };
 
@@ -641,6 +699,124 @@ where
         Ok(())
     }
 
+    /// Execute a batch of 32 bit reads and writes, see [`ArmProbe::transfer_batch_32`].
+    ///
+    /// The CSW, TAR and DRW accesses of all transfers are sent as a single batch of DAP transfers.
+    pub fn transfer_batch_32(
+        &mut self,
+        access_port: MemoryAp,
+        transfers: &mut [MemoryTransfer],
+    ) -> Result<(), ArmError> {
+        if let Some(index) = transfers
+            .iter()
+            .position(|transfer| transfer.address % 4 != 0)
+        {
+            return Err(ArmError::BatchTransfer {
+                index,
+                source: Box::new(ArmError::alignment_error(transfers[index].address, 4)),
+            });
+        }
+
+        // TAR2 would double the number of transfers, use the regular accesses instead.
+        let batch_supported = !self.ap_information.has_large_address_extension
+            && transfers
+                .iter()
+                .all(|transfer| transfer.address <= u32::MAX as u64);
+
+        if batch_supported && self.transfer_dap_batch_32(access_port, transfers)? {
+            return Ok(());
+        }
+
+        for (index, transfer) in transfers.iter_mut().enumerate() {
+            let result = if transfer.write {
+                self.write_word_32_single(access_port, transfer.address, transfer.value)
+            } else {
+                self.read_word_32_single(access_port, transfer.address)
+                    .map(|value| transfer.value = value)
+            };
+
+            result.map_err(|source| ArmError::BatchTransfer {
+                index,
+                source: Box::new(source),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Execute the 32 bit transfers as a batch of DAP transfers.
+    ///
+    /// Returns `false` if the interface doesn't support batches, then nothing was transferred.
+    fn transfer_dap_batch_32(
+        &mut self,
+        access_port: MemoryAp,
+        transfers: &mut [MemoryTransfer],
+    ) -> Result<bool, ArmError> {
+        let csw = self.build_csw_register(DataSize::U32);
+
+        let mut batch = Vec::with_capacity(2 * transfers.len() + 1);
+        // The index of the memory transfer each DAP transfer belongs to.
+        let mut owners = Vec::with_capacity(batch.capacity());
+
+        if self.cached_csw_value != Some(csw) {
+            batch.push(DapTransfer::write(
+                PortType::AccessPort,
+                CSW::ADDRESS,
+                csw.into(),
+            ));
+            owners.push(0);
+        }
+
+        for (index, transfer) in transfers.iter().enumerate() {
+            batch.push(DapTransfer::write(
+                PortType::AccessPort,
+                TAR::ADDRESS,
+                transfer.address as u32,
+            ));
+
+            batch.push(if transfer.write {
+                DapTransfer::write(PortType::AccessPort, DRW::ADDRESS, transfer.value)
+            } else {
+                DapTransfer::read(PortType::AccessPort, DRW::ADDRESS)
+            });
+
+            owners.extend([index, index]);
+        }
+
+        match self
+            .interface
+            .ap_transfer_batch(access_port.ap_address(), &mut batch)
+        {
+            Ok(true) => {}
+            Ok(false) => return Ok(false),
+            Err(error) => {
+                // It is unknown whether the CSW write was executed.
+                self.cached_csw_value = None;
+
+                return Err(match error {
+                    ArmError::BatchTransfer { index, source } => ArmError::BatchTransfer {
+                        index: owners[index],
+                        source,
+                    },
+                    other => other,
+                });
+            }
+        }
+
+        self.cached_csw_value = Some(csw);
+
+        // The batch ends with a TAR and a DRW access for each memory transfer.
+        let accesses = batch[batch.len() - 2 * transfers.len()..].chunks_exact(2);
+
+        for (transfer, access) in transfers.iter_mut().zip(accesses) {
+            if !transfer.write {
+                transfer.value = access[1].value;
+            }
+        }
+
+        Ok(true)
+    }
+
     /// Write an 8 bit word at `address`.
     pub fn write_word_8(
         &mut self,
@@ -668,6 +844,38 @@ where
         Ok(())
     }
 
+    /// Write a 16 bit word at `address`.
+    ///
+    /// The address where the write should be performed at has to be halfword aligned.
+    /// Returns `ArmError::MemoryNotAligned` if this does not hold true.
+    pub fn write_word_16(
+        &mut self,
+        access_port: MemoryAp,
+        address: u64,
+        data: u16,
+    ) -> Result<(), ArmError> {
+        if self.ap_information.supports_only_32bit_data_size {
+            return Err(ArmError::UnsupportedTransferWidth(16));
+        }
+
+        if (address % 2) != 0 {
+            return Err(ArmError::alignment_error(address, 2));
+        }
+
+        // Offset of halfword in word (little endian)
+        let bit_offset = (address % 4) * 8;
+
+        let csw = self.build_csw_register(DataSize::U16);
+        let drw = DRW {
+            data: u32::from(data) << bit_offset,
+        };
+        self.write_csw_register(access_port, csw)?;
+        self.write_tar_register(access_port, address)?;
+        self.write_ap_register(access_port, drw)?;
+
+        Ok(())
+    }
+
     /// Write a block of 32 bit words at `address`.
     ///
     /// The number of words written is `data.len()`.
@@ -938,6 +1146,10 @@ where
         Ok(())
     }
 
+    fn write_word_16(&mut self, address: u64, data: u16) -> Result<(), ArmError> {
+        self.write_word_16(self.memory_ap, address, data)
+    }
+
     fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
         if data.len() == 1 {
             self.write_word_32(self.memory_ap, address, data[0])?;
@@ -956,6 +1168,10 @@ where
         Ok(())
     }
 
+    fn transfer_batch_32(&mut self, transfers: &mut [MemoryTransfer]) -> Result<(), ArmError> {
+        self.transfer_batch_32(self.memory_ap, transfers)
+    }
+
     fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
         Ok(!self.ap_information.supports_only_32bit_data_size)
     }
@@ -979,6 +1195,14 @@ where
 }
 
 /// Calculates a 32-bit word aligned range from an address/length pair.
+/// Returns the error of the failed transfer of a batch, for batches of a single memory access.
+fn single_transfer_error(error: ArmError) -> ArmError {
+    match error {
+        ArmError::BatchTransfer { source, .. } => *source,
+        other => other,
+    }
+}
+
 fn aligned_range(address: u64, len: usize) -> Result<Range<u64>, ArmError> {
     // Round start address down to the nearest multiple of 4
     let start = address - (address % 4);
@@ -1006,6 +1230,8 @@ mod tests {
     use super::super::super::ap::memory_ap::mock::MockMemoryAp;
     use super::super::super::ap::memory_ap::MemoryAp;
     use super::ADIMemoryInterface;
+    use crate::architecture::arm::ArmError;
+    use crate::memory::MemoryTransfer;
 
     const DUMMY_AP: MemoryAp = MemoryAp::new(ApAddress {
         dp: DpAddress::Default,
@@ -1086,6 +1312,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn transfer_batch_32() {
+        let mut mock = MockMemoryAp::with_pattern();
+        let mut mi = ADIMemoryInterface::new_mock(&mut mock);
+
+        let mut transfers = [
+            MemoryTransfer::write(4, DATA32[0]),
+            MemoryTransfer::read(0),
+            MemoryTransfer::read(4),
+        ];
+
+        mi.transfer_batch_32(DUMMY_AP, &mut transfers).unwrap();
+
+        assert_eq!(transfers[1].value, 0x04030201);
+        assert_eq!(transfers[2].value, DATA32[0]);
+    }
+
+    #[test]
+    fn transfer_batch_32_unaligned() {
+        let mut mock = MockMemoryAp::with_pattern();
+        let mut mi = ADIMemoryInterface::new_mock(&mut mock);
+
+        let mut transfers = [MemoryTransfer::read(0), MemoryTransfer::write(6, 0)];
+
+        assert!(matches!(
+            mi.transfer_batch_32(DUMMY_AP, &mut transfers),
+            Err(ArmError::BatchTransfer { index: 1, source })
+                if matches!(*source, ArmError::MemoryNotAligned { address: 6, .. })
+        ));
+    }
+
     #[test]
     fn write_word_8() {
         for address in 0..8 {
@@ -1101,6 +1358,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn write_word_16() {
+        for address in (0..8).step_by(2) {
+            let mut mock = MockMemoryAp::with_pattern();
+            let mut mi = ADIMemoryInterface::new_mock(&mut mock);
+
+            let mut expected = Vec::from(mi.mock_memory());
+            expected[address..address + 2].copy_from_slice(&DATA8[..2]);
+
+            mi.write_word_16(
+                DUMMY_AP,
+                address as u64,
+                u16::from_le_bytes([DATA8[0], DATA8[1]]),
+            )
+            .unwrap_or_else(|_| panic!("write_word_16 failed, address = {address}"));
+            assert_eq!(mi.mock_memory(), expected.as_slice(), "address = {address}");
+        }
+    }
+
     #[test]
     fn read_32() {
         let mut mock = MockMemoryAp::with_pattern();
@@ -4,4 +4,6 @@ pub(crate) mod adi_v5_memory_interface;
 pub(crate) mod romtable;
 
 use super::ap::AccessPortError;
-pub use romtable::{Component, ComponentId, CoresightComponent, PeripheralType};
+pub use romtable::{
+    Component, ComponentId, CoresightComponent, DiscoveredComponent, PeripheralType,
+};
@@ -531,6 +531,47 @@ impl PartialEq for CoresightComponent {
     }
 }
 
+/// A component found by walking the ROM tables of a target, see [`CoresightComponent::discover`].
+#[derive(Clone, Debug)]
+pub struct DiscoveredComponent {
+    /// The access port through which the component is accessed.
+    pub ap: MemoryAp,
+    /// The base address of the component.
+    pub address: u64,
+    /// The JEP106 code of the designer of the component, `None` for legacy components.
+    pub designer: Option<jep106::JEP106Code>,
+    /// The part number of the component.
+    pub part: u16,
+    /// The name of the part, if it is known.
+    pub part_name: Option<&'static str>,
+    /// The type of the part, if it is known.
+    pub peripheral_type: Option<PeripheralType>,
+    /// Whether the component is a ROM table.
+    pub is_rom_table: bool,
+}
+
+impl CoresightComponent {
+    /// Lists this component and all components in its ROM tables, recursively.
+    pub fn discover(&self) -> Vec<DiscoveredComponent> {
+        self.iter()
+            .map(|component| {
+                let id = component.component.id();
+                let part_info = id.peripheral_id.determine_part();
+
+                DiscoveredComponent {
+                    ap: component.ap,
+                    address: id.component_address,
+                    designer: id.peripheral_id.jep106(),
+                    part: id.peripheral_id.part(),
+                    part_name: part_info.map(|info| info.name()),
+                    peripheral_type: part_info.map(|info| info.peripheral_type()),
+                    is_rom_table: matches!(component.component, Component::Class1RomTable(..)),
+                }
+            })
+            .collect()
+    }
+}
+
 /// This is a recursive iterator over all CoreSight components.
 pub struct CoresightComponentIter<'a> {
     /// The components of this iterator level.
@@ -10,11 +10,13 @@ pub mod sequences;
 pub mod swo;
 mod traits;
 
-pub use self::core::{armv6m, armv7a, armv7m, armv8a, armv8m, Dump};
+pub use self::core::{
+    armv6m, armv7a, armv7m, armv8a, armv8m, Dump, DwtComparator, MpuAccess, MpuConfig, MpuRegion,
+};
 use self::{
     ap::{AccessPort, AccessPortError},
     communication_interface::RegisterParseError,
-    dp::DebugPortError,
+    dp::{DebugPortError, StickyErrors},
     memory::romtable::RomTableError,
     sequences::ArmDebugSequenceError,
     {armv7a::Armv7aError, armv8a::Armv8aError},
@@ -22,7 +24,7 @@ use self::{
 use crate::DebugProbeError;
 pub use communication_interface::{
     ApInformation, ArmChipInfo, ArmCommunicationInterface, ArmProbeInterface, DapError,
-    MemoryApInformation, Register,
+    DapStatistics, MemoryApInformation, Register,
 };
 pub use swo::{SwoAccess, SwoConfig, SwoMode, SwoReader};
 pub use traits::*;
@@ -67,6 +69,14 @@ pub enum ArmError {
     /// Then the correct permission needs to be given to automatically unlock the core to prevent accidental erases.
     #[error("An operation could not be performed because it lacked the permission to do so: {0}")]
     MissingPermissions(String),
+    /// The device is locked against debug access, e.g. by APPROTECT on nRF devices.
+    ///
+    /// Erasing all memory of the device unlocks it. This is done while attaching when the
+    /// `erase_all` permission is given with
+    /// [`Permissions::allow_erase_all`](crate::Permissions::allow_erase_all), or with the
+    /// `--allow-erase-all` option of the probe-rs tools.
+    #[error("The device is locked. Erasing all of its memory unlocks it, attach with the `erase_all` permission (`--allow-erase-all`) to do so.")]
+    DeviceLocked,
 
     /// An error occurred in the communication with an access port or debug port.
     #[error("An error occurred in the communication with an access port or debug port.")]
@@ -103,6 +113,50 @@ pub enum ArmError {
     #[error("Unable to create a breakpoint at address {0:#010X}. Hardware breakpoints are only supported at addresses < 0x2000'0000.")]
     UnsupportedBreakpointAddress(u32),
 
+    /// The revision of the Flash Patch and Breakpoint unit is not supported,
+    /// so hardware breakpoints are not available.
+    #[error("The Flash Patch and Breakpoint unit revision {0} is not supported. HW breakpoints are not available.")]
+    UnsupportedFpbRevision(u32),
+
+    /// The core did not complete a core register transfer in time, even after retrying it.
+    #[error("Timeout while waiting for the transfer of core register {name} (selector {selector:#04x}).")]
+    RegisterTransferTimeout {
+        /// The name of the register, if it is known.
+        name: &'static str,
+        /// The register selector written to DCRSR.
+        selector: u16,
+    },
+
+    /// A transfer to an access port failed with a FAULT response.
+    ///
+    /// The sticky error flags of the debug port were cleared, so the following transfers can succeed.
+    #[error("Access to register {register:#04x} of access port {address:x?} failed, sticky errors: {sticky_errors}.")]
+    ApTransferFault {
+        /// The address of the access port.
+        address: ApAddress,
+        /// The address of the accessed register.
+        register: u8,
+        /// The sticky error flags which were set after the transfer.
+        sticky_errors: StickyErrors,
+    },
+
+    /// A transfer in a batch of transfers failed.
+    ///
+    /// The transfers before `index` were executed, the ones after it were not.
+    #[error("Transfer {index} of a batch failed.")]
+    BatchTransfer {
+        /// The index of the failed transfer in the batch.
+        index: usize,
+        /// The error of the failed transfer.
+        #[source]
+        source: Box<ArmError>,
+    },
+
+    /// The core is locked up as a result of an unrecoverable exception,
+    /// so it won't halt on its own.
+    #[error("The core is locked up as a result of an unrecoverable exception.")]
+    CoreLockedUp,
+
     /// ARMv8a specific error occurred.
     Armv8a(#[from] Armv8aError),
 
@@ -0,0 +1,279 @@
+//! Sequences for NXP Kinetis K, KL, KV and KW devices.
+//!
+//! Kinetis devices expose a Miscellaneous Debug Module access port (MDM-AP) next to the AHB-AP.
+//! When flash security is enabled, the AHB-AP is unusable until the MDM-AP has been used to
+//! mass erase the device.
+
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use bitfield::bitfield;
+
+use super::ArmDebugSequence;
+use crate::architecture::arm::{
+    ap::MemoryAp, ApAddress, ArmError, ArmProbeInterface, DapAccess, DpAddress,
+};
+
+/// The MDM-AP is always the second access port on the default debug port.
+const MDM_AP: ApAddress = ApAddress {
+    dp: DpAddress::Default,
+    ap: 1,
+};
+
+/// A mass erase of the largest parts takes a few hundred milliseconds, leave plenty of margin.
+const MASS_ERASE_TIMEOUT: Duration = Duration::from_secs(10);
+
+bitfield! {
+    /// The MDM-AP status register, described in "K64 Sub-Family Reference Manual" section 9.3.5.
+    #[derive(Copy, Clone)]
+    pub struct MdmStatus(u32);
+    impl Debug;
+    /// The core is halted.
+    pub core_halted, _: 16;
+    /// A mass erase is allowed by the flash configuration field.
+    pub mass_erase_enabled, _: 5;
+    /// The system is secured and the AHB-AP cannot be used.
+    pub system_security, _: 2;
+    /// The flash controller finished initialization.
+    pub flash_ready, _: 1;
+    /// A mass erase request was acknowledged.
+    pub mass_erase_ack, _: 0;
+}
+
+impl MdmStatus {
+    /// The address of the status register in the MDM-AP.
+    const ADDRESS: u8 = 0x00;
+
+    fn read(interface: &mut dyn ArmProbeInterface) -> Result<Self, ArmError> {
+        Ok(Self(interface.read_raw_ap_register(MDM_AP, Self::ADDRESS)?))
+    }
+}
+
+bitfield! {
+    /// The MDM-AP control register, described in "K64 Sub-Family Reference Manual" section 9.3.6.
+    #[derive(Copy, Clone)]
+    pub struct MdmControl(u32);
+    impl Debug;
+    /// Hold the system in reset.
+    pub system_reset_request, set_system_reset_request: 3;
+    /// Start a mass erase. The bit is cleared by hardware when the erase is done.
+    pub mass_erase_in_progress, set_mass_erase_in_progress: 0;
+}
+
+impl MdmControl {
+    /// The address of the control register in the MDM-AP.
+    const ADDRESS: u8 = 0x04;
+
+    fn read(interface: &mut dyn ArmProbeInterface) -> Result<Self, ArmError> {
+        Ok(Self(interface.read_raw_ap_register(MDM_AP, Self::ADDRESS)?))
+    }
+
+    fn write(self, interface: &mut dyn ArmProbeInterface) -> Result<(), ArmError> {
+        interface.write_raw_ap_register(MDM_AP, Self::ADDRESS, self.0)
+    }
+}
+
+/// Marker structure for Kinetis devices.
+#[derive(Debug)]
+pub struct Kinetis {}
+
+impl Kinetis {
+    /// Create the sequencer for Kinetis devices.
+    pub fn create() -> Arc<Self> {
+        Arc::new(Self {})
+    }
+
+    /// Mass erase the device through the MDM-AP, which also clears flash security.
+    fn mass_erase(&self, interface: &mut dyn ArmProbeInterface) -> Result<(), ArmError> {
+        let start = Instant::now();
+        while !MdmStatus::read(interface)?.flash_ready() {
+            if start.elapsed() > MASS_ERASE_TIMEOUT {
+                return Err(ArmError::Timeout);
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        if !MdmStatus::read(interface)?.mass_erase_enabled() {
+            return Err(ArmError::DeviceLocked);
+        }
+
+        // Keep the core in reset so the (possibly broken) firmware cannot interfere.
+        let mut control = MdmControl(0);
+        control.set_system_reset_request(true);
+        control.set_mass_erase_in_progress(true);
+        control.write(interface)?;
+
+        let start = Instant::now();
+        while MdmControl::read(interface)?.mass_erase_in_progress() {
+            if start.elapsed() > MASS_ERASE_TIMEOUT {
+                return Err(ArmError::Timeout);
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        MdmControl(0).write(interface)?;
+
+        if MdmStatus::read(interface)?.system_security() {
+            return Err(ArmError::DeviceLocked);
+        }
+
+        Ok(())
+    }
+}
+
+impl ArmDebugSequence for Kinetis {
+    fn debug_device_unlock(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        _default_ap: MemoryAp,
+        permissions: &crate::Permissions,
+    ) -> Result<(), ArmError> {
+        if !MdmStatus::read(interface)?.system_security() {
+            return Ok(());
+        }
+
+        tracing::warn!("The device is secured. A mass erase is required to unlock it.");
+        permissions
+            .erase_all()
+            .map_err(|_| ArmError::DeviceLocked)?;
+
+        self.mass_erase(interface)?;
+        tracing::info!("Device unlocked by mass erase");
+
+        Ok(())
+    }
+
+    fn debug_device_recover(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        _default_ap: MemoryAp,
+    ) -> Result<(), ArmError> {
+        self.mass_erase(interface)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::architecture::arm::sequences::DefaultArmSequence;
+    use crate::probe::fake_probe::{FakeProbe, Operation};
+    use crate::{DebugProbe, Permissions};
+
+    const SECURED: u32 = 1 << 2;
+    const MASS_ERASE_ENABLED: u32 = 1 << 5;
+    const FLASH_READY: u32 = 1 << 1;
+
+    fn read(address: u8, result: u32) -> Operation {
+        Operation::ReadRawApRegister {
+            ap: MDM_AP,
+            address,
+            result,
+        }
+    }
+
+    fn write(address: u8, value: u32) -> Operation {
+        Operation::WriteRawApRegister {
+            ap: MDM_AP,
+            address,
+            value,
+        }
+    }
+
+    /// Run the unlock sequence against an MDM-AP answering with the expected `operations`
+    fn unlock(operations: Vec<Operation>, permissions: &Permissions) -> Result<(), ArmError> {
+        let probe = FakeProbe::new();
+        for operation in operations {
+            probe.expect_operation(operation);
+        }
+
+        let mut interface = Box::new(probe)
+            .try_get_arm_interface()
+            .unwrap()
+            .initialize(DefaultArmSequence::create())
+            .unwrap();
+
+        let default_ap = MemoryAp::new(ApAddress {
+            dp: DpAddress::Default,
+            ap: 0,
+        });
+
+        Kinetis::create().debug_device_unlock(&mut *interface, default_ap, permissions)
+    }
+
+    #[test]
+    fn unsecured_device_is_not_erased() {
+        let operations = vec![read(MdmStatus::ADDRESS, FLASH_READY)];
+
+        unlock(operations, &Permissions::new()).unwrap();
+    }
+
+    #[test]
+    fn secured_device_is_mass_erased() {
+        let secured = SECURED | MASS_ERASE_ENABLED | FLASH_READY;
+
+        let operations = vec![
+            read(MdmStatus::ADDRESS, secured),
+            // Wait for the flash controller
+            read(MdmStatus::ADDRESS, SECURED | MASS_ERASE_ENABLED),
+            read(MdmStatus::ADDRESS, secured),
+            read(MdmStatus::ADDRESS, secured),
+            // Start the erase with the system held in reset, and wait until it is done
+            write(MdmControl::ADDRESS, 0b1001),
+            read(MdmControl::ADDRESS, 0b1001),
+            read(MdmControl::ADDRESS, 0b1000),
+            // Release the reset
+            write(MdmControl::ADDRESS, 0),
+            read(MdmStatus::ADDRESS, MASS_ERASE_ENABLED | FLASH_READY),
+        ];
+
+        unlock(operations, &Permissions::new().allow_erase_all()).unwrap();
+    }
+
+    #[test]
+    fn secured_device_needs_erase_permission() {
+        let operations = vec![read(MdmStatus::ADDRESS, SECURED | FLASH_READY)];
+
+        assert!(matches!(
+            unlock(operations, &Permissions::new()),
+            Err(ArmError::DeviceLocked)
+        ));
+    }
+
+    #[test]
+    fn disabled_mass_erase_keeps_device_locked() {
+        let operations = vec![
+            read(MdmStatus::ADDRESS, SECURED | FLASH_READY),
+            read(MdmStatus::ADDRESS, SECURED | FLASH_READY),
+            read(MdmStatus::ADDRESS, SECURED | FLASH_READY),
+        ];
+
+        assert!(matches!(
+            unlock(operations, &Permissions::new().allow_erase_all()),
+            Err(ArmError::DeviceLocked)
+        ));
+    }
+
+    #[test]
+    fn device_still_secured_after_erase() {
+        let secured = SECURED | MASS_ERASE_ENABLED | FLASH_READY;
+
+        let operations = vec![
+            read(MdmStatus::ADDRESS, secured),
+            read(MdmStatus::ADDRESS, secured),
+            read(MdmStatus::ADDRESS, secured),
+            write(MdmControl::ADDRESS, 0b1001),
+            read(MdmControl::ADDRESS, 0b1000),
+            write(MdmControl::ADDRESS, 0),
+            read(MdmStatus::ADDRESS, secured),
+        ];
+
+        assert!(matches!(
+            unlock(operations, &Permissions::new().allow_erase_all()),
+            Err(ArmError::DeviceLocked)
+        ));
+    }
+}
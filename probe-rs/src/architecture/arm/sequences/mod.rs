@@ -3,6 +3,7 @@
 pub mod atsam;
 pub mod efm32xg2;
 pub mod infineon;
+pub mod kinetis;
 mod nrf;
 pub mod nrf52;
 pub mod nrf53;
@@ -36,9 +37,73 @@ use super::{
         adi_v5_memory_interface::ArmProbe,
         romtable::{CoresightComponent, PeripheralType},
     },
-    ArmCommunicationInterface, ArmError, DpAddress, Pins, PortType, Register,
+    ArmCommunicationInterface, ArmError, DapAccess, DpAddress, Pins, PortType, Register,
 };
 
+/// Time to wait for the debug and system power-up requests to be acknowledged.
+const POWER_UP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Request debug and system power from the debug port `dp`, and wait until both are acknowledged.
+///
+/// If the acknowledge bits don't get set in time, the requests are cleared and made once more
+/// before giving up with [`DebugPortError::DebugPowerUpFailed`].
+fn power_up_debug_port(
+    interface: &mut ArmCommunicationInterface<Initialized>,
+    dp: DpAddress,
+) -> Result<(), ArmError> {
+    let mut request = Ctrl(0);
+    request.set_cdbgpwrupreq(true);
+    request.set_csyspwrupreq(true);
+
+    interface.write_dp_register(dp, request.clone())?;
+
+    let (powered_up, ctrl) = wait_for_power_up_ack(interface, dp, true)?;
+    if powered_up {
+        return Ok(());
+    }
+
+    tracing::warn!(
+        "Debug power-up was not acknowledged, CTRL/STAT is {:#010x}. Requesting it again.",
+        u32::from(ctrl)
+    );
+
+    interface.write_dp_register(dp, Ctrl(0))?;
+    wait_for_power_up_ack(interface, dp, false)?;
+
+    interface.write_dp_register(dp, request)?;
+
+    match wait_for_power_up_ack(interface, dp, true)? {
+        (true, _) => Ok(()),
+        (false, ctrl) => Err(DebugPortError::DebugPowerUpFailed {
+            ctrl_stat: ctrl.into(),
+        }
+        .into()),
+    }
+}
+
+/// Poll CTRL/STAT until both power-up acknowledge bits are `set` or cleared, or [`POWER_UP_TIMEOUT`]
+/// passes. Returns whether the bits reached the state, and the last value of CTRL/STAT.
+fn wait_for_power_up_ack(
+    interface: &mut ArmCommunicationInterface<Initialized>,
+    dp: DpAddress,
+    set: bool,
+) -> Result<(bool, Ctrl), ArmError> {
+    let start = Instant::now();
+
+    loop {
+        let ctrl: Ctrl = interface.read_dp_register(dp)?;
+        if ctrl.csyspwrupack() == set && ctrl.cdbgpwrupack() == set {
+            return Ok((true, ctrl));
+        }
+
+        if start.elapsed() >= POWER_UP_TIMEOUT {
+            return Ok((false, ctrl));
+        }
+
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
 /// An error occurred when executing an ARM debug sequence
 #[derive(thiserror::Error, Debug)]
 pub enum ArmDebugSequenceError {
@@ -381,6 +446,9 @@ fn cortex_m_reset_system(interface: &mut dyn ArmProbe) -> Result<(), ArmError> {
 
     interface.write_word_32(Aircr::get_mmio_address(), aircr.into())?;
 
+    // S_RESET_ST is set by the reset and cleared when DHCSR is read, so the first reads can
+    // still come from before the reset. Only return once the reset has been seen, waiting for
+    // the core to come out of reset is up to the caller.
     let start = Instant::now();
 
     while start.elapsed() < Duration::from_micros(50_0000) {
@@ -397,8 +465,7 @@ fn cortex_m_reset_system(interface: &mut dyn ArmProbe) -> Result<(), ArmError> {
             Err(err) => return Err(err),
         };
 
-        // Wait until the S_RESET_ST bit is cleared on a read
-        if !dhcsr.s_reset_st() {
+        if dhcsr.s_reset_st() {
             return Ok(());
         }
     }
@@ -540,24 +607,7 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
         let powered_down = !(ctrl.csyspwrupack() && ctrl.cdbgpwrupack());
 
         if powered_down {
-            let mut ctrl = Ctrl(0);
-            ctrl.set_cdbgpwrupreq(true);
-            ctrl.set_csyspwrupreq(true);
-            interface.write_dp_register(dp, ctrl)?;
-
-            let start = Instant::now();
-            let mut timeout = true;
-            while start.elapsed() < Duration::from_micros(100_0000) {
-                let ctrl = interface.read_dp_register::<Ctrl>(dp)?;
-                if ctrl.csyspwrupack() && ctrl.cdbgpwrupack() {
-                    timeout = false;
-                    break;
-                }
-            }
-
-            if timeout {
-                return Err(ArmError::Timeout);
-            }
+            power_up_debug_port(interface, dp)?;
 
             // TODO: Handle JTAG Specific part
 
@@ -573,7 +623,10 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
             let ctrl_reg: Ctrl = interface.read_dp_register(dp)?;
             if !(ctrl_reg.csyspwrupack() && ctrl_reg.cdbgpwrupack()) {
                 tracing::error!("Debug power request failed");
-                return Err(DebugPortError::TargetPowerUpFailed.into());
+                return Err(DebugPortError::DebugPowerUpFailed {
+                    ctrl_stat: ctrl_reg.into(),
+                }
+                .into());
             }
 
             // According to CMSIS docs, here's where we would clear errors
@@ -583,6 +636,21 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
         Ok(())
     }
 
+    /// Power down the debug port when the debugger disconnects, so the target can enter
+    /// low power modes again. This is based on the `DebugPortStop` function from the
+    /// [ARM SVD Debug Description].
+    ///
+    /// [ARM SVD Debug Description]: http://www.keil.com/pack/doc/cmsis/Pack/html/debug_description.html#debugPortStop
+    #[doc(alias = "DebugPortStop")]
+    fn debug_port_stop(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        dp: DpAddress,
+    ) -> Result<(), ArmError> {
+        // Clear the debug and system power-up requests.
+        interface.write_raw_dp_register(dp, Ctrl::ADDRESS, 0)
+    }
+
     /// Initialize core debug system. This is based on the
     /// `DebugCoreStart` function from the [ARM SVD Debug Description].
     ///
@@ -707,6 +775,20 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
         }
     }
 
+    /// Prepare the core for debugging after it was reset and halted, e.g. by freezing watchdogs
+    /// which would otherwise reset the device while it is halted.
+    ///
+    /// This is run by the GDB `monitor reset init` command. The STM32 sequences freeze the
+    /// independent and window watchdogs. By default, nothing is done.
+    fn reset_init(
+        &self,
+        _core: &mut dyn ArmProbe,
+        _core_type: CoreType,
+        _debug_base: Option<u64>,
+    ) -> Result<(), ArmError> {
+        Ok(())
+    }
+
     /// Check if the device is in a locked state and unlock it.
     /// Use query command elements for user confirmation.
     /// Executed after having powered up the debug port. This is based on the
@@ -724,6 +806,22 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
         Ok(())
     }
 
+    /// Recover a locked device by erasing it, even if it is not locked.
+    ///
+    /// This is the destructive counterpart to [`ArmDebugSequence::debug_device_unlock`],
+    /// e.g. an ERASEALL through the CTRL-AP on nRF devices. By default, the
+    /// [`DebugEraseSequence`] of the device is used, if it has one.
+    fn debug_device_recover(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        _default_ap: MemoryAp,
+    ) -> Result<(), ArmError> {
+        match self.debug_erase_sequence() {
+            Some(erase_sequence) => erase_sequence.erase_all(interface),
+            None => Err(DebugProbeError::NotImplemented("device recovery").into()),
+        }
+    }
+
     /// Executed before step or run command to support recovery from a lost target connection, e.g. after a low power mode.
     /// This is based on the `RecoverSupportStart` function from the [ARM SVD Debug Description].
     ///
@@ -747,10 +845,14 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
     ) -> Result<(), ArmError> {
         if core_type.is_cortex_m() {
             // System Control Space (SCS) offset as defined in Armv6-M/Armv7-M.
-            // Disable Core Debug via DHCSR
-            let mut dhcsr = Dhcsr(0);
-            dhcsr.enable_write();
-            interface.write_word_32(Dhcsr::get_mmio_address(), dhcsr.0)?;
+            // Disable Core Debug via DHCSR. This would resume a halted core, which is kept halted
+            // with halting debug enabled instead.
+            let dhcsr = Dhcsr(interface.read_word_32(Dhcsr::get_mmio_address())?);
+            if !dhcsr.s_halt() {
+                let mut dhcsr = Dhcsr(0);
+                dhcsr.enable_write();
+                interface.write_word_32(Dhcsr::get_mmio_address(), dhcsr.0)?;
+            }
 
             // Disable DWT and ITM blocks, DebugMonitor handler,
             // halting debug traps, and Reset Vector Catch.
@@ -764,6 +866,49 @@ pub trait ArmDebugSequence: Send + Sync + Debug {
     fn debug_erase_sequence(&self) -> Option<Arc<dyn DebugEraseSequence>> {
         None
     }
+
+    /// Configure the device to start its built-in bootloader, and reset it.
+    ///
+    /// This is done by vendor specific means, e.g. by selecting the system memory as boot source
+    /// in the option bytes. The boot configuration is kept, so the device keeps starting the
+    /// bootloader until the boot source is changed back.
+    ///
+    /// Devices which reset the debug connection as well return `ArmError::ReAttachRequired`.
+    fn reset_to_bootloader(
+        &self,
+        _interface: &mut dyn ArmProbeInterface,
+        _default_ap: MemoryAp,
+    ) -> Result<(), ArmError> {
+        Err(DebugProbeError::NotImplemented(
+            "Resetting into the bootloader is not available on this device",
+        )
+        .into())
+    }
+
+    /// Read the option bytes of the device, as the raw values of its option registers.
+    fn read_option_bytes(
+        &self,
+        _interface: &mut dyn ArmProbeInterface,
+        _default_ap: MemoryAp,
+    ) -> Result<Vec<u32>, ArmError> {
+        Err(DebugProbeError::NotImplemented("Option bytes are not available on this device").into())
+    }
+
+    /// Program the option bytes of the device and reload them.
+    ///
+    /// Settings which permanently lock the device have to be refused with
+    /// [`ArmError::MissingPermissions`] unless `force` is set.
+    ///
+    /// Returns [`ArmError::ReAttachRequired`] if reloading the option bytes reset the device.
+    fn write_option_bytes(
+        &self,
+        _interface: &mut dyn ArmProbeInterface,
+        _default_ap: MemoryAp,
+        _option_bytes: &[u32],
+        _force: bool,
+    ) -> Result<(), ArmError> {
+        Err(DebugProbeError::NotImplemented("Option bytes are not available on this device").into())
+    }
 }
 
 /// Chip-Erase Handling via the Device's Debug Interface
@@ -784,3 +929,90 @@ pub trait DebugEraseSequence: Send + Sync {
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use super::DefaultArmSequence;
+    use crate::architecture::arm::communication_interface::UninitializedArmProbe;
+    use crate::architecture::arm::{ArmCommunicationInterface, DapAccess, DpAddress, PortType};
+    use crate::probe::fake_probe::FakeProbe;
+
+    /// CDBGPWRUPREQ and CSYSPWRUPREQ of CTRL/STAT
+    const POWER_UP_REQUESTS: u32 = (1 << 28) | (1 << 30);
+    /// CDBGPWRUPACK and CSYSPWRUPACK of CTRL/STAT
+    const POWER_UP_ACKS: u32 = (1 << 29) | (1 << 31);
+
+    /// A debug port which only acknowledges the second power-up request
+    #[derive(Default)]
+    struct PowerUpDp {
+        ctrl: u32,
+        power_up_requests: usize,
+        /// All values written to CTRL/STAT
+        ctrl_writes: Vec<u32>,
+    }
+
+    #[test]
+    fn power_up_is_requested_again() {
+        let dp = Arc::new(Mutex::new(PowerUpDp::default()));
+        let mut probe = FakeProbe::new();
+
+        let read_dp = dp.clone();
+        probe.set_dap_register_read_handler(Box::new(move |port, address| {
+            let dp = read_dp.lock().unwrap();
+
+            match (port, address) {
+                (PortType::DebugPort, 0x0) => Ok(0x2ba0_1477),
+                (PortType::DebugPort, 0x4) => {
+                    let requested = dp.ctrl & POWER_UP_REQUESTS == POWER_UP_REQUESTS;
+
+                    if requested && dp.power_up_requests > 1 {
+                        Ok(dp.ctrl | POWER_UP_ACKS)
+                    } else {
+                        Ok(dp.ctrl)
+                    }
+                }
+                _ => Ok(0),
+            }
+        }));
+
+        let write_dp = dp.clone();
+        probe.set_dap_register_write_handler(Box::new(move |port, address, value| {
+            let mut dp = write_dp.lock().unwrap();
+
+            if (port, address) == (PortType::DebugPort, 0x4) {
+                let value = value & !POWER_UP_ACKS;
+
+                if value & POWER_UP_REQUESTS == POWER_UP_REQUESTS {
+                    dp.power_up_requests += 1;
+                }
+                dp.ctrl = value;
+                dp.ctrl_writes.push(value);
+            }
+
+            Ok(())
+        }));
+
+        let mut interface = Box::new(ArmCommunicationInterface::new(Box::new(probe), false))
+            .initialize(DefaultArmSequence::create())
+            .unwrap();
+
+        // The first access starts the debug port, read CTRL/STAT
+        let ctrl = interface
+            .read_raw_dp_register(DpAddress::Default, 0x4)
+            .unwrap();
+        assert_eq!(ctrl & POWER_UP_ACKS, POWER_UP_ACKS);
+
+        // The first request is cleared, and made again
+        assert_eq!(
+            dp.lock().unwrap().ctrl_writes[..4],
+            [
+                POWER_UP_REQUESTS,
+                0,
+                POWER_UP_REQUESTS,
+                POWER_UP_REQUESTS | 0xF00
+            ]
+        );
+    }
+}
@@ -9,7 +9,6 @@ use crate::architecture::arm::{
     communication_interface::Initialized, ApAddress, ArmCommunicationInterface, ArmProbeInterface,
     DapAccess,
 };
-use crate::session::MissingPermissions;
 use std::fmt::Debug;
 
 pub trait Nrf: Sync + Send + Debug {
@@ -47,7 +46,7 @@ fn unlock_core(
 ) -> Result<(), ArmError> {
     permissions
         .erase_all()
-        .map_err(|MissingPermissions(desc)| ArmError::MissingPermissions(desc))?;
+        .map_err(|_| ArmError::DeviceLocked)?;
 
     arm_interface.write_raw_ap_register(ap_address, ERASEALL, 1)?;
 
@@ -132,4 +131,46 @@ impl<T: Nrf> ArmDebugSequence for T {
 
         Ok(())
     }
+
+    fn debug_device_recover(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        default_ap: MemoryAp,
+    ) -> Result<(), ArmError> {
+        let mut interface = interface.memory_interface(default_ap)?;
+
+        // Recovering is requested explicitly, so erasing is allowed.
+        let permissions = crate::Permissions::new().allow_erase_all();
+
+        for (core_index, (core_ahb_ap_address, core_ctrl_ap_address)) in
+            self.core_aps(&mut *interface).iter().copied().enumerate()
+        {
+            tracing::warn!("Erasing core {} to recover it.", core_index);
+            unlock_core(
+                interface.get_arm_communication_interface()?,
+                core_ctrl_ap_address,
+                &permissions,
+            )?;
+
+            if !self.is_core_unlocked(
+                interface.get_arm_communication_interface()?,
+                core_ahb_ap_address,
+                core_ctrl_ap_address,
+            )? {
+                return Err(ArmDebugSequenceError::custom(format!(
+                    "Could not recover core {core_index}"
+                ))
+                .into());
+            }
+        }
+
+        if self.has_network_core() {
+            tracing::debug!("Setting network core to running");
+            set_network_core_running(&mut *interface)?;
+        }
+
+        interface.flush()?;
+
+        Ok(())
+    }
 }
@@ -7,7 +7,6 @@ use crate::architecture::arm::{
     ap::MemoryAp, component::TraceSink, memory::CoresightComponent, ApAddress, ArmError,
     ArmProbeInterface, DpAddress,
 };
-use crate::session::MissingPermissions;
 
 /// An error when operating a core ROM table component occurred.
 #[derive(thiserror::Error, Debug)]
@@ -36,6 +35,35 @@ impl Nrf52 {
         Arc::new(Self {})
     }
 
+    /// Erase all flash and UICR through the CTRL-AP, which also disables APPROTECT.
+    ///
+    /// Returns [`ArmError::ReAttachRequired`] on success.
+    fn erase_all(
+        &self,
+        iface: &mut dyn ArmProbeInterface,
+        ctrl_ap: ApAddress,
+    ) -> Result<(), ArmError> {
+        // Reset
+        iface.write_raw_ap_register(ctrl_ap, RESET, 1)?;
+        iface.write_raw_ap_register(ctrl_ap, RESET, 0)?;
+
+        // Start erase
+        iface.write_raw_ap_register(ctrl_ap, ERASEALL, 1)?;
+
+        // Wait for erase done
+        while iface.read_raw_ap_register(ctrl_ap, ERASEALLSTATUS)? != 0 {}
+
+        // Reset again
+        iface.write_raw_ap_register(ctrl_ap, RESET, 1)?;
+        iface.write_raw_ap_register(ctrl_ap, RESET, 0)?;
+
+        if !self.is_core_unlocked(iface, ctrl_ap)? {
+            return Err(ArmDebugSequenceError::custom("Could not unlock core").into());
+        }
+
+        Err(ArmError::ReAttachRequired)
+    }
+
     fn is_core_unlocked(
         &self,
         iface: &mut dyn ArmProbeInterface,
@@ -102,27 +130,23 @@ impl ArmDebugSequence for Nrf52 {
         tracing::warn!("Core is locked. Erase procedure will be started to unlock it.");
         permissions
             .erase_all()
-            .map_err(|MissingPermissions(desc)| ArmError::MissingPermissions(desc))?;
+            .map_err(|_| ArmError::DeviceLocked)?;
 
-        // Reset
-        iface.write_raw_ap_register(ctrl_ap, RESET, 1)?;
-        iface.write_raw_ap_register(ctrl_ap, RESET, 0)?;
-
-        // Start erase
-        iface.write_raw_ap_register(ctrl_ap, ERASEALL, 1)?;
-
-        // Wait for erase done
-        while iface.read_raw_ap_register(ctrl_ap, ERASEALLSTATUS)? != 0 {}
-
-        // Reset again
-        iface.write_raw_ap_register(ctrl_ap, RESET, 1)?;
-        iface.write_raw_ap_register(ctrl_ap, RESET, 0)?;
+        self.erase_all(iface, ctrl_ap)
+    }
 
-        if !self.is_core_unlocked(iface, ctrl_ap)? {
-            return Err(ArmDebugSequenceError::custom("Could not unlock core").into());
-        }
+    fn debug_device_recover(
+        &self,
+        iface: &mut dyn ArmProbeInterface,
+        _default_ap: MemoryAp,
+    ) -> Result<(), ArmError> {
+        let ctrl_ap = ApAddress {
+            ap: 1,
+            dp: DpAddress::Default,
+        };
 
-        Err(ArmError::ReAttachRequired)
+        tracing::warn!("Erasing the device to recover it.");
+        self.erase_all(iface, ctrl_ap)
     }
 
     fn trace_start(
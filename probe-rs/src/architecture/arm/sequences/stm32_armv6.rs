@@ -10,6 +10,7 @@ use super::ArmDebugSequence;
 use crate::architecture::arm::{
     ap::MemoryAp, memory::adi_v5_memory_interface::ArmProbe, ArmError, ArmProbeInterface,
 };
+use crate::DebugProbeError;
 
 /// Supported families for custom sequences on ARMv6 STM32 devices.
 #[derive(Debug)]
@@ -35,6 +36,29 @@ impl Stm32Armv6 {
     pub fn create(family: Stm32Armv6Family) -> Arc<Self> {
         Arc::new(Self { family })
     }
+
+    /// Enable or disable the clock of the DBGMCU, which is required to access its registers.
+    fn enable_dbgmcu_clock(&self, memory: &mut dyn ArmProbe, enable: bool) -> Result<(), ArmError> {
+        match self.family {
+            Stm32Armv6Family::F0 => {
+                let mut enr = rcc::EnrF0::read(&mut *memory)?;
+                enr.enable_dbg(enable);
+                enr.write(&mut *memory)?;
+            }
+            Stm32Armv6Family::L0 => {
+                let mut enr = rcc::EnrL0::read(&mut *memory)?;
+                enr.enable_dbg(enable);
+                enr.write(&mut *memory)?;
+            }
+            Stm32Armv6Family::G0 => {
+                let mut enr = rcc::EnrG0::read(&mut *memory)?;
+                enr.enable_dbg(enable);
+                enr.write(&mut *memory)?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 mod rcc {
@@ -106,6 +130,185 @@ mod dbgmcu {
             memory.write_word_32(DBGMCU + Self::ADDRESS, self.0)
         }
     }
+
+    bitfield! {
+        /// The APB freeze register (APB1_FZ) of the DBGMCU, which stops peripherals while the core
+        /// is halted. This register is described in "RM0360: STM32F0 family reference manual",
+        /// and is called DBG_APB_FZ1 on the STM32G0.
+        pub struct ApbFreeze(u32);
+        impl Debug;
+
+        pub u8, dbg_iwdg_stop, freeze_iwdg: 12;
+        pub u8, dbg_wwdg_stop, freeze_wwdg: 11;
+    }
+
+    impl ApbFreeze {
+        /// The offset of the APB freeze register in the DBGMCU block.
+        const ADDRESS: u64 = 0x08;
+
+        /// Read the APB freeze register from memory.
+        pub fn read(memory: &mut dyn ArmProbe) -> Result<Self, ArmError> {
+            let contents = memory.read_word_32(DBGMCU + Self::ADDRESS)?;
+            Ok(Self(contents))
+        }
+
+        /// Write the APB freeze register to memory.
+        pub fn write(&mut self, memory: &mut dyn ArmProbe) -> Result<(), ArmError> {
+            memory.write_word_32(DBGMCU + Self::ADDRESS, self.0)
+        }
+    }
+}
+
+mod flash_g0 {
+    use std::time::{Duration, Instant};
+
+    use crate::architecture::arm::{memory::adi_v5_memory_interface::ArmProbe, ArmError};
+    use bitfield::bitfield;
+
+    /// The base address of the FLASH peripheral of the STM32G0
+    const FLASH: u64 = 0x40022000;
+
+    /// The offset of the key register, unlocking the control register
+    const KEYR: u64 = 0x08;
+
+    /// The offset of the option key register, unlocking the option bytes
+    const OPTKEYR: u64 = 0x0c;
+
+    bitfield! {
+        /// The status register (SR) of the FLASH. This register is described in "RM0444: STM32G0x1
+        /// reference manual" section 3.7.5.
+        pub struct Status(u32);
+        impl Debug;
+
+        pub bsy1, _: 16;
+    }
+
+    impl Status {
+        /// The offset of the Status register in the FLASH block.
+        const ADDRESS: u64 = 0x10;
+
+        /// Read the status register from memory.
+        pub fn read(memory: &mut dyn ArmProbe) -> Result<Self, ArmError> {
+            let contents = memory.read_word_32(FLASH + Self::ADDRESS)?;
+            Ok(Self(contents))
+        }
+    }
+
+    bitfield! {
+        /// The control register (CR) of the FLASH. This register is described in "RM0444: STM32G0x1
+        /// reference manual" section 3.7.6.
+        pub struct Control(u32);
+        impl Debug;
+
+        pub lock, _: 31;
+        pub optlock, _: 30;
+        pub obl_launch, set_obl_launch: 27;
+        pub optstrt, set_optstrt: 17;
+    }
+
+    impl Control {
+        /// The offset of the Control register in the FLASH block.
+        const ADDRESS: u64 = 0x14;
+
+        /// Read the control register from memory.
+        pub fn read(memory: &mut dyn ArmProbe) -> Result<Self, ArmError> {
+            let contents = memory.read_word_32(FLASH + Self::ADDRESS)?;
+            Ok(Self(contents))
+        }
+
+        /// Write the control register to memory.
+        pub fn write(&mut self, memory: &mut dyn ArmProbe) -> Result<(), ArmError> {
+            memory.write_word_32(FLASH + Self::ADDRESS, self.0)
+        }
+    }
+
+    bitfield! {
+        /// The option register (OPTR) of the FLASH. This register is described in "RM0444: STM32G0x1
+        /// reference manual" section 3.7.8.
+        pub struct Options(u32);
+        impl Debug;
+
+        pub n_boot0, set_n_boot0: 26;
+        pub n_boot1, set_n_boot1: 25;
+        pub n_boot_sel, set_n_boot_sel: 24;
+        pub u8, rdp, _: 7, 0;
+    }
+
+    impl Options {
+        /// The offset of the Option register in the FLASH block.
+        const ADDRESS: u64 = 0x20;
+
+        /// Read the option register from memory.
+        pub fn read(memory: &mut dyn ArmProbe) -> Result<Self, ArmError> {
+            let contents = memory.read_word_32(FLASH + Self::ADDRESS)?;
+            Ok(Self(contents))
+        }
+
+        /// Write the option register to memory.
+        pub fn write(&mut self, memory: &mut dyn ArmProbe) -> Result<(), ArmError> {
+            memory.write_word_32(FLASH + Self::ADDRESS, self.0)
+        }
+    }
+
+    /// Unlock the control register and the option bytes.
+    pub fn unlock_options(memory: &mut dyn ArmProbe) -> Result<(), ArmError> {
+        let cr = Control::read(memory)?;
+
+        if cr.lock() {
+            memory.write_word_32(FLASH + KEYR, 0x4567_0123)?;
+            memory.write_word_32(FLASH + KEYR, 0xcdef_89ab)?;
+        }
+
+        if cr.optlock() {
+            memory.write_word_32(FLASH + OPTKEYR, 0x0819_2a3b)?;
+            memory.write_word_32(FLASH + OPTKEYR, 0x4c5d_6e7f)?;
+        }
+
+        Ok(())
+    }
+
+    /// Wait until the current flash operation is done.
+    pub fn wait_while_busy(memory: &mut dyn ArmProbe, timeout: Duration) -> Result<(), ArmError> {
+        let start = Instant::now();
+
+        while Status::read(memory)?.bsy1() {
+            if start.elapsed() > timeout {
+                return Err(ArmError::Timeout);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Program the option register and reload the option bytes.
+    ///
+    /// Reloading the option bytes resets the device, so the debug connection has to be
+    /// re-established afterwards.
+    pub fn program_options(
+        memory: &mut dyn ArmProbe,
+        mut options: Options,
+    ) -> Result<(), ArmError> {
+        unlock_options(memory)?;
+        wait_while_busy(memory, Duration::from_millis(100))?;
+
+        options.write(memory)?;
+
+        let mut cr = Control::read(memory)?;
+        cr.set_optstrt(true);
+        cr.write(memory)?;
+
+        // Lowering the read protection level includes a mass erase, which takes a while
+        wait_while_busy(memory, Duration::from_secs(1))?;
+
+        // Reloading the option bytes resets the device, which may drop this write's acknowledgement
+        let mut cr = Control::read(memory)?;
+        cr.set_obl_launch(true);
+        if let Err(e) = cr.write(memory).and_then(|_| memory.flush()) {
+            tracing::debug!("Error during option byte reload, as expected: {e}");
+        }
+
+        Ok(())
+    }
 }
 
 impl ArmDebugSequence for Stm32Armv6 {
@@ -117,23 +320,7 @@ impl ArmDebugSequence for Stm32Armv6 {
     ) -> Result<(), ArmError> {
         let mut memory = interface.memory_interface(default_ap)?;
 
-        match self.family {
-            Stm32Armv6Family::F0 => {
-                let mut enr = rcc::EnrF0::read(&mut *memory)?;
-                enr.enable_dbg(true);
-                enr.write(&mut *memory)?;
-            }
-            Stm32Armv6Family::L0 => {
-                let mut enr = rcc::EnrL0::read(&mut *memory)?;
-                enr.enable_dbg(true);
-                enr.write(&mut *memory)?;
-            }
-            Stm32Armv6Family::G0 => {
-                let mut enr = rcc::EnrG0::read(&mut *memory)?;
-                enr.enable_dbg(true);
-                enr.write(&mut *memory)?;
-            }
-        }
+        self.enable_dbgmcu_clock(&mut *memory, true)?;
 
         let mut cr = dbgmcu::Control::read(&mut *memory)?;
         cr.enable_standby_debug(true);
@@ -143,29 +330,116 @@ impl ArmDebugSequence for Stm32Armv6 {
         Ok(())
     }
 
-    fn debug_core_stop(
+    fn reset_init(
         &self,
         memory: &mut dyn ArmProbe,
         _core_type: CoreType,
+        _debug_base: Option<u64>,
     ) -> Result<(), ArmError> {
-        match self.family {
-            Stm32Armv6Family::F0 => {
-                let mut enr = rcc::EnrF0::read(&mut *memory)?;
-                enr.enable_dbg(false);
-                enr.write(&mut *memory)?;
-            }
-            Stm32Armv6Family::L0 => {
-                let mut enr = rcc::EnrL0::read(&mut *memory)?;
-                enr.enable_dbg(false);
-                enr.write(&mut *memory)?;
-            }
-            Stm32Armv6Family::G0 => {
-                let mut enr = rcc::EnrG0::read(&mut *memory)?;
-                enr.enable_dbg(false);
-                enr.write(&mut *memory)?;
-            }
+        // The reset disabled the DBGMCU clock again
+        self.enable_dbgmcu_clock(&mut *memory, true)?;
+
+        let mut cr = dbgmcu::Control::read(&mut *memory)?;
+        cr.enable_standby_debug(true);
+        cr.enable_stop_debug(true);
+        cr.write(&mut *memory)?;
+
+        // Keep the watchdogs from resetting the device while the core is halted
+        let mut freeze = dbgmcu::ApbFreeze::read(&mut *memory)?;
+        freeze.freeze_iwdg(true);
+        freeze.freeze_wwdg(true);
+        freeze.write(&mut *memory)?;
+
+        Ok(())
+    }
+
+    fn reset_to_bootloader(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        default_ap: MemoryAp,
+    ) -> Result<(), ArmError> {
+        // Only the STM32G0 can select the boot source in the option bytes
+        let Stm32Armv6Family::G0 = self.family else {
+            return Err(DebugProbeError::NotImplemented(
+                "Resetting into the bootloader is only available on STM32G0 devices",
+            )
+            .into());
+        };
+
+        let mut memory = interface.memory_interface(default_ap)?;
+
+        // Use the nBOOT0 option bit instead of the BOOT0 pin, and boot from system memory
+        let mut options = flash_g0::Options::read(&mut *memory)?;
+        options.set_n_boot_sel(true);
+        options.set_n_boot1(true);
+        options.set_n_boot0(false);
+
+        flash_g0::program_options(&mut *memory, options)?;
+
+        Err(ArmError::ReAttachRequired)
+    }
+
+    fn read_option_bytes(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        default_ap: MemoryAp,
+    ) -> Result<Vec<u32>, ArmError> {
+        let Stm32Armv6Family::G0 = self.family else {
+            return Err(DebugProbeError::NotImplemented(
+                "Option bytes are only available on STM32G0 devices",
+            )
+            .into());
+        };
+
+        let mut memory = interface.memory_interface(default_ap)?;
+
+        Ok(vec![flash_g0::Options::read(&mut *memory)?.0])
+    }
+
+    fn write_option_bytes(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        default_ap: MemoryAp,
+        option_bytes: &[u32],
+        force: bool,
+    ) -> Result<(), ArmError> {
+        let Stm32Armv6Family::G0 = self.family else {
+            return Err(DebugProbeError::NotImplemented(
+                "Option bytes are only available on STM32G0 devices",
+            )
+            .into());
+        };
+
+        let &[optr] = option_bytes else {
+            return Err(ArmError::Other(anyhow::anyhow!(
+                "Expected 1 option register for STM32G0 devices, got {}",
+                option_bytes.len()
+            )));
+        };
+
+        let options = flash_g0::Options(optr);
+
+        // Level 2 disables the debug port and can never be undone
+        if options.rdp() == 0xcc && !force {
+            return Err(ArmError::MissingPermissions(
+                "Setting read protection level 2 permanently locks the device".to_string(),
+            ));
         }
 
+        let mut memory = interface.memory_interface(default_ap)?;
+
+        flash_g0::program_options(&mut *memory, options)?;
+
+        Err(ArmError::ReAttachRequired)
+    }
+
+    fn debug_core_stop(
+        &self,
+        memory: &mut dyn ArmProbe,
+        _core_type: CoreType,
+    ) -> Result<(), ArmError> {
+        self.enable_dbgmcu_clock(&mut *memory, false)?;
+
         let mut cr = dbgmcu::Control::read(&mut *memory)?;
         cr.enable_standby_debug(false);
         cr.enable_stop_debug(false);
@@ -66,6 +66,113 @@ mod dbgmcu {
             memory.write_word_32(DBGMCU + Self::ADDRESS, self.0)
         }
     }
+
+    bitfield! {
+        /// The ID code register (IDCODE) of the DBGMCU. This register is described in "RM0090:
+        /// STM32F4 reference manual".
+        pub struct IdCode(u32);
+        impl Debug;
+
+        pub u16, dev_id, _: 11, 0;
+    }
+
+    impl IdCode {
+        /// The offset of the ID code register in the DBGMCU block.
+        const ADDRESS: u64 = 0x00;
+
+        /// Read the ID code register from memory.
+        pub fn read(memory: &mut dyn ArmProbe) -> Result<Self, ArmError> {
+            let contents = memory.read_word_32(DBGMCU + Self::ADDRESS)?;
+            Ok(Self(contents))
+        }
+
+        /// Whether the device is an STM32F1, which has the watchdog freeze bits in the control
+        /// register instead of the APB1 freeze register.
+        pub fn is_stm32f1(&self) -> bool {
+            matches!(
+                self.dev_id(),
+                0x410 | 0x412 | 0x414 | 0x418 | 0x420 | 0x428 | 0x430
+            )
+        }
+
+        /// Whether the device is an STM32WB or STM32WL, which have the APB1 freeze register
+        /// of the Cortex-M4 at a different offset.
+        fn is_stm32wb_or_wl(&self) -> bool {
+            matches!(self.dev_id(), 0x494 | 0x495 | 0x497)
+        }
+    }
+
+    bitfield! {
+        /// The control register (CR) of the DBGMCU on the STM32F1. This register is described
+        /// in "RM0008: STM32F101xx, STM32F102xx, STM32F103xx, STM32F105xx and STM32F107xx
+        /// reference manual".
+        pub struct ControlF1(u32);
+        impl Debug;
+
+        pub u8, dbg_wwdg_stop, freeze_wwdg: 9;
+        pub u8, dbg_iwdg_stop, freeze_iwdg: 8;
+    }
+
+    impl ControlF1 {
+        /// The offset of the Control register in the DBGMCU block.
+        const ADDRESS: u64 = 0x04;
+
+        /// Read the control register from memory.
+        pub fn read(memory: &mut dyn ArmProbe) -> Result<Self, ArmError> {
+            let contents = memory.read_word_32(DBGMCU + Self::ADDRESS)?;
+            Ok(Self(contents))
+        }
+
+        /// Write the control register to memory.
+        pub fn write(&mut self, memory: &mut dyn ArmProbe) -> Result<(), ArmError> {
+            memory.write_word_32(DBGMCU + Self::ADDRESS, self.0)
+        }
+    }
+
+    bitfield! {
+        /// The APB1 freeze register (APB1_FZ) of the DBGMCU, which stops peripherals while the
+        /// core is halted. This register is described in "RM0090: STM32F4 reference manual",
+        /// and is called APB1FZR1 on the STM32G4, STM32L4, STM32WB and STM32WL.
+        pub struct Apb1Freeze(u32);
+        impl Debug;
+
+        pub u8, dbg_iwdg_stop, freeze_iwdg: 12;
+        pub u8, dbg_wwdg_stop, freeze_wwdg: 11;
+    }
+
+    impl Apb1Freeze {
+        /// The offset of the APB1 freeze register in the DBGMCU block.
+        const ADDRESS: u64 = 0x08;
+
+        /// The offset of the APB1 freeze register of the Cortex-M4 on the STM32WB and STM32WL,
+        /// described in "RM0434: STM32WB55xx reference manual" and "RM0453: STM32WL5x reference
+        /// manual".
+        const ADDRESS_WB_WL: u64 = 0x3C;
+
+        /// The address of the APB1 freeze register on the device identified by `id_code`.
+        fn address(id_code: &IdCode) -> u64 {
+            if id_code.is_stm32wb_or_wl() {
+                DBGMCU + Self::ADDRESS_WB_WL
+            } else {
+                DBGMCU + Self::ADDRESS
+            }
+        }
+
+        /// Read the APB1 freeze register from memory.
+        pub fn read(memory: &mut dyn ArmProbe, id_code: &IdCode) -> Result<Self, ArmError> {
+            let contents = memory.read_word_32(Self::address(id_code))?;
+            Ok(Self(contents))
+        }
+
+        /// Write the APB1 freeze register to memory.
+        pub fn write(
+            &mut self,
+            memory: &mut dyn ArmProbe,
+            id_code: &IdCode,
+        ) -> Result<(), ArmError> {
+            memory.write_word_32(Self::address(id_code), self.0)
+        }
+    }
 }
 
 impl ArmDebugSequence for Stm32Armv7 {
@@ -86,6 +193,30 @@ impl ArmDebugSequence for Stm32Armv7 {
         Ok(())
     }
 
+    fn reset_init(
+        &self,
+        memory: &mut dyn ArmProbe,
+        _core_type: CoreType,
+        _debug_base: Option<u64>,
+    ) -> Result<(), ArmError> {
+        // Keep the watchdogs from resetting the device while the core is halted
+        let id_code = dbgmcu::IdCode::read(&mut *memory)?;
+
+        if id_code.is_stm32f1() {
+            let mut cr = dbgmcu::ControlF1::read(&mut *memory)?;
+            cr.freeze_iwdg(true);
+            cr.freeze_wwdg(true);
+            cr.write(&mut *memory)?;
+        } else {
+            let mut freeze = dbgmcu::Apb1Freeze::read(&mut *memory, &id_code)?;
+            freeze.freeze_iwdg(true);
+            freeze.freeze_wwdg(true);
+            freeze.write(&mut *memory, &id_code)?;
+        }
+
+        Ok(())
+    }
+
     fn debug_core_stop(
         &self,
         memory: &mut dyn ArmProbe,
@@ -121,3 +252,48 @@ impl ArmDebugSequence for Stm32Armv7 {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::architecture::arm::core::replay::{Op::*, ReplayMemory};
+
+    /// Run the reset init sequence with the recorded DBGMCU accesses of `memory`
+    fn reset_init(mut memory: ReplayMemory) {
+        Stm32Armv7::create()
+            .reset_init(&mut &mut memory, CoreType::Armv7em, None)
+            .unwrap();
+        memory.assert_finished();
+    }
+
+    #[test]
+    fn reset_init_freezes_watchdogs() {
+        // STM32F411
+        reset_init(ReplayMemory::new([
+            (Read, 0xE004_2000, 0x1000_6431),
+            (Read, 0xE004_2008, 0x0000_0000),
+            (Write, 0xE004_2008, 0x0000_1800),
+        ]));
+    }
+
+    #[test]
+    fn reset_init_freezes_watchdogs_on_stm32f1() {
+        // STM32F103 medium density
+        reset_init(ReplayMemory::new([
+            (Read, 0xE004_2000, 0x2003_6410),
+            (Read, 0xE004_2004, 0x0000_0007),
+            (Write, 0xE004_2004, 0x0000_0307),
+        ]));
+    }
+
+    #[test]
+    fn reset_init_freezes_watchdogs_on_stm32wl() {
+        // STM32WL55, APB1FZR1 of the Cortex-M4
+        reset_init(ReplayMemory::new([
+            (Read, 0xE004_2000, 0x1001_6497),
+            (Read, 0xE004_203C, 0x0000_0000),
+            (Write, 0xE004_203C, 0x0000_1800),
+        ]));
+    }
+}
@@ -115,6 +115,58 @@ mod dbgmcu {
             memory.write_word_32(DBGMCU + Self::ADDRESS, self.0)
         }
     }
+
+    bitfield! {
+        /// The APB3 freeze register (APB3FZ1) of the DBGMCU, which stops peripherals while the
+        /// core is halted. This register is described in "RM0433: STM32H7 family reference
+        /// manual".
+        pub struct Apb3Freeze(u32);
+        impl Debug;
+
+        pub u8, dbg_wwdg1, freeze_wwdg1: 6;
+    }
+
+    impl Apb3Freeze {
+        /// The offset of the APB3 freeze register in the DBGMCU block.
+        const ADDRESS: u64 = 0x34;
+
+        /// Read the APB3 freeze register from memory.
+        pub fn read(memory: &mut (impl ArmProbe + ?Sized)) -> Result<Self, ArmError> {
+            let contents = memory.read_word_32(DBGMCU + Self::ADDRESS)?;
+            Ok(Self(contents))
+        }
+
+        /// Write the APB3 freeze register to memory.
+        pub fn write(&mut self, memory: &mut (impl ArmProbe + ?Sized)) -> Result<(), ArmError> {
+            memory.write_word_32(DBGMCU + Self::ADDRESS, self.0)
+        }
+    }
+
+    bitfield! {
+        /// The APB4 freeze register (APB4FZ1) of the DBGMCU, which stops peripherals while the
+        /// core is halted. This register is described in "RM0433: STM32H7 family reference
+        /// manual".
+        pub struct Apb4Freeze(u32);
+        impl Debug;
+
+        pub u8, dbg_iwdg1, freeze_iwdg1: 18;
+    }
+
+    impl Apb4Freeze {
+        /// The offset of the APB4 freeze register in the DBGMCU block.
+        const ADDRESS: u64 = 0x54;
+
+        /// Read the APB4 freeze register from memory.
+        pub fn read(memory: &mut (impl ArmProbe + ?Sized)) -> Result<Self, ArmError> {
+            let contents = memory.read_word_32(DBGMCU + Self::ADDRESS)?;
+            Ok(Self(contents))
+        }
+
+        /// Write the APB4 freeze register to memory.
+        pub fn write(&mut self, memory: &mut (impl ArmProbe + ?Sized)) -> Result<(), ArmError> {
+            memory.write_word_32(DBGMCU + Self::ADDRESS, self.0)
+        }
+    }
 }
 
 /// Get the Coresight component associated with one of the trace funnels.
@@ -164,6 +216,24 @@ impl ArmDebugSequence for Stm32h7 {
         Ok(())
     }
 
+    fn reset_init(
+        &self,
+        memory: &mut dyn ArmProbe,
+        _core_type: CoreType,
+        _debug_base: Option<u64>,
+    ) -> Result<(), ArmError> {
+        // Keep the watchdogs from resetting the device while the core is halted
+        let mut apb3 = dbgmcu::Apb3Freeze::read(&mut *memory)?;
+        apb3.freeze_wwdg1(true);
+        apb3.write(&mut *memory)?;
+
+        let mut apb4 = dbgmcu::Apb4Freeze::read(&mut *memory)?;
+        apb4.freeze_iwdg1(true);
+        apb4.write(&mut *memory)?;
+
+        Ok(())
+    }
+
     fn debug_core_stop(
         &self,
         memory: &mut dyn ArmProbe,
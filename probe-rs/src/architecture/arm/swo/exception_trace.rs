@@ -0,0 +1,270 @@
+//! Decoder for the exception trace emitted by the DWT.
+//!
+//! With `DWT_CTRL.EXCTRCENA` set, the DWT emits a hardware source packet over the ITM
+//! whenever an exception is entered, exited or returned to, see the ARMv7-M Architecture
+//! Reference Manual, Appendix D4. The [`ExceptionTraceDecoder`] turns the raw SWO byte
+//! stream into [`ExceptionTraceEvent`]s, and uses the local timestamp packets to tell when
+//! each event happened.
+
+use std::collections::VecDeque;
+
+use super::itm::{ItmDecoder, ItmPacket};
+
+/// Discriminator of the hardware source packets carrying exception trace.
+const EXCEPTION_TRACE_DISCRIMINATOR: u8 = 1;
+
+/// What happened to an exception.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExceptionAction {
+    /// The processor entered the exception handler.
+    Entry,
+    /// The processor exited the exception handler.
+    Exit,
+    /// The processor returned to the exception, e.g. thread mode or a preempted handler.
+    Return,
+}
+
+/// A single exception trace event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExceptionTraceEvent {
+    /// The exception number, e.g. `11` for SVCall or `16 + n` for external interrupt `n`.
+    ///
+    /// `0` is used for thread mode when returning from the last active handler.
+    pub exception: u16,
+    /// What happened to the exception.
+    pub action: ExceptionAction,
+    /// When the event happened, in timestamp clock cycles since the start of the trace.
+    ///
+    /// This is the sum of all local timestamps received so far, so it is only meaningful if
+    /// local timestamps are enabled in the ITM. `None` if no timestamp followed the event,
+    /// e.g. because the ITM overflowed.
+    pub timestamp: Option<u64>,
+}
+
+/// Incremental decoder for the exception trace in an ITM byte stream.
+///
+/// Feed raw SWO data with [`ExceptionTraceDecoder::feed`], and retrieve the events with
+/// [`ExceptionTraceDecoder::next_event`] or by using the decoder as an iterator. All other
+/// packets are ignored.
+///
+/// The ITM emits a local timestamp after the packets it belongs to, so events are held back
+/// until their timestamp arrives. Use [`ExceptionTraceDecoder::without_timestamps`] if local
+/// timestamps are disabled, and [`ExceptionTraceDecoder::flush`] to get the remaining events
+/// at the end of a trace.
+#[derive(Debug)]
+pub struct ExceptionTraceDecoder {
+    itm: ItmDecoder,
+    /// Whether the stream contains local timestamps.
+    timestamps: bool,
+    /// Sum of all local timestamps received so far.
+    time: u64,
+    /// Events still waiting for their timestamp.
+    pending: Vec<ExceptionTraceEvent>,
+    /// Events ready to be returned.
+    events: VecDeque<ExceptionTraceEvent>,
+}
+
+impl Default for ExceptionTraceDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExceptionTraceDecoder {
+    /// Create a new decoder for a trace stream with local timestamps.
+    pub fn new() -> Self {
+        Self {
+            itm: ItmDecoder::new(),
+            timestamps: true,
+            time: 0,
+            pending: Vec::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Create a new decoder for a trace stream without local timestamps.
+    ///
+    /// Events are returned as soon as they are decoded, without a timestamp.
+    pub fn without_timestamps() -> Self {
+        Self {
+            timestamps: false,
+            ..Self::new()
+        }
+    }
+
+    /// Add raw trace data to the decoder.
+    pub fn feed(&mut self, data: &[u8]) {
+        self.itm.feed(data);
+    }
+
+    /// Return the events still waiting for a timestamp, e.g. at the end of a trace.
+    pub fn flush(&mut self) {
+        self.events.extend(self.pending.drain(..));
+    }
+
+    /// Decode the next complete event.
+    ///
+    /// Returns `None` if more data is needed.
+    pub fn next_event(&mut self) -> Option<ExceptionTraceEvent> {
+        while self.events.is_empty() {
+            let packet = self.itm.next_packet()?;
+            self.handle_packet(packet);
+        }
+
+        self.events.pop_front()
+    }
+
+    fn handle_packet(&mut self, packet: ItmPacket) {
+        match packet {
+            ItmPacket::Hardware {
+                discriminator: EXCEPTION_TRACE_DISCRIMINATOR,
+                payload,
+            } => {
+                let Some(event) = decode_exception_trace(&payload) else {
+                    tracing::debug!("Ignoring invalid exception trace packet {:x?}", payload);
+                    return;
+                };
+
+                if self.timestamps {
+                    self.pending.push(event);
+                } else {
+                    self.events.push_back(event);
+                }
+            }
+            ItmPacket::LocalTimestamp { delta, .. } => {
+                self.time += delta as u64;
+
+                let time = self.time;
+                self.events
+                    .extend(self.pending.drain(..).map(|event| ExceptionTraceEvent {
+                        timestamp: Some(time),
+                        ..event
+                    }));
+            }
+            // The timestamps of the pending events are lost
+            ItmPacket::Overflow => self.flush(),
+            _ => {}
+        }
+    }
+}
+
+impl Iterator for ExceptionTraceDecoder {
+    type Item = ExceptionTraceEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event()
+    }
+}
+
+/// Decode the payload of an exception trace packet.
+fn decode_exception_trace(payload: &[u8]) -> Option<ExceptionTraceEvent> {
+    let [low, high] = *payload else {
+        return None;
+    };
+
+    let action = match (high >> 4) & 0x3 {
+        0b01 => ExceptionAction::Entry,
+        0b10 => ExceptionAction::Exit,
+        0b11 => ExceptionAction::Return,
+        _ => return None,
+    };
+
+    Some(ExceptionTraceEvent {
+        exception: low as u16 | ((high & 0x1) as u16) << 8,
+        action,
+        timestamp: None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn events_are_timestamped() {
+        let mut decoder = ExceptionTraceDecoder::new();
+
+        decoder.feed(&[
+            0x0e, 0x2f, 0x11, // Entry of exception 303
+            0xc0, 0x90, 0x01, // Local timestamp, 0x90 cycles
+            0x0e, 0x2f, 0x21, // Exit of exception 303
+            0x0e, 0x00, 0x30, // Return to thread mode
+        ]);
+
+        assert_eq!(
+            decoder.next_event(),
+            Some(ExceptionTraceEvent {
+                exception: 303,
+                action: ExceptionAction::Entry,
+                timestamp: Some(0x90),
+            })
+        );
+
+        // The other events wait for their timestamp
+        assert_eq!(decoder.next_event(), None);
+
+        decoder.feed(&[0x20]);
+
+        assert_eq!(
+            decoder.collect::<Vec<_>>(),
+            vec![
+                ExceptionTraceEvent {
+                    exception: 303,
+                    action: ExceptionAction::Exit,
+                    timestamp: Some(0x92),
+                },
+                ExceptionTraceEvent {
+                    exception: 0,
+                    action: ExceptionAction::Return,
+                    timestamp: Some(0x92),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn events_without_timestamps() {
+        let mut decoder = ExceptionTraceDecoder::without_timestamps();
+
+        decoder.feed(&[
+            0x0e, 0x0f, 0x10, // Entry of SysTick
+            0x01, b'a', // Stimulus port 0, ignored
+            0x0e, 0x0f, 0x00, // Reserved function, ignored
+            0x0e, 0x0f, 0x20, // Exit of SysTick
+        ]);
+
+        assert_eq!(
+            decoder.collect::<Vec<_>>(),
+            vec![
+                ExceptionTraceEvent {
+                    exception: 15,
+                    action: ExceptionAction::Entry,
+                    timestamp: None,
+                },
+                ExceptionTraceEvent {
+                    exception: 15,
+                    action: ExceptionAction::Exit,
+                    timestamp: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn flush_returns_pending_events() {
+        let mut decoder = ExceptionTraceDecoder::new();
+
+        decoder.feed(&[0x0e, 0x0b, 0x10]);
+        assert_eq!(decoder.next_event(), None);
+
+        decoder.flush();
+        assert_eq!(
+            decoder.next_event(),
+            Some(ExceptionTraceEvent {
+                exception: 11,
+                action: ExceptionAction::Entry,
+                timestamp: None,
+            })
+        );
+    }
+}
@@ -0,0 +1,435 @@
+//! Decoder for the ITM (Instrumentation Trace Macrocell) protocol.
+//!
+//! The ITM protocol is described in the ARMv7-M Architecture Reference Manual, Appendix D4.
+//! The [`ItmDecoder`] turns the raw byte stream received over SWO into [`ItmPacket`]s.
+
+use std::collections::VecDeque;
+
+/// Minimum number of zero bytes before the terminating `0x80` of a synchronization packet.
+///
+/// A synchronization packet is at least 47 zero bits followed by a single one bit.
+const SYNC_ZERO_BYTES: usize = 5;
+
+/// A single packet decoded from an ITM trace stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ItmPacket {
+    /// A synchronization packet.
+    Sync,
+    /// The ITM or the trace sink dropped data because its FIFO overflowed.
+    Overflow,
+    /// Data written by software to a stimulus port.
+    Instrumentation {
+        /// The stimulus port number, 0 to 31.
+        port: u8,
+        /// The payload, which is either 1, 2 or 4 bytes long.
+        payload: Vec<u8>,
+    },
+    /// Data emitted by a hardware source, e.g. the DWT.
+    Hardware {
+        /// The discriminator identifying the kind of hardware packet.
+        discriminator: u8,
+        /// The payload, which is either 1, 2 or 4 bytes long.
+        payload: Vec<u8>,
+    },
+    /// A local timestamp.
+    LocalTimestamp {
+        /// Number of timestamp clock cycles since the previous local timestamp.
+        delta: u32,
+        /// Relationship of the timestamp to the packet it belongs to, the `TC` field of the header.
+        ///
+        /// `0` means the timestamp is synchronous to the corresponding data.
+        relation: u8,
+    },
+    /// The low-order bits `[25:0]` of a global timestamp.
+    GlobalTimestamp1 {
+        /// Bits `[25:0]` of the global timestamp. Only the changed bits are transmitted.
+        value: u32,
+        /// The high-order bits changed since the last `GlobalTimestamp2` packet.
+        wrap: bool,
+        /// The system has asserted a clock change input.
+        clock_change: bool,
+    },
+    /// The high-order bits `[63:26]` of a global timestamp.
+    GlobalTimestamp2 {
+        /// Bits `[63:26]` of the global timestamp, shifted down to bit 0.
+        value: u64,
+    },
+    /// An extension packet, e.g. the page number of the following stimulus ports.
+    Extension {
+        /// `true` if the extension information comes from a hardware source.
+        hardware: bool,
+        /// The extension information.
+        value: u32,
+    },
+}
+
+/// Kind of a multi-byte packet where the payload length is given by continuation bits.
+#[derive(Debug, Clone, Copy)]
+enum ContinuationKind {
+    LocalTimestamp { relation: u8 },
+    GlobalTimestamp1,
+    GlobalTimestamp2,
+    Extension { hardware: bool, header: u8 },
+}
+
+impl ContinuationKind {
+    /// Maximum number of payload bytes following the header.
+    fn max_len(&self) -> usize {
+        match self {
+            ContinuationKind::LocalTimestamp { .. } => 4,
+            ContinuationKind::GlobalTimestamp1 => 4,
+            ContinuationKind::GlobalTimestamp2 => 6,
+            ContinuationKind::Extension { .. } => 4,
+        }
+    }
+}
+
+/// Incremental decoder for an ITM byte stream.
+///
+/// Feed raw bytes with [`ItmDecoder::feed`], and retrieve the packets with
+/// [`ItmDecoder::next_packet`] or by using the decoder as an iterator.
+/// Incomplete packets are kept until the remaining bytes arrive.
+///
+/// The decoder never gets stuck on bad data: reserved headers and malformed
+/// packets are skipped one byte at a time until a valid header is found again,
+/// and a synchronization packet always restarts decoding at a packet boundary.
+#[derive(Debug, Default)]
+pub struct ItmDecoder {
+    buf: VecDeque<u8>,
+    /// Number of bytes dropped because they could not be decoded.
+    skipped_bytes: usize,
+}
+
+impl ItmDecoder {
+    /// Create a new decoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add raw trace data to the decoder.
+    pub fn feed(&mut self, data: &[u8]) {
+        self.buf.extend(data);
+    }
+
+    /// The number of bytes which were dropped so far because they did not form a valid packet.
+    pub fn skipped_bytes(&self) -> usize {
+        self.skipped_bytes
+    }
+
+    /// Decode the next complete packet.
+    ///
+    /// Returns `None` if more data is needed.
+    pub fn next_packet(&mut self) -> Option<ItmPacket> {
+        loop {
+            let header = *self.buf.front()?;
+
+            match self.decode(header) {
+                Decoded::Packet(packet, len) => {
+                    self.buf.drain(..len);
+                    return Some(packet);
+                }
+                Decoded::Incomplete => return None,
+                Decoded::Invalid(len) => {
+                    tracing::debug!("Skipping {} invalid ITM byte(s)", len);
+                    self.buf.drain(..len);
+                    self.skipped_bytes += len;
+                }
+            }
+        }
+    }
+
+    /// Try to decode a packet starting with `header` at the front of the buffer.
+    fn decode(&self, header: u8) -> Decoded {
+        match header {
+            0x00 => self.decode_sync(),
+            0x70 => Decoded::Packet(ItmPacket::Overflow, 1),
+            // Local timestamp, format 2: the timestamp value is contained in the header
+            _ if header & 0x8f == 0 => Decoded::Packet(
+                ItmPacket::LocalTimestamp {
+                    delta: (header >> 4) as u32,
+                    relation: 0,
+                },
+                1,
+            ),
+            // Local timestamp, format 1
+            _ if header & 0xcf == 0xc0 => {
+                self.decode_continuation(ContinuationKind::LocalTimestamp {
+                    relation: (header >> 4) & 0x3,
+                })
+            }
+            0x94 => self.decode_continuation(ContinuationKind::GlobalTimestamp1),
+            0xb4 => self.decode_continuation(ContinuationKind::GlobalTimestamp2),
+            _ if header & 0x0b == 0x08 => self.decode_continuation(ContinuationKind::Extension {
+                hardware: header & 0x04 != 0,
+                header,
+            }),
+            _ if header & 0x03 != 0 => self.decode_source(header),
+            // Reserved header
+            _ => Decoded::Invalid(1),
+        }
+    }
+
+    /// Decode a synchronization packet, which consists of at least 47 zero bits followed by a one bit.
+    fn decode_sync(&self) -> Decoded {
+        let zeros = self.buf.iter().take_while(|&&b| b == 0).count();
+
+        match self.buf.get(zeros) {
+            None => Decoded::Incomplete,
+            Some(0x80) if zeros >= SYNC_ZERO_BYTES => Decoded::Packet(ItmPacket::Sync, zeros + 1),
+            // Not a valid synchronization packet, skip the zero bytes and continue with the next byte
+            Some(_) => Decoded::Invalid(zeros),
+        }
+    }
+
+    /// Decode a stimulus port or hardware source packet.
+    fn decode_source(&self, header: u8) -> Decoded {
+        let len = match header & 0x3 {
+            1 => 1,
+            2 => 2,
+            _ => 4,
+        };
+
+        if self.buf.len() < len + 1 {
+            return Decoded::Incomplete;
+        }
+
+        let payload = self.buf.iter().skip(1).take(len).copied().collect();
+        let id = header >> 3;
+
+        let packet = if header & 0x04 == 0 {
+            ItmPacket::Instrumentation { port: id, payload }
+        } else {
+            ItmPacket::Hardware {
+                discriminator: id,
+                payload,
+            }
+        };
+
+        Decoded::Packet(packet, len + 1)
+    }
+
+    /// Decode a packet whose payload bytes are terminated by a cleared continuation bit.
+    fn decode_continuation(&self, kind: ContinuationKind) -> Decoded {
+        // Packets without the continuation bit in the header only consist of the header
+        let header = self.buf[0];
+        let mut payload = Vec::new();
+
+        if header & 0x80 != 0 {
+            for &byte in self.buf.iter().skip(1) {
+                payload.push(byte);
+
+                if byte & 0x80 == 0 {
+                    break;
+                }
+
+                if payload.len() == kind.max_len() {
+                    // The last allowed byte still has its continuation bit set, the packet is corrupt
+                    return Decoded::Invalid(1);
+                }
+            }
+
+            match payload.last() {
+                Some(byte) if byte & 0x80 == 0 => {}
+                _ => return Decoded::Incomplete,
+            }
+        }
+
+        let value = payload.iter().enumerate().fold(0u64, |value, (i, byte)| {
+            value | ((byte & 0x7f) as u64) << (7 * i)
+        });
+
+        let packet = match kind {
+            ContinuationKind::LocalTimestamp { relation } => ItmPacket::LocalTimestamp {
+                delta: value as u32,
+                relation,
+            },
+            ContinuationKind::GlobalTimestamp1 => {
+                // The fourth payload byte only holds bits [25:21], together with two flags
+                let (value, wrap, clock_change) = if payload.len() == 4 {
+                    let last = payload[3];
+                    (
+                        (value & 0x1f_ffff) as u32 | ((last & 0x1f) as u32) << 21,
+                        last & 0x40 != 0,
+                        last & 0x20 != 0,
+                    )
+                } else {
+                    (value as u32, false, false)
+                };
+
+                ItmPacket::GlobalTimestamp1 {
+                    value,
+                    wrap,
+                    clock_change,
+                }
+            }
+            ContinuationKind::GlobalTimestamp2 => ItmPacket::GlobalTimestamp2 { value },
+            ContinuationKind::Extension { hardware, header } => ItmPacket::Extension {
+                hardware,
+                // The header holds the lowest three bits of the extension information
+                value: ((header >> 4) & 0x7) as u32 | (value as u32) << 3,
+            },
+        };
+
+        Decoded::Packet(packet, payload.len() + 1)
+    }
+}
+
+impl Iterator for ItmDecoder {
+    type Item = ItmPacket;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_packet()
+    }
+}
+
+/// Result of trying to decode a packet at the front of the buffer.
+enum Decoded {
+    /// A complete packet, and the number of bytes it occupies.
+    Packet(ItmPacket, usize),
+    /// More data is needed to decode the packet.
+    Incomplete,
+    /// The given number of bytes does not form a valid packet and has to be skipped.
+    Invalid(usize),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn decode(data: &[u8]) -> Vec<ItmPacket> {
+        let mut decoder = ItmDecoder::new();
+        decoder.feed(data);
+        decoder.collect()
+    }
+
+    #[test]
+    fn stimulus_packets() {
+        let packets = decode(&[0x01, b'a', 0x0a, 0x34, 0x12, 0x1b, 0x78, 0x56, 0x34, 0x12]);
+
+        assert_eq!(
+            packets,
+            vec![
+                ItmPacket::Instrumentation {
+                    port: 0,
+                    payload: vec![b'a']
+                },
+                ItmPacket::Instrumentation {
+                    port: 1,
+                    payload: vec![0x34, 0x12]
+                },
+                ItmPacket::Instrumentation {
+                    port: 3,
+                    payload: vec![0x78, 0x56, 0x34, 0x12]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn hardware_packet() {
+        // Discriminator 1, exception trace
+        let packets = decode(&[0x0e, 0x0f, 0x10]);
+
+        assert_eq!(
+            packets,
+            vec![ItmPacket::Hardware {
+                discriminator: 1,
+                payload: vec![0x0f, 0x10]
+            }]
+        );
+    }
+
+    #[test]
+    fn sync_and_overflow() {
+        let packets = decode(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x70]);
+
+        assert_eq!(packets, vec![ItmPacket::Sync, ItmPacket::Overflow]);
+    }
+
+    #[test]
+    fn timestamps() {
+        let packets = decode(&[
+            0x30, // Local timestamp, format 2
+            0xc0, 0x81, 0x01, // Local timestamp, format 1
+            0x94, 0x81, 0x82, 0x83, 0x61, // Global timestamp 1, with wrap and clock change
+            0xb4, 0x85, 0x03, // Global timestamp 2
+        ]);
+
+        assert_eq!(
+            packets,
+            vec![
+                ItmPacket::LocalTimestamp {
+                    delta: 3,
+                    relation: 0
+                },
+                ItmPacket::LocalTimestamp {
+                    delta: 0x81,
+                    relation: 0
+                },
+                ItmPacket::GlobalTimestamp1 {
+                    value: 0x1 | 0x2 << 7 | 0x3 << 14 | 0x1 << 21,
+                    wrap: true,
+                    clock_change: true,
+                },
+                ItmPacket::GlobalTimestamp2 {
+                    value: 0x5 | 0x3 << 7
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn partial_packets_are_buffered() {
+        let mut decoder = ItmDecoder::new();
+
+        decoder.feed(&[0x03, 0x01, 0x02]);
+        assert_eq!(decoder.next_packet(), None);
+
+        decoder.feed(&[0x03, 0x04]);
+        assert_eq!(
+            decoder.next_packet(),
+            Some(ItmPacket::Instrumentation {
+                port: 0,
+                payload: vec![1, 2, 3, 4]
+            })
+        );
+        assert_eq!(decoder.next_packet(), None);
+    }
+
+    #[test]
+    fn resynchronizes_after_corrupt_data() {
+        let packets = decode(&[
+            0x04, // Reserved header
+            0xc0, 0x80, 0x80, 0x80, 0x80, // Local timestamp without end
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x80, // Sync
+            0x01, b'x',
+        ]);
+
+        assert_eq!(packets.first(), Some(&ItmPacket::Sync));
+        assert_eq!(
+            packets.last(),
+            Some(&ItmPacket::Instrumentation {
+                port: 0,
+                payload: vec![b'x']
+            })
+        );
+    }
+
+    #[test]
+    fn skipped_bytes_are_counted() {
+        let mut decoder = ItmDecoder::new();
+        decoder.feed(&[0x04, 0x70, 0x01, b'y']);
+
+        assert_eq!(
+            decoder.by_ref().collect::<Vec<_>>(),
+            vec![
+                ItmPacket::Overflow,
+                ItmPacket::Instrumentation {
+                    port: 0,
+                    payload: vec![b'y']
+                }
+            ]
+        );
+        assert_eq!(decoder.skipped_bytes(), 1);
+    }
+}
@@ -4,6 +4,11 @@ use crate::architecture::arm::communication_interface::ArmProbeInterface;
 
 use super::ArmError;
 
+pub mod exception_trace;
+pub mod itm;
+
+use std::collections::VecDeque;
+
 /// The protocol the SWO pin should use for data transmission.
 #[derive(Debug, Copy, Clone)]
 pub enum SwoMode {
@@ -147,6 +152,14 @@ pub trait SwoAccess {
     fn swo_buffer_size(&mut self) -> Option<usize> {
         None
     }
+
+    /// Check if the probe dropped SWO data since the last call, because its trace buffer
+    /// overflowed.
+    ///
+    /// Probes which can't detect this always return `false`.
+    fn take_swo_overrun(&mut self) -> bool {
+        false
+    }
 }
 
 /// Helper function to compute a poll interval from a SwoConfig and SWO buffer size.
@@ -167,46 +180,82 @@ pub(crate) fn poll_interval_from_buf_size(
     Some(std::time::Duration::from_millis(time_to_full_ms as u64 / 4))
 }
 
+/// Default capacity of the [SwoReader] ring buffer, in bytes.
+const DEFAULT_BUFFER_CAPACITY: usize = 64 * 1024;
+
 /// A reader interface to pull SWO data from the underlying driver.
+///
+/// Received bytes are stored in a bounded ring buffer. If the buffer is not
+/// drained quickly enough, the oldest bytes are discarded and counted,
+/// see [SwoReader::overflow_count].
 pub struct SwoReader<'a> {
     interface: &'a mut dyn ArmProbeInterface,
-    buf: Vec<u8>,
+    buf: VecDeque<u8>,
+    capacity: usize,
+    overflow_count: usize,
 }
 
 impl<'a> SwoReader<'a> {
     pub(crate) fn new(interface: &'a mut dyn ArmProbeInterface) -> Self {
         Self {
             interface,
-            buf: Vec::new(),
+            buf: VecDeque::new(),
+            capacity: DEFAULT_BUFFER_CAPACITY,
+            overflow_count: 0,
+        }
+    }
+
+    /// Set the capacity of the ring buffer, in bytes.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// The number of bytes which were discarded because the ring buffer was full.
+    pub fn overflow_count(&self) -> usize {
+        self.overflow_count
+    }
+
+    /// The number of bytes currently held in the ring buffer.
+    pub fn buffered_len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Pull all available bytes from the probe into the ring buffer.
+    fn fill_buffer(&mut self) -> Result<(), ArmError> {
+        let data = self.interface.read_swo()?;
+
+        self.buf.extend(data);
+
+        if self.buf.len() > self.capacity {
+            let excess = self.buf.len() - self.capacity;
+
+            tracing::warn!("SWO buffer overflow, discarding {} bytes", excess);
+
+            self.buf.drain(..excess);
+            self.overflow_count += excess;
         }
+
+        Ok(())
     }
 }
 
 impl<'a> std::io::Read for SwoReader<'a> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        use core::cmp;
-        use std::{
-            io::{Error, ErrorKind},
-            mem,
-        };
+        use std::io::{Error, ErrorKind};
 
         // Always buffer: this pulls data as quickly as possible from
         // the target to clear it's embedded trace buffer, minimizing
         // the chance of an overflow event during which packets are
         // lost.
-        self.buf.append(
-            &mut self
-                .interface
-                .read_swo()
-                .map_err(|e| Error::new(ErrorKind::Other, e))?,
-        );
-
-        let swo = {
-            let next_buf = self.buf.split_off(cmp::min(self.buf.len(), buf.len()));
-            mem::replace(&mut self.buf, next_buf)
-        };
-
-        buf[..swo.len()].copy_from_slice(&swo);
-        Ok(swo.len())
+        self.fill_buffer()
+            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+
+        let len = buf.len().min(self.buf.len());
+        for (dst, src) in buf.iter_mut().zip(self.buf.drain(..len)) {
+            *dst = src;
+        }
+
+        Ok(len)
     }
 }
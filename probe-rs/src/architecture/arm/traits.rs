@@ -41,6 +41,17 @@ pub enum DpAddress {
     Multidrop(u32),
 }
 
+impl DpAddress {
+    /// Select the DP with the given `TARGETID` and instance on a SWDv2 multidrop bus.
+    ///
+    /// `target_id` is the value of the DP `TARGETID` register, its revision field is ignored.
+    /// `instance` is the `TINSTANCE` value, which tells apart DPs with the same `TARGETID`,
+    /// e.g. the two cores of an RP2040.
+    pub fn multidrop(target_id: u32, instance: u8) -> Self {
+        DpAddress::Multidrop((instance as u32 & 0xf) << 28 | (target_id & 0x0fff_ffff) | 1)
+    }
+}
+
 /// Access port address.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct ApAddress {
@@ -60,6 +71,60 @@ impl ApAddress {
     }
 }
 
+/// A single register access in a batch of DAP transfers.
+///
+/// See [`RawDapAccess::raw_transfer_batch`] and [`DapAccess::transfer_batch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DapTransfer {
+    /// The port of the register.
+    pub port: PortType,
+    /// The address of the register.
+    pub address: u8,
+    /// `true` if the register is written, `false` if it is read.
+    pub write: bool,
+    /// The value to write, or the value which was read once the batch is executed.
+    pub value: u32,
+}
+
+impl DapTransfer {
+    /// Read the register at `address`.
+    pub fn read(port: PortType, address: u8) -> Self {
+        Self {
+            port,
+            address,
+            write: false,
+            value: 0,
+        }
+    }
+
+    /// Write `value` to the register at `address`.
+    pub fn write(port: PortType, address: u8, value: u32) -> Self {
+        Self {
+            port,
+            address,
+            write: true,
+            value,
+        }
+    }
+}
+
+/// Execute `transfers` one after the other, using `transfer` for each of them.
+///
+/// An error is attributed to the failed transfer using [`ArmError::BatchTransfer`].
+pub(crate) fn transfer_sequentially(
+    transfers: &mut [DapTransfer],
+    mut transfer: impl FnMut(&mut DapTransfer) -> Result<(), ArmError>,
+) -> Result<(), ArmError> {
+    for (index, item) in transfers.iter_mut().enumerate() {
+        transfer(item).map_err(|source| ArmError::BatchTransfer {
+            index,
+            source: Box::new(source),
+        })?;
+    }
+
+    Ok(())
+}
+
 /// Low-level DAP register access.
 ///
 /// Operations on this trait closely match the transactions on the wire. Implementors
@@ -125,6 +190,24 @@ pub trait RawDapAccess {
         Ok(())
     }
 
+    /// Execute a batch of register reads and writes, in order.
+    ///
+    /// The read values are stored in the transfers. Probes which support it send the batch
+    /// in as few packets as possible, instead of one round trip per read. If a transfer fails,
+    /// [`ArmError::BatchTransfer`] contains its index.
+    ///
+    /// Only the lowest 4 bits of the addresses are used. Bank switching is the caller's responsibility.
+    fn raw_transfer_batch(&mut self, transfers: &mut [DapTransfer]) -> Result<(), ArmError> {
+        transfer_sequentially(transfers, |transfer| {
+            if transfer.write {
+                self.raw_write_register(transfer.port, transfer.address, transfer.value)
+            } else {
+                transfer.value = self.raw_read_register(transfer.port, transfer.address)?;
+                Ok(())
+            }
+        })
+    }
+
     /// Flush any outstanding writes.
     ///
     /// By default, this does nothing -- but in probes that implement write
@@ -259,4 +342,36 @@ pub trait DapAccess {
         }
         Ok(())
     }
+
+    /// Execute a batch of register reads and writes on the access port `ap`, in order.
+    ///
+    /// Transfers with [`PortType::AccessPort`] access registers of `ap`, transfers with
+    /// [`PortType::DebugPort`] access registers of its debug port. The read values are
+    /// stored in the transfers. If a transfer fails, [`ArmError::BatchTransfer`] contains its index.
+    ///
+    /// Highest 4 bits of the addresses are interpreted as the bank number, implementations
+    /// will do bank switching if necessary.
+    fn transfer_batch(
+        &mut self,
+        ap: ApAddress,
+        transfers: &mut [DapTransfer],
+    ) -> Result<(), ArmError> {
+        transfer_sequentially(transfers, |transfer| {
+            match (transfer.port, transfer.write) {
+                (PortType::DebugPort, true) => {
+                    self.write_raw_dp_register(ap.dp, transfer.address, transfer.value)?
+                }
+                (PortType::DebugPort, false) => {
+                    transfer.value = self.read_raw_dp_register(ap.dp, transfer.address)?
+                }
+                (PortType::AccessPort, true) => {
+                    self.write_raw_ap_register(ap, transfer.address, transfer.value)?
+                }
+                (PortType::AccessPort, false) => {
+                    transfer.value = self.read_raw_ap_register(ap, transfer.address)?
+                }
+            }
+            Ok(())
+        })
+    }
 }
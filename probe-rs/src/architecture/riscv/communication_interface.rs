@@ -14,7 +14,8 @@ use crate::{
     memory::valid_32bit_address,
     memory_mapped_bitfield_register,
     probe::{DeferredResultIndex, JTAGAccess},
-    DebugProbeError, Error as ProbeRsError, MemoryInterface, MemoryMappedRegister, Probe,
+    DebugProbe, DebugProbeError, Error as ProbeRsError, MemoryInterface, MemoryMappedRegister,
+    Probe,
 };
 use std::{
     collections::HashMap,
@@ -330,6 +331,11 @@ impl RiscvCommunicationInterface {
         Ok(s)
     }
 
+    /// Assert the target reset.
+    pub fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
+        self.dtm.target_reset_assert()
+    }
+
     /// Deassert the target reset.
     pub fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
         self.dtm.target_reset_deassert()
@@ -1346,6 +1352,11 @@ impl RiscvCommunicationInterface {
         Ok(())
     }
 
+    /// Get the maximum speed of the debug protocol in kHz.
+    pub(crate) fn speed_khz(&self) -> u32 {
+        self.dtm.probe.speed_khz()
+    }
+
     /// Destruct the interface and return the stored probe driver.
     pub fn close(self) -> Probe {
         Probe::from_attached_probe(self.dtm.probe.into_probe())
@@ -265,7 +265,11 @@ impl<'probe> CoreInterface for Riscv32<'probe> {
 
         let pc = self.read_core_reg(self.program_counter().into())?;
 
-        Ok(CoreInformation { pc: pc.try_into()? })
+        Ok(CoreInformation {
+            pc: pc.try_into()?,
+            sp: None,
+            lr: None,
+        })
     }
     fn run(&mut self) -> Result<(), crate::Error> {
         // Before we run, we always perform a single instruction step, to account for possible breakpoints that might get us stuck on the current instruction.
@@ -362,7 +366,11 @@ impl<'probe> CoreInterface for Riscv32<'probe> {
 
         let pc = self.read_core_reg(RegisterId(0x7b1))?;
 
-        Ok(CoreInformation { pc: pc.try_into()? })
+        Ok(CoreInformation {
+            pc: pc.try_into()?,
+            sp: None,
+            lr: None,
+        })
     }
 
     fn step(&mut self) -> Result<crate::core::CoreInformation, crate::Error> {
@@ -387,6 +395,8 @@ impl<'probe> CoreInterface for Riscv32<'probe> {
             self.write_core_reg(RegisterId(0x7b1), debug_pc)?;
             return Ok(CoreInformation {
                 pc: debug_pc.try_into()?,
+                sp: None,
+                lr: None,
             });
         } else if matches!(
             halt_reason,
@@ -427,7 +437,11 @@ impl<'probe> CoreInterface for Riscv32<'probe> {
             self.enable_breakpoints(true)?;
         }
 
-        Ok(CoreInformation { pc: pc.try_into()? })
+        Ok(CoreInformation {
+            pc: pc.try_into()?,
+            sp: None,
+            lr: None,
+        })
     }
 
     fn read_core_reg(&mut self, address: RegisterId) -> Result<RegisterValue, crate::Error> {
@@ -6,7 +6,7 @@ use anyhow::{anyhow, Context, Result};
 use clap::Parser;
 use colored::*;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use probe_rs::gdb_server::GdbInstanceConfiguration;
+use probe_rs::gdb_server::{GdbInstanceConfiguration, GdbServer};
 use probe_rs::rtt::{Rtt, ScanRegion};
 use probe_rs::Lister;
 use probe_rs::{
@@ -184,6 +184,7 @@ fn main_try(mut args: Vec<OsString>, offset: UtcOffset) -> Result<()> {
                     vendor_id: u16::from_str_radix(vid, 16)?,
                     product_id: u16::from_str_radix(pid, 16)?,
                     serial_number: config.probe.serial.clone(),
+                    cmsisdap_v1: false,
                 };
                 // if two probes with the same VID:PID pair exist we just choose one
                 lister.open(selector)?
@@ -301,18 +302,26 @@ fn main_try(mut args: Vec<OsString>, offset: UtcOffset) -> Result<()> {
             let gdb_connection_string =
                 gdb_connection_string.as_deref().unwrap_or("127.0.0.1:1337");
 
-            logging::println(format!(
-                "    {} listening at {}",
-                "GDB stub".green().bold(),
-                gdb_connection_string,
-            ));
-
             let instances = {
                 let session = session.lock().unwrap();
                 GdbInstanceConfiguration::from_session(&session, Some(gdb_connection_string))
             };
 
-            if let Err(e) = probe_rs::gdb_server::run(&session, instances.iter()) {
+            let server = instances.and_then(|instances| {
+                let server = GdbServer::bind(&session, instances.iter())?;
+
+                for addr in server.local_addrs()? {
+                    logging::println(format!(
+                        "    {} listening at {}",
+                        "GDB stub".green().bold(),
+                        addr,
+                    ));
+                }
+
+                Ok(server)
+            });
+
+            if let Err(e) = server.and_then(|mut server| server.run()) {
                 logging::eprintln("During the execution of GDB an error was encountered:");
                 logging::eprintln(format!("{e:?}"));
             }
@@ -71,7 +71,7 @@ pub(crate) fn render_diagnostics(error: OperationError) {
                 "This could be a permission issue. Check our guide on how to make all probes work properly on your system: https://probe.rs/docs/getting-started/probe-setup".into()
             ],
         ),
-        OperationError::MultipleProbesFound { .. } => (
+        OperationError::MultipleProbesFound(_) => (
             error.to_string(),
             vec![
                 "You can select a probe with the `--probe` argument. See `--help` for how to use it.".into()
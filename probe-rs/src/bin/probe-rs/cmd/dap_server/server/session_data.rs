@@ -79,24 +79,18 @@ impl SessionData {
                 }
                 other_error => DebuggerError::DebugProbe(other_error),
             }),
-            None => {
-                // Only automatically select a probe if there is only a single probe detected.
-                let list = lister.list_all();
-                if list.len() > 1 {
-                    return Err(DebuggerError::Other(anyhow!(
-                        "Found multiple ({}) probes",
-                        list.len()
-                    )));
-                }
-
-                if let Some(info) = list.first() {
-                    lister.open(info).map_err(DebuggerError::DebugProbe)
-                } else {
-                    return Err(DebuggerError::Other(anyhow!(
+            // Only automatically select a probe if there is only a single probe detected.
+            None => lister.open_single().map_err(|e| match e {
+                DebugProbeError::ProbeCouldNotBeCreated(ProbeCreationError::NotFound) => {
+                    DebuggerError::Other(anyhow!(
                         "No probes found. Please check your USB connections."
-                    )));
+                    ))
                 }
-            }
+                DebugProbeError::ProbeCouldNotBeCreated(
+                    e @ ProbeCreationError::MultipleProbesFound { .. },
+                ) => DebuggerError::Other(anyhow!("{e}")),
+                other_error => DebuggerError::DebugProbe(other_error),
+            }),
         }?;
 
         let target_selector = match &config.chip {
@@ -1,15 +1,20 @@
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use std::time::Duration;
 
+use probe_rs::gdb_server::{
+    GdbMemoryMap, GdbMemoryRegion, GdbServer, GdbServerConfig, SectionOffsets,
+};
 use probe_rs::Lister;
 
 use crate::util::common_options::ProbeOptions;
+use crate::util::parse_u64;
 
 #[derive(clap::Parser)]
 pub struct Cmd {
     #[clap(
         long,
-        help = "Use this flag to override the default GDB connection string (localhost:1337)."
+        help = "Use this flag to override the default GDB connection string (localhost:1337), e.g. 0.0.0.0:1337 to accept connections from other hosts. Use port 0 to let the operating system pick a free port."
     )]
     gdb_connection_string: Option<String>,
 
@@ -20,12 +25,89 @@ pub struct Cmd {
     )]
     reset_halt: bool,
 
+    #[clap(
+        long,
+        conflicts_with = "reset-halt",
+        help = "Use this flag to leave the cores running when GDB connects, e.g. to attach to a live system. GDB considers the target stopped until it is continued."
+    )]
+    no_halt: bool,
+
+    #[clap(
+        long,
+        value_name = "SECONDS",
+        help = "Send a keep-alive packet to GDB when the target has been running this long without any other traffic, for front-ends which time out during long runs."
+    )]
+    keep_alive: Option<u64>,
+
+    #[clap(
+        long,
+        value_name = "MILLISECONDS",
+        default_value_t = 10,
+        help = "Interval in which running cores are checked for a halt. Longer intervals reduce the load on the probe, but delay reporting breakpoints to GDB."
+    )]
+    halt_poll_interval: u64,
+
+    #[clap(
+        long,
+        value_name = "FILE",
+        help = "Log all packets exchanged with GDB to this file, with a timestamp and a short description of each packet. When several GDB stubs are started, the stub number is added to the file name of all but the first one."
+    )]
+    log_gdb_traffic: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "BYTES",
+        default_value_t = 4096,
+        help = "Maximum packet size advertised to GDB. Larger packets speed up reading and writing large blocks of memory."
+    )]
+    packet_size: usize,
+
+    #[clap(
+        long,
+        value_name = "DIRECTORY",
+        help = "Let GDB read and write files in this directory with its host I/O packets, e.g. for `remote get` and `remote put`. File names are resolved relative to the directory and cannot leave it."
+    )]
+    host_io_root: Option<PathBuf>,
+
+    #[clap(
+        long = "memory-region",
+        value_name = "KIND:START:LENGTH",
+        help = "Report this memory region to GDB instead of the memory map of the chip, e.g. `flash:0x08000000:0x10000` or `ram:0x20000000:0x5000`. Can be given multiple times, for chips which are not in the registry or lack parts of their memory description."
+    )]
+    memory_regions: Vec<GdbMemoryRegion>,
+
+    #[clap(
+        long,
+        value_parser = parse_u64,
+        help = "Offset of the .text section from its link address, for relocated images. Reported to GDB with qOffsets."
+    )]
+    text_offset: Option<u64>,
+
+    #[clap(
+        long,
+        value_parser = parse_u64,
+        requires = "text_offset",
+        help = "Offset of the .data and .bss sections from their link address. Defaults to the .text offset."
+    )]
+    data_offset: Option<u64>,
+
     #[clap(flatten)]
     common: ProbeOptions,
 }
 
 impl Cmd {
     pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
+        // Chips from a description file are only known after attaching.
+        if let (Some(chip), None) = (&self.common.chip, &self.common.chip_description_path) {
+            resolve_chip(chip)?;
+        }
+
+        let memory_map = if self.memory_regions.is_empty() {
+            None
+        } else {
+            Some(GdbMemoryMap::new(self.memory_regions.clone())?)
+        };
+
         let (mut session, _probe_options) = self.common.simple_attach(lister)?;
 
         if self.reset_halt {
@@ -38,21 +120,47 @@ impl Cmd {
             .gdb_connection_string
             .unwrap_or_else(|| "localhost:1337".to_string());
 
-        let instances = probe_rs::gdb_server::GdbInstanceConfiguration::from_session(
-            &session,
-            Some(gdb_connection_string),
-        );
+        let mut config = GdbServerConfig::from_session(&session, Some(gdb_connection_string))?;
+        config.halt_poll_interval = Duration::from_millis(self.halt_poll_interval);
 
-        for instance in instances.iter() {
-            println!(
-                "Firing up GDB stub for {:?} cores at {:?}",
-                instance.core_type, instance.socket_addrs
-            );
+        for instance in config.instances.iter_mut() {
+            instance.halt_on_attach = !self.no_halt;
+            instance.keep_alive = self.keep_alive.map(Duration::from_secs);
+            instance.packet_size = self.packet_size;
+            instance.host_io_root = self.host_io_root.clone();
+            instance.memory_map = memory_map.clone();
+        }
+
+        if let Some(path) = &self.log_gdb_traffic {
+            for (i, instance) in config.instances.iter_mut().enumerate() {
+                instance.traffic_log = Some(traffic_log_path(path, i));
+            }
+        }
+
+        if let Some(text) = self.text_offset {
+            let data = self.data_offset.unwrap_or(text);
+
+            for instance in config.instances.iter_mut() {
+                instance.section_offsets = Some(SectionOffsets {
+                    text,
+                    data,
+                    bss: Some(data),
+                });
+            }
         }
 
         let session = Mutex::new(session);
 
-        if let Err(e) = probe_rs::gdb_server::run(&session, instances.iter()) {
+        let mut server = GdbServer::from_config(&session, &config)?;
+
+        for (instance, addr) in config.instances.iter().zip(server.local_addrs()?) {
+            println!(
+                "Firing up GDB stub for {:?} cores at {}",
+                instance.core_type, addr
+            );
+        }
+
+        if let Err(e) = server.run_until(&config.shutdown) {
             eprintln!("During the execution of GDB an error was encountered:");
             eprintln!("{e:?}");
         }
@@ -60,3 +168,42 @@ impl Cmd {
         Ok(())
     }
 }
+
+/// Check that `name` names a single chip in the registry, suggesting similar names if it doesn't.
+fn resolve_chip(name: &str) -> anyhow::Result<()> {
+    if probe_rs::config::chip_by_name(name).is_some() {
+        return Ok(());
+    }
+
+    let pattern = name.to_ascii_lowercase();
+    let suggestions: Vec<_> = probe_rs::config::list_chips()
+        .into_iter()
+        .map(|chip| chip.name)
+        .filter(|chip| chip.to_ascii_lowercase().contains(&pattern))
+        .take(10)
+        .collect();
+
+    if suggestions.is_empty() {
+        anyhow::bail!("The chip '{name}' was not found in the database.");
+    }
+
+    anyhow::bail!(
+        "The chip '{name}' was not found in the database, or is ambiguous. Similar chips: {}",
+        suggestions.join(", ")
+    )
+}
+
+/// Log file of the GDB stub with index `i`, e.g. `gdb.1.log` for the second stub logging to `gdb.log`
+fn traffic_log_path(path: &Path, i: usize) -> PathBuf {
+    if i == 0 {
+        return path.to_path_buf();
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(ext) => format!("{stem}.{i}.{}", ext.to_string_lossy()),
+        None => format!("{stem}.{i}"),
+    };
+
+    path.with_file_name(file_name)
+}
@@ -5,7 +5,7 @@ use anyhow::Result;
 use probe_rs::{
     architecture::{
         arm::{
-            ap::{GenericAp, MemoryAp},
+            ap::{scan_access_ports, GenericAp, MemoryAp},
             armv6m::Demcr,
             component::Scs,
             dp::{DPIDR, TARGETID},
@@ -32,6 +32,15 @@ impl Cmd {
         let probe_options = self.common.load()?;
         let mut probe = probe_options.attach_probe(lister)?;
 
+        match probe.get_target_voltage() {
+            Ok(Some(voltage)) => {
+                println!("Target voltage: {voltage:.2} V");
+                println!();
+            }
+            Ok(None) => {}
+            Err(e) => log::debug!("Failed to read the target voltage: {}", e),
+        }
+
         let protocols = if let Some(protocol) = probe_options.protocol() {
             vec![protocol]
         } else {
@@ -253,9 +262,27 @@ fn show_arm_info(interface: &mut dyn ArmProbeInterface) -> Result<()> {
     println!("ARM Chip:");
     println!("{tree}");
 
+    print_access_ports(interface, dp);
+
     Ok(())
 }
 
+/// Print a table of all access ports found by scanning the IDR registers.
+fn print_access_ports(interface: &mut dyn ArmProbeInterface, dp: DpAddress) {
+    println!("Access Ports:");
+    println!("  AP   IDR         Kind              Designer");
+
+    for ap in scan_access_ports(interface, dp) {
+        println!(
+            "  {:<4} {:#010x}  {:<16}  {}",
+            ap.address.ap,
+            ap.idr,
+            ap.kind(),
+            ap.designer().get().unwrap_or("<unknown>")
+        );
+    }
+}
+
 fn handle_memory_ap(
     access_port: MemoryAp,
     base_address: u64,
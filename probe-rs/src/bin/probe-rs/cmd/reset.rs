@@ -18,7 +18,7 @@ impl Cmd {
     pub fn run(self, lister: &Lister) -> anyhow::Result<()> {
         let (mut session, _probe_options) = self.common.simple_attach(lister)?;
 
-        session.core(self.shared.core)?.reset()?;
+        session.core(self.shared.core)?.reset_and_run()?;
 
         Ok(())
     }
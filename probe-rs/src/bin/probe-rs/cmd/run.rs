@@ -195,6 +195,14 @@ fn run_loop(
                 tracing::error!("Target wanted to run semihosting operation {:#x}, but probe-rs does not support this operation yet. Continuing...", operation);
                 core.run()?;
             }
+            probe_rs::CoreStatus::Halted(HaltReason::Breakpoint(BreakpointCause::Semihosting(
+                SemihostingCommand::WriteChar { .. }
+                | SemihostingCommand::WriteString { .. }
+                | SemihostingCommand::Write { .. },
+            ))) => {
+                tracing::debug!("Ignoring semihosting console output. Continuing...");
+                core.run()?;
+            }
             probe_rs::CoreStatus::Halted(r) => halt_reason = Some(r),
             probe_rs::CoreStatus::Running
             | probe_rs::CoreStatus::LockedUp
@@ -40,8 +40,8 @@ use clap;
 use probe_rs::{
     config::{RegistryError, TargetSelector},
     flashing::{FileDownloadError, FlashError},
-    DebugProbeError, DebugProbeSelector, FakeProbe, Lister, Permissions, Probe, Session, Target,
-    WireProtocol,
+    DebugProbeError, DebugProbeSelector, FakeProbe, Lister, Permissions, Probe, ProbeCreationError,
+    Session, Target, WireProtocol,
 };
 use serde::{Deserialize, Serialize};
 
@@ -135,9 +135,15 @@ pub struct ProbeOptions {
     /// Use this flag to select a specific probe in the list.
     ///
     /// Use '--probe VID:PID' or '--probe VID:PID:Serial' if you have more than one
-    /// probe with the same VID:PID.",
+    /// probe with the same VID:PID. A probe can also be selected by its serial number
+    /// alone, or by its index in the list printed by `probe-rs list`.
     #[arg(long = "probe", help_heading = "PROBE CONFIGURATION")]
-    pub probe_selector: Option<DebugProbeSelector>,
+    pub probe_selector: Option<String>,
+    /// Use the CMSIS-DAP v1 (HID) interface, even if the probe supports CMSIS-DAP v2.
+    ///
+    /// Use this for probe firmware with a broken CMSIS-DAP v2 implementation.
+    #[arg(long = "cmsisdap-v1", help_heading = "PROBE CONFIGURATION")]
+    pub cmsisdap_v1: bool,
     /// The protocol speed in kHz.
     #[arg(long, help_heading = "PROBE CONFIGURATION")]
     pub speed: Option<u32>,
@@ -236,25 +242,29 @@ impl LoadedProbeOptions {
         } else {
             // If we got a probe selector as an argument, open the probe
             // matching the selector if possible.
-            let probe = match &self.0.probe_selector {
-                Some(selector) => lister.open(selector),
-                None => {
-                    // Only automatically select a probe if there is
-                    // only a single probe detected.
-                    let list = lister.list_all();
-                    if list.len() > 1 {
-                        return Err(OperationError::MultipleProbesFound { number: list.len() });
+            let info = match &self.0.probe_selector {
+                Some(selector) => lister
+                    .select_matching(selector)
+                    .map_err(OperationError::FailedToOpenProbe)?,
+                // Only automatically select a probe if there is
+                // only a single probe detected.
+                None => lister.select_single().map_err(|error| match error {
+                    DebugProbeError::ProbeCouldNotBeCreated(ProbeCreationError::NotFound) => {
+                        OperationError::NoProbesFound
                     }
-
-                    let Some(info) = list.first() else {
-                        return Err(OperationError::NoProbesFound);
-                    };
-
-                    lister.open(info)
-                }
+                    DebugProbeError::ProbeCouldNotBeCreated(
+                        error @ ProbeCreationError::MultipleProbesFound { .. },
+                    ) => OperationError::MultipleProbesFound(error),
+                    error => OperationError::FailedToOpenProbe(error),
+                })?,
             };
 
-            probe.map_err(OperationError::FailedToOpenProbe)?
+            let mut selector = DebugProbeSelector::from(info);
+            selector.cmsisdap_v1 = self.0.cmsisdap_v1;
+
+            lister
+                .open(selector)
+                .map_err(OperationError::FailedToOpenProbe)?
         };
 
         if let Some(protocol) = self.0.protocol {
@@ -268,7 +278,7 @@ impl LoadedProbeOptions {
         }
 
         if let Some(speed) = self.0.speed {
-            let _actual_speed = probe.set_speed(speed).map_err(|error| {
+            let protocol_speed = probe.set_speed(speed).map_err(|error| {
                 OperationError::FailedToSelectProtocolSpeed {
                     source: error,
                     speed,
@@ -277,18 +287,19 @@ impl LoadedProbeOptions {
 
             // Warn the user if they specified a speed the debug probe does not support
             // and a fitting speed was automatically selected.
-            let protocol_speed = probe.speed_khz();
-            if let Some(speed) = self.0.speed {
-                if protocol_speed < speed {
-                    log::warn!(
-                        "Unable to use specified speed of {} kHz, actual speed used is {} kHz",
-                        speed,
-                        protocol_speed
-                    );
-                }
+            if protocol_speed < speed {
+                log::warn!(
+                    "Unable to use specified speed of {} kHz, actual speed used is {} kHz",
+                    speed,
+                    protocol_speed
+                );
             }
 
-            log::info!("Protocol speed {} kHz", protocol_speed);
+            if probe.speed_is_approximate() {
+                log::info!("Protocol speed up to {} kHz", protocol_speed);
+            } else {
+                log::info!("Protocol speed {} kHz", protocol_speed);
+            }
         }
 
         Ok(probe)
@@ -471,8 +482,8 @@ pub enum OperationError {
     FailedToLoadElfData(#[source] FileDownloadError),
     #[error("Failed to open the debug probe.")]
     FailedToOpenProbe(#[source] DebugProbeError),
-    #[error("{number} probes were found.")]
-    MultipleProbesFound { number: usize },
+    #[error("{0}")]
+    MultipleProbesFound(ProbeCreationError),
     #[error("The flashing procedure failed for '{path}'.")]
     FlashingFailed {
         #[source]
@@ -8,14 +8,14 @@ use crate::architecture::arm::ArmChipInfo;
 ///
 /// [ArmProbeInterface::read_from_rom_table]: crate::architecture::arm::communication_interface::ArmProbeInterface::read_from_rom_table
 #[derive(Debug)]
-pub(crate) enum ChipInfo {
+pub(crate) enum ChipDetectionInfo {
     /// ARM specific information for chip
     /// auto-detection. See [ArmChipInfo].
     Arm(ArmChipInfo),
 }
 
-impl From<ArmChipInfo> for ChipInfo {
+impl From<ArmChipInfo> for ChipDetectionInfo {
     fn from(info: ArmChipInfo) -> Self {
-        ChipInfo::Arm(info)
+        ChipDetectionInfo::Arm(info)
     }
 }
@@ -27,18 +27,20 @@ mod registry;
 mod target;
 
 pub use probe_rs_target::{
-    Chip, ChipFamily, Core, CoreType, FlashProperties, GenericRegion, InstructionSet, MemoryRange,
-    MemoryRegion, NvmRegion, PageInfo, RamRegion, RawFlashAlgorithm, ScanChainElement,
-    SectorDescription, SectorInfo, TargetDescriptionSource,
+    AccessWidth, Chip, ChipFamily, Core, CoreType, FlashProperties, GenericRegion, InstructionSet,
+    MemoryRange, MemoryRegion, NvmRegion, PageInfo, RamRegion, RawFlashAlgorithm, ResetCause,
+    ResetCauseFlag, ResetCauseRegister, ScanChainElement, SectorDescription, SectorInfo,
+    TargetDescriptionSource,
 };
 
 pub use registry::{
-    add_target_from_yaml, families, get_target_and_family_by_name, get_target_by_name,
-    get_targets_by_family_name, search_chips, RegistryError,
+    add_target_from_yaml, chip_by_name, families, get_target_and_family_by_name,
+    get_target_by_name, get_targets_by_family_name, list_chips, search_chips, ChipInfo,
+    RegistryError,
 };
 pub use target::{DebugSequence, Target, TargetParseError, TargetSelector};
 
 // Crate-internal API
-pub(crate) use chip_info::ChipInfo;
+pub(crate) use chip_info::ChipDetectionInfo;
 pub(crate) use registry::get_target_by_chip_info;
 pub(crate) use target::CoreExt;
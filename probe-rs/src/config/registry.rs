@@ -1,7 +1,8 @@
 //! Internal target registry
 
-use super::{Chip, ChipFamily, ChipInfo, Core, Target, TargetDescriptionSource};
-use crate::config::CoreType;
+use super::{Chip, ChipDetectionInfo, ChipFamily, Core, Target, TargetDescriptionSource};
+use crate::config::{CoreType, MemoryRegion};
+use jep106::JEP106Code;
 use once_cell::sync::Lazy;
 use probe_rs_target::{BinaryFormat, CoreAccessOptions, RiscvCoreAccessOptions};
 use std::io::Read;
@@ -111,6 +112,7 @@ fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
                 rtt_scan_ranges: None,
                 scan_chain: Some(vec![]),
                 default_binary_format: Some(BinaryFormat::Raw),
+                reset_cause: None,
             }],
             flash_algorithms: vec![],
             source: TargetDescriptionSource::Generic,
@@ -118,6 +120,33 @@ fn add_generic_targets(vec: &mut Vec<ChipFamily>) {
     ]);
 }
 
+/// Summary of a chip in the internal registry, see [list_chips].
+#[derive(Debug, Clone)]
+pub struct ChipInfo {
+    /// The name of the chip, e.g. `nRF52832_xxAA`.
+    pub name: String,
+    /// The name of the family the chip belongs to.
+    pub family: String,
+    /// The JEP106 code of the manufacturer, if known.
+    pub manufacturer: Option<JEP106Code>,
+    /// The cores available on the chip.
+    pub cores: Vec<Core>,
+    /// The memory regions available on the chip.
+    pub memory_map: Vec<MemoryRegion>,
+}
+
+impl ChipInfo {
+    fn new(family: &ChipFamily, chip: &Chip) -> Self {
+        Self {
+            name: chip.name.clone(),
+            family: family.name.clone(),
+            manufacturer: family.manufacturer,
+            cores: chip.cores.clone(),
+            memory_map: chip.memory_map.clone(),
+        }
+    }
+}
+
 /// Registry of all available targets.
 struct Registry {
     /// All the available chips.
@@ -168,8 +197,13 @@ impl Registry {
         &self,
         name: impl AsRef<str>,
     ) -> Result<(Target, ChipFamily), RegistryError> {
-        let name = name.as_ref();
+        let (family, chip) = self.find_chip(name.as_ref())?;
+        let targ = self.get_target(family, chip)?;
+        Ok((targ, family.clone()))
+    }
 
+    /// Find the chip `name`, which may be given partially, or with `x` wildcards.
+    fn find_chip(&self, name: &str) -> Result<(&ChipFamily, &Chip), RegistryError> {
         tracing::debug!("Searching registry for chip with name {}", name);
 
         let (family, chip) = {
@@ -226,11 +260,22 @@ impl Registry {
                 );
             }
 
-            // Try get the correspnding flash algorithm.
             (family, chip)
         };
-        let targ = self.get_target(family, chip)?;
-        Ok((targ, family.clone()))
+
+        Ok((family, chip))
+    }
+
+    fn list_chips(&self) -> Vec<ChipInfo> {
+        self.families
+            .iter()
+            .flat_map(|family| {
+                family
+                    .variants
+                    .iter()
+                    .map(|chip| ChipInfo::new(family, chip))
+            })
+            .collect()
     }
 
     fn get_targets_by_family_name(
@@ -288,10 +333,13 @@ impl Registry {
         targets
     }
 
-    fn get_target_by_chip_info(&self, chip_info: ChipInfo) -> Result<Target, RegistryError> {
+    fn get_target_by_chip_info(
+        &self,
+        chip_info: ChipDetectionInfo,
+    ) -> Result<Target, RegistryError> {
         let (family, chip) = {
             match chip_info {
-                ChipInfo::Arm(chip_info) => {
+                ChipDetectionInfo::Arm(chip_info) => {
                     // Try get the corresponding chip.
 
                     let families = self.families.iter().filter(|f| {
@@ -385,8 +433,28 @@ pub fn search_chips(name: impl AsRef<str>) -> Result<Vec<String>, RegistryError>
     Ok(REGISTRY.lock().unwrap().search_chips(name.as_ref()))
 }
 
-/// Try to retrieve a target based on [ChipInfo] read from a target.
-pub(crate) fn get_target_by_chip_info(chip_info: ChipInfo) -> Result<Target, RegistryError> {
+/// Get a summary of all chips in the internal registry.
+///
+/// This can be used to validate a chip name given by a user, or to offer completions.
+pub fn list_chips() -> Vec<ChipInfo> {
+    REGISTRY.lock().unwrap().list_chips()
+}
+
+/// Get a summary of the chip `name` from the internal registry.
+///
+/// The name is matched like in [get_target_by_name], `None` is returned if it
+/// doesn't match any chip, or matches several chips.
+pub fn chip_by_name(name: impl AsRef<str>) -> Option<ChipInfo> {
+    let registry = REGISTRY.lock().unwrap();
+    let (family, chip) = registry.find_chip(name.as_ref()).ok()?;
+
+    Some(ChipInfo::new(family, chip))
+}
+
+/// Try to retrieve a target based on [ChipDetectionInfo] read from a target.
+pub(crate) fn get_target_by_chip_info(
+    chip_info: ChipDetectionInfo,
+) -> Result<Target, RegistryError> {
     REGISTRY.lock().unwrap().get_target_by_chip_info(chip_info)
 }
 
@@ -444,7 +512,7 @@ fn match_name_prefix(pattern: &str, name: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use probe_rs_target::get_ir_lengths;
+    use probe_rs_target::{get_ir_lengths, ResetCause};
     use std::fs::File;
     type TestResult = Result<(), RegistryError>;
 
@@ -492,6 +560,32 @@ mod tests {
         assert!(registry.get_target_by_name("nrf51822_Xxaa").is_ok());
     }
 
+    #[test]
+    fn list_and_find_chips() {
+        let registry = Registry::from_builtin_families();
+        let chips = registry.list_chips();
+
+        assert!(chips.iter().any(|chip| chip.name == "nRF51822_xxAA"));
+        assert_eq!(
+            registry.find_chip("nrf51822_Xxaa").unwrap().1.name,
+            "nRF51822_xxAA"
+        );
+        assert!(registry.find_chip("STM32G081KBU").is_err());
+    }
+
+    #[test]
+    fn decode_reset_cause() {
+        let registry = Registry::from_builtin_families();
+        let target = registry.get_target_by_name("nRF52840_xxAA").unwrap();
+        let register = target.reset_cause.unwrap();
+
+        assert_eq!(register.decode(0), ResetCause::PowerOn);
+        assert_eq!(register.decode(0b0001), ResetCause::Pin);
+        assert_eq!(register.decode(0b0011), ResetCause::Watchdog);
+        assert_eq!(register.decode(1 << 20), ResetCause::LowPower);
+        assert_eq!(register.clear_value(0b0011), 0x001f_000f);
+    }
+
     #[test]
     fn validate_generic_targets() {
         let mut families = vec![];
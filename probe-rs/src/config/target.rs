@@ -7,6 +7,7 @@ use crate::architecture::arm::{
         atsam::AtSAM,
         efm32xg2::EFM32xG2,
         infineon::XMC4000,
+        kinetis::Kinetis,
         nrf52::Nrf52,
         nrf53::Nrf5340,
         nrf91::Nrf9160,
@@ -24,7 +25,7 @@ use crate::architecture::riscv::sequences::{
     RiscvDebugSequence,
 };
 use crate::flashing::FlashLoader;
-use probe_rs_target::{Architecture, BinaryFormat, ChipFamily, MemoryRange};
+use probe_rs_target::{Architecture, BinaryFormat, ChipFamily, MemoryRange, ResetCauseRegister};
 use std::sync::Arc;
 
 /// This describes a complete target with a fixed chip model and variant.
@@ -55,6 +56,8 @@ pub struct Target {
     pub scan_chain: Option<Vec<ScanChainElement>>,
     /// The default executable format for the target.
     pub default_format: BinaryFormat,
+    /// The register reporting the cause of the last reset, if known.
+    pub reset_cause: Option<ResetCauseRegister>,
 }
 
 impl std::fmt::Debug for Target {
@@ -171,6 +174,8 @@ impl Target {
             || chip.name.starts_with("ATSAME5")
         {
             DebugSequence::Arm(AtSAM::create())
+        } else if chip.name.starts_with("MK") {
+            DebugSequence::Arm(Kinetis::create())
         } else if chip.name.starts_with("XMC4") {
             DebugSequence::Arm(XMC4000::create())
         } else {
@@ -225,6 +230,7 @@ impl Target {
             rtt_scan_regions,
             scan_chain: chip.scan_chain.clone(),
             default_format: chip.default_binary_format.clone().unwrap_or_default(),
+            reset_cause: chip.reset_cause.clone(),
         })
     }
 
@@ -7,19 +7,24 @@ use crate::{
                     AARCH32_WITH_FP_32_CORE_REGSISTERS,
                 },
                 aarch64::AARCH64_CORE_REGSISTERS,
-                cortex_m::{CORTEX_M_CORE_REGISTERS, CORTEX_M_WITH_FP_CORE_REGISTERS},
+                cortex_m::cortex_m_core_registers,
             },
             sequences::ArmDebugSequence,
+            ArmError, DapError, DwtComparator, MpuConfig,
         },
-        riscv::registers::RISCV_CORE_REGSISTERS,
+        riscv::{communication_interface::RiscvError, registers::RISCV_CORE_REGSISTERS},
+        xtensa::communication_interface::XtensaError,
     },
     debug::{DebugRegister, DebugRegisters},
-    error, CoreType, Error, InstructionSet, MemoryInterface, Target,
+    error, CoreType, Error, InstructionSet, MemoryInterface, MemoryTransfer, Target,
 };
 use anyhow::anyhow;
 pub use probe_rs_target::{Architecture, CoreAccessOptions};
 use probe_rs_target::{ArmCoreAccessOptions, MemoryRange, RiscvCoreAccessOptions};
-use scroll::Pread;
+use scroll::{
+    ctx::{SizeWith, TryFromCtx, TryIntoCtx},
+    Endian, Pread, Pwrite,
+};
 use std::{
     collections::HashMap,
     fs::OpenOptions,
@@ -27,7 +32,7 @@ use std::{
     ops::Range,
     path::{Path, PathBuf},
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 pub mod core_state;
@@ -45,6 +50,14 @@ pub use registers::*;
 pub struct CoreInformation {
     /// The current Program Counter.
     pub pc: u64,
+    /// The current Stack Pointer.
+    ///
+    /// Only read if enabled with [`Core::set_read_stack_registers()`].
+    pub sp: Option<u64>,
+    /// The current Link Register, a.k.a. return address.
+    ///
+    /// Only read if enabled with [`Core::set_read_stack_registers()`].
+    pub lr: Option<u64>,
 }
 
 /// A generic interface to control a MCU core.
@@ -115,7 +128,56 @@ pub trait CoreInterface: MemoryInterface {
     /// Clears the breakpoint configured in unit `unit_index`.
     fn clear_hw_breakpoint(&mut self, unit_index: usize) -> Result<(), error::Error>;
 
+    /// Returns the number of data watchpoint units of the core.
+    fn available_watchpoint_units(&mut self) -> Result<u32, error::Error> {
+        Ok(0)
+    }
+
+    /// Read the data watchpoints configured in the watchpoint units.
+    /// A value of None in any position of the Vector indicates that the position is unset/available.
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<u64>>, error::Error> {
+        Ok(Vec::new())
+    }
+
+    /// Sets a data watchpoint for `len` bytes at `addr`. It does so by using unit `unit_index`.
+    fn set_hw_watchpoint(
+        &mut self,
+        _unit_index: usize,
+        _addr: u64,
+        _len: u64,
+        _kind: WatchpointKind,
+    ) -> Result<(), error::Error> {
+        Err(Error::NotImplemented("data watchpoints"))
+    }
+
+    /// Clears the watchpoint configured in unit `unit_index`.
+    fn clear_hw_watchpoint(&mut self, _unit_index: usize) -> Result<(), error::Error> {
+        Err(Error::NotImplemented("data watchpoints"))
+    }
+
+    /// Returns the index of the watchpoint unit which caused the core to halt, if any.
+    ///
+    /// Returns `None` if the hardware does not report which unit matched.
+    fn triggered_hw_watchpoint(&mut self) -> Result<Option<usize>, error::Error> {
+        Ok(None)
+    }
+
+    /// Read the state of the DWT comparators of a Cortex-M core.
+    ///
+    /// Reading the state clears the `MATCHED` flag of the comparators.
+    fn read_dwt_comparators(&mut self) -> Result<Vec<DwtComparator>, error::Error> {
+        Err(Error::NotImplemented("DWT comparators"))
+    }
+
+    /// Read the configuration of the Memory Protection Unit of a Cortex-M core.
+    fn read_mpu_config(&mut self) -> Result<MpuConfig, error::Error> {
+        Err(Error::NotImplemented("MPU"))
+    }
+
     /// Returns a list of all the registers of this core.
+    ///
+    /// The list depends on the features of this particular core, e.g. whether it has an FPU,
+    /// so cores of the same architecture can return different lists.
     fn registers(&self) -> &'static registers::CoreRegisters;
 
     /// Returns the program counter register.
@@ -175,6 +237,18 @@ pub trait CoreInterface: MemoryInterface {
     /// Called when we stop debugging a core.
     fn debug_core_stop(&mut self) -> Result<(), Error>;
 
+    /// Run the target specific initialization of a core which was reset and halted.
+    fn reset_init(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Read the debug status of the core once, without changing the state of the core.
+    ///
+    /// This is used by [`Core::ping`] to check whether the debug connection is alive.
+    fn ping(&mut self) -> Result<(), Error> {
+        self.core_halted().map(|_| ())
+    }
+
     /// Called during session stop to do any pending cleanup
     fn on_session_stop(&mut self) -> Result<(), Error> {
         Ok(())
@@ -189,6 +263,20 @@ pub trait CoreInterface: MemoryInterface {
     fn disable_vector_catch(&mut self, _condition: VectorCatchCondition) -> Result<(), Error> {
         Err(Error::NotImplemented("vector catch"))
     }
+
+    /// Keep memory accesses of the debugger coherent with the core's data cache.
+    ///
+    /// Cores without a data cache are always coherent and ignore this.
+    fn set_cache_coherent(&mut self, _enabled: bool) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Set how long [`CoreInterface::reset_and_halt`] waits for the core to halt after the reset.
+    ///
+    /// Cores which don't wait for a reset vector catch ignore this.
+    fn set_reset_catch_timeout(&mut self, _timeout: Duration) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 /// A snapshot representation of a core state.
@@ -239,37 +327,19 @@ impl CoreDump {
 
     /// Read all registers defined in [`crate::core::CoreRegisters`] from the given core.
     pub fn debug_registers(&self) -> DebugRegisters {
-        let reg_list = match self.core_type {
-            CoreType::Armv6m => &CORTEX_M_CORE_REGISTERS,
+        let reg_list: &registers::CoreRegisters = match self.core_type {
+            CoreType::Armv6m => cortex_m_core_registers(false, false),
             CoreType::Armv7a => match self.floating_point_register_count {
                 Some(16) => &AARCH32_WITH_FP_16_CORE_REGSISTERS,
                 Some(32) => &AARCH32_WITH_FP_32_CORE_REGSISTERS,
                 _ => &AARCH32_CORE_REGSISTERS,
             },
-            CoreType::Armv7m => {
-                if self.fpu_support {
-                    &CORTEX_M_WITH_FP_CORE_REGISTERS
-                } else {
-                    &CORTEX_M_CORE_REGISTERS
-                }
-            }
-            CoreType::Armv7em => {
-                if self.fpu_support {
-                    &CORTEX_M_WITH_FP_CORE_REGISTERS
-                } else {
-                    &CORTEX_M_CORE_REGISTERS
-                }
+            CoreType::Armv7m | CoreType::Armv7em | CoreType::Armv8m => {
+                cortex_m_core_registers(self.fpu_support, false)
             }
             // TODO: This can be wrong if the CPU is 32 bit. For lack of better design at the time of writing this code
             // this differentiation has been omitted.
             CoreType::Armv8a => &AARCH64_CORE_REGSISTERS,
-            CoreType::Armv8m => {
-                if self.fpu_support {
-                    &CORTEX_M_WITH_FP_CORE_REGISTERS
-                } else {
-                    &CORTEX_M_CORE_REGISTERS
-                }
-            }
             CoreType::Riscv => &RISCV_CORE_REGSISTERS,
         };
 
@@ -482,6 +552,10 @@ impl<'probe> MemoryInterface for Core<'probe> {
         self.inner.write_word_32(addr, data)
     }
 
+    fn write_word_16(&mut self, addr: u64, data: u16) -> Result<(), Error> {
+        self.inner.write_word_16(addr, data)
+    }
+
     fn write_word_8(&mut self, addr: u64, data: u8) -> Result<(), Error> {
         self.inner.write_word_8(addr, data)
     }
@@ -502,6 +576,10 @@ impl<'probe> MemoryInterface for Core<'probe> {
         self.inner.write(addr, data)
     }
 
+    fn transfer_batch_32(&mut self, transfers: &mut [MemoryTransfer]) -> Result<(), Error> {
+        self.inner.transfer_batch_32(transfers)
+    }
+
     fn supports_8bit_transfers(&self) -> Result<bool, error::Error> {
         self.inner.supports_8bit_transfers()
     }
@@ -616,6 +694,8 @@ pub fn exception_handler_for_core(core_type: CoreType) -> Box<dyn ExceptionInter
 /// to allow potential other shareholders of the session struct to grab a core handle too.
 pub struct Core<'probe> {
     inner: Box<dyn CoreInterface + 'probe>,
+    /// Settings made through this handle, stored in the session
+    debug_settings: &'probe mut DebugSettings,
 }
 
 impl<'probe> Core<'probe> {
@@ -625,9 +705,13 @@ impl<'probe> Core<'probe> {
     }
 
     /// Create a new [`Core`].
-    pub(crate) fn new(core: impl CoreInterface + 'probe) -> Core<'probe> {
+    pub(crate) fn new(
+        core: impl CoreInterface + 'probe,
+        debug_settings: &'probe mut DebugSettings,
+    ) -> Core<'probe> {
         Self {
             inner: Box::new(core),
+            debug_settings,
         }
     }
 
@@ -655,6 +739,8 @@ impl<'probe> Core<'probe> {
                     id,
                     core_state,
                     specific_state,
+                    debug_state_on_attach: None,
+                    debug_settings: DebugSettings::default(),
                 }
             }
             CoreAccessOptions::Riscv(options) => {
@@ -663,6 +749,8 @@ impl<'probe> Core<'probe> {
                     id,
                     core_state,
                     specific_state,
+                    debug_state_on_attach: None,
+                    debug_settings: DebugSettings::default(),
                 }
             }
         }
@@ -686,11 +774,31 @@ impl<'probe> Core<'probe> {
         self.inner.core_halted()
     }
 
+    /// Check whether the debug connection to the core is alive.
+    ///
+    /// This does a single read of the debug status register, e.g. DHCSR on Cortex-M, and
+    /// doesn't halt the core or change any other state. Returns `true` if the core responded,
+    /// no matter whether it is running or halted, and `false` if the transfer wasn't
+    /// acknowledged or timed out, e.g. because the target lost power. Other errors, like a
+    /// FAULT response, are returned as is.
+    pub fn ping(&mut self) -> Result<bool, error::Error> {
+        match self.inner.ping() {
+            Ok(()) => Ok(true),
+            Err(e) if is_transfer_error(&e) => {
+                tracing::debug!("Core did not respond: {e}");
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     /// Try to halt the core. This function ensures the core is actually halted, and
     /// returns a [`DebugProbeError::Timeout`](crate::DebugProbeError::Timeout) otherwise.
     #[tracing::instrument(skip(self))]
     pub fn halt(&mut self, timeout: Duration) -> Result<CoreInformation, error::Error> {
-        self.inner.halt(timeout)
+        let info = self.inner.halt(timeout)?;
+
+        self.with_stack_registers(info)
     }
 
     /// Continue to execute instructions.
@@ -699,6 +807,30 @@ impl<'probe> Core<'probe> {
         self.inner.run()
     }
 
+    /// Run `f` with the core halted, and leave the core in its previous state afterwards.
+    ///
+    /// A running core is halted before `f` is called, and resumed once it returns, so e.g.
+    /// registers can be inspected without stopping the firmware. A core which is already
+    /// halted stays halted.
+    ///
+    /// The core is resumed even if `f` returns an error. If resuming fails, that error is
+    /// returned instead of the result of `f`.
+    pub fn with_halted<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> Result<T, error::Error> {
+        let was_running = !self.core_halted()?;
+
+        if was_running {
+            self.halt(Duration::from_millis(100))?;
+        }
+
+        let result = f(self);
+
+        if was_running {
+            self.run()?;
+        }
+
+        Ok(result)
+    }
+
     /// Reset the core, and then continue to execute instructions. If the core
     /// should be halted after reset, use the [`reset_and_halt`] function.
     ///
@@ -708,19 +840,163 @@ impl<'probe> Core<'probe> {
         self.inner.reset()
     }
 
+    /// Reset the core and let it run from the reset vector.
+    ///
+    /// Unlike [`reset`], this first clears the reset catch, which may be left set by an
+    /// interrupted [`reset_and_halt`], so the core doesn't stop at the reset vector.
+    ///
+    /// [`reset`]: Core::reset
+    /// [`reset_and_halt`]: Core::reset_and_halt
+    #[tracing::instrument(skip(self))]
+    pub fn reset_and_run(&mut self) -> Result<(), error::Error> {
+        match self.inner.reset_catch_clear() {
+            // Cores without a reset catch can't be left halting at the reset vector either
+            Ok(()) | Err(Error::Riscv(RiscvError::ResetHaltRequestNotSupported)) => {}
+            Err(e) => return Err(e),
+        }
+
+        self.inner.reset()
+    }
+
     /// Reset the core, and then immediately halt. To continue execution after
     /// reset, use the [`reset`] function.
     ///
     /// [`reset`]: Core::reset
     #[tracing::instrument(skip(self))]
     pub fn reset_and_halt(&mut self, timeout: Duration) -> Result<CoreInformation, error::Error> {
-        self.inner.reset_and_halt(timeout)
+        let info = self.inner.reset_and_halt(timeout)?;
+
+        self.with_stack_registers(info)
+    }
+
+    /// Reset the core, halt it at the reset vector and run the target specific initialization,
+    /// e.g. freezing watchdogs while the core is halted.
+    ///
+    /// Returns the program counter after the initialization.
+    #[tracing::instrument(skip(self))]
+    pub fn reset_and_init(&mut self, timeout: Duration) -> Result<CoreInformation, error::Error> {
+        self.inner.reset_and_halt(timeout)?;
+        self.inner.reset_init()?;
+
+        let info = CoreInformation {
+            pc: self.read_core_reg(self.program_counter())?,
+            sp: None,
+            lr: None,
+        };
+
+        self.with_stack_registers(info)
     }
 
     /// Steps one instruction and then enters halted state again.
     #[tracing::instrument(skip(self))]
     pub fn step(&mut self) -> Result<CoreInformation, error::Error> {
-        self.inner.step()
+        let info = self.inner.step()?;
+
+        self.with_stack_registers(info)
+    }
+
+    /// Steps over a function call, e.g. for the `nexti` command of a debugger.
+    ///
+    /// If the instruction at the program counter is a call, the core runs until the call
+    /// returns, using a temporary hardware breakpoint at the return address. The core can also
+    /// halt earlier for another reason, e.g. another breakpoint. Otherwise, or if no breakpoint
+    /// unit is available, this is the same as [`Core::step`].
+    ///
+    /// Calls are only detected in the Thumb instruction set. If the call doesn't return within
+    /// `timeout`, the core is halted and an error is returned.
+    #[tracing::instrument(skip(self))]
+    pub fn step_over(&mut self, timeout: Duration) -> Result<CoreInformation, error::Error> {
+        let pc: u64 = self.read_core_reg(self.program_counter().id())?;
+
+        let call_length = match self.instruction_set()? {
+            InstructionSet::Thumb2 => {
+                let mut code = [0; 4];
+                self.read(pc, &mut code)?;
+
+                crate::architecture::arm::core::instructions::thumb::call_length(code)
+            }
+            _ => None,
+        };
+
+        let Some(call_length) = call_length else {
+            return self.step();
+        };
+
+        let return_address = pc + call_length;
+
+        // Don't remove a breakpoint which is already set at the return address
+        let breakpoint_set = self.hw_breakpoints()?.contains(&Some(return_address));
+        if !breakpoint_set && self.set_hw_breakpoint(return_address).is_err() {
+            tracing::debug!("No breakpoint available to step over the call at {pc:#010x}");
+            return self.step();
+        }
+
+        let sp: u64 = self.read_core_reg(self.stack_pointer().id())?;
+
+        let result = self.run_to_return(return_address, sp, timeout);
+
+        if !breakpoint_set {
+            self.clear_hw_breakpoint(return_address)?;
+        }
+
+        let info = result?;
+
+        self.with_stack_registers(info)
+    }
+
+    /// Run until the core halts at `return_address` with the stack pointer at or above `sp`,
+    /// or halts for another reason.
+    fn run_to_return(
+        &mut self,
+        return_address: u64,
+        sp: u64,
+        timeout: Duration,
+    ) -> Result<CoreInformation, error::Error> {
+        let start = Instant::now();
+
+        loop {
+            self.run()?;
+
+            let remaining = timeout.saturating_sub(start.elapsed());
+            if let Err(error) = self.wait_for_core_halted(remaining) {
+                self.halt(Duration::from_millis(100))?;
+                return Err(error);
+            }
+
+            let pc: u64 = self.read_core_reg(self.program_counter().id())?;
+            let current_sp: u64 = self.read_core_reg(self.stack_pointer().id())?;
+
+            // A recursive call returns to the same address with a deeper stack
+            if pc != return_address || current_sp >= sp {
+                return Ok(CoreInformation {
+                    pc,
+                    sp: None,
+                    lr: None,
+                });
+            }
+        }
+    }
+
+    /// Also read the stack pointer and link register into the [`CoreInformation`] returned
+    /// by [`Core::halt()`], [`Core::step()`], [`Core::reset_and_halt()`] and [`Core::reset_and_init()`].
+    ///
+    /// This costs two additional register reads, so it is disabled by default. The setting is
+    /// stored in the [`Session`](crate::Session), so it also applies to later handles of this core.
+    pub fn set_read_stack_registers(&mut self, enabled: bool) {
+        self.debug_settings.read_stack_registers = enabled;
+    }
+
+    /// Add the stack pointer and link register to `info`, if enabled
+    fn with_stack_registers(
+        &mut self,
+        mut info: CoreInformation,
+    ) -> Result<CoreInformation, error::Error> {
+        if self.debug_settings.read_stack_registers {
+            info.sp = Some(self.read_core_reg(self.stack_pointer())?);
+            info.lr = Some(self.read_core_reg(self.return_address())?);
+        }
+
+        Ok(info)
     }
 
     /// Returns the current status of the core.
@@ -729,6 +1005,23 @@ impl<'probe> Core<'probe> {
         self.inner.status()
     }
 
+    /// Returns the status of the core together with its program counter if it is halted.
+    ///
+    /// This is meant to be polled by debugger front ends, to update their view of the core
+    /// with a single call.
+    #[tracing::instrument(skip(self))]
+    pub fn snapshot(&mut self) -> Result<CoreSnapshot, error::Error> {
+        let status = self.inner.status()?;
+
+        let pc = if status.is_halted() {
+            Some(self.read_core_reg(self.program_counter())?)
+        } else {
+            None
+        };
+
+        Ok(CoreSnapshot { status, pc })
+    }
+
     /// Read the value of a core register.
     ///
     /// # Remarks
@@ -785,6 +1078,14 @@ impl<'probe> Core<'probe> {
         self.inner.available_breakpoint_units()
     }
 
+    /// Returns the address set in each hardware breakpoint comparator, read back from the core.
+    ///
+    /// The vector has one entry per breakpoint unit, `None` marks a unit which is unused.
+    /// Breakpoints set by an earlier session, or by another tool, are included as well.
+    pub fn hw_breakpoints(&mut self) -> Result<Vec<Option<u64>>, error::Error> {
+        self.inner.hw_breakpoints()
+    }
+
     /// Enables breakpoints on this core. If a breakpoint is set, it will halt as soon as it is hit.
     fn enable_breakpoints(&mut self, state: bool) -> Result<(), error::Error> {
         self.inner.enable_breakpoints(state)
@@ -868,6 +1169,11 @@ impl<'probe> Core<'probe> {
         // Actually set the breakpoint. Even if it has been set, set it again so it will be active.
         self.inner
             .set_hw_breakpoint(breakpoint_comparator_index, address)?;
+
+        if !self.debug_settings.hw_breakpoints.contains(&address) {
+            self.debug_settings.hw_breakpoints.push(address);
+        }
+
         Ok(())
     }
 
@@ -891,6 +1197,9 @@ impl<'probe> Core<'probe> {
         match bp_position {
             Some(bp_position) => {
                 self.inner.clear_hw_breakpoint(bp_position)?;
+                self.debug_settings
+                    .hw_breakpoints
+                    .retain(|&bp| bp != address);
                 Ok(())
             }
             None => Err(error::Error::Other(anyhow!(
@@ -910,9 +1219,231 @@ impl<'probe> Core<'probe> {
         for breakpoint in (self.inner.hw_breakpoints()?).into_iter().flatten() {
             self.clear_hw_breakpoint(breakpoint)?
         }
+        self.debug_settings.hw_breakpoints.clear();
         Ok(())
     }
 
+    /// Clear all data watchpoints
+    ///
+    /// Like [`Core::clear_all_hw_breakpoints`], this clears all watchpoints configured on the
+    /// target, regardless if they are set by probe-rs.
+    #[tracing::instrument(skip(self))]
+    pub fn clear_all_hw_watchpoints(&mut self) -> Result<(), error::Error> {
+        let watchpoints = self.inner.hw_watchpoints()?;
+
+        for (unit_index, _) in watchpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, wp)| wp.is_some())
+        {
+            self.inner.clear_hw_watchpoint(unit_index)?;
+        }
+        self.debug_settings.hw_watchpoints.clear();
+
+        Ok(())
+    }
+
+    /// Returns all the available data watchpoint units of the core.
+    pub fn available_watchpoint_units(&mut self) -> Result<u32, error::Error> {
+        self.inner.available_watchpoint_units()
+    }
+
+    /// Set a data watchpoint
+    ///
+    /// This function will try to set a watchpoint halting the core when `len` bytes
+    /// at `address` are accessed as specified by `kind`.
+    ///
+    /// The amount of watchpoints which are supported is chip specific,
+    /// and can be queried using the `available_watchpoint_units` function.
+    #[tracing::instrument(skip(self))]
+    pub fn set_hw_watchpoint(
+        &mut self,
+        address: u64,
+        len: u64,
+        kind: WatchpointKind,
+    ) -> Result<(), error::Error> {
+        let watchpoints = self.inner.hw_watchpoints()?;
+
+        // If there is a watchpoint set already, reconfigure its unit, else use the next free unit.
+        let unit_index = match watchpoints.iter().position(|&wp| wp == Some(address)) {
+            Some(unit_index) => unit_index,
+            None => watchpoints
+                .iter()
+                .position(|wp| wp.is_none())
+                .ok_or_else(|| error::Error::Other(anyhow!("No available hardware watchpoints")))?,
+        };
+
+        tracing::debug!(
+            "Trying to set watchpoint #{} for {} bytes at {:#010x}",
+            unit_index,
+            len,
+            address
+        );
+
+        self.inner
+            .set_hw_watchpoint(unit_index, address, len, kind)?;
+
+        let watchpoints = &mut self.debug_settings.hw_watchpoints;
+        watchpoints.retain(|&(wp, _, _)| wp != address);
+        watchpoints.push((address, len, kind));
+
+        Ok(())
+    }
+
+    /// Clear a data watchpoint
+    ///
+    /// This function will try to clear a watchpoint at `address` if there exists a watchpoint at that address.
+    #[tracing::instrument(skip(self))]
+    pub fn clear_hw_watchpoint(&mut self, address: u64) -> Result<(), error::Error> {
+        let unit_index = self
+            .inner
+            .hw_watchpoints()?
+            .iter()
+            .position(|&wp| wp == Some(address));
+
+        match unit_index {
+            Some(unit_index) => {
+                self.inner.clear_hw_watchpoint(unit_index)?;
+                self.debug_settings
+                    .hw_watchpoints
+                    .retain(|&(wp, _, _)| wp != address);
+                Ok(())
+            }
+            None => Err(error::Error::Other(anyhow!(
+                "No watchpoint found at address {:#010x}",
+                address
+            ))),
+        }
+    }
+
+    /// Returns the address of the watchpoint which caused the core to halt, if any.
+    ///
+    /// Not every core reports which watchpoint matched, and on Cortex-M the DWT `MATCHED` flag
+    /// is cleared when it is read, so a match is only reported once. In these cases, `None`
+    /// is returned even though the core halted because of a watchpoint.
+    pub fn triggered_hw_watchpoint(&mut self) -> Result<Option<u64>, error::Error> {
+        let Some(unit_index) = self.inner.triggered_hw_watchpoint()? else {
+            return Ok(None);
+        };
+
+        Ok(self
+            .inner
+            .hw_watchpoints()?
+            .get(unit_index)
+            .copied()
+            .flatten())
+    }
+
+    /// Read the state of the DWT comparators, which are used for data watchpoints on Cortex-M cores.
+    ///
+    /// The `matched` flag of a comparator tells which watchpoint caused a halt. Reading it clears
+    /// it, so afterwards [Core::triggered_hw_watchpoint] no longer reports the watchpoint.
+    pub fn read_dwt_comparators(&mut self) -> Result<Vec<DwtComparator>, error::Error> {
+        self.inner.read_dwt_comparators()
+    }
+
+    /// Read the configuration of the Memory Protection Unit (MPU) of a Cortex-M core.
+    ///
+    /// Use [MpuConfig::region_for] to find the region which applies to the faulting address
+    /// of a MemManage fault. Cores without an MPU report no regions.
+    pub fn read_mpu_config(&mut self) -> Result<MpuConfig, error::Error> {
+        self.inner.read_mpu_config()
+    }
+
+    /// Read a register of the Private Peripheral Bus of a Cortex-M core.
+    ///
+    /// This is an escape hatch for registers without a typed helper, e.g. the MPU, cache
+    /// maintenance or vendor specific registers in the System Control Space. It is a plain
+    /// 32 bit memory read, which is only allowed for word aligned addresses in the PPB
+    /// (`0xE000_0000..0xE010_0000`).
+    pub fn read_system_reg(&mut self, address: u32) -> Result<u32, error::Error> {
+        self.check_system_reg_address(address)?;
+
+        self.read_word_32(address.into())
+    }
+
+    /// Write a register of the Private Peripheral Bus of a Cortex-M core.
+    ///
+    /// See [Core::read_system_reg] for the allowed addresses. The write bypasses the state
+    /// probe-rs keeps about the core, so e.g. writing `DHCSR` or the breakpoint units can
+    /// confuse later operations.
+    pub fn write_system_reg(&mut self, address: u32, value: u32) -> Result<(), error::Error> {
+        self.check_system_reg_address(address)?;
+
+        self.write_word_32(address.into(), value)
+    }
+
+    fn check_system_reg_address(&self, address: u32) -> Result<(), error::Error> {
+        const PPB: std::ops::Range<u32> = 0xE000_0000..0xE010_0000;
+
+        if !self.core_type().is_cortex_m() {
+            return Err(Error::NotImplemented("system register access"));
+        }
+
+        if !PPB.contains(&address) {
+            return Err(Error::Other(anyhow!(
+                "Address {:#010x} is not a system register, it is outside of the Private Peripheral Bus",
+                address
+            )));
+        }
+
+        if address % 4 != 0 {
+            return Err(Error::MemoryNotAligned {
+                address: address.into(),
+                alignment: 4,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Read a value of type `T` from target memory at `address`.
+    ///
+    /// The value is decoded with [`scroll`] in little endian byte order, which all supported
+    /// targets use, so the result doesn't depend on the byte order of the host. This works for
+    /// integers, and for structs deriving `Pread` and `SizeWith` from `scroll_derive`, e.g. to
+    /// read a configuration block with a known C layout. The address doesn't have to be aligned.
+    pub fn read_into<T>(&mut self, address: u64) -> Result<T, error::Error>
+    where
+        T: for<'a> TryFromCtx<'a, Endian, Error = scroll::Error> + SizeWith<Endian>,
+    {
+        let mut data = vec![0u8; T::size_with(&scroll::LE)];
+        self.read(address, &mut data)?;
+
+        data.pread_with(0, scroll::LE).map_err(|e| {
+            Error::Other(anyhow!(
+                "Failed to decode the value read from {:#010x}: {}",
+                address,
+                e
+            ))
+        })
+    }
+
+    /// Write a value of type `T` to target memory at `address`.
+    ///
+    /// This is the counterpart of [Core::read_into], the value is encoded in little endian byte
+    /// order, e.g. with `Pwrite` and `SizeWith` derived from `scroll_derive`. Unaligned values
+    /// are written with 8 bit accesses.
+    pub fn write_from<T>(&mut self, address: u64, value: T) -> Result<(), error::Error>
+    where
+        T: TryIntoCtx<Endian, Error = scroll::Error> + SizeWith<Endian>,
+    {
+        let mut data = vec![0u8; T::size_with(&scroll::LE)];
+        data.pwrite_with(value, 0, scroll::LE).map_err(|e| {
+            Error::Other(anyhow!(
+                "Failed to encode the value to write to {:#010x}: {}",
+                address,
+                e
+            ))
+        })?;
+
+        if address % 4 == 0 && data.len() % 4 == 0 {
+            self.write_mem_32bit(address, &data)
+        } else {
+            self.write_8(address, &data)
+        }
+    }
+
     /// Returns the architecture of the core.
     pub fn architecture(&self) -> Architecture {
         self.inner.architecture()
@@ -943,22 +1474,90 @@ impl<'probe> Core<'probe> {
         self.inner.floating_point_register_count()
     }
 
+    pub(crate) fn reset_catch_set(&mut self) -> Result<(), Error> {
+        self.inner.reset_catch_set()
+    }
+
     pub(crate) fn reset_catch_clear(&mut self) -> Result<(), Error> {
         self.inner.reset_catch_clear()
     }
 
+    pub(crate) fn reset_init(&mut self) -> Result<(), Error> {
+        self.inner.reset_init()
+    }
+
     pub(crate) fn debug_core_stop(&mut self) -> Result<(), Error> {
         self.inner.debug_core_stop()
     }
 
     /// Enables vector catching for the given `condition`
     pub fn enable_vector_catch(&mut self, condition: VectorCatchCondition) -> Result<(), Error> {
-        self.inner.enable_vector_catch(condition)
+        self.inner.enable_vector_catch(condition)?;
+
+        if !self.debug_settings.vector_catch.contains(&condition) {
+            self.debug_settings.vector_catch.push(condition);
+        }
+
+        Ok(())
     }
 
     /// Disables vector catching for the given `condition`
     pub fn disable_vector_catch(&mut self, condition: VectorCatchCondition) -> Result<(), Error> {
-        self.inner.disable_vector_catch(condition)
+        self.inner.disable_vector_catch(condition)?;
+
+        self.debug_settings
+            .vector_catch
+            .retain(|&catch| catch != condition);
+
+        Ok(())
+    }
+
+    /// Set the breakpoints, watchpoints and vector catches recorded for this core again,
+    /// e.g. after they were lost because the target was power cycled.
+    pub(crate) fn restore_debug_settings(&mut self) -> Result<(), Error> {
+        let settings = self.debug_settings.clone();
+
+        // The breakpoint unit is disabled after a power cycle, while the core may still
+        // consider it enabled
+        if !settings.hw_breakpoints.is_empty() {
+            self.enable_breakpoints(true)?;
+        }
+
+        for address in settings.hw_breakpoints {
+            self.set_hw_breakpoint(address)?;
+        }
+
+        for (address, len, kind) in settings.hw_watchpoints {
+            self.set_hw_watchpoint(address, len, kind)?;
+        }
+
+        for condition in settings.vector_catch {
+            self.enable_vector_catch(condition)?;
+        }
+
+        Ok(())
+    }
+
+    /// Keep memory accesses through this core coherent with its data cache.
+    ///
+    /// With a data cache enabled, e.g. on a Cortex-M7, reads through the debug port can return
+    /// stale values and writes may not be seen by the core. When enabled, the affected cache
+    /// lines are cleaned before every memory access, and invalidated after every write.
+    ///
+    /// This costs one additional write per cache line accessed, plus two register reads per
+    /// access, so it slows down large transfers considerably. The setting is kept for the whole
+    /// session. This is currently implemented for the Cortex-M7, other cores ignore it.
+    pub fn set_cache_coherent(&mut self, enabled: bool) -> Result<(), Error> {
+        self.inner.set_cache_coherent(enabled)
+    }
+
+    /// Set how long [Core::reset_and_halt()] waits for the core to halt after the reset.
+    ///
+    /// Coming out of reset can take much longer than halting a running core, e.g. to lock a PLL
+    /// or to run a boot ROM, so this is separate from the halt timeout. The default is one
+    /// second. This is currently used by Cortex-M cores, other cores ignore it.
+    pub fn set_reset_catch_timeout(&mut self, timeout: Duration) -> Result<(), Error> {
+        self.inner.set_reset_catch_timeout(timeout)
     }
 
     /// Dumps core info with the current state.
@@ -1055,7 +1654,7 @@ impl<'probe> CoreInterface for Core<'probe> {
     }
 
     fn hw_breakpoints(&mut self) -> Result<Vec<Option<u64>>, error::Error> {
-        todo!()
+        self.hw_breakpoints()
     }
 
     fn enable_breakpoints(&mut self, state: bool) -> Result<(), error::Error> {
@@ -1115,7 +1714,7 @@ impl<'probe> CoreInterface for Core<'probe> {
     }
 
     fn reset_catch_set(&mut self) -> Result<(), Error> {
-        todo!()
+        self.reset_catch_set()
     }
 
     fn reset_catch_clear(&mut self) -> Result<(), Error> {
@@ -1125,6 +1724,18 @@ impl<'probe> CoreInterface for Core<'probe> {
     fn debug_core_stop(&mut self) -> Result<(), Error> {
         self.debug_core_stop()
     }
+
+    fn set_cache_coherent(&mut self, enabled: bool) -> Result<(), Error> {
+        self.set_cache_coherent(enabled)
+    }
+
+    fn set_reset_catch_timeout(&mut self, timeout: Duration) -> Result<(), Error> {
+        self.set_reset_catch_timeout(timeout)
+    }
+
+    fn reset_init(&mut self) -> Result<(), Error> {
+        self.inner.reset_init()
+    }
 }
 
 pub enum ResolvedCoreOptions {
@@ -1149,3 +1760,31 @@ impl std::fmt::Debug for ResolvedCoreOptions {
         }
     }
 }
+
+/// Check whether an error means that the target didn't respond to a transfer
+///
+/// A FAULT response means that the target responded, e.g. to an access of invalid memory, so it
+/// is not a transfer error.
+pub(crate) fn is_transfer_error(e: &Error) -> bool {
+    match e {
+        Error::Arm(e) => is_arm_transfer_error(e),
+        _ => matches!(
+            e,
+            Error::Probe(_)
+                | Error::Timeout
+                | Error::Riscv(RiscvError::DebugProbe(_) | RiscvError::Timeout)
+                | Error::Xtensa(XtensaError::DebugProbe(_))
+        ),
+    }
+}
+
+/// Check whether an ARM error means that the target didn't respond to a transfer
+fn is_arm_transfer_error(e: &ArmError) -> bool {
+    match e {
+        ArmError::BatchTransfer { source, .. } => is_arm_transfer_error(source),
+        _ => matches!(
+            e,
+            ArmError::Probe(_) | ArmError::Timeout | ArmError::Dap(DapError::NoAcknowledge)
+        ),
+    }
+}
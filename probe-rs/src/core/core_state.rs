@@ -2,16 +2,23 @@ use crate::{
     architecture::{
         arm::{
             ap::MemoryAp,
-            core::{CortexAState, CortexMState},
+            armv6m::Demcr,
+            core::{
+                cortex_m::{ComponentBases, Dhcsr},
+                CortexAState, CortexMState,
+            },
             ApAddress, ArmProbeInterface, DpAddress,
         },
         riscv::{communication_interface::RiscvCommunicationInterface, RiscVState},
     },
-    Core, CoreType, Error,
+    Core, CoreType, Error, MemoryMappedRegister, VectorCatchCondition, WatchpointKind,
 };
 
 use super::ResolvedCoreOptions;
 
+/// The vector catch bits of DEMCR, VC_CORERESET and VC_MMERR to VC_SFERR
+const DEMCR_VECTOR_CATCH_MASK: u32 = 0x0000_0ff1;
+
 #[derive(Debug)]
 pub(crate) struct CombinedCoreState {
     pub(crate) core_state: CoreState,
@@ -19,6 +26,33 @@ pub(crate) struct CombinedCoreState {
     pub(crate) specific_state: SpecificCoreState,
 
     pub(crate) id: usize,
+
+    /// Debug registers of a Cortex-M core before probe-rs attached, restored when detaching
+    pub(crate) debug_state_on_attach: Option<DebugRegisterState>,
+
+    /// Debug settings made through [Core], kept for later handles and applied again after re-attaching
+    pub(crate) debug_settings: DebugSettings,
+}
+
+/// Settings made through a [Core] handle, kept in the session so they outlive the handle
+///
+/// Breakpoints, watchpoints and vector catches are lost when the target is power cycled, so
+/// they are recorded to restore them after re-attaching, see
+/// [Session::reattach()](crate::Session::reattach).
+#[derive(Debug, Default, Clone)]
+pub(crate) struct DebugSettings {
+    pub hw_breakpoints: Vec<u64>,
+    pub hw_watchpoints: Vec<(u64, u64, WatchpointKind)>,
+    pub vector_catch: Vec<VectorCatchCondition>,
+    /// Read the stack pointer and link register when the core halts
+    pub read_stack_registers: bool,
+}
+
+/// The debug registers of a Cortex-M core which are changed by attaching to it
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DebugRegisterState {
+    dhcsr: Dhcsr,
+    demcr: Demcr,
 }
 
 impl CombinedCoreState {
@@ -45,34 +79,47 @@ impl CombinedCoreState {
             }
         };
 
+        if let SpecificCoreState::Armv6m(s)
+        | SpecificCoreState::Armv7m(s)
+        | SpecificCoreState::Armv7em(s)
+        | SpecificCoreState::Armv8m(s) = &mut self.specific_state
+        {
+            s.set_component_bases(ComponentBases::new(options.fpb_base, options.dwt_base));
+        }
+
         Ok(match &mut self.specific_state {
             SpecificCoreState::Armv6m(s) => Core::new(
                 crate::architecture::arm::armv6m::Armv6m::new(memory, s, debug_sequence, self.id)?,
+                &mut self.debug_settings,
             ),
-            SpecificCoreState::Armv7a(s) => {
-                Core::new(crate::architecture::arm::armv7a::Armv7a::new(
+            SpecificCoreState::Armv7a(s) => Core::new(
+                crate::architecture::arm::armv7a::Armv7a::new(
                     memory,
                     s,
                     options.debug_base.expect("base_address not specified"),
                     debug_sequence,
                     self.id,
-                )?)
-            }
+                )?,
+                &mut self.debug_settings,
+            ),
             SpecificCoreState::Armv7m(s) | SpecificCoreState::Armv7em(s) => Core::new(
                 crate::architecture::arm::armv7m::Armv7m::new(memory, s, debug_sequence, self.id)?,
+                &mut self.debug_settings,
             ),
-            SpecificCoreState::Armv8a(s) => {
-                Core::new(crate::architecture::arm::armv8a::Armv8a::new(
+            SpecificCoreState::Armv8a(s) => Core::new(
+                crate::architecture::arm::armv8a::Armv8a::new(
                     memory,
                     s,
                     options.debug_base.expect("base_address not specified"),
                     options.cti_base.expect("cti_address not specified"),
                     debug_sequence,
                     self.id,
-                )?)
-            }
+                )?,
+                &mut self.debug_settings,
+            ),
             SpecificCoreState::Armv8m(s) => Core::new(
                 crate::architecture::arm::armv8m::Armv8m::new(memory, s, debug_sequence, self.id)?,
+                &mut self.debug_settings,
             ),
             _ => {
                 return Err(Error::UnableToOpenProbe(
@@ -83,7 +130,7 @@ impl CombinedCoreState {
     }
 
     pub(crate) fn enable_arm_debug(
-        &self,
+        &mut self,
         interface: &mut dyn ArmProbeInterface,
     ) -> Result<(), Error> {
         let (sequence_handle, arm_core_access_options) = match &self.core_state.core_access_options
@@ -94,6 +141,11 @@ impl CombinedCoreState {
             }
         };
 
+        // Only the state found on the first attach is kept, re-attaching would capture our own settings
+        if self.debug_state_on_attach.is_none() && self.core_type().is_cortex_m() {
+            self.debug_state_on_attach = self.read_debug_register_state(interface);
+        }
+
         tracing::debug_span!("debug_core_start", id = self.id()).in_scope(|| {
             // Enable debug mode
             sequence_handle.debug_core_start(
@@ -108,6 +160,103 @@ impl CombinedCoreState {
         Ok(())
     }
 
+    /// Clear vector catches which an earlier debug session left enabled in DEMCR
+    ///
+    /// Vector catches have no effect while halting debug is disabled, but halt the core as soon
+    /// as C_DEBUGEN is set. DEMCR is only written if halting debug is disabled and a vector catch
+    /// is set, the other bits of the register are kept.
+    pub(crate) fn clear_stale_vector_catch(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+    ) -> Result<(), Error> {
+        if !self.core_type().is_cortex_m() {
+            return Ok(());
+        }
+
+        let mut memory = interface.memory_interface(self.arm_memory_ap())?;
+
+        let dhcsr = Dhcsr(memory.read_word_32(Dhcsr::get_mmio_address())?);
+        if dhcsr.c_debugen() {
+            return Ok(());
+        }
+
+        let demcr = memory.read_word_32(Demcr::get_mmio_address())?;
+        if demcr & DEMCR_VECTOR_CATCH_MASK != 0 {
+            tracing::info!(
+                "Clearing vector catches left enabled on core {}: DEMCR = {demcr:#010x}",
+                self.id
+            );
+            memory.write_word_32(Demcr::get_mmio_address(), demcr & !DEMCR_VECTOR_CATCH_MASK)?;
+            memory.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Read DHCSR and DEMCR before debugging is enabled
+    ///
+    /// This is best effort, e.g. while attaching under reset the registers may not be accessible.
+    fn read_debug_register_state(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+    ) -> Option<DebugRegisterState> {
+        let mut read = || -> Result<DebugRegisterState, Error> {
+            let mut memory = interface.memory_interface(self.arm_memory_ap())?;
+
+            Ok(DebugRegisterState {
+                dhcsr: Dhcsr(memory.read_word_32(Dhcsr::get_mmio_address())?),
+                demcr: Demcr(memory.read_word_32(Demcr::get_mmio_address())?),
+            })
+        };
+
+        match read() {
+            Ok(state) => Some(state),
+            Err(e) => {
+                tracing::debug!("Failed to read the debug state of core {}: {e}", self.id);
+                None
+            }
+        }
+    }
+
+    /// Restore DHCSR and DEMCR of a Cortex-M core to their state before attaching
+    ///
+    /// This disables the vector catches and the DWT and ITM blocks enabled by probe-rs, and
+    /// disables halting debug unless it was enabled before. Without a saved state, both are
+    /// cleared. A halted core with halting debug disabled resumes execution.
+    /// Restore DHCSR and DEMCR to their values before attaching
+    ///
+    /// If the core is `halted`, halting debug stays enabled to keep it halted.
+    pub(crate) fn restore_arm_debug_state(
+        &self,
+        interface: &mut dyn ArmProbeInterface,
+        halted: bool,
+    ) -> Result<(), Error> {
+        if !self.core_type().is_cortex_m() {
+            return Ok(());
+        }
+
+        let (debugen, demcr) = match self.debug_state_on_attach {
+            Some(state) => (state.dhcsr.c_debugen(), state.demcr),
+            None => (false, Demcr(0)),
+        };
+
+        let mut memory = interface.memory_interface(self.arm_memory_ap())?;
+
+        memory.write_word_32(Demcr::get_mmio_address(), demcr.into())?;
+
+        // Halting and stepping are never restored, only whether halting debug is enabled
+        if !halted {
+            let mut dhcsr = Dhcsr(0);
+            dhcsr.enable_write();
+            dhcsr.set_c_debugen(debugen);
+            memory.write_word_32(Dhcsr::get_mmio_address(), dhcsr.into())?;
+        }
+
+        memory.flush()?;
+
+        Ok(())
+    }
+
     pub(crate) fn arm_reset_catch_set(
         &self,
         interface: &mut dyn ArmProbeInterface,
@@ -139,9 +288,10 @@ impl CombinedCoreState {
         interface: &'probe mut RiscvCommunicationInterface,
     ) -> Result<Core<'probe>, Error> {
         Ok(match &mut self.specific_state {
-            SpecificCoreState::Riscv(s) => Core::new(crate::architecture::riscv::Riscv32::new(
-                interface, s, self.id,
-            )),
+            SpecificCoreState::Riscv(s) => Core::new(
+                crate::architecture::riscv::Riscv32::new(interface, s, self.id),
+                &mut self.debug_settings,
+            ),
             _ => {
                 return Err(Error::UnableToOpenProbe(
                     "Core architecture and Probe mismatch.",
@@ -25,6 +25,15 @@ impl CoreStatus {
     }
 }
 
+/// A consistent snapshot of the state of a core, as returned by [`Core::snapshot`](crate::Core::snapshot).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct CoreSnapshot {
+    /// The status of the core, including the halt reason if it is halted.
+    pub status: CoreStatus,
+    /// The program counter, if the core is halted.
+    pub pc: Option<u64>,
+}
+
 /// Indicates the operation the target would like the debugger to perform.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum SemihostingCommand {
@@ -37,6 +46,22 @@ pub enum SemihostingCommand {
         /// Some architecture-specific or application specific exit code
         code: u64,
     },
+    /// The target wants to print a single character to the debug console (SYS_WRITEC).
+    WriteChar {
+        /// The address of the character to print
+        address: u32,
+    },
+    /// The target wants to print a NUL-terminated string to the debug console (SYS_WRITE0).
+    WriteString {
+        /// The address of the string to print
+        address: u32,
+    },
+    /// The target wants to write a buffer to a file handle (SYS_WRITE).
+    Write {
+        /// The address of the parameter block, containing the file handle,
+        /// the address of the buffer and the length of the buffer
+        parameter_block: u32,
+    },
     /// The target indicated that it would like to run a semihosting operation which we don't support yet
     Unknown {
         /// The semihosting operation requested
@@ -103,3 +128,14 @@ pub enum VectorCatchCondition {
     /// We encountered any exception.
     All,
 }
+
+/// The kind of memory access which triggers a data watchpoint.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum WatchpointKind {
+    /// Halt when the memory location is read.
+    Read,
+    /// Halt when the memory location is written.
+    Write,
+    /// Halt when the memory location is read or written.
+    ReadWrite,
+}
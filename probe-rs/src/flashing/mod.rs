@@ -51,6 +51,7 @@ mod error;
 mod flash_algorithm;
 mod flasher;
 mod loader;
+mod plan;
 mod progress;
 mod visualizer;
 
@@ -63,5 +64,6 @@ pub use erase::*;
 pub use error::*;
 pub use flash_algorithm::*;
 pub use loader::*;
+pub use plan::FlashPlan;
 pub use progress::*;
 pub use visualizer::*;
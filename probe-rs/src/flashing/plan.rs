@@ -0,0 +1,187 @@
+use probe_rs_target::MemoryRegion;
+use std::ops::Range;
+
+use super::{FlashAlgorithm, FlashBuilder, FlashError, FlashLoader, FlashPage, FlashSector};
+use crate::Target;
+
+/// The operations a flash write would perform, without touching the target.
+///
+/// Use [Session::plan_flash](crate::Session::plan_flash) to create a plan, e.g. to show a summary
+/// and ask for confirmation before the data is written.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FlashPlan {
+    sectors: Vec<FlashSector>,
+    pages: Vec<FlashPage>,
+    ram_data: Vec<Range<u64>>,
+    unmapped_data: Vec<Range<u64>>,
+}
+
+impl FlashPlan {
+    /// Plan writing `data` to `base` on `target`.
+    ///
+    /// Errors if the data falls into a flash region without a usable flash algorithm.
+    pub(crate) fn new(target: &Target, base: u64, data: &[u8]) -> Result<Self, FlashError> {
+        let mut plan = Self::default();
+
+        if data.is_empty() {
+            return Ok(plan);
+        }
+
+        let mut builder = FlashBuilder::new();
+        builder.add_data(base, data)?;
+
+        for region in &target.memory_map {
+            let MemoryRegion::Nvm(region) = region else {
+                continue;
+            };
+
+            if !builder.has_data_in_range(&region.range) {
+                continue;
+            }
+
+            let raw_algorithm = FlashLoader::get_flash_algorithm_for_region(region, target)?;
+
+            // The layout only depends on the flash properties, so the algorithm doesn't
+            // have to be assembled for the target's RAM.
+            let algorithm = FlashAlgorithm {
+                flash_properties: raw_algorithm.flash_properties.clone(),
+                ..Default::default()
+            };
+
+            let layout = builder.build_sectors_and_pages(region, &algorithm, false)?;
+
+            plan.sectors.extend_from_slice(layout.sectors());
+            plan.pages.extend_from_slice(layout.pages());
+        }
+
+        let end = base + data.len() as u64;
+        let mut address = base;
+
+        while address < end {
+            let region = FlashLoader::get_region_for_address(&target.memory_map, address);
+
+            let region_end = match region {
+                Some(region) => region_range(region).end,
+                None => target
+                    .memory_map
+                    .iter()
+                    .map(|region| region_range(region).start)
+                    .filter(|start| *start > address)
+                    .min()
+                    .unwrap_or(end),
+            }
+            .min(end);
+
+            match region {
+                Some(MemoryRegion::Nvm(_)) => {}
+                Some(MemoryRegion::Ram(_)) => push_range(&mut plan.ram_data, address..region_end),
+                _ => push_range(&mut plan.unmapped_data, address..region_end),
+            }
+
+            address = region_end;
+        }
+
+        Ok(plan)
+    }
+
+    /// The flash sectors which are erased.
+    pub fn sectors(&self) -> &[FlashSector] {
+        &self.sectors
+    }
+
+    /// The flash pages which are programmed, one chunk per page.
+    pub fn pages(&self) -> &[FlashPage] {
+        &self.pages
+    }
+
+    /// The address ranges of the data which falls into RAM instead of flash.
+    pub fn ram_data(&self) -> &[Range<u64>] {
+        &self.ram_data
+    }
+
+    /// The address ranges of the data which falls outside of the memory map,
+    /// or into a memory region which can't be written, e.g. peripherals.
+    pub fn unmapped_data(&self) -> &[Range<u64>] {
+        &self.unmapped_data
+    }
+
+    /// Returns `true` if all data falls into flash.
+    pub fn is_within_flash(&self) -> bool {
+        self.ram_data.is_empty() && self.unmapped_data.is_empty()
+    }
+
+    /// The number of bytes which are erased.
+    pub fn erase_size(&self) -> u64 {
+        self.sectors.iter().map(|sector| sector.size()).sum()
+    }
+
+    /// The number of bytes which are programmed, including the unused parts of the pages.
+    pub fn program_size(&self) -> u64 {
+        self.pages.iter().map(|page| page.size() as u64).sum()
+    }
+}
+
+/// The address range of a memory region.
+fn region_range(region: &MemoryRegion) -> &Range<u64> {
+    match region {
+        MemoryRegion::Ram(region) => &region.range,
+        MemoryRegion::Generic(region) => &region.range,
+        MemoryRegion::Nvm(region) => &region.range,
+    }
+}
+
+/// Add `range` to `ranges`, merging it with the last range if they are adjacent.
+fn push_range(ranges: &mut Vec<Range<u64>>, range: Range<u64>) {
+    match ranges.last_mut() {
+        Some(last) if last.end == range.start => last.end = range.end,
+        _ => ranges.push(range),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_target_by_name;
+
+    #[test]
+    fn plan_flash_write() {
+        let target = get_target_by_name("nRF52840_xxAA").unwrap();
+
+        let plan = FlashPlan::new(&target, 0x1800, &[0x55; 0x1000]).unwrap();
+
+        assert_eq!(
+            plan.sectors()
+                .iter()
+                .map(|sector| sector.address())
+                .collect::<Vec<_>>(),
+            vec![0x1000, 0x2000]
+        );
+        assert_eq!(plan.pages().len(), 2);
+        assert_eq!(plan.erase_size(), 0x2000);
+        assert_eq!(plan.program_size(), 0x2000);
+        assert!(plan.is_within_flash());
+    }
+
+    #[test]
+    fn plan_ram_write() {
+        let target = get_target_by_name("nRF52840_xxAA").unwrap();
+
+        let plan = FlashPlan::new(&target, 0x2000_0000, &[0x55; 0x100]).unwrap();
+
+        assert!(plan.sectors().is_empty());
+        assert!(plan.pages().is_empty());
+        assert_eq!(plan.ram_data(), &[0x2000_0000..0x2000_0100]);
+        assert!(!plan.is_within_flash());
+    }
+
+    #[test]
+    fn plan_write_beyond_flash() {
+        let target = get_target_by_name("nRF52840_xxAA").unwrap();
+
+        let plan = FlashPlan::new(&target, 0xf_ff00, &[0x55; 0x200]).unwrap();
+
+        assert_eq!(plan.sectors().len(), 1);
+        assert_eq!(plan.unmapped_data(), &[0x10_0000..0x10_0100]);
+        assert!(!plan.is_within_flash());
+    }
+}
@@ -1,5 +1,45 @@
 use gdbstub::arch::{Arch, RegId, Registers};
 
+use std::cell::RefCell;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+thread_local! {
+    /// Register sizes of the target whose packets are currently processed
+    static REGISTER_SIZES: RefCell<Arc<RegisterSizes>> = RefCell::default();
+}
+
+/// Register sizes of a target, as reported to gdbstub
+#[derive(Debug, Default)]
+pub(crate) struct RegisterSizes {
+    /// Sizes in bytes, indexed by GDB register number
+    sizes: Vec<Option<NonZeroUsize>>,
+    /// Size of register numbers which are not part of the target description
+    unmapped: Option<NonZeroUsize>,
+}
+
+impl RegisterSizes {
+    pub fn new(sizes: Vec<Option<NonZeroUsize>>, unmapped: Option<NonZeroUsize>) -> Self {
+        Self { sizes, unmapped }
+    }
+
+    /// The size of the register with GDB register number `regnum`
+    ///
+    /// Unmapped registers are reported with the size of a general purpose register, so they can
+    /// be padded with `x` like any other register which can't be read.
+    pub fn get(&self, regnum: usize) -> Option<NonZeroUsize> {
+        self.sizes.get(regnum).copied().flatten().or(self.unmapped)
+    }
+}
+
+/// Set the register sizes used for the packets processed next on this thread
+///
+/// [RegId::from_raw_id] has no access to the target, but gdbstub needs the size of a register
+/// to reply with `x` padding when the register can't be read.
+pub(crate) fn set_register_sizes(sizes: Arc<RegisterSizes>) {
+    REGISTER_SIZES.with(|current| *current.borrow_mut() = sizes);
+}
+
 // Placeholder type for runtime architecture trait data
 pub(crate) enum RuntimeArch {}
 
@@ -15,7 +55,8 @@ impl Arch for RuntimeArch {
 #[derive(Clone, Default, Debug, PartialEq)]
 pub(crate) struct RuntimeRegisters {
     pub pc: u64,
-    pub regs: Vec<u8>,
+    /// Register contents in `g` packet order, `None` for bytes which are unavailable
+    pub regs: Vec<Option<u8>>,
 }
 
 impl Registers for RuntimeRegisters {
@@ -27,26 +68,32 @@ impl Registers for RuntimeRegisters {
 
     fn gdb_serialize(&self, mut write_byte: impl FnMut(Option<u8>)) {
         for b in &self.regs {
-            write_byte(Some(*b))
+            write_byte(*b)
         }
     }
 
     fn gdb_deserialize(&mut self, bytes: &[u8]) -> Result<(), ()> {
-        self.regs = bytes.to_vec();
+        self.regs = bytes.iter().copied().map(Some).collect();
 
         Ok(())
     }
 }
 
 #[derive(Debug)]
-pub(crate) struct RuntimeRegId(u16);
+pub(crate) struct RuntimeRegId(pub(crate) u16);
 
 impl RegId for RuntimeRegId {
-    fn from_raw_id(id: usize) -> Option<(Self, Option<std::num::NonZeroUsize>)> {
+    fn from_raw_id(id: usize) -> Option<(Self, Option<NonZeroUsize>)> {
+        let size = REGISTER_SIZES.with(|sizes| sizes.borrow().get(id));
+
         id.try_into()
             .map(Some)
             .unwrap_or(None)
-            .map(|reg_num| (Self(reg_num), None))
+            .map(|reg_num| (Self(reg_num), size))
+    }
+
+    fn to_raw_id(&self) -> Option<usize> {
+        Some(self.0.into())
     }
 }
 
@@ -61,3 +108,23 @@ impl From<RuntimeRegId> for usize {
         r.0.into()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unmapped_registers_have_the_general_purpose_register_size() {
+        let word = NonZeroUsize::new(4);
+        let double = NonZeroUsize::new(8);
+
+        set_register_sizes(Arc::new(RegisterSizes::new(vec![word, None, double], word)));
+
+        assert_eq!(RuntimeRegId::from_raw_id(0).unwrap().1, word);
+        assert_eq!(RuntimeRegId::from_raw_id(2).unwrap().1, double);
+
+        // Gaps in the register numbers and numbers beyond the last register
+        assert_eq!(RuntimeRegId::from_raw_id(1).unwrap().1, word);
+        assert_eq!(RuntimeRegId::from_raw_id(100).unwrap().1, word);
+    }
+}
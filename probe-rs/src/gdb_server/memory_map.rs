@@ -0,0 +1,182 @@
+//! Memory maps supplied by the user, for chips without a usable memory description
+
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Kind of a region in a [GdbMemoryMap]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GdbMemoryKind {
+    /// Memory which GDB can write directly
+    Ram,
+    /// Non-volatile memory, which is read-only for GDB
+    Flash,
+}
+
+/// A memory region in a [GdbMemoryMap]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GdbMemoryRegion {
+    /// Kind of the memory
+    pub kind: GdbMemoryKind,
+    /// Address range of the region
+    pub range: Range<u64>,
+}
+
+/// Parse a region given as `KIND:START:LENGTH`, e.g. `flash:0x08000000:0x10000`
+///
+/// `KIND` is either `flash` or `ram`, start and length are decimal or hexadecimal with a
+/// `0x` prefix.
+impl FromStr for GdbMemoryRegion {
+    type Err = GdbMemoryMapError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || GdbMemoryMapError::InvalidRegion(s.to_string());
+
+        let mut parts = s.split(':');
+
+        let (Some(kind), Some(start), Some(length), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+
+        let kind = match kind.to_ascii_lowercase().as_str() {
+            "flash" => GdbMemoryKind::Flash,
+            "ram" => GdbMemoryKind::Ram,
+            _ => return Err(invalid()),
+        };
+
+        let start = parse_number(start).ok_or_else(invalid)?;
+        let length = parse_number(length).ok_or_else(invalid)?;
+        let end = start.checked_add(length).ok_or_else(invalid)?;
+
+        Ok(Self {
+            kind,
+            range: start..end,
+        })
+    }
+}
+
+fn parse_number(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Memory map reported to GDB instead of the one of the target, see
+/// [GdbInstanceConfiguration::memory_map](crate::gdb_server::GdbInstanceConfiguration::memory_map)
+///
+/// This allows debugging chips which are not in the registry, or whose description lacks
+/// parts of the memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GdbMemoryMap {
+    /// Regions sorted by their start address, without overlaps
+    regions: Vec<GdbMemoryRegion>,
+}
+
+impl GdbMemoryMap {
+    /// Create a memory map from the given regions.
+    ///
+    /// Fails if a region is empty or overlaps another region.
+    pub fn new(mut regions: Vec<GdbMemoryRegion>) -> Result<Self, GdbMemoryMapError> {
+        if let Some(region) = regions.iter().find(|region| region.range.is_empty()) {
+            return Err(GdbMemoryMapError::EmptyRegion(region.range.clone()));
+        }
+
+        regions.sort_by_key(|region| region.range.start);
+
+        if let Some(pair) = regions
+            .windows(2)
+            .find(|pair| pair[1].range.start < pair[0].range.end)
+        {
+            return Err(GdbMemoryMapError::Overlap(
+                pair[0].range.clone(),
+                pair[1].range.clone(),
+            ));
+        }
+
+        Ok(Self { regions })
+    }
+
+    /// The regions of the map, sorted by their start address
+    pub fn regions(&self) -> &[GdbMemoryRegion] {
+        &self.regions
+    }
+}
+
+/// An invalid [GdbMemoryMap]
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum GdbMemoryMapError {
+    /// A region could not be parsed
+    #[error(
+        "Invalid memory region '{0}', expected KIND:START:LENGTH with KIND either flash or ram"
+    )]
+    InvalidRegion(String),
+    /// A region has a length of zero
+    #[error("The memory region at {:#x} is empty", .0.start)]
+    EmptyRegion(Range<u64>),
+    /// Two regions overlap
+    #[error("The memory regions {0:#x?} and {1:#x?} overlap")]
+    Overlap(Range<u64>, Range<u64>),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn region(kind: GdbMemoryKind, range: Range<u64>) -> GdbMemoryRegion {
+        GdbMemoryRegion { kind, range }
+    }
+
+    #[test]
+    fn parse_regions() {
+        assert_eq!(
+            "flash:0x08000000:0x10000".parse(),
+            Ok(region(GdbMemoryKind::Flash, 0x0800_0000..0x0801_0000))
+        );
+        assert_eq!(
+            "RAM:536870912:1024".parse(),
+            Ok(region(GdbMemoryKind::Ram, 0x2000_0000..0x2000_0400))
+        );
+
+        for invalid in [
+            "rom:0x0:0x10",
+            "flash:0x0",
+            "flash:0x0:0x10:0x10",
+            "ram:zero:0x10",
+            "ram:0xffffffffffffffff:0x10",
+        ] {
+            assert_eq!(
+                invalid.parse::<GdbMemoryRegion>(),
+                Err(GdbMemoryMapError::InvalidRegion(invalid.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn regions_are_sorted() {
+        let map = GdbMemoryMap::new(vec![
+            region(GdbMemoryKind::Ram, 0x2000_0000..0x2000_1000),
+            region(GdbMemoryKind::Flash, 0x0..0x1000),
+        ])
+        .unwrap();
+
+        assert_eq!(map.regions()[0].range, 0x0..0x1000);
+    }
+
+    #[test]
+    fn invalid_maps_are_rejected() {
+        assert_eq!(
+            GdbMemoryMap::new(vec![
+                region(GdbMemoryKind::Flash, 0x0..0x1000),
+                region(GdbMemoryKind::Ram, 0x800..0x2000),
+            ]),
+            Err(GdbMemoryMapError::Overlap(0x0..0x1000, 0x800..0x2000))
+        );
+
+        assert_eq!(
+            GdbMemoryMap::new(vec![region(GdbMemoryKind::Ram, 0x100..0x100)]),
+            Err(GdbMemoryMapError::EmptyRegion(0x100..0x100))
+        );
+    }
+}
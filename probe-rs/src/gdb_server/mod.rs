@@ -1,7 +1,36 @@
 //! GDB server
+//!
+//! The server runs in all-stop mode: when one core of a target halts, all other cores
+//! served by the same GDB stub are halted as well. GDB's non-stop mode is not supported, as
+//! `gdbstub` implements neither `QNonStop` nor the `%Stop` notifications. `QNonStop+` is not
+//! advertised in the `qSupported` reply, so GDB refuses to connect with `set non-stop on`.
+//!
+//! Packets which are not supported are rejected with an empty reply: the remote protocol
+//! reserves `OK` for supported packets which succeeded, so unknown packets must never get it.
+//!
+//! The actions of a `vCont` packet are applied to the cores of the threads they name, e.g.
+//! `vCont;s:2;c` steps the second core while the other cores run, until one of them halts.
+//! Without a default action, e.g. `vCont;s:2` sent with GDB's `set scheduler-locking on`,
+//! the cores which are not named stay halted.
+//!
+//! A core halted by a watchpoint is reported with a `watch`, `rwatch` or `awatch` stop reply
+//! containing the watched address, so GDB can show which watchpoint triggered. If the core
+//! doesn't report which watchpoint matched, a plain `SIGTRAP` is reported instead.
+//!
+//! GDB's host I/O packets (`vFile:open`, `vFile:pread`, `vFile:pwrite` and `vFile:close`) operate
+//! on the files of a sandbox directory on the host, and are only supported when such a directory
+//! is configured in [GdbInstanceConfiguration::host_io_root].
+//!
+//! The memory map sent to GDB is taken from the target description, unless a map is supplied
+//! in [GdbInstanceConfiguration::memory_map], e.g. for chips which are not in the registry.
 
 mod arch;
+mod memory_map;
 mod stub;
 mod target;
 
-pub use stub::{run, GdbInstanceConfiguration};
+pub use memory_map::{GdbMemoryKind, GdbMemoryMap, GdbMemoryMapError, GdbMemoryRegion};
+pub use stub::{
+    run, run_server, run_server_async, GdbInstanceConfiguration, GdbServer, GdbServerConfig,
+    SectionOffsets, ShutdownSignal,
+};
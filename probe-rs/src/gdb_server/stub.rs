@@ -1,16 +1,28 @@
 use crate::{CoreType, Error, Session};
 use anyhow::Result;
 
+use std::future::Future;
 use std::net::{SocketAddr, ToSocketAddrs};
-use std::sync::Mutex;
+use std::panic::AssertUnwindSafe;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
 use std::time::Duration;
 
 use itertools::Itertools;
 
-use super::target;
+use super::{target, GdbMemoryMap};
 
 const CONNECTION_STRING: &str = "127.0.0.1:1337";
 
+/// Default interval in which running cores are checked for a halt
+const HALT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Default size of the packet buffer, which is also the packet size advertised to GDB
+const PACKET_SIZE: usize = 4096;
+
 /// Configuration for a single GDB endpoint
 pub struct GdbInstanceConfiguration {
     /// The core type that will be sent to GDB
@@ -19,6 +31,110 @@ pub struct GdbInstanceConfiguration {
     pub cores: Vec<usize>,
     /// The list of [SocketAddr] addresses to bind to
     pub socket_addrs: Vec<SocketAddr>,
+    /// Section offsets reported to GDB, for images which are not loaded at their link address
+    pub section_offsets: Option<SectionOffsets>,
+    /// Halt the cores when GDB connects.
+    ///
+    /// Otherwise the cores keep running while GDB considers them stopped, until GDB
+    /// continues them. Registers are then read by briefly halting the core.
+    pub halt_on_attach: bool,
+    /// Send an empty console output packet to GDB when the target has been running for this long
+    /// without any other traffic.
+    ///
+    /// Some GDB front-ends give up waiting for a stop reply when the target runs for a long time.
+    pub keep_alive: Option<Duration>,
+    /// File to log the packets exchanged with GDB to, for debugging the GDB server.
+    ///
+    /// Each packet is logged with a timestamp, its direction and a short description. Binary
+    /// payloads are replaced by their length, and logging stops once the file reaches 16 MiB.
+    pub traffic_log: Option<PathBuf>,
+    /// Maximum size of a packet in bytes, advertised to GDB with `qSupported`.
+    ///
+    /// GDB splits memory reads and writes to fit into a packet, so larger packets speed up
+    /// the transfer of large memory blocks. Must be at least 256 bytes.
+    pub packet_size: usize,
+    /// Directory GDB can access with the `vFile` host I/O packets, e.g. for `remote get` and
+    /// `remote put`.
+    ///
+    /// File names are resolved relative to this directory and cannot leave it. Host I/O is
+    /// disabled if not set.
+    pub host_io_root: Option<PathBuf>,
+    /// Memory map reported to GDB instead of the memory map of the target.
+    ///
+    /// Flash regions are reported as read-only memory, like the non-volatile memory of
+    /// the target.
+    pub memory_map: Option<GdbMemoryMap>,
+}
+
+/// Offsets of a relocated image, relative to the addresses it was linked for
+///
+/// These are reported to GDB with the `qOffsets` packet, so it can relocate the symbols.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectionOffsets {
+    /// Offset of the `.text` section
+    pub text: u64,
+    /// Offset of the `.data` section
+    pub data: u64,
+    /// Offset of the `.bss` section. GDB expects this to be either `None` or equal to `data`.
+    pub bss: Option<u64>,
+}
+
+/// Configuration of a complete GDB server, see [run_server()]
+pub struct GdbServerConfig {
+    /// The GDB endpoints to serve, usually one per core type
+    pub instances: Vec<GdbInstanceConfiguration>,
+    /// Interval in which running cores are checked for a halt.
+    ///
+    /// The server sleeps between checks, so a halt is reported to GDB at most one interval
+    /// after it happened. Shorter intervals cause more traffic on the probe.
+    pub halt_poll_interval: Duration,
+    /// Service semihosting console output, forwarding it to GDB, instead of reporting it as a trap.
+    ///
+    /// This can be changed at runtime with `monitor semihosting enable|disable`.
+    pub semihosting: bool,
+    /// Stops the server when triggered
+    pub shutdown: ShutdownSignal,
+}
+
+impl GdbServerConfig {
+    /// Build a server configuration from a session object, serving all cores.
+    ///
+    /// See [GdbInstanceConfiguration::from_session()] for the meaning of `connection_string`.
+    pub fn from_session(
+        session: &Session,
+        connection_string: Option<impl Into<String>>,
+    ) -> Result<Self> {
+        Ok(Self {
+            instances: GdbInstanceConfiguration::from_session(session, connection_string)?,
+            halt_poll_interval: HALT_POLL_INTERVAL,
+            semihosting: false,
+            shutdown: ShutdownSignal::default(),
+        })
+    }
+}
+
+/// Signal to stop a running GDB server from another thread
+///
+/// Clones of a signal share their state, so one clone can be kept by the application
+/// while the other one is passed to the server in the [GdbServerConfig].
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownSignal(Arc<AtomicBool>);
+
+impl ShutdownSignal {
+    /// Create a new signal which has not been triggered yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the server to stop. Open GDB connections are closed.
+    pub fn trigger(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Check whether the server was asked to stop
+    pub fn is_triggered(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
 }
 
 impl GdbInstanceConfiguration {
@@ -27,21 +143,27 @@ impl GdbInstanceConfiguration {
     /// # Arguments
     ///
     /// * session - the [Session] object to load target information from
-    /// * connection_string - The optional connection string to use.  
+    /// * connection_string - The optional connection string to use, e.g. `0.0.0.0:1337`.
     ///                       If not specified `localhost:1337` is used.
     ///                       Multiple instances are bound by adding an offset to the supplied port.
+    ///                       Port 0 lets the operating system pick a free port for every instance.
     ///
     /// # Returns
-    /// Vec with the computed configuration
+    /// Vec with the computed configuration, or an error if the connection string is not a valid address
     pub fn from_session(
         session: &Session,
         connection_string: Option<impl Into<String>>,
-    ) -> Vec<Self> {
+    ) -> Result<Vec<Self>> {
         let connection_string = connection_string
             .map(|cs| cs.into())
             .unwrap_or_else(|| CONNECTION_STRING.to_owned());
 
-        let addrs: Vec<SocketAddr> = connection_string.to_socket_addrs().unwrap().collect();
+        let addrs: Vec<SocketAddr> = connection_string
+            .to_socket_addrs()
+            .map_err(|e| {
+                anyhow::anyhow!("Invalid GDB connection string '{connection_string}': {e}")
+            })?
+            .collect();
 
         // Build a grouped list of cores by core type
         // GDB only supports one architecture per stub so if we have two core types,
@@ -65,10 +187,108 @@ impl GdbInstanceConfiguration {
                 core_type: *core_type,
                 cores: cores.to_vec(),
                 socket_addrs: adjust_addrs(&addrs, i),
+                section_offsets: None,
+                halt_on_attach: true,
+                keep_alive: None,
+                traffic_log: None,
+                packet_size: PACKET_SIZE,
+                host_io_root: None,
+                memory_map: None,
             })
             .collect();
 
-        ret
+        Ok(ret)
+    }
+}
+
+/// A GDB server with all its endpoints bound, ready to accept connections
+pub struct GdbServer<'a> {
+    targets: Vec<target::RuntimeTarget<'a>>,
+}
+
+impl<'a> GdbServer<'a> {
+    /// Bind the endpoints of all GDB instances.
+    ///
+    /// # Arguments
+    ///
+    /// * session - The [Session] to use, protected by a [std::sync::Mutex]
+    /// * instances - a list of [GdbInstanceConfiguration] objects used to configure the GDB session
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the endpoints cannot be bound, e.g. because the port is already in use.
+    pub fn bind<'b>(
+        session: &'a Mutex<Session>,
+        instances: impl Iterator<Item = &'b GdbInstanceConfiguration>,
+    ) -> Result<Self> {
+        Self::bind_with(session, instances, false, HALT_POLL_INTERVAL)
+    }
+
+    /// Bind the endpoints of all GDB instances in `config`.
+    ///
+    /// The server has to be run with [GdbServer::run_until()] for the shutdown signal to be used.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the endpoints cannot be bound, e.g. because the port is already in use.
+    pub fn from_config(session: &'a Mutex<Session>, config: &GdbServerConfig) -> Result<Self> {
+        Self::bind_with(
+            session,
+            config.instances.iter(),
+            config.semihosting,
+            config.halt_poll_interval,
+        )
+    }
+
+    fn bind_with<'b>(
+        session: &'a Mutex<Session>,
+        instances: impl Iterator<Item = &'b GdbInstanceConfiguration>,
+        semihosting: bool,
+        halt_poll_interval: Duration,
+    ) -> Result<Self> {
+        // Turn our group list into GDB targets
+        let targets = instances
+            .map(|instance| {
+                target::RuntimeTarget::new(session, instance, semihosting, halt_poll_interval)
+            })
+            .collect::<Result<Vec<target::RuntimeTarget>, Error>>()?;
+
+        Ok(Self { targets })
+    }
+
+    /// The addresses the endpoints are listening on, in the same order as the instances passed to [GdbServer::bind()].
+    ///
+    /// When binding to port 0, this contains the port picked by the operating system.
+    pub fn local_addrs(&self) -> Result<Vec<SocketAddr>> {
+        let addrs = self
+            .targets
+            .iter()
+            .map(|target| target.local_addr())
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(addrs)
+    }
+
+    /// Serve GDB connections on all endpoints. This only returns when an error occurs.
+    pub fn run(&mut self) -> Result<()> {
+        self.run_until(&ShutdownSignal::default())
+    }
+
+    /// Serve GDB connections on all endpoints, until `shutdown` is triggered or an error occurs.
+    pub fn run_until(&mut self, shutdown: &ShutdownSignal) -> Result<()> {
+        // Process every target in a loop
+        while !shutdown.is_triggered() {
+            let wait_times = self
+                .targets
+                .iter_mut()
+                .map(|target| target.process())
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            // Wait until we were asked to check again
+            std::thread::sleep(next_wait_time(wait_times));
+        }
+
+        Ok(())
     }
 }
 
@@ -81,31 +301,96 @@ impl GdbInstanceConfiguration {
 ///
 /// # Remarks
 ///
-/// A default configuration can be created by calling [GdbInstanceConfiguration::from_session()]
+/// A default configuration can be created by calling [GdbInstanceConfiguration::from_session()].
+/// Use [GdbServer] instead to find out which addresses the endpoints are bound to.
 pub fn run<'a>(
     session: &Mutex<Session>,
     instances: impl Iterator<Item = &'a GdbInstanceConfiguration>,
 ) -> Result<()> {
-    // Turn our group list into GDB targets
-    let mut targets = instances
-        .map(|instance| {
-            target::RuntimeTarget::new(session, instance.cores.to_vec(), &instance.socket_addrs[..])
-        })
-        .collect::<Result<Vec<target::RuntimeTarget>, Error>>()?;
+    GdbServer::bind(session, instances)?.run()
+}
+
+/// Run a GDB server on a session owned by the application.
+///
+/// This blocks until the [ShutdownSignal] of the configuration is triggered, or an error occurs.
+/// The session is locked while a GDB request is handled, so it can be used by the application
+/// in between, e.g. to read memory while the target is running.
+///
+/// # Arguments
+///
+/// * session - The [Session] to use
+/// * config - The endpoints and settings of the server
+///
+/// # Remarks
+///
+/// A default configuration can be created by calling [GdbServerConfig::from_session()].
+pub fn run_server(session: Arc<Mutex<Session>>, config: GdbServerConfig) -> Result<()> {
+    GdbServer::from_config(&session, &config)?.run_until(&config.shutdown)
+}
+
+/// Run a GDB server on a session owned by the application, see [run_server()].
+///
+/// The server runs on its own thread, the returned future completes when the server stopped.
+/// It doesn't depend on a specific async runtime. Dropping the future doesn't stop the
+/// server, use the [ShutdownSignal] of the configuration for that.
+pub fn run_server_async(
+    session: Arc<Mutex<Session>>,
+    config: GdbServerConfig,
+) -> impl Future<Output = Result<()>> {
+    let state = Arc::new(Mutex::new(ServerTaskState::default()));
+    let server_state = state.clone();
+
+    std::thread::spawn(move || {
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| run_server(session, config)))
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("The GDB server thread panicked")));
 
-    // Process every target in a loop
-    loop {
-        let mut wait_time = Duration::ZERO;
+        let mut state = server_state.lock().unwrap();
+        state.result = Some(result);
 
-        for target in targets.iter_mut() {
-            wait_time = wait_time.min(target.process()?);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
         }
+    });
 
-        // Wait until we were asked to check again
-        std::thread::sleep(wait_time);
+    ServerTask { state }
+}
+
+/// Shared state of a GDB server thread and the future waiting for it
+#[derive(Default)]
+struct ServerTaskState {
+    result: Option<Result<()>>,
+    waker: Option<Waker>,
+}
+
+/// Future completing when the GDB server thread stopped
+struct ServerTask {
+    state: Arc<Mutex<ServerTaskState>>,
+}
+
+impl Future for ServerTask {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap();
+
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
     }
 }
 
+/// Time to wait before the targets are processed again
+///
+/// This is the shortest wait any target asked for, so a target with pending GDB input
+/// is processed again immediately, while idle and running targets let the loop sleep.
+fn next_wait_time(wait_times: impl IntoIterator<Item = Duration>) -> Duration {
+    wait_times.into_iter().min().unwrap_or(HALT_POLL_INTERVAL)
+}
+
 /// Given a list of socket addresses, adjust the port by `offset` and return
 /// the new values
 fn adjust_addrs(addrs: &[SocketAddr], offset: usize) -> Vec<SocketAddr> {
@@ -114,9 +399,43 @@ fn adjust_addrs(addrs: &[SocketAddr], offset: usize) -> Vec<SocketAddr> {
         .map(|addr| {
             let mut new_addr = *addr;
 
-            new_addr.set_port(new_addr.port() + offset as u16);
+            // Port 0 lets the operating system pick a port, which works for every instance
+            if new_addr.port() != 0 {
+                new_addr.set_port(new_addr.port() + offset as u16);
+            }
 
             new_addr
         })
         .collect()
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn idle_targets_sleep() {
+        let wait_time = next_wait_time([Duration::from_millis(10), Duration::from_millis(50)]);
+
+        assert_eq!(wait_time, Duration::from_millis(10));
+    }
+
+    #[test]
+    fn pending_input_is_processed_immediately() {
+        let wait_time = next_wait_time([Duration::from_millis(50), Duration::ZERO]);
+
+        assert_eq!(wait_time, Duration::ZERO);
+    }
+
+    #[test]
+    fn running_target_is_polled_at_interval() {
+        let wait_time = next_wait_time([Duration::from_millis(50)]);
+
+        assert_eq!(wait_time, Duration::from_millis(50));
+    }
+
+    #[test]
+    fn no_targets() {
+        assert_eq!(next_wait_time([]), HALT_POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,274 @@
+use super::{is_probe_error, GdbErrorExt, RuntimeTarget};
+
+use crate::config::{MemoryRange, MemoryRegion};
+use crate::gdb_server::arch::{RuntimeRegId, RuntimeRegisters};
+use crate::{Error, MemoryInterface, RegisterValue};
+
+use gdbstub::common::Tid;
+use gdbstub::target::ext::base::multithread::{MultiThreadBase, MultiThreadResumeOps};
+use gdbstub::target::ext::base::single_register_access::{
+    SingleRegisterAccess, SingleRegisterAccessOps,
+};
+use gdbstub::target::{TargetError, TargetResult};
+
+impl MultiThreadBase for RuntimeTarget<'_> {
+    fn read_registers(&mut self, regs: &mut RuntimeRegisters, tid: Tid) -> TargetResult<(), Self> {
+        let mut session = self.session.lock().unwrap();
+        let mut core = self.target_result(session.core(tid.get() - 1))?;
+
+        // A running core is halted for the access and resumed afterwards, so e.g. an IDE
+        // polling registers does not stop the firmware.
+        let result = core.with_halted(|core| -> Result<(), Error> {
+            regs.pc = core.read_core_reg(core.program_counter())?;
+            regs.regs.clear();
+
+            for reg in self.target_desc.get_registers_for_main_group() {
+                match reg.read(core) {
+                    Ok(value) => {
+                        let mut bytes = Vec::with_capacity(reg.size_in_bytes());
+                        append_register_bytes(&mut bytes, value, reg.size_in_bytes());
+
+                        regs.regs.extend(bytes.into_iter().map(Some));
+                    }
+                    Err(e) => {
+                        // Report the register as unavailable, but keep the packet layout intact
+                        tracing::debug!("Failed to read register {:?}: {}", reg.source(), e);
+
+                        regs.regs
+                            .extend(std::iter::repeat(None).take(reg.size_in_bytes()));
+                    }
+                }
+            }
+
+            Ok(())
+        });
+
+        self.target_result(result.and_then(|result| result))
+    }
+
+    fn write_registers(&mut self, regs: &RuntimeRegisters, tid: Tid) -> TargetResult<(), Self> {
+        let mut session = self.session.lock().unwrap();
+        let mut core = self.target_result(session.core(tid.get() - 1))?;
+
+        let result = core.with_halted(|core| -> Result<(), Error> {
+            let mut bytes = &regs.regs[..];
+
+            for reg in self.target_desc.get_registers_for_main_group() {
+                let size = reg.size_in_bytes();
+
+                if bytes.len() < size {
+                    // GDB may send a shortened register set, the remaining registers are left untouched
+                    break;
+                }
+
+                let (value, rest) = bytes.split_at(size);
+                bytes = rest;
+
+                // Registers which were reported as unavailable are left untouched
+                let Some(value) = value.iter().copied().collect::<Option<Vec<u8>>>() else {
+                    continue;
+                };
+
+                reg.write(core, register_value_from_bytes(&value))?;
+            }
+
+            Ok(())
+        });
+
+        self.target_result(result.and_then(|result| result))
+    }
+
+    fn support_single_register_access(&mut self) -> Option<SingleRegisterAccessOps<'_, Tid, Self>> {
+        Some(self)
+    }
+
+    fn read_addrs(
+        &mut self,
+        start_addr: u64,
+        data: &mut [u8],
+        tid: Tid,
+    ) -> TargetResult<usize, Self> {
+        let mut session = self.session.lock().unwrap();
+        let mut core = self.target_result(session.core(tid.get() - 1))?;
+
+        if let Err(e) = core.read(start_addr, data) {
+            tracing::debug!(
+                "Failed to read {} bytes at {:#010x}: {}",
+                data.len(),
+                start_addr,
+                e
+            );
+
+            // GDB accepts short replies, so return everything up to the first unreadable word
+            let mut offset = 0;
+            while offset < data.len() {
+                let address = start_addr + offset as u64;
+                let len = (4 - (address % 4) as usize).min(data.len() - offset);
+
+                if core.read(address, &mut data[offset..offset + len]).is_err() {
+                    break;
+                }
+
+                offset += len;
+            }
+
+            if offset == 0 {
+                // Reported to GDB as `E14`
+                return Err(TargetError::Errno(0x14));
+            }
+
+            return Ok(offset);
+        }
+
+        Ok(data.len())
+    }
+
+    fn write_addrs(&mut self, start_addr: u64, data: &[u8], tid: Tid) -> TargetResult<(), Self> {
+        let mut session = self.session.lock().unwrap();
+
+        // Device memory, e.g. peripheral registers, only accepts accesses of a fixed width
+        let range = start_addr..start_addr + data.len() as u64;
+        let access_width = session
+            .target()
+            .memory_map
+            .iter()
+            .find_map(|region| match region {
+                MemoryRegion::Generic(region) if region.range.contains_range(&range) => {
+                    region.access_width
+                }
+                _ => None,
+            });
+
+        let mut core = self.target_result(session.core(tid.get() - 1))?;
+
+        match access_width {
+            Some(width) => core.write_block_with_width(start_addr, data, width),
+            None => core.write_8(start_addr, data),
+        }
+        .into_target_result_non_fatal()?;
+
+        // Reading back device memory can have side effects, and doesn't necessarily
+        // return the written value.
+        if self.verify && access_width.is_none() {
+            let mut readback = vec![0u8; data.len()];
+            core.read(start_addr, &mut readback)
+                .into_target_result_non_fatal()?;
+
+            if let Some(offset) = data.iter().zip(&readback).position(|(a, b)| a != b) {
+                tracing::error!(
+                    "Verification of memory write failed at address {:#010x}: wrote {:#04x}, read back {:#04x}",
+                    start_addr + offset as u64,
+                    data[offset],
+                    readback[offset]
+                );
+
+                // Reported to GDB as `E01`
+                return Err(TargetError::Errno(1));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn list_active_threads(
+        &mut self,
+        thread_is_active: &mut dyn FnMut(Tid),
+    ) -> Result<(), Self::Error> {
+        for id in self.active_cores() {
+            thread_is_active(Tid::new(id + 1).unwrap());
+        }
+
+        Ok(())
+    }
+
+    fn support_resume(&mut self) -> Option<MultiThreadResumeOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SingleRegisterAccess<Tid> for RuntimeTarget<'_> {
+    fn read_register(
+        &mut self,
+        tid: Tid,
+        reg_id: RuntimeRegId,
+        buf: &mut [u8],
+    ) -> TargetResult<usize, Self> {
+        let mut session = self.session.lock().unwrap();
+        let mut core = self.target_result(session.core(tid.get() - 1))?;
+
+        let Some(reg) = self.target_desc.get_register(reg_id.into()) else {
+            // Reported as unavailable, like a register which can't be read
+            tracing::debug!("GDB register {} is not mapped", usize::from(reg_id));
+
+            return Ok(0);
+        };
+
+        let value = match core
+            .with_halted(|core| reg.read(core))
+            .and_then(|result| result)
+        {
+            Ok(value) => value,
+            Err(e) if !is_probe_error(&e) => {
+                // Replying without data makes gdbstub pad the register with `x`, which
+                // GDB shows as unavailable
+                tracing::debug!("Failed to read register {:?}: {}", reg.source(), e);
+
+                return Ok(0);
+            }
+            Err(e) => return self.target_result(Err(e)),
+        };
+
+        let mut bytes = Vec::with_capacity(reg.size_in_bytes());
+        append_register_bytes(&mut bytes, value, reg.size_in_bytes());
+
+        let len = bytes.len().min(buf.len());
+        buf[..len].copy_from_slice(&bytes[..len]);
+
+        Ok(len)
+    }
+
+    fn write_register(
+        &mut self,
+        tid: Tid,
+        reg_id: RuntimeRegId,
+        val: &[u8],
+    ) -> TargetResult<(), Self> {
+        let mut session = self.session.lock().unwrap();
+        let mut core = self.target_result(session.core(tid.get() - 1))?;
+
+        let Some(reg) = self.target_desc.get_register(reg_id.into()) else {
+            return Err(TargetError::NonFatal);
+        };
+
+        self.target_result(
+            core.with_halted(|core| reg.write(core, register_value_from_bytes(val)))
+                .and_then(|result| result),
+        )
+    }
+}
+
+/// Append the little endian representation of `value`, truncated or zero extended to `size` bytes
+pub(super) fn append_register_bytes(bytes: &mut Vec<u8>, value: RegisterValue, size: usize) {
+    let value = match value {
+        RegisterValue::U32(v) => v as u128,
+        RegisterValue::U64(v) => v as u128,
+        RegisterValue::U128(v) => v,
+    };
+
+    bytes.extend_from_slice(&value.to_le_bytes()[..size.min(16)]);
+}
+
+/// Convert little endian register bytes sent by GDB into a [RegisterValue]
+fn register_value_from_bytes(bytes: &[u8]) -> RegisterValue {
+    let mut raw = [0u8; 16];
+    let len = bytes.len().min(raw.len());
+    raw[..len].copy_from_slice(&bytes[..len]);
+
+    let value = u128::from_le_bytes(raw);
+
+    match len {
+        0..=4 => RegisterValue::U32(value as u32),
+        5..=8 => RegisterValue::U64(value as u64),
+        _ => RegisterValue::U128(value),
+    }
+}
@@ -0,0 +1,309 @@
+use super::{GdbErrorExt, RuntimeTarget};
+
+use crate::{Error, InstructionSet, MemoryInterface, Session, WatchpointKind};
+
+use gdbstub::target::ext::breakpoints::{
+    Breakpoints, HwBreakpoint, HwBreakpointOps, HwWatchpoint, HwWatchpointOps, WatchKind,
+};
+use gdbstub::target::{TargetError, TargetResult};
+
+impl Breakpoints for RuntimeTarget<'_> {
+    fn support_hw_breakpoint(&mut self) -> Option<HwBreakpointOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_hw_watchpoint(&mut self) -> Option<HwWatchpointOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl HwBreakpoint for RuntimeTarget<'_> {
+    fn add_hw_breakpoint(&mut self, addr: u64, kind: usize) -> TargetResult<bool, Self> {
+        if self.sw_breakpoints.iter().any(|bp| bp.address == addr) {
+            return Ok(true);
+        }
+
+        let mut session = self.session.lock().unwrap();
+
+        // Fall back to a breakpoint instruction once a core has no free comparator left
+        let mut units_exhausted = false;
+
+        if !self.hw_breakpoints.contains(&addr) {
+            for core_id in &self.cores {
+                let mut core = self.target_result(session.core(*core_id))?;
+
+                units_exhausted |= self
+                    .target_result(core.hw_breakpoints())?
+                    .iter()
+                    .all(Option::is_some);
+            }
+        }
+
+        if units_exhausted {
+            let breakpoint =
+                insert_sw_breakpoint(&mut session, &self.cores, addr, kind).map_err(|e| {
+                    tracing::debug!(
+                        "Failed to set software breakpoint at {:#010x}: {:?}",
+                        addr,
+                        e
+                    );
+
+                    TargetError::Errno(1)
+                })?;
+
+            self.sw_breakpoints.push(breakpoint);
+
+            return Ok(true);
+        }
+
+        for core_id in &self.cores {
+            let mut core = self.target_result(session.core(*core_id))?;
+
+            core.set_hw_breakpoint(addr)
+                .into_target_result_non_fatal()?;
+        }
+
+        if !self.hw_breakpoints.contains(&addr) {
+            self.hw_breakpoints.push(addr);
+        }
+
+        Ok(true)
+    }
+
+    fn remove_hw_breakpoint(&mut self, addr: u64, _kind: usize) -> TargetResult<bool, Self> {
+        let mut session = self.session.lock().unwrap();
+
+        if let Some(index) = self.sw_breakpoints.iter().position(|bp| bp.address == addr) {
+            let breakpoint = self.sw_breakpoints.remove(index);
+
+            let mut core = self.target_result(session.core(self.cores[0]))?;
+            core.write_8(addr, &breakpoint.original)
+                .into_target_result_non_fatal()?;
+
+            return Ok(true);
+        }
+
+        for core_id in &self.cores {
+            let mut core = self.target_result(session.core(*core_id))?;
+
+            core.clear_hw_breakpoint(addr)
+                .into_target_result_non_fatal()?;
+        }
+
+        self.hw_breakpoints.retain(|&bp| bp != addr);
+
+        Ok(true)
+    }
+}
+
+impl HwWatchpoint for RuntimeTarget<'_> {
+    fn add_hw_watchpoint(
+        &mut self,
+        addr: u64,
+        len: u64,
+        kind: WatchKind,
+    ) -> TargetResult<bool, Self> {
+        let mut session = self.session.lock().unwrap();
+
+        for core_id in &self.cores {
+            let mut core = self.target_result(session.core(*core_id))?;
+
+            // Most likely all comparators are in use, which GDB expects to be reported as `E01`
+            core.set_hw_watchpoint(addr, len, watchpoint_kind(kind))
+                .map_err(|e| {
+                    tracing::debug!("Failed to set watchpoint at {:#010x}: {:?}", addr, e);
+
+                    TargetError::Errno(1)
+                })?;
+        }
+
+        self.hw_watchpoints.retain(|&(wp, _, _)| wp != addr);
+        self.hw_watchpoints.push((addr, len, kind));
+
+        Ok(true)
+    }
+
+    fn remove_hw_watchpoint(
+        &mut self,
+        addr: u64,
+        _len: u64,
+        _kind: WatchKind,
+    ) -> TargetResult<bool, Self> {
+        let mut session = self.session.lock().unwrap();
+
+        for core_id in &self.cores {
+            let mut core = self.target_result(session.core(*core_id))?;
+
+            core.clear_hw_watchpoint(addr)
+                .into_target_result_non_fatal()?;
+        }
+
+        self.hw_watchpoints.retain(|&(wp, _, _)| wp != addr);
+
+        Ok(true)
+    }
+}
+
+/// A breakpoint instruction written to memory, because no breakpoint comparator was free
+pub(crate) struct SwBreakpoint {
+    /// Address of the breakpoint
+    pub(crate) address: u64,
+    /// The code replaced by the breakpoint instruction, restored when it is removed
+    pub(crate) original: Vec<u8>,
+}
+
+/// Replace the instruction at `address` with a breakpoint instruction
+///
+/// The memory is read back, so breakpoints in flash or ROM, where the write has no effect,
+/// are reported as an error instead of never being hit.
+fn insert_sw_breakpoint(
+    session: &mut Session,
+    cores: &[usize],
+    address: u64,
+    kind: usize,
+) -> Result<SwBreakpoint, Error> {
+    let mut core = session.core(cores[0])?;
+
+    let instruction = breakpoint_instruction(core.instruction_set()?, kind).ok_or_else(|| {
+        anyhow::anyhow!("Software breakpoints of kind {kind} are not supported on this core")
+    })?;
+
+    let mut original = vec![0; instruction.len()];
+    core.read_8(address, &mut original)?;
+    core.write_8(address, instruction)?;
+
+    let mut written = vec![0; instruction.len()];
+    core.read_8(address, &mut written)?;
+
+    if written != instruction {
+        return Err(anyhow::anyhow!(
+            "The code at {address:#010x} can't be replaced by a breakpoint instruction"
+        )
+        .into());
+    }
+
+    // RISC-V cores only halt on a breakpoint instruction if this is enabled
+    for core_id in cores {
+        session.core(*core_id)?.debug_on_sw_breakpoint(true)?;
+    }
+
+    Ok(SwBreakpoint { address, original })
+}
+
+/// The breakpoint instruction for a GDB breakpoint of `kind`, which is the length of the
+/// instruction to replace, in target byte order.
+fn breakpoint_instruction(instruction_set: InstructionSet, kind: usize) -> Option<&'static [u8]> {
+    match (instruction_set, kind) {
+        // BKPT #0, which also replaces the first halfword of a 32 bit instruction
+        (InstructionSet::Thumb2, 2 | 3) => Some(&[0x00, 0xbe]),
+        // BKPT #0
+        (InstructionSet::A32, 4) => Some(&[0x70, 0x00, 0x20, 0xe1]),
+        // BRK #0
+        (InstructionSet::A64, 4) => Some(&[0x00, 0x00, 0x20, 0xd4]),
+        // C.EBREAK
+        (InstructionSet::RV32C, 2) => Some(&[0x02, 0x90]),
+        // EBREAK
+        (InstructionSet::RV32 | InstructionSet::RV32C, 4) => Some(&[0x73, 0x00, 0x10, 0x00]),
+        _ => None,
+    }
+}
+
+/// Convert the GDB watchpoint kind into the probe-rs watchpoint kind
+pub(crate) fn watchpoint_kind(kind: WatchKind) -> WatchpointKind {
+    match kind {
+        WatchKind::Write => WatchpointKind::Write,
+        WatchKind::Read => WatchpointKind::Read,
+        WatchKind::ReadWrite => WatchpointKind::ReadWrite,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::breakpoint_instruction;
+    use crate::architecture::arm::armv7m::{Aircr, FpCtrl};
+    use crate::architecture::arm::ApAddress;
+    use crate::gdb_server::target::conn::test::process_packets;
+    use crate::gdb_server::target::RuntimeTarget;
+    use crate::gdb_server::GdbInstanceConfiguration;
+    use crate::probe::fake_probe::{FakeProbe, Operation};
+    use crate::{InstructionSet, MemoryMappedRegister, Permissions};
+
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    fn breakpoint_instruction_matches_kind() {
+        assert_eq!(
+            breakpoint_instruction(InstructionSet::Thumb2, 2),
+            Some(&[0x00, 0xbe][..])
+        );
+        assert_eq!(
+            breakpoint_instruction(InstructionSet::Thumb2, 3),
+            Some(&[0x00, 0xbe][..])
+        );
+        assert_eq!(
+            breakpoint_instruction(InstructionSet::RV32C, 2),
+            Some(&[0x02, 0x90][..])
+        );
+        assert_eq!(
+            breakpoint_instruction(InstructionSet::RV32C, 4),
+            Some(&[0x73, 0x00, 0x10, 0x00][..])
+        );
+        assert_eq!(breakpoint_instruction(InstructionSet::RV32, 2), None);
+        assert_eq!(breakpoint_instruction(InstructionSet::Thumb2, 4), None);
+    }
+
+    #[test]
+    fn breakpoints_are_reprogrammed_after_reset() {
+        let mut fake_probe = FakeProbe::with_mocked_core();
+        fake_probe.set_breakpoint_units(4);
+
+        // Indicate that the core is unlocked
+        fake_probe.expect_operation(Operation::ReadRawApRegister {
+            ap: ApAddress::with_default_dp(1),
+            address: 0xC,
+            result: 1,
+        });
+
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let recorded_writes = writes.clone();
+        fake_probe.set_memory_write_handler(Box::new(move |address, value| {
+            recorded_writes.lock().unwrap().push((address, value));
+        }));
+
+        let session = fake_probe
+            .into_probe()
+            .attach("nrf52833_xxaa", Permissions::default())
+            .unwrap();
+        let instance = GdbInstanceConfiguration::from_session(&session, Some("127.0.0.1:0"))
+            .unwrap()
+            .remove(0);
+        let session = Mutex::new(session);
+        let mut target =
+            RuntimeTarget::new(&session, &instance, false, Duration::from_millis(10)).unwrap();
+
+        // `break *0x1000` followed by `monitor reset`
+        process_packets(&mut target, b"$Z1,1000,2#d6$qRcmd,7265736574#37");
+
+        let writes = writes.lock().unwrap();
+        let reset = writes
+            .iter()
+            .position(|&(address, _)| address == Aircr::ADDRESS_OFFSET)
+            .expect("the target was not reset");
+
+        // The breakpoint unit is enabled and the comparator is programmed again
+        assert!(writes[reset..]
+            .iter()
+            .any(|&(address, value)| address == FpCtrl::ADDRESS_OFFSET && value & 0b11 == 0b11));
+        assert!(writes[reset..]
+            .iter()
+            .any(|&(address, value)| address == FpCtrl::ADDRESS_OFFSET + 8 && value & 1 == 1));
+
+        drop(writes);
+
+        let mut session = session.lock().unwrap();
+        let breakpoints = session.core(0).unwrap().hw_breakpoints().unwrap();
+
+        assert_eq!(breakpoints, vec![Some(0x1000), None, None, None]);
+    }
+}
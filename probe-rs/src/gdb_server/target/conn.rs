@@ -0,0 +1,860 @@
+use super::crc::parse_crc_request;
+use super::traffic::{Direction, TrafficLog};
+
+use gdbstub::conn::{Connection, ConnectionExt};
+
+use std::collections::VecDeque;
+use std::io;
+
+/// Number of times a packet is sent again after GDB rejected it, before giving up
+const MAX_RETRANSMISSIONS: usize = 3;
+
+/// Symbol of the RTT control block, looked up with `qSymbol`
+const RTT_SYMBOL: &str = "_SEGGER_RTT";
+
+/// Connection to GDB, which handles acknowledgements beyond what gdbstub supports
+///
+/// gdbstub ends the session when GDB rejects a packet with `-`, or sends a packet with
+/// a bad checksum. Instead, the last packet sent to GDB is kept until it is acknowledged
+/// and sent again when rejected, and received packets with a bad checksum are rejected
+/// with `-` and dropped. All of this is disabled once no-ack mode is negotiated.
+///
+/// The `qSymbol` exchange, which gdbstub doesn't support, is answered here as well, to look up
+/// the address of the RTT control block. `qCRC` requests are acknowledged here and handed to
+/// the target, which replies once the CRC is computed.
+pub(crate) struct GdbConnection<C> {
+    conn: C,
+
+    /// State of the packet currently being received
+    incoming_state: PacketState,
+    /// Packet currently being received, handed to gdbstub once its checksum is validated
+    incoming: Vec<u8>,
+    /// Validated bytes waiting to be handed to gdbstub
+    pending: VecDeque<u8>,
+
+    /// State of the packet currently being sent
+    outgoing_state: PacketState,
+    /// Packet currently being sent
+    outgoing: Vec<u8>,
+    /// Last packet sent to GDB, kept until it is acknowledged
+    unacked: Option<Vec<u8>>,
+    /// Number of times the unacknowledged packet was sent again
+    retransmissions: usize,
+
+    /// GDB requested no-ack mode, which is active after the reply was sent
+    no_ack_requested: bool,
+    /// No-ack mode is active, packets are neither acknowledged nor validated
+    no_ack_mode: bool,
+
+    /// Address of the RTT control block, provided by GDB with `qSymbol`
+    rtt_address: Option<u64>,
+    /// Address and length of a `qCRC` request, which has to be answered by the target
+    crc_request: Option<(u64, u64)>,
+
+    /// Log of all packets exchanged with GDB
+    traffic_log: Option<TrafficLog>,
+    /// State of the packet currently being sent, tracked for the traffic log
+    logged_state: PacketState,
+    /// Packet currently being sent, tracked for the traffic log
+    logged: Vec<u8>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum PacketState {
+    /// Waiting for the start of a packet
+    #[default]
+    Idle,
+    /// Transferring the packet body
+    Body,
+    /// Transferring the checksum, with the number of remaining checksum characters
+    Checksum(u8),
+}
+
+impl PacketState {
+    /// Advance the state by one transferred byte
+    ///
+    /// Returns `true` if `byte` is the last byte of a packet.
+    fn advance(&mut self, byte: u8) -> bool {
+        match (*self, byte) {
+            // A packet start always resets the state, e.g. after a dropped packet
+            (_, b'$') => *self = PacketState::Body,
+            (PacketState::Body, b'#') => *self = PacketState::Checksum(2),
+            (PacketState::Body, _) | (PacketState::Idle, _) => {}
+            (PacketState::Checksum(1), _) => {
+                *self = PacketState::Idle;
+
+                return true;
+            }
+            (PacketState::Checksum(n), _) => *self = PacketState::Checksum(n - 1),
+        }
+
+        false
+    }
+}
+
+impl<C> GdbConnection<C> {
+    /// Wrap a connection to GDB
+    pub fn new(conn: C) -> Self {
+        Self {
+            conn,
+            incoming_state: PacketState::Idle,
+            incoming: Vec::new(),
+            pending: VecDeque::new(),
+            outgoing_state: PacketState::Idle,
+            outgoing: Vec::new(),
+            unacked: None,
+            retransmissions: 0,
+            no_ack_requested: false,
+            no_ack_mode: false,
+            rtt_address: None,
+            crc_request: None,
+            traffic_log: None,
+            logged_state: PacketState::Idle,
+            logged: Vec::new(),
+        }
+    }
+
+    /// Log all packets exchanged with GDB to `log`
+    pub fn with_traffic_log(mut self, log: Option<TrafficLog>) -> Self {
+        self.traffic_log = log;
+        self
+    }
+
+    /// Track a byte sent to GDB for the traffic log, which is written once the packet is complete
+    fn log_outgoing(&mut self, byte: u8) {
+        let Some(log) = &mut self.traffic_log else {
+            return;
+        };
+
+        if byte == b'$' {
+            self.logged.clear();
+        }
+
+        let in_packet = self.logged_state != PacketState::Idle || byte == b'$';
+        if in_packet {
+            self.logged.push(byte);
+        }
+
+        if self.logged_state.advance(byte) {
+            // The packet has the form `$<body>#<checksum>`
+            log.packet(Direction::Outbound, &self.logged[1..self.logged.len() - 3]);
+        }
+    }
+
+    /// Track a byte sent to GDB, to keep the last packet until it is acknowledged
+    fn track_outgoing(&mut self, byte: u8) {
+        if self.no_ack_mode {
+            return;
+        }
+
+        if byte == b'$' {
+            self.outgoing.clear();
+        }
+
+        let in_packet = self.outgoing_state != PacketState::Idle || byte == b'$';
+        let complete = self.outgoing_state.advance(byte);
+
+        if in_packet {
+            self.outgoing.push(byte);
+        }
+
+        if complete {
+            self.unacked = Some(std::mem::take(&mut self.outgoing));
+            self.retransmissions = 0;
+
+            // The reply to `QStartNoAckMode` is the last acknowledged packet
+            if self.no_ack_requested {
+                self.no_ack_mode = true;
+                self.unacked = None;
+            }
+        }
+    }
+}
+
+impl<C: ConnectionExt<Error = io::Error>> GdbConnection<C> {
+    /// Read the next byte to be handed to gdbstub, if available
+    ///
+    /// Packets are only handed over once they are completely received and their checksum is valid.
+    pub fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        loop {
+            if let Some(byte) = self.pending.pop_front() {
+                return Ok(Some(byte));
+            }
+
+            if self.conn.peek()?.is_none() {
+                return Ok(None);
+            }
+
+            let byte = self.conn.read()?;
+            self.receive(byte)?;
+        }
+    }
+
+    /// Take the address of the RTT control block, if GDB provided it since the last call
+    pub fn take_rtt_address(&mut self) -> Option<u64> {
+        self.rtt_address.take()
+    }
+
+    /// Take the address and length of a pending `qCRC` request
+    ///
+    /// The request is already acknowledged, the target replies with [Self::send_packet].
+    pub fn take_crc_request(&mut self) -> Option<(u64, u64)> {
+        self.crc_request.take()
+    }
+
+    /// Process a byte received from GDB
+    fn receive(&mut self, byte: u8) -> io::Result<()> {
+        match (self.incoming_state, byte) {
+            (PacketState::Idle, b'+') if !self.no_ack_mode => {
+                self.unacked = None;
+                self.pending.push_back(byte);
+            }
+            (PacketState::Idle, b'-') if !self.no_ack_mode => self.retransmit()?,
+            // Anything else outside of a packet, e.g. an interrupt request
+            (PacketState::Idle, byte) if byte != b'$' => {
+                if byte == 0x03 {
+                    if let Some(log) = &mut self.traffic_log {
+                        log.interrupt();
+                    }
+                }
+
+                self.pending.push_back(byte);
+            }
+            _ => {
+                if byte == b'$' {
+                    self.incoming.clear();
+                }
+
+                self.incoming.push(byte);
+
+                if self.incoming_state.advance(byte) {
+                    self.complete_incoming()?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Hand a completely received packet to gdbstub, unless it is rejected or answered here
+    fn complete_incoming(&mut self) -> io::Result<()> {
+        let packet = std::mem::take(&mut self.incoming);
+
+        // The packet has the form `$<body>#<checksum>`
+        let body = &packet[1..packet.len() - 3];
+
+        if !self.no_ack_mode {
+            let checksum = std::str::from_utf8(&packet[packet.len() - 2..])
+                .ok()
+                .and_then(|checksum| u8::from_str_radix(checksum, 16).ok());
+
+            if checksum != Some(packet_checksum(body)) {
+                tracing::warn!(
+                    "Rejecting packet with invalid checksum from GDB: {}",
+                    String::from_utf8_lossy(&packet)
+                );
+
+                self.conn.write(b'-')?;
+                return self.conn.flush();
+            }
+
+            if body == b"QStartNoAckMode" {
+                self.no_ack_requested = true;
+            }
+        }
+
+        if let Some(log) = &mut self.traffic_log {
+            log.packet(Direction::Inbound, body);
+        }
+
+        if let Some(reply) = self.symbol_reply(body) {
+            if !self.no_ack_mode {
+                self.conn.write(b'+')?;
+            }
+
+            return self.send_packet(&reply);
+        }
+
+        if body.starts_with(b"qCRC:") {
+            if !self.no_ack_mode {
+                self.conn.write(b'+')?;
+            }
+
+            self.crc_request = parse_crc_request(body);
+
+            if self.crc_request.is_none() {
+                return self.send_packet(b"E01");
+            }
+
+            return self.conn.flush();
+        }
+
+        self.pending.extend(packet);
+
+        Ok(())
+    }
+
+    /// Answer a `qSymbol` packet
+    ///
+    /// GDB offers to look up symbols with `qSymbol::`, which is answered by requesting the RTT
+    /// control block. GDB then sends its value, or an empty value if the symbol is unknown.
+    fn symbol_reply(&mut self, body: &[u8]) -> Option<Vec<u8>> {
+        let args = body.strip_prefix(b"qSymbol:")?;
+
+        if args == b":" {
+            return Some(format!("qSymbol:{}", hex_encode(RTT_SYMBOL)).into_bytes());
+        }
+
+        let (value, name) = std::str::from_utf8(args).ok()?.split_once(':')?;
+
+        if name == hex_encode(RTT_SYMBOL) {
+            self.rtt_address = u64::from_str_radix(value, 16).ok();
+
+            match self.rtt_address {
+                Some(address) => tracing::info!("RTT control block at {:#010x}", address),
+                None => tracing::debug!("GDB does not know the RTT control block"),
+            }
+        }
+
+        Some(b"OK".to_vec())
+    }
+
+    /// Send a packet with the given body to GDB
+    pub fn send_packet(&mut self, body: &[u8]) -> io::Result<()> {
+        let checksum = format!("#{:02x}", packet_checksum(body));
+
+        self.write(b'$')?;
+        self.write_all(body)?;
+        self.write_all(checksum.as_bytes())?;
+        self.flush()
+    }
+
+    /// Send the last packet again, after GDB rejected it
+    fn retransmit(&mut self) -> io::Result<()> {
+        let Some(packet) = &self.unacked else {
+            tracing::debug!("GDB rejected a packet, but there is no packet to send again");
+
+            return Ok(());
+        };
+
+        if self.retransmissions >= MAX_RETRANSMISSIONS {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("GDB rejected a packet {} times", MAX_RETRANSMISSIONS + 1),
+            ));
+        }
+
+        self.retransmissions += 1;
+
+        tracing::debug!(
+            "GDB rejected a packet, sending it again: {}",
+            String::from_utf8_lossy(packet)
+        );
+
+        self.conn.write_all(packet)?;
+        self.conn.flush()
+    }
+}
+
+impl<C: Connection> Connection for GdbConnection<C> {
+    type Error = C::Error;
+
+    fn write(&mut self, byte: u8) -> Result<(), Self::Error> {
+        self.track_outgoing(byte);
+        self.log_outgoing(byte);
+
+        self.conn.write(byte)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        for byte in buf {
+            self.track_outgoing(*byte);
+            self.log_outgoing(*byte);
+        }
+
+        self.conn.write_all(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.conn.flush()
+    }
+
+    fn on_session_start(&mut self) -> Result<(), Self::Error> {
+        self.conn.on_session_start()
+    }
+}
+
+/// Encode a symbol name as hex, as used by `qSymbol`
+fn hex_encode(name: &str) -> String {
+    name.bytes().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Checksum of a packet body, the sum of all bytes modulo 256
+fn packet_checksum(body: &[u8]) -> u8 {
+    body.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))
+}
+
+#[cfg(test)]
+pub(super) mod test {
+    use super::*;
+
+    use crate::gdb_server::arch::{RuntimeArch, RuntimeRegisters};
+    use crate::gdb_server::target::resume::{continue_other_cores, push_resume_action};
+    use crate::gdb_server::target::ResumeAction;
+
+    use gdbstub::common::{Signal, Tid};
+    use gdbstub::stub::state_machine::GdbStubStateMachine;
+    use gdbstub::stub::GdbStub;
+    use gdbstub::target::ext::base::multithread::{
+        MultiThreadBase, MultiThreadResume, MultiThreadResumeOps, MultiThreadSchedulerLocking,
+        MultiThreadSchedulerLockingOps, MultiThreadSingleStep, MultiThreadSingleStepOps,
+    };
+    use gdbstub::target::ext::base::BaseOps;
+    use gdbstub::target::ext::breakpoints::{
+        Breakpoints, BreakpointsOps, HwBreakpoint, HwBreakpointOps, HwWatchpoint, HwWatchpointOps,
+        WatchKind,
+    };
+    use gdbstub::target::{Target, TargetResult};
+
+    /// A connection replaying scripted input from GDB, and recording the output
+    #[derive(Default)]
+    pub(crate) struct FakeStream {
+        input: VecDeque<u8>,
+        output: Vec<u8>,
+    }
+
+    impl Connection for FakeStream {
+        type Error = io::Error;
+
+        fn write(&mut self, byte: u8) -> io::Result<()> {
+            self.output.push(byte);
+
+            Ok(())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl ConnectionExt for FakeStream {
+        fn read(&mut self) -> io::Result<u8> {
+            self.input
+                .pop_front()
+                .ok_or_else(|| io::Error::from(io::ErrorKind::WouldBlock))
+        }
+
+        fn peek(&mut self) -> io::Result<Option<u8>> {
+            Ok(self.input.front().copied())
+        }
+    }
+
+    pub(crate) fn connection(input: &[u8]) -> GdbConnection<FakeStream> {
+        let mut conn = GdbConnection::new(FakeStream::default());
+        conn.conn.input.extend(input);
+
+        conn
+    }
+
+    /// Everything sent on `conn` so far
+    pub(crate) fn take_output(conn: &mut GdbConnection<FakeStream>) -> Vec<u8> {
+        std::mem::take(&mut conn.conn.output)
+    }
+
+    /// Hand `input` to gdbstub, until all of it is processed or the target is resumed
+    pub(crate) fn process_packets<T>(target: &mut T, input: &[u8])
+    where
+        T: Target<Arch = RuntimeArch>,
+        T::Error: std::fmt::Debug,
+    {
+        let stub = GdbStub::new(connection(input));
+        let mut state = stub.run_state_machine(target).unwrap();
+
+        while let GdbStubStateMachine::Idle(mut idle) = state {
+            let Some(byte) = idle.borrow_conn().read_byte().unwrap() else {
+                break;
+            };
+
+            state = idle.incoming_data(target, byte).unwrap();
+        }
+    }
+
+    fn read_all(conn: &mut GdbConnection<FakeStream>) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+
+        while let Some(byte) = conn.read_byte()? {
+            data.push(byte);
+        }
+
+        Ok(data)
+    }
+
+    /// A target without memory or registers, to check which replies gdbstub sends
+    ///
+    /// It has one thread per core, supports the same resume and breakpoint kinds as the gdb
+    /// server, and records the resume actions of the cores when resumed.
+    #[derive(Default)]
+    pub(crate) struct StubTarget {
+        cores: usize,
+        resume_actions: Vec<(usize, ResumeAction)>,
+        scheduler_locked: bool,
+        /// The resume actions when the target was last resumed
+        pub(crate) resumed: Option<Vec<(usize, ResumeAction)>>,
+    }
+
+    impl StubTarget {
+        pub(crate) fn new(cores: usize) -> Self {
+            Self {
+                cores,
+                ..Default::default()
+            }
+        }
+    }
+
+    impl Target for StubTarget {
+        type Arch = RuntimeArch;
+        type Error = ();
+
+        fn base_ops(&mut self) -> BaseOps<'_, Self::Arch, Self::Error> {
+            BaseOps::MultiThread(self)
+        }
+
+        fn support_breakpoints(&mut self) -> Option<BreakpointsOps<'_, Self>> {
+            Some(self)
+        }
+    }
+
+    impl MultiThreadBase for StubTarget {
+        fn read_registers(
+            &mut self,
+            _regs: &mut RuntimeRegisters,
+            _tid: Tid,
+        ) -> TargetResult<(), Self> {
+            Ok(())
+        }
+
+        fn write_registers(
+            &mut self,
+            _regs: &RuntimeRegisters,
+            _tid: Tid,
+        ) -> TargetResult<(), Self> {
+            Ok(())
+        }
+
+        fn read_addrs(
+            &mut self,
+            _start_addr: u64,
+            data: &mut [u8],
+            _tid: Tid,
+        ) -> TargetResult<usize, Self> {
+            Ok(data.len())
+        }
+
+        fn write_addrs(
+            &mut self,
+            _start_addr: u64,
+            _data: &[u8],
+            _tid: Tid,
+        ) -> TargetResult<(), Self> {
+            Ok(())
+        }
+
+        fn list_active_threads(
+            &mut self,
+            thread_is_active: &mut dyn FnMut(Tid),
+        ) -> Result<(), Self::Error> {
+            for core_id in 0..self.cores {
+                thread_is_active(Tid::new(core_id + 1).unwrap());
+            }
+
+            Ok(())
+        }
+
+        fn support_resume(&mut self) -> Option<MultiThreadResumeOps<'_, Self>> {
+            Some(self)
+        }
+    }
+
+    impl MultiThreadResume for StubTarget {
+        fn resume(&mut self) -> Result<(), Self::Error> {
+            if !self.scheduler_locked {
+                let cores: Vec<usize> = (0..self.cores).collect();
+
+                continue_other_cores(&mut self.resume_actions, &cores);
+            }
+
+            self.resumed = Some(self.resume_actions.clone());
+
+            Ok(())
+        }
+
+        fn clear_resume_actions(&mut self) -> Result<(), Self::Error> {
+            self.resume_actions.clear();
+            self.scheduler_locked = false;
+
+            Ok(())
+        }
+
+        fn set_resume_action_continue(
+            &mut self,
+            tid: Tid,
+            _signal: Option<Signal>,
+        ) -> Result<(), Self::Error> {
+            push_resume_action(&mut self.resume_actions, tid, ResumeAction::Resume);
+
+            Ok(())
+        }
+
+        fn support_single_step(&mut self) -> Option<MultiThreadSingleStepOps<'_, Self>> {
+            Some(self)
+        }
+
+        fn support_scheduler_locking(
+            &mut self,
+        ) -> Option<MultiThreadSchedulerLockingOps<'_, Self>> {
+            Some(self)
+        }
+    }
+
+    impl MultiThreadSingleStep for StubTarget {
+        fn set_resume_action_step(
+            &mut self,
+            tid: Tid,
+            _signal: Option<Signal>,
+        ) -> Result<(), Self::Error> {
+            push_resume_action(&mut self.resume_actions, tid, ResumeAction::Step);
+
+            Ok(())
+        }
+    }
+
+    impl MultiThreadSchedulerLocking for StubTarget {
+        fn set_resume_action_scheduler_lock(&mut self) -> Result<(), Self::Error> {
+            self.scheduler_locked = true;
+
+            Ok(())
+        }
+    }
+
+    impl Breakpoints for StubTarget {
+        fn support_hw_breakpoint(&mut self) -> Option<HwBreakpointOps<'_, Self>> {
+            Some(self)
+        }
+
+        fn support_hw_watchpoint(&mut self) -> Option<HwWatchpointOps<'_, Self>> {
+            Some(self)
+        }
+    }
+
+    impl HwBreakpoint for StubTarget {
+        fn add_hw_breakpoint(&mut self, _addr: u64, _kind: usize) -> TargetResult<bool, Self> {
+            Ok(true)
+        }
+
+        fn remove_hw_breakpoint(&mut self, _addr: u64, _kind: usize) -> TargetResult<bool, Self> {
+            Ok(true)
+        }
+    }
+
+    impl HwWatchpoint for StubTarget {
+        fn add_hw_watchpoint(
+            &mut self,
+            _addr: u64,
+            _len: u64,
+            _kind: WatchKind,
+        ) -> TargetResult<bool, Self> {
+            Ok(true)
+        }
+
+        fn remove_hw_watchpoint(
+            &mut self,
+            _addr: u64,
+            _len: u64,
+            _kind: WatchKind,
+        ) -> TargetResult<bool, Self> {
+            Ok(true)
+        }
+    }
+
+    /// Feed `input` through the connection into gdbstub, and return everything sent back
+    fn exchange(input: &[u8]) -> Vec<u8> {
+        let mut target = StubTarget::new(1);
+        let stub = GdbStub::new(connection(input));
+        let mut state = stub.run_state_machine(&mut target).unwrap();
+
+        loop {
+            state = match state {
+                GdbStubStateMachine::Idle(mut idle) => {
+                    match idle.borrow_conn().read_byte().unwrap() {
+                        Some(byte) => idle.incoming_data(&mut target, byte).unwrap(),
+                        None => return std::mem::take(&mut idle.borrow_conn().conn.output),
+                    }
+                }
+                _ => panic!("gdbstub left the idle state"),
+            };
+        }
+    }
+
+    #[test]
+    fn unknown_packet_gets_empty_reply() {
+        // `OK` would tell GDB that the packet is supported and succeeded
+        assert_eq!(exchange(b"$vMadeUpPacket#0a"), b"+$#00");
+        assert_eq!(exchange(b"$vMustReplyEmpty#3a"), b"+$#00");
+    }
+
+    #[test]
+    fn unsupported_optional_packet_gets_empty_reply() {
+        // Tracepoints are not supported by the gdb server. `qOffsets` can't be used here any
+        // more, as section offsets are always supported and default to zero.
+        assert_eq!(exchange(b"$qTStatus#49"), b"+$#00");
+    }
+
+    #[test]
+    fn valid_packet_is_handed_over() {
+        let mut conn = connection(b"+$g#67\x03");
+
+        assert_eq!(read_all(&mut conn).unwrap(), b"+$g#67\x03");
+        assert!(conn.conn.output.is_empty());
+    }
+
+    #[test]
+    fn packet_with_bad_checksum_is_rejected() {
+        let mut conn = connection(b"$g#00$g#67");
+
+        assert_eq!(read_all(&mut conn).unwrap(), b"$g#67");
+        assert_eq!(conn.conn.output, b"-");
+    }
+
+    #[test]
+    fn incomplete_packet_is_not_handed_over() {
+        let mut conn = connection(b"$g#6");
+
+        assert_eq!(read_all(&mut conn).unwrap(), b"");
+
+        conn.conn.input.extend(b"7");
+        assert_eq!(read_all(&mut conn).unwrap(), b"$g#67");
+    }
+
+    #[test]
+    fn rejected_packet_is_sent_again() {
+        let mut conn = connection(b"-");
+        conn.write_all(b"+$OK#9a").unwrap();
+
+        assert_eq!(read_all(&mut conn).unwrap(), b"");
+        assert_eq!(conn.conn.output, b"+$OK#9a$OK#9a");
+    }
+
+    #[test]
+    fn acknowledged_packet_is_not_sent_again() {
+        let mut conn = connection(b"+-");
+        conn.write_all(b"$OK#9a").unwrap();
+
+        assert_eq!(read_all(&mut conn).unwrap(), b"+");
+        assert_eq!(conn.conn.output, b"$OK#9a");
+    }
+
+    #[test]
+    fn retransmissions_are_bounded() {
+        let mut conn = connection(b"----");
+        conn.write_all(b"$OK#9a").unwrap();
+
+        assert!(read_all(&mut conn).is_err());
+        assert_eq!(conn.conn.output, b"$OK#9a".repeat(MAX_RETRANSMISSIONS + 1));
+    }
+
+    #[test]
+    fn no_ack_mode_disables_retransmission_and_validation() {
+        let mut conn = connection(b"$QStartNoAckMode#b0");
+
+        assert_eq!(read_all(&mut conn).unwrap(), b"$QStartNoAckMode#b0");
+        conn.write_all(b"+$OK#9a").unwrap();
+
+        conn.conn.input.extend(b"+$g#00");
+        assert_eq!(read_all(&mut conn).unwrap(), b"+$g#00");
+
+        conn.write_all(b"$OK#9a").unwrap();
+        conn.conn.input.extend(b"-");
+        assert_eq!(read_all(&mut conn).unwrap(), b"-");
+        assert_eq!(conn.conn.output, b"+$OK#9a$OK#9a");
+    }
+
+    #[test]
+    fn symbol_lookup_requests_rtt_control_block() {
+        let mut conn = connection(b"$qSymbol::#5b");
+
+        assert_eq!(read_all(&mut conn).unwrap(), b"");
+        assert_eq!(
+            conn.conn.output,
+            b"+$qSymbol:5f5345474745525f525454#07".as_slice()
+        );
+
+        conn.conn.output.clear();
+        conn.conn
+            .input
+            .extend(b"+$qSymbol:20000000:5f5345474745525f525454#c3");
+
+        assert_eq!(read_all(&mut conn).unwrap(), b"+");
+        assert_eq!(conn.conn.output, b"+$OK#9a");
+        assert_eq!(conn.take_rtt_address(), Some(0x2000_0000));
+        assert_eq!(conn.take_rtt_address(), None);
+    }
+
+    #[test]
+    fn crc_request_is_handed_to_target() {
+        let mut conn = connection(b"$qCRC:8000000,100#98");
+
+        assert_eq!(read_all(&mut conn).unwrap(), b"");
+        assert_eq!(conn.conn.output, b"+");
+        assert_eq!(conn.take_crc_request(), Some((0x800_0000, 0x100)));
+        assert_eq!(conn.take_crc_request(), None);
+    }
+
+    #[test]
+    fn malformed_crc_request_is_rejected() {
+        let mut conn = connection(b"$qCRC:zz#77");
+
+        assert_eq!(read_all(&mut conn).unwrap(), b"");
+        assert_eq!(conn.conn.output, b"+$E01#a6");
+        assert_eq!(conn.take_crc_request(), None);
+    }
+
+    #[test]
+    fn traffic_is_logged() {
+        #[derive(Clone, Default)]
+        struct SharedBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+        impl io::Write for SharedBuffer {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buffer = SharedBuffer::default();
+        let mut conn =
+            connection(b"$m0,4#fd\x03").with_traffic_log(Some(TrafficLog::new(buffer.clone())));
+
+        read_all(&mut conn).unwrap();
+        conn.write_all(b"+$00112233#8c").unwrap();
+
+        let log = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines = log.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("<- m0,4") && lines[0].ends_with("read memory 0x0 len 4"));
+        assert!(lines[1].ends_with("interrupt"));
+        assert!(lines[2].contains("-> 00112233") && lines[2].ends_with("reply, 8 bytes"));
+    }
+
+    #[test]
+    fn unknown_symbol_ends_lookup() {
+        let mut conn = connection(b"$qSymbol::5f5345474745525f525454#41");
+
+        assert_eq!(read_all(&mut conn).unwrap(), b"");
+        assert_eq!(conn.conn.output, b"+$OK#9a");
+        assert_eq!(conn.take_rtt_address(), None);
+    }
+}
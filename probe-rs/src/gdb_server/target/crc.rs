@@ -0,0 +1,101 @@
+use crate::{Error, MemoryInterface};
+
+/// Number of bytes read from the target and checksummed per step of a [CrcRequest]
+const CHUNK_SIZE: usize = 4096;
+
+/// CRC-32 computation requested by GDB with `qCRC:<addr>,<length>`, e.g. for `compare-sections`
+///
+/// The memory is read and checksummed on the host in chunks, so the server keeps servicing
+/// GDB, e.g. an interrupt request, while a large range is being checksummed.
+#[derive(Debug)]
+pub(crate) struct CrcRequest {
+    /// Address of the next chunk
+    address: u64,
+    /// Number of bytes left to checksum
+    remaining: u64,
+    /// CRC of the bytes checksummed so far
+    crc: u32,
+}
+
+impl CrcRequest {
+    /// Start a CRC computation over `length` bytes at `address`
+    pub fn new(address: u64, length: u64) -> Self {
+        Self {
+            address,
+            remaining: length,
+            crc: 0xffff_ffff,
+        }
+    }
+
+    /// Checksum the next chunk of memory
+    ///
+    /// Returns the CRC of the whole range once the last chunk is checksummed.
+    pub fn step(&mut self, memory: &mut impl MemoryInterface) -> Result<Option<u32>, Error> {
+        let len = self.remaining.min(CHUNK_SIZE as u64) as usize;
+        let mut data = vec![0u8; len];
+
+        memory.read(self.address, &mut data)?;
+
+        self.crc = crc32(self.crc, &data);
+        self.address += len as u64;
+        self.remaining -= len as u64;
+
+        Ok((self.remaining == 0).then_some(self.crc))
+    }
+}
+
+/// Parse the address and length of a `qCRC:<addr>,<length>` packet
+pub(crate) fn parse_crc_request(body: &[u8]) -> Option<(u64, u64)> {
+    let args = std::str::from_utf8(body.strip_prefix(b"qCRC:")?).ok()?;
+    let (address, length) = args.split_once(',')?;
+
+    Some((
+        u64::from_str_radix(address, 16).ok()?,
+        u64::from_str_radix(length, 16).ok()?,
+    ))
+}
+
+/// Update `crc` with `data`, using the CRC-32 variant of GDB
+///
+/// GDB uses the polynomial `0x04c11db7` without reflection and without a final XOR,
+/// starting from `0xffffffff`.
+fn crc32(mut crc: u32, data: &[u8]) -> u32 {
+    for byte in data {
+        crc ^= (*byte as u32) << 24;
+
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04c1_1db7
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn gdb_crc32() {
+        assert_eq!(crc32(0xffff_ffff, b""), 0xffff_ffff);
+        assert_eq!(crc32(0xffff_ffff, b"123456789"), 0x0376_e6e7);
+
+        // Checksumming in chunks gives the same result
+        assert_eq!(crc32(crc32(0xffff_ffff, b"1234"), b"56789"), 0x0376_e6e7);
+    }
+
+    #[test]
+    fn crc_request() {
+        assert_eq!(
+            parse_crc_request(b"qCRC:8000000,1a0"),
+            Some((0x800_0000, 0x1a0))
+        );
+        assert_eq!(parse_crc_request(b"qCRC:8000000"), None);
+        assert_eq!(parse_crc_request(b"qCRC:xyz,10"), None);
+        assert_eq!(parse_crc_request(b"qC"), None);
+    }
+}
@@ -0,0 +1,534 @@
+use crate::gdb_server::arch::RegisterSizes;
+use crate::{Core, CoreType, Error, InstructionSet, RegisterId, RegisterValue};
+
+use std::fmt::Write;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+/// How a GDB register is backed by probe-rs registers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum RegisterSource {
+    /// A single core register
+    Register(RegisterId),
+    /// A 64-bit view of two consecutive core registers, e.g. the VFP register `d0` made of `s0` and `s1`
+    Pair { low: RegisterId, high: RegisterId },
+    /// A bit field of a core register, e.g. PRIMASK within the combined Cortex-M CONTROL/FAULTMASK/BASEPRI/PRIMASK register
+    Field {
+        register: RegisterId,
+        offset: u8,
+        bits: u8,
+    },
+}
+
+impl RegisterSource {
+    /// All core registers this source is made of
+    fn registers(&self) -> Vec<RegisterId> {
+        match *self {
+            RegisterSource::Register(id) => vec![id],
+            RegisterSource::Pair { low, high } => vec![low, high],
+            RegisterSource::Field { register, .. } => vec![register],
+        }
+    }
+}
+
+/// A single register as it is presented to GDB
+#[derive(Debug, Clone)]
+pub(crate) struct GdbRegister {
+    /// GDB register number
+    regnum: usize,
+    /// Name of the register as expected by GDB
+    name: String,
+    /// Size of the register in bits
+    size: usize,
+    /// GDB type of the register
+    gdb_type: &'static str,
+    /// The probe-rs registers backing this GDB register
+    source: RegisterSource,
+}
+
+impl GdbRegister {
+    /// GDB register number
+    pub fn regnum(&self) -> usize {
+        self.regnum
+    }
+
+    /// The probe-rs registers backing this GDB register
+    pub fn source(&self) -> RegisterSource {
+        self.source
+    }
+
+    /// Size of the register in bytes
+    pub fn size_in_bytes(&self) -> usize {
+        (self.size + 7) / 8
+    }
+
+    /// Read the value of this register from the core
+    pub fn read(&self, core: &mut Core<'_>) -> Result<RegisterValue, Error> {
+        match self.source {
+            RegisterSource::Register(id) => core.read_core_reg(id),
+            RegisterSource::Pair { low, high } => {
+                let low: u32 = core.read_core_reg(low)?;
+                let high: u32 = core.read_core_reg(high)?;
+
+                Ok(RegisterValue::U64(((high as u64) << 32) | low as u64))
+            }
+            RegisterSource::Field {
+                register,
+                offset,
+                bits,
+            } => {
+                let value: u32 = core.read_core_reg(register)?;
+
+                Ok(RegisterValue::U32((value >> offset) & field_mask(bits)))
+            }
+        }
+    }
+
+    /// Write `value` to the core registers backing this register
+    pub fn write(&self, core: &mut Core<'_>, value: RegisterValue) -> Result<(), Error> {
+        match self.source {
+            RegisterSource::Register(id) => core.write_core_reg(id, value),
+            RegisterSource::Pair { low, high } => {
+                let value: u64 = value.try_into()?;
+
+                core.write_core_reg(low, value as u32)?;
+                core.write_core_reg(high, (value >> 32) as u32)
+            }
+            RegisterSource::Field {
+                register,
+                offset,
+                bits,
+            } => {
+                let field: u32 = value.try_into()?;
+                let mask = field_mask(bits) << offset;
+
+                // The other fields share the core register, so they have to be preserved
+                let current: u32 = core.read_core_reg(register)?;
+                core.write_core_reg(register, (current & !mask) | ((field << offset) & mask))
+            }
+        }
+    }
+}
+
+/// Mask for a bit field of `bits` bits at offset 0
+fn field_mask(bits: u8) -> u32 {
+    u32::MAX.checked_shr(32 - bits as u32).unwrap_or(0)
+}
+
+/// A group of registers, served as a `<feature>` element in `target.xml`
+#[derive(Debug, Default)]
+struct GdbFeature {
+    /// GDB feature name
+    name: &'static str,
+    /// Registers in this feature, in ascending GDB register number order
+    regs: Vec<GdbRegister>,
+}
+
+/// Mapping of a GDB register number onto a probe-rs register
+struct RegisterMapping {
+    /// GDB register number
+    regnum: usize,
+    /// Name of the register as expected by GDB
+    name: String,
+    /// GDB type of the register
+    gdb_type: &'static str,
+    /// The probe-rs registers backing this GDB register
+    source: RegisterSource,
+}
+
+impl RegisterMapping {
+    fn new(regnum: usize, name: impl Into<String>, source: u16) -> Self {
+        Self {
+            regnum,
+            name: name.into(),
+            gdb_type: "int",
+            source: RegisterSource::Register(RegisterId(source)),
+        }
+    }
+
+    /// A 64-bit register made of the core registers `low` and `high`
+    fn pair(regnum: usize, name: impl Into<String>, low: u16, high: u16) -> Self {
+        Self {
+            source: RegisterSource::Pair {
+                low: RegisterId(low),
+                high: RegisterId(high),
+            },
+            ..Self::new(regnum, name, low)
+        }
+    }
+
+    /// A register made of `bits` bits at `offset` of the core register `register`
+    fn field(regnum: usize, name: impl Into<String>, register: u16, offset: u8, bits: u8) -> Self {
+        Self {
+            source: RegisterSource::Field {
+                register: RegisterId(register),
+                offset,
+                bits,
+            },
+            ..Self::new(regnum, name, register)
+        }
+    }
+
+    fn with_type(mut self, gdb_type: &'static str) -> Self {
+        self.gdb_type = gdb_type;
+        self
+    }
+}
+
+/// Description of the target's architecture and registers, served to GDB as `target.xml`
+///
+/// This is the single source of truth for the GDB register numbers used by the `p`/`P` and `g`/`G` packets.
+#[derive(Debug, Default)]
+pub(crate) struct TargetDescription {
+    /// GDB architecture name
+    arch_name: &'static str,
+    /// Register features
+    features: Vec<GdbFeature>,
+    /// Register sizes in bytes, indexed by GDB register number
+    register_sizes: Arc<RegisterSizes>,
+}
+
+impl TargetDescription {
+    /// Build a target description for the given core
+    pub fn new(core: &mut Core) -> Result<Self, Error> {
+        let mut desc = match core.core_type() {
+            CoreType::Armv6m | CoreType::Armv7m | CoreType::Armv7em | CoreType::Armv8m => {
+                Self::cortex_m()
+            }
+            CoreType::Armv7a => Self::aarch32(),
+            CoreType::Armv8a => match core.instruction_set()? {
+                InstructionSet::A64 => Self::aarch64(),
+                _ => Self::aarch32(),
+            },
+            CoreType::Riscv => Self::riscv32(),
+        };
+
+        // Only keep the registers which actually exist on this core, e.g. FPU registers
+        let available = core.registers().all_registers().collect::<Vec<_>>();
+        for feature in &mut desc.features {
+            feature.regs.retain_mut(|reg| {
+                let Some(sizes) = reg
+                    .source
+                    .registers()
+                    .into_iter()
+                    .map(|id| {
+                        available
+                            .iter()
+                            .find(|r| r.id() == id)
+                            .map(|r| r.size_in_bits())
+                    })
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    return false;
+                };
+
+                reg.size = match reg.source {
+                    RegisterSource::Field { bits, .. } => bits as usize,
+                    _ => sizes.iter().sum(),
+                };
+
+                true
+            });
+        }
+        desc.features.retain(|feature| !feature.regs.is_empty());
+
+        let max_regnum = desc
+            .get_registers_for_main_group()
+            .map(|reg| reg.regnum)
+            .max()
+            .unwrap_or(0);
+        let mut sizes = vec![None; max_regnum + 1];
+        for reg in desc.get_registers_for_main_group() {
+            sizes[reg.regnum] = NonZeroUsize::new(reg.size_in_bytes());
+        }
+
+        // Registers GDB asks for without knowing them are reported as unavailable, with the
+        // size of the program counter
+        let unmapped = desc
+            .get_registers_for_main_group()
+            .find(|reg| reg.gdb_type == "code_ptr")
+            .and_then(|reg| NonZeroUsize::new(reg.size_in_bytes()));
+        desc.register_sizes = Arc::new(RegisterSizes::new(sizes, unmapped));
+
+        Ok(desc)
+    }
+
+    /// Cortex-M registers, using the register numbers of GDB's `arm` architecture
+    ///
+    /// GDB reserves 16 to 24 for the legacy FPA registers, so `xpsr` is number 25.
+    /// The FPU is described as the `vfp` feature with the 64-bit `d` registers, GDB
+    /// derives the single precision `s` registers from them.
+    fn cortex_m() -> Self {
+        let mut core_regs = arm_core_registers();
+        core_regs.push(RegisterMapping::new(25, "xpsr", 0b1_0000));
+
+        let mut fpu_regs = (0..16)
+            .map(|n| {
+                RegisterMapping::pair(
+                    26 + n,
+                    format!("d{n}"),
+                    64 + 2 * n as u16,
+                    65 + 2 * n as u16,
+                )
+            })
+            .map(|reg| reg.with_type("ieee_double"))
+            .collect::<Vec<_>>();
+        fpu_regs.push(RegisterMapping::new(42, "fpscr", 33));
+
+        // CONTROL, FAULTMASK, BASEPRI and PRIMASK are combined into a single core register
+        let mut system_regs = vec![
+            RegisterMapping::new(43, "msp", 0b1_0001),
+            RegisterMapping::new(44, "psp", 0b1_0010),
+            RegisterMapping::field(45, "primask", 0b1_0100, 0, 1),
+            RegisterMapping::field(46, "basepri", 0b1_0100, 8, 8),
+            RegisterMapping::field(47, "faultmask", 0b1_0100, 16, 1),
+            RegisterMapping::field(48, "control", 0b1_0100, 24, 8),
+        ];
+
+        // Only present on ARMv8-M cores with the Security Extension
+        let security_regs = [
+            "msp_ns",
+            "psp_ns",
+            "msp_s",
+            "psp_s",
+            "msplim_s",
+            "psplim_s",
+            "msplim_ns",
+            "psplim_ns",
+        ];
+        system_regs.extend(
+            security_regs
+                .iter()
+                .enumerate()
+                .map(|(n, name)| RegisterMapping::new(49 + n, *name, 0b1_1000 + n as u16)),
+        );
+
+        Self::from_mappings(
+            "arm",
+            vec![
+                ("org.gnu.gdb.arm.m-profile", core_regs),
+                ("org.gnu.gdb.arm.vfp", fpu_regs),
+                ("org.gnu.gdb.arm.m-system", system_regs),
+            ],
+        )
+    }
+
+    /// ARMv7-A / ARMv8-A registers in AArch32 state
+    fn aarch32() -> Self {
+        let mut core_regs = arm_core_registers();
+        core_regs.push(RegisterMapping::new(25, "cpsr", 0b1_0000));
+
+        Self::from_mappings("arm", vec![("org.gnu.gdb.arm.core", core_regs)])
+    }
+
+    /// ARMv8-A registers in AArch64 state
+    fn aarch64() -> Self {
+        let mut core_regs = (0..31)
+            .map(|n| RegisterMapping::new(n, format!("x{n}"), n as u16))
+            .collect::<Vec<_>>();
+        core_regs.push(RegisterMapping::new(31, "sp", 31).with_type("data_ptr"));
+        core_regs.push(RegisterMapping::new(32, "pc", 32).with_type("code_ptr"));
+        core_regs.push(RegisterMapping::new(33, "cpsr", 33));
+
+        Self::from_mappings("aarch64", vec![("org.gnu.gdb.aarch64.core", core_regs)])
+    }
+
+    /// RV32 registers
+    fn riscv32() -> Self {
+        let mut core_regs = (0..32)
+            .map(|n| RegisterMapping::new(n, format!("x{n}"), 0x1000 + n as u16))
+            .map(|reg| match reg.regnum {
+                2 => reg.with_type("data_ptr"),
+                _ => reg,
+            })
+            .collect::<Vec<_>>();
+        core_regs.push(RegisterMapping::new(32, "pc", 0x7b1).with_type("code_ptr"));
+
+        Self::from_mappings("riscv:rv32", vec![("org.gnu.gdb.riscv.cpu", core_regs)])
+    }
+
+    fn from_mappings(
+        arch_name: &'static str,
+        features: Vec<(&'static str, Vec<RegisterMapping>)>,
+    ) -> Self {
+        let features = features
+            .into_iter()
+            .map(|(name, mappings)| GdbFeature {
+                name,
+                regs: mappings
+                    .into_iter()
+                    .map(|mapping| GdbRegister {
+                        regnum: mapping.regnum,
+                        name: mapping.name,
+                        // The actual size is filled in from the core's register description
+                        size: 0,
+                        gdb_type: mapping.gdb_type,
+                        source: mapping.source,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Self {
+            arch_name,
+            features,
+            register_sizes: Arc::default(),
+        }
+    }
+
+    /// Get the register with the given GDB register number
+    pub fn get_register(&self, regnum: usize) -> Option<&GdbRegister> {
+        self.features
+            .iter()
+            .flat_map(|feature| feature.regs.iter())
+            .find(|reg| reg.regnum == regnum)
+    }
+
+    /// All registers in GDB register number order, as used by the `g` and `G` packets
+    pub fn get_registers_for_main_group(&self) -> impl Iterator<Item = &GdbRegister> {
+        let mut regs = self
+            .features
+            .iter()
+            .flat_map(|feature| feature.regs.iter())
+            .collect::<Vec<_>>();
+        regs.sort_by_key(|reg| reg.regnum);
+
+        regs.into_iter()
+    }
+
+    /// Registers which are sent along with every stop reply, the program counter and the stack pointer
+    pub fn get_expedited_registers(&self) -> impl Iterator<Item = &GdbRegister> {
+        self.get_registers_for_main_group()
+            .filter(|reg| matches!(reg.gdb_type, "code_ptr" | "data_ptr"))
+    }
+
+    /// Register sizes in bytes, indexed by GDB register number
+    ///
+    /// gdbstub needs these to pad registers which can't be read with `x` in `p` replies.
+    pub fn register_sizes(&self) -> Arc<RegisterSizes> {
+        self.register_sizes.clone()
+    }
+
+    /// Generate the `target.xml` contents for this description
+    pub fn get_target_xml(&self) -> String {
+        let mut xml = format!(
+            r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+<architecture>{}</architecture>
+"#,
+            self.arch_name
+        );
+
+        // Writing into a String cannot fail
+        for feature in &self.features {
+            let _ = writeln!(xml, r#"<feature name="{}">"#, feature.name);
+
+            for reg in &feature.regs {
+                let _ = writeln!(
+                    xml,
+                    r#"<reg name="{}" bitsize="{}" type="{}" regnum="{}"/>"#,
+                    reg.name, reg.size, reg.gdb_type, reg.regnum
+                );
+            }
+
+            xml.push_str("</feature>\n");
+        }
+
+        xml.push_str("</target>");
+
+        xml
+    }
+}
+
+/// r0 to r15, which share their GDB register numbers with the probe-rs register ids on all ARM profiles
+fn arm_core_registers() -> Vec<RegisterMapping> {
+    let mut regs = (0..13)
+        .map(|n| RegisterMapping::new(n, format!("r{n}"), n as u16))
+        .collect::<Vec<_>>();
+    regs.push(RegisterMapping::new(13, "sp", 13).with_type("data_ptr"));
+    regs.push(RegisterMapping::new(14, "lr", 14));
+    regs.push(RegisterMapping::new(15, "pc", 15).with_type("code_ptr"));
+
+    regs
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cortex_m_register_numbers() {
+        let desc = TargetDescription::cortex_m();
+
+        assert_eq!(desc.get_register(25).unwrap().name, "xpsr");
+        assert_eq!(
+            desc.get_register(25).unwrap().source(),
+            RegisterSource::Register(RegisterId(0b1_0000))
+        );
+        assert!(desc.get_register(19).is_none());
+        assert_eq!(
+            desc.get_register(26).unwrap().source(),
+            RegisterSource::Pair {
+                low: RegisterId(64),
+                high: RegisterId(65)
+            }
+        );
+        assert_eq!(
+            desc.get_register(41).unwrap().source(),
+            RegisterSource::Pair {
+                low: RegisterId(94),
+                high: RegisterId(95)
+            }
+        );
+        assert_eq!(
+            desc.get_register(42).unwrap().source(),
+            RegisterSource::Register(RegisterId(33))
+        );
+        assert_eq!(desc.get_register(43).unwrap().name, "msp");
+        assert_eq!(
+            desc.get_register(48).unwrap().source(),
+            RegisterSource::Field {
+                register: RegisterId(0b1_0100),
+                offset: 24,
+                bits: 8
+            }
+        );
+    }
+
+    #[test]
+    fn expedited_registers() {
+        let desc = TargetDescription::cortex_m();
+
+        let names = desc
+            .get_expedited_registers()
+            .map(|reg| reg.name.as_str())
+            .collect::<Vec<_>>();
+
+        // msp and psp are not expedited, sp already holds the active stack pointer
+        assert_eq!(names, ["sp", "pc"]);
+    }
+
+    #[test]
+    fn field_masks() {
+        assert_eq!(field_mask(1), 0x1);
+        assert_eq!(field_mask(8), 0xff);
+        assert_eq!(field_mask(32), u32::MAX);
+        assert_eq!(field_mask(0), 0);
+    }
+
+    #[test]
+    fn register_packet_order() {
+        let desc = TargetDescription::cortex_m();
+
+        let regnums = desc
+            .get_registers_for_main_group()
+            .map(|reg| reg.regnum)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            &regnums[..17],
+            &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 25]
+        );
+    }
+}
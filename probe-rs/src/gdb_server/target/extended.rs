@@ -0,0 +1,66 @@
+use super::RuntimeTarget;
+
+use gdbstub::common::Pid;
+use gdbstub::target::ext::extended_mode::{
+    Args, AttachKind, CurrentActivePid, CurrentActivePidOps, ExtendedMode, ShouldTerminate,
+};
+use gdbstub::target::{TargetError, TargetResult};
+
+use std::time::Duration;
+
+/// Every core is exposed as an inferior, with the process ID being the core ID plus one
+///
+/// This matches the thread IDs, so a core has the same number as process and as thread.
+impl ExtendedMode for RuntimeTarget<'_> {
+    fn run(&mut self, _filename: Option<&[u8]>, _args: Args<'_, '_>) -> TargetResult<Pid, Self> {
+        // There is no program to spawn, cores can only be attached to
+        Err(TargetError::NonFatal)
+    }
+
+    fn attach(&mut self, pid: Pid) -> TargetResult<(), Self> {
+        let core_id = pid.get() - 1;
+
+        if !self.cores.contains(&core_id) {
+            return Err(TargetError::NonFatal);
+        }
+
+        let mut session = self.session.lock().unwrap();
+        let mut core = self.target_result(session.core(core_id))?;
+
+        self.target_result(core.halt(Duration::from_millis(100)))?;
+
+        self.attached_core = Some(core_id);
+
+        Ok(())
+    }
+
+    fn query_if_attached(&mut self, pid: Pid) -> TargetResult<AttachKind, Self> {
+        if self.cores.contains(&(pid.get() - 1)) {
+            Ok(AttachKind::Attach)
+        } else {
+            Err(TargetError::NonFatal)
+        }
+    }
+
+    fn kill(&mut self, _pid: Option<Pid>) -> TargetResult<ShouldTerminate, Self> {
+        // A core cannot be killed, end the debug session instead
+        Ok(ShouldTerminate::Yes)
+    }
+
+    fn restart(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn support_current_active_pid(&mut self) -> Option<CurrentActivePidOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl CurrentActivePid for RuntimeTarget<'_> {
+    fn current_active_pid(&mut self) -> Result<Pid, Self::Error> {
+        // Without an attached inferior, all cores are part of the default process
+        let pid = self.attached_core.map_or(1, |core_id| core_id + 1);
+
+        Ok(Pid::new(pid).unwrap())
+    }
+}
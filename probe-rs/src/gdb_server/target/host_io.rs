@@ -0,0 +1,295 @@
+use super::RuntimeTarget;
+
+use gdbstub::target::ext::host_io::{
+    HostIo, HostIoClose, HostIoCloseOps, HostIoErrno, HostIoError, HostIoOpen, HostIoOpenFlags,
+    HostIoOpenMode, HostIoOpenOps, HostIoPread, HostIoPreadOps, HostIoPwrite, HostIoPwriteOps,
+    HostIoResult,
+};
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+
+/// Maximum number of files GDB can keep open at the same time
+const MAX_OPEN_FILES: usize = 64;
+
+/// Files on the host opened by GDB with `vFile:open`, confined to a sandbox directory
+pub(crate) struct HostFiles {
+    /// Canonical path of the directory GDB has access to
+    root: PathBuf,
+    /// Open files, indexed by their file descriptor
+    files: Vec<Option<File>>,
+}
+
+impl HostFiles {
+    /// Give GDB access to the files below `root`, which must be an existing directory
+    pub fn new(root: &Path) -> std::io::Result<Self> {
+        let root = root.canonicalize()?;
+
+        if !root.is_dir() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} is not a directory", root.display()),
+            ));
+        }
+
+        Ok(Self {
+            root,
+            files: Vec::new(),
+        })
+    }
+
+    /// Close all files, e.g. when GDB disconnects
+    pub fn close_all(&mut self) {
+        self.files.clear();
+    }
+
+    /// Resolve a file name sent by GDB to a path inside the sandbox
+    ///
+    /// Paths through symbolic links below the root are rejected. A link can point outside of
+    /// the sandbox, and opening a dangling link with `O_CREAT` creates the file it points to.
+    fn resolve(&self, filename: &[u8]) -> Result<PathBuf, HostIoErrno> {
+        let path = sandboxed_path(&self.root, filename)?;
+
+        // The root is canonical, so only the components below it have to be checked
+        let mut current = self.root.clone();
+
+        for component in path.strip_prefix(&self.root).unwrap_or(&path).components() {
+            current.push(component);
+
+            match current.symlink_metadata() {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    return Err(HostIoErrno::EACCES)
+                }
+                Ok(_) => {}
+                // The remaining components don't exist yet
+                Err(_) => break,
+            }
+        }
+
+        Ok(path)
+    }
+
+    fn file(&mut self, fd: u32) -> Result<&mut File, HostIoErrno> {
+        self.files
+            .get_mut(fd as usize)
+            .and_then(Option::as_mut)
+            .ok_or(HostIoErrno::EBADF)
+    }
+}
+
+/// Map a file name sent by GDB to a path below `root`, without touching the file system
+///
+/// Absolute names are taken relative to `root`. Names containing `..` are rejected, so they
+/// cannot leave the sandbox.
+fn sandboxed_path(root: &Path, filename: &[u8]) -> Result<PathBuf, HostIoErrno> {
+    let name = std::str::from_utf8(filename).map_err(|_| HostIoErrno::EINVAL)?;
+
+    let mut path = root.to_path_buf();
+
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::RootDir | Component::CurDir => {}
+            Component::ParentDir | Component::Prefix(_) => return Err(HostIoErrno::EACCES),
+        }
+    }
+
+    Ok(path)
+}
+
+impl RuntimeTarget<'_> {
+    fn host_files(&mut self) -> HostIoResult<&mut HostFiles, Self> {
+        self.host_files
+            .as_mut()
+            .ok_or(HostIoError::Errno(HostIoErrno::EPERM))
+    }
+}
+
+/// Host I/O is only offered when a sandbox directory is configured, see
+/// [`GdbInstanceConfiguration::host_io_root`](crate::gdb_server::GdbInstanceConfiguration::host_io_root).
+impl HostIo for RuntimeTarget<'_> {
+    fn support_open(&mut self) -> Option<HostIoOpenOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_close(&mut self) -> Option<HostIoCloseOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_pread(&mut self) -> Option<HostIoPreadOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_pwrite(&mut self) -> Option<HostIoPwriteOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl HostIoOpen for RuntimeTarget<'_> {
+    fn open(
+        &mut self,
+        filename: &[u8],
+        flags: HostIoOpenFlags,
+        mode: HostIoOpenMode,
+    ) -> HostIoResult<u32, Self> {
+        let host_files = self.host_files()?;
+        let path = host_files.resolve(filename).map_err(HostIoError::Errno)?;
+
+        let mut options = OpenOptions::new();
+        if flags.contains(HostIoOpenFlags::O_RDWR) {
+            options.read(true).write(true);
+        } else if flags.contains(HostIoOpenFlags::O_WRONLY) {
+            options.write(true);
+        } else {
+            options.read(true);
+        }
+
+        options
+            .append(flags.contains(HostIoOpenFlags::O_APPEND))
+            .truncate(flags.contains(HostIoOpenFlags::O_TRUNC));
+
+        if flags.contains(HostIoOpenFlags::O_CREAT | HostIoOpenFlags::O_EXCL) {
+            options.create_new(true);
+        } else if flags.contains(HostIoOpenFlags::O_CREAT) {
+            options.create(true);
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+
+            options.mode(mode.bits());
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+
+        let file = options.open(&path)?;
+
+        tracing::debug!("GDB opened host file {}", path.display());
+
+        let fd = match host_files.files.iter().position(Option::is_none) {
+            Some(fd) => fd,
+            None if host_files.files.len() < MAX_OPEN_FILES => {
+                host_files.files.push(None);
+                host_files.files.len() - 1
+            }
+            None => return Err(HostIoError::Errno(HostIoErrno::EMFILE)),
+        };
+
+        host_files.files[fd] = Some(file);
+
+        Ok(fd as u32)
+    }
+}
+
+impl HostIoClose for RuntimeTarget<'_> {
+    fn close(&mut self, fd: u32) -> HostIoResult<(), Self> {
+        let host_files = self.host_files()?;
+
+        match host_files.files.get_mut(fd as usize) {
+            Some(file @ Some(_)) => {
+                *file = None;
+                Ok(())
+            }
+            _ => Err(HostIoError::Errno(HostIoErrno::EBADF)),
+        }
+    }
+}
+
+impl HostIoPread for RuntimeTarget<'_> {
+    fn pread(
+        &mut self,
+        fd: u32,
+        count: usize,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> HostIoResult<usize, Self> {
+        let file = self.host_files()?.file(fd).map_err(HostIoError::Errno)?;
+
+        file.seek(SeekFrom::Start(offset))?;
+
+        let len = count.min(buf.len());
+        let mut read = 0;
+
+        // Fill the buffer as far as possible, a short read tells GDB that the end of the file was reached
+        while read < len {
+            match file.read(&mut buf[read..len])? {
+                0 => break,
+                n => read += n,
+            }
+        }
+
+        Ok(read)
+    }
+}
+
+impl HostIoPwrite for RuntimeTarget<'_> {
+    fn pwrite(&mut self, fd: u32, offset: u64, data: &[u8]) -> HostIoResult<u64, Self> {
+        let file = self.host_files()?.file(fd).map_err(HostIoError::Errno)?;
+
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(data)?;
+
+        Ok(data.len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sandboxed_paths_stay_below_the_root() {
+        let root = Path::new("/srv/gdb");
+
+        assert_eq!(
+            sandboxed_path(root, b"firmware.bin").ok(),
+            Some(root.join("firmware.bin"))
+        );
+        assert_eq!(
+            sandboxed_path(root, b"/logs/./trace.txt").ok(),
+            Some(root.join("logs").join("trace.txt"))
+        );
+
+        assert!(matches!(
+            sandboxed_path(root, b"../etc/passwd"),
+            Err(HostIoErrno::EACCES)
+        ));
+        assert!(matches!(
+            sandboxed_path(root, b"/logs/../../etc/passwd"),
+            Err(HostIoErrno::EACCES)
+        ));
+        assert!(matches!(
+            sandboxed_path(root, b"\xff"),
+            Err(HostIoErrno::EINVAL)
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_rejected() {
+        let dir = std::env::temp_dir().join(format!("probe-rs-host-io-{}", std::process::id()));
+        let root = dir.join("root");
+        let outside = dir.join("outside.bin");
+
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("firmware.bin"), b"").unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("dangling")).unwrap();
+        std::os::unix::fs::symlink(&dir, root.join("parent")).unwrap();
+
+        let host_files = HostFiles::new(&root).unwrap();
+
+        let existing = host_files.resolve(b"firmware.bin");
+        let new_file = host_files.resolve(b"new.bin");
+        let dangling = host_files.resolve(b"dangling");
+        let through_link = host_files.resolve(b"parent/outside.bin");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(existing.ok(), Some(host_files.root.join("firmware.bin")));
+        assert_eq!(new_file.ok(), Some(host_files.root.join("new.bin")));
+        assert!(matches!(dangling, Err(HostIoErrno::EACCES)));
+        assert!(matches!(through_link, Err(HostIoErrno::EACCES)));
+    }
+}
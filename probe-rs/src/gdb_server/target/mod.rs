@@ -0,0 +1,826 @@
+mod base;
+mod breakpoints;
+mod conn;
+mod crc;
+mod desc;
+mod extended;
+mod host_io;
+mod monitor;
+mod packet;
+mod resume;
+mod semihosting;
+mod stop;
+mod traffic;
+mod traits;
+mod utils;
+
+use super::arch::{self, RuntimeArch, RuntimeRegId};
+use super::{GdbInstanceConfiguration, GdbMemoryMap, SectionOffsets};
+use crate::architecture::arm::ArmError;
+use crate::architecture::riscv::communication_interface::RiscvError;
+use crate::architecture::xtensa::communication_interface::XtensaError;
+use crate::{BreakpointCause, CoreStatus, Error, HaltReason, MemoryInterface, Session};
+
+use gdbstub::common::Signal;
+use gdbstub::stub::state_machine::GdbStubStateMachine;
+use gdbstub::stub::{GdbStub, MultiThreadStopReason};
+use gdbstub::target::ext::base::BaseOps;
+use gdbstub::target::ext::breakpoints::{BreakpointsOps, WatchKind};
+use gdbstub::target::ext::extended_mode::ExtendedModeOps;
+use gdbstub::target::ext::host_io::HostIoOps;
+use gdbstub::target::ext::memory_map::{MemoryMap, MemoryMapOps};
+use gdbstub::target::ext::monitor_cmd::MonitorCmdOps;
+use gdbstub::target::ext::section_offsets::{self, Offsets, SectionOffsetsOps};
+use gdbstub::target::ext::target_description_xml_override::{
+    TargetDescriptionXmlOverride, TargetDescriptionXmlOverrideOps,
+};
+use gdbstub::target::{Target, TargetError, TargetResult};
+use itertools::Itertools;
+
+use std::cell::Cell;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use conn::GdbConnection;
+use crc::CrcRequest;
+use desc::TargetDescription;
+use host_io::HostFiles;
+use packet::PacketSniffer;
+use traffic::TrafficLog;
+use traits::{GdbErrorExt, ProbeRsErrorExt};
+use utils::{copy_range_to_buf, gdb_memory_map, user_memory_map};
+
+/// Smallest packet size which leaves room for the packets GDB sends during the handshake
+const MIN_PACKET_SIZE: usize = 256;
+
+/// Actions for resuming a core
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum ResumeAction {
+    /// Resume core
+    Resume,
+    /// Single step core
+    Step,
+    /// Step core until the program counter leaves `start..end`
+    RangeStep { start: u64, end: u64 },
+}
+
+/// The top level gdbstub target for a probe-rs debug session
+pub(crate) struct RuntimeTarget<'a> {
+    /// The probe-rs session object
+    session: &'a Mutex<Session>,
+    /// A list of core IDs for this stub
+    cores: Vec<usize>,
+
+    /// TCP listener accepting incoming connections
+    listener: TcpListener,
+    /// The current GDB stub state machine
+    gdb: Option<GdbStubStateMachine<'a, RuntimeTarget<'a>, GdbConnection<TcpStream>>>,
+    /// Resume actions of the cores to be used upon a continue request, cores without an
+    /// action are continued unless the scheduler is locked
+    resume_actions: Vec<(usize, ResumeAction)>,
+    /// Keep cores without a resume action halted, requested by a `vCont` without a
+    /// default continue action
+    scheduler_locked: bool,
+
+    /// Description of target's architecture and registers
+    target_desc: TargetDescription,
+
+    /// Service semihosting console output instead of reporting it as a trap
+    semihosting: bool,
+    /// Read back and compare memory after every write from GDB
+    verify: bool,
+    /// Hardware breakpoints installed by GDB, re-applied after a reset
+    hw_breakpoints: Vec<u64>,
+    /// Breakpoints set by GDB as breakpoint instructions, because all comparators were in use
+    sw_breakpoints: Vec<breakpoints::SwBreakpoint>,
+    /// Watchpoints installed by GDB as address, length and kind, re-applied after a reset
+    hw_watchpoints: Vec<(u64, u64, WatchKind)>,
+    /// Tracks incoming packets which need handling beyond gdbstub
+    sniffer: PacketSniffer,
+    /// Address to resume from, requested by a `c<addr>` or `s<addr>` packet
+    resume_address: Option<u64>,
+    /// Section offsets of a relocated image, reported with `qOffsets`
+    section_offsets: Option<SectionOffsets>,
+    /// Core selected by GDB with `vAttach`, all cores are debugged if not set
+    attached_core: Option<usize>,
+    /// Probe communication error raised while handling a packet, recovered from afterwards
+    probe_error: Cell<Option<String>>,
+    /// Halt the cores when GDB connects
+    halt_on_attach: bool,
+    /// Address of the RTT control block, looked up by GDB with `qSymbol`
+    rtt_address: Option<u64>,
+    /// Interval of the keep-alive packets sent while the target is running
+    keep_alive: Option<Duration>,
+    /// Time of the last packet exchanged with GDB
+    last_activity: Instant,
+    /// Interval in which running cores are checked for a halt
+    halt_poll_interval: Duration,
+    /// File to log the packets exchanged with GDB to
+    traffic_log: Option<PathBuf>,
+    /// Size of the packet buffer advertised to GDB
+    packet_size: usize,
+    /// `qCRC` request which is being computed
+    crc_request: Option<CrcRequest>,
+    /// Host files GDB can access with `vFile` packets, disabled without a sandbox directory
+    host_files: Option<HostFiles>,
+    /// Memory map supplied by the user, replacing the one of the target
+    memory_map: Option<GdbMemoryMap>,
+}
+
+impl<'a> RuntimeTarget<'a> {
+    /// Create a new RuntimeTarget and get ready to start processing GDB input
+    ///
+    /// # Arguments
+    ///
+    /// * session - The [Session] shared with the other targets of the server
+    /// * instance - The cores and endpoint served by this target
+    /// * semihosting - Service semihosting console output instead of reporting it as a trap
+    /// * halt_poll_interval - Interval in which running cores are checked for a halt
+    pub fn new(
+        session: &'a Mutex<Session>,
+        instance: &GdbInstanceConfiguration,
+        semihosting: bool,
+        halt_poll_interval: Duration,
+    ) -> Result<Self, Error> {
+        if instance.packet_size < MIN_PACKET_SIZE {
+            return Err(anyhow::anyhow!(
+                "The GDB packet size of {} bytes is too small, it must be at least {MIN_PACKET_SIZE} bytes",
+                instance.packet_size
+            )
+            .into());
+        }
+
+        let addrs = &instance.socket_addrs[..];
+        let listener = TcpListener::bind(addrs).map_err(|e| {
+            let addrs = addrs.iter().join(", ");
+
+            match e.kind() {
+                std::io::ErrorKind::AddrInUse => anyhow::anyhow!(
+                    "Failed to start the GDB server at {addrs}: the address is already in use, \
+                     is another GDB server running?"
+                ),
+                _ => anyhow::anyhow!("Failed to start the GDB server at {addrs}: {e}"),
+            }
+        })?;
+        listener.set_nonblocking(true).into_error()?;
+
+        let host_files = instance
+            .host_io_root
+            .as_deref()
+            .map(|root| {
+                HostFiles::new(root).map_err(|e| {
+                    anyhow::anyhow!("Invalid GDB host I/O directory {}: {e}", root.display())
+                })
+            })
+            .transpose()?;
+
+        Ok(Self {
+            session,
+            cores: instance.cores.to_vec(),
+            listener,
+            gdb: None,
+            resume_actions: Vec::new(),
+            scheduler_locked: false,
+            target_desc: TargetDescription::default(),
+            semihosting,
+            verify: false,
+            hw_breakpoints: Vec::new(),
+            sw_breakpoints: Vec::new(),
+            hw_watchpoints: Vec::new(),
+            sniffer: PacketSniffer::default(),
+            resume_address: None,
+            section_offsets: instance.section_offsets,
+            attached_core: None,
+            probe_error: Cell::new(None),
+            halt_on_attach: instance.halt_on_attach,
+            rtt_address: None,
+            keep_alive: instance.keep_alive,
+            last_activity: Instant::now(),
+            halt_poll_interval,
+            traffic_log: instance.traffic_log.clone(),
+            packet_size: instance.packet_size,
+            crc_request: None,
+            host_files,
+            memory_map: instance.memory_map.clone(),
+        })
+    }
+
+    /// The address this target is listening on
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.listener.local_addr().into_error()
+    }
+
+    /// Process any pending work for this target
+    ///
+    /// Returns: Duration to wait before processing this target again
+    pub fn process(&mut self) -> Result<Duration, Error> {
+        // State 1 - unconnected
+        if self.gdb.is_none() {
+            // See if we have a connection
+            match self.listener.accept() {
+                Ok((s, addr)) => {
+                    tracing::info!("New connection from {:#?}", addr);
+
+                    self.attached_core = None;
+                    self.rtt_address = None;
+                    self.crc_request = None;
+
+                    if let Some(host_files) = &mut self.host_files {
+                        host_files.close_all();
+                    }
+
+                    {
+                        let mut session = self.session.lock().unwrap();
+
+                        // Keep serving, GDB can connect again once the target is powered
+                        if let Some(core_id) = self.unresponsive_core(&mut session) {
+                            tracing::error!(
+                                "Core {core_id} is not responding, check the power supply and \
+                                 the debug connection of the target. Closing the connection \
+                                 from {addr}."
+                            );
+
+                            return Ok(Duration::from_millis(10));
+                        }
+
+                        if self.halt_on_attach {
+                            for core_id in &self.cores {
+                                // When we first attach to the core, GDB expects us to halt the core, so we do this here when a new client connects.
+                                // If the core is already halted, nothing happens if we issue a halt command again, so we always do this no matter of core state.
+                                session.core(*core_id)?.halt(Duration::from_millis(100))?;
+                            }
+                        }
+
+                        let mut core = session.core(self.cores[0])?;
+                        self.target_desc = TargetDescription::new(&mut core)?;
+                    }
+
+                    let traffic_log =
+                        self.traffic_log
+                            .as_ref()
+                            .and_then(|path| match TrafficLog::open(path) {
+                                Ok(mut log) => {
+                                    log.note(&format!("New connection from {addr}"));
+                                    Some(log)
+                                }
+                                Err(e) => {
+                                    tracing::warn!(
+                                        "Failed to open GDB traffic log {}: {e}",
+                                        path.display()
+                                    );
+                                    None
+                                }
+                            });
+
+                    // Start the GDB Stub state machine
+                    let conn = GdbConnection::new(s).with_traffic_log(traffic_log);
+                    let stub = GdbStub::<RuntimeTarget, _>::builder(conn)
+                        .packet_buffer_size(self.packet_size)
+                        .build()
+                        .map_err(|e| anyhow::anyhow!("Failed to start GDB stub: {e}"))?;
+                    match stub.run_state_machine(self) {
+                        Ok(gdbstub) => {
+                            self.gdb = Some(gdbstub);
+                        }
+                        Err(e) => {
+                            // Any errors at this state are either IO errors or fatal config errors
+                            return Err(anyhow::anyhow!("Failed to start GDB stub: {e}").into());
+                        }
+                    };
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    // No connection yet
+                    return Ok(Duration::from_millis(10));
+                }
+                Err(e) => {
+                    // Fatal error
+                    return Err(anyhow::Error::from(e).into());
+                }
+            };
+        }
+
+        // Stage 2 - connected
+        let mut wait_time = Duration::ZERO;
+
+        // Multiple targets are processed on the same thread, each with its own register layout
+        arch::set_register_sizes(self.target_desc.register_sizes());
+
+        if let Some(gdb) = self.gdb.take() {
+            self.gdb = match gdb {
+                GdbStubStateMachine::Idle(mut state) => {
+                    // Read data if available
+                    let next_byte = state.borrow_conn().read_byte().into_error()?;
+
+                    if let Some(address) = state.borrow_conn().take_rtt_address() {
+                        self.rtt_address = Some(address);
+                    }
+
+                    if let Some((address, length)) = state.borrow_conn().take_crc_request() {
+                        self.crc_request = Some(CrcRequest::new(address, length));
+                    }
+
+                    if next_byte == Some(0x03) && self.crc_request.is_some() {
+                        // GDB is waiting for the CRC, so the interrupt only aborts its computation
+                        tracing::info!("CRC computation interrupted by GDB");
+
+                        self.crc_request = None;
+                        self.last_activity = Instant::now();
+
+                        state.borrow_conn().send_packet(b"E01").into_error()?;
+
+                        Some(state.into())
+                    } else if let Some(b) = next_byte {
+                        self.last_activity = Instant::now();
+
+                        // gdbstub ignores the address of `c<addr>` and `s<addr>`, it is applied on resume
+                        if let Some(body) = self.sniffer.push(b) {
+                            self.resume_address = packet::parse_resume_address(body);
+                        }
+
+                        let state = state.incoming_data(self, b).map_err(stub_error)?;
+
+                        // The failed request was answered with an error, GDB is not waiting for
+                        // console output at this point, so the error is only logged
+                        if let Some(message) = self.probe_error.take() {
+                            self.recover_from_probe_error(&message)?;
+                        }
+
+                        Some(state)
+                    } else if self.crc_request.is_some() {
+                        self.process_crc_request(state.borrow_conn())?;
+
+                        Some(state.into())
+                    } else {
+                        wait_time = Duration::from_millis(10);
+                        Some(state.into())
+                    }
+                }
+                GdbStubStateMachine::Running(mut state) => {
+                    // Read data if available
+                    let next_byte = state.borrow_conn().read_byte().into_error()?;
+
+                    if let Some(b) = next_byte {
+                        self.last_activity = Instant::now();
+
+                        Some(state.incoming_data(self, b).map_err(stub_error)?)
+                    } else {
+                        match self.poll_stop_reason(state.borrow_conn()) {
+                            Ok(Some((reason, expedited))) => {
+                                let mut regs = expedited
+                                    .iter()
+                                    .map(|(regnum, value)| (RuntimeRegId(*regnum), &value[..]));
+
+                                Some(
+                                    state
+                                        .report_stop_with_regs(self, reason, &mut regs)
+                                        .map_err(stub_error)?,
+                                )
+                            }
+                            Ok(None) => {
+                                if self.keep_alive.is_some_and(|interval| {
+                                    self.last_activity.elapsed() >= interval
+                                }) {
+                                    semihosting::send_keep_alive(state.borrow_conn())?;
+                                    self.last_activity = Instant::now();
+                                }
+
+                                wait_time = self.halt_poll_interval;
+                                Some(state.into())
+                            }
+                            Err(e) if is_probe_error(&e) => {
+                                let message = error_message(e);
+
+                                // GDB is waiting for a stop reply, so it shows console output
+                                semihosting::send_console_output(
+                                    state.borrow_conn(),
+                                    format!("probe communication lost: {message}\n").as_bytes(),
+                                )?;
+
+                                // The cores are halted after re-attaching
+                                self.recover_from_probe_error(&message)?;
+
+                                Some(
+                                    state
+                                        .report_stop(
+                                            self,
+                                            MultiThreadStopReason::Signal(Signal::SIGINT),
+                                        )
+                                        .map_err(stub_error)?,
+                                )
+                            }
+                            Err(e) => return Err(e),
+                        }
+                    }
+                }
+                GdbStubStateMachine::CtrlCInterrupt(state) => {
+                    // Break core, handle interrupt
+                    {
+                        let mut session = self.session.lock().unwrap();
+                        for core_id in self.active_cores() {
+                            let mut core = session.core(core_id)?;
+
+                            core.halt(Duration::from_millis(100))?;
+                        }
+                    }
+
+                    Some(
+                        state
+                            .interrupt_handled(
+                                self,
+                                Some(MultiThreadStopReason::Signal(Signal::SIGINT)),
+                            )
+                            .map_err(stub_error)?,
+                    )
+                }
+                GdbStubStateMachine::Disconnected(state) => {
+                    tracing::info!("GDB client disconnected: {:?}", state.get_reason());
+
+                    if let Err(e) = self.remove_sw_breakpoints() {
+                        tracing::warn!("Failed to remove software breakpoints: {e}");
+                    }
+
+                    None
+                }
+            };
+        }
+
+        Ok(wait_time)
+    }
+
+    /// Checksum the next chunk of the pending `qCRC` request, and reply once the CRC is complete
+    ///
+    /// Memory read failures are reported to GDB as `E01`.
+    fn process_crc_request(&mut self, conn: &mut GdbConnection<TcpStream>) -> Result<(), Error> {
+        let core_id = self.active_cores()[0];
+
+        let Some(request) = &mut self.crc_request else {
+            return Ok(());
+        };
+
+        let result = {
+            let mut session = self.session.lock().unwrap();
+
+            session
+                .core(core_id)
+                .and_then(|mut core| request.step(&mut core))
+        };
+
+        let reply = match result {
+            Ok(None) => return Ok(()),
+            Ok(Some(crc)) => format!("C{crc:08x}"),
+            Err(e) => {
+                let probe_error = is_probe_error(&e);
+                let message = error_message(e);
+
+                tracing::debug!("Failed to compute CRC: {message}");
+
+                if probe_error {
+                    self.recover_from_probe_error(&message)?;
+                }
+
+                "E01".to_string()
+            }
+        };
+
+        self.crc_request = None;
+
+        conn.send_packet(reply.as_bytes()).into_error()
+    }
+
+    /// Convert the result of a probe-rs operation into a gdbstub result
+    ///
+    /// Probe communication errors are reported to GDB as a non-fatal error and recovered from
+    /// once the current packet has been handled, all other errors end the debug session.
+    fn target_result<T>(&self, result: Result<T, impl Into<Error>>) -> TargetResult<T, Self> {
+        result.map_err(|e| {
+            let e = e.into();
+
+            if is_probe_error(&e) {
+                self.probe_error.set(Some(error_message(e)));
+
+                TargetError::NonFatal
+            } else {
+                TargetError::Fatal(e)
+            }
+        })
+    }
+
+    /// Try to re-attach to the target once after the communication with the probe failed
+    ///
+    /// If re-attaching succeeds the debug session continues, otherwise the GDB server is stopped.
+    fn recover_from_probe_error(&self, message: &str) -> Result<(), Error> {
+        tracing::warn!("Probe communication lost: {message}, trying to re-attach");
+
+        let mut session = self.session.lock().unwrap();
+
+        match self.reattach(&mut session) {
+            Ok(()) => {
+                tracing::info!("Re-attached to the target");
+
+                Ok(())
+            }
+            Err(e) => Err(anyhow::anyhow!(
+                "Probe communication lost: {message}, re-attaching failed: {}",
+                error_message(e)
+            )
+            .into()),
+        }
+    }
+
+    /// The first core of this stub which doesn't respond to a ping, if any
+    fn unresponsive_core(&self, session: &mut Session) -> Option<usize> {
+        self.cores.iter().copied().find(|&core_id| {
+            match session.core(core_id).and_then(|mut core| core.ping()) {
+                Ok(responds) => !responds,
+                Err(e) => {
+                    tracing::debug!("Failed to ping core {core_id}: {}", error_message(e));
+
+                    true
+                }
+            }
+        })
+    }
+
+    /// The cores controlled by GDB, either the attached core or all cores of this stub
+    fn active_cores(&self) -> Vec<usize> {
+        match self.attached_core {
+            Some(core_id) => vec![core_id],
+            None => self.cores.clone(),
+        }
+    }
+
+    /// The cores resumed by the last resume request, or all active cores if none was resumed
+    ///
+    /// Cores which were left halted by the request must not be reported as a stop.
+    fn resumed_cores(&self) -> Vec<usize> {
+        if self.resume_actions.is_empty() {
+            self.active_cores()
+        } else {
+            self.resume_actions
+                .iter()
+                .map(|(core_id, _)| *core_id)
+                .collect()
+        }
+    }
+
+    /// Re-synchronize the target state with GDB after a reset
+    ///
+    /// A reset may clear the breakpoint and watchpoint units, while GDB still considers its
+    /// breakpoints and watchpoints to be installed, so they are applied again.
+    fn resync(&self, session: &mut Session) -> Result<(), Error> {
+        for core_id in &self.cores {
+            let mut core = session.core(*core_id)?;
+
+            for addr in &self.hw_breakpoints {
+                core.set_hw_breakpoint(*addr)?;
+            }
+
+            for (addr, len, kind) in &self.hw_watchpoints {
+                core.set_hw_watchpoint(*addr, *len, breakpoints::watchpoint_kind(*kind))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Addresses of all breakpoints set by GDB, whether they use a comparator or an instruction
+    fn breakpoint_addresses(&self) -> Vec<u64> {
+        self.hw_breakpoints
+            .iter()
+            .copied()
+            .chain(self.sw_breakpoints.iter().map(|bp| bp.address))
+            .collect()
+    }
+
+    /// Restore the code replaced by software breakpoints, so they don't outlive the connection
+    fn remove_sw_breakpoints(&mut self) -> Result<(), Error> {
+        let mut session = self.session.lock().unwrap();
+        let mut core = session.core(self.cores[0])?;
+
+        for breakpoint in self.sw_breakpoints.drain(..) {
+            core.write_8(breakpoint.address, &breakpoint.original)?;
+        }
+
+        Ok(())
+    }
+
+    /// Re-attach the probe and halt the cores
+    ///
+    /// The session programs GDB's breakpoints and watchpoints again while re-attaching.
+    fn reattach(&self, session: &mut Session) -> Result<(), Error> {
+        session.reattach()?;
+
+        for core_id in &self.cores {
+            session.core(*core_id)?.halt(Duration::from_millis(100))?;
+        }
+
+        Ok(())
+    }
+
+    /// Check whether one of the cores halted while GDB considers the target running
+    ///
+    /// Returns the stop reason together with the expedited registers of the halted core.
+    /// Semihosting console output is forwarded to GDB and the core is resumed again,
+    /// these halts are never reported as a stop.
+    fn poll_stop_reason(
+        &mut self,
+        conn: &mut GdbConnection<TcpStream>,
+    ) -> Result<Option<(MultiThreadStopReason<u64>, Vec<(u16, Vec<u8>)>)>, Error> {
+        let mut session = self.session.lock().unwrap();
+        let mut stop = None;
+
+        for core_id in self.resumed_cores() {
+            let mut core = session.core(core_id)?;
+
+            let CoreStatus::Halted(reason) = core.status()? else {
+                continue;
+            };
+
+            let tid = NonZeroUsize::new(core_id + 1).unwrap();
+
+            if let HaltReason::Breakpoint(BreakpointCause::Semihosting(command)) = reason {
+                if self.semihosting {
+                    if let Some(text) = semihosting::service_console_output(&mut core, command)? {
+                        semihosting::send_console_output(conn, &text)?;
+                        core.run()?;
+
+                        continue;
+                    }
+                }
+            }
+
+            let on_hw_breakpoint = match reason {
+                HaltReason::Breakpoint(BreakpointCause::Software | BreakpointCause::Unknown)
+                | HaltReason::Step
+                | HaltReason::Multiple => {
+                    let pc = core.read_core_reg(core.program_counter())?;
+
+                    self.breakpoint_addresses().contains(&pc)
+                }
+                _ => false,
+            };
+
+            let watchpoint = match reason {
+                HaltReason::Watchpoint => core.triggered_hw_watchpoint()?.and_then(|addr| {
+                    self.hw_watchpoints
+                        .iter()
+                        .find(|(wp, _, _)| *wp == addr)
+                        .map(|&(_, _, kind)| (addr, kind))
+                }),
+                _ => None,
+            };
+
+            let reason = stop::stop_reason(tid, reason, on_hw_breakpoint, watchpoint);
+
+            let mut expedited = Vec::new();
+            for reg in self.target_desc.get_expedited_registers() {
+                let value = reg.read(&mut core)?;
+
+                let mut bytes = Vec::with_capacity(reg.size_in_bytes());
+                base::append_register_bytes(&mut bytes, value, reg.size_in_bytes());
+
+                expedited.push((reg.regnum() as u16, bytes));
+            }
+
+            stop = Some((reason, expedited));
+
+            break;
+        }
+
+        // halt all remaining cores that are still running
+        // GDB expects all or nothing stops
+        if stop.is_some() {
+            for core_id in self.active_cores() {
+                let mut core = session.core(core_id)?;
+
+                if !core.core_halted()? {
+                    core.halt(Duration::from_millis(100))?;
+                }
+            }
+        }
+
+        Ok(stop)
+    }
+}
+
+impl Target for RuntimeTarget<'_> {
+    type Arch = RuntimeArch;
+    type Error = Error;
+
+    fn base_ops(&mut self) -> BaseOps<'_, Self::Arch, Self::Error> {
+        BaseOps::MultiThread(self)
+    }
+
+    fn support_target_description_xml_override(
+        &mut self,
+    ) -> Option<TargetDescriptionXmlOverrideOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_breakpoints(&mut self) -> Option<BreakpointsOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_memory_map(&mut self) -> Option<MemoryMapOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_extended_mode(&mut self) -> Option<ExtendedModeOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_monitor_cmd(&mut self) -> Option<MonitorCmdOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_section_offsets(&mut self) -> Option<SectionOffsetsOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_host_io(&mut self) -> Option<HostIoOps<'_, Self>> {
+        if self.host_files.is_some() {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn guard_rail_implicit_sw_breakpoints(&self) -> bool {
+        true
+    }
+}
+
+impl TargetDescriptionXmlOverride for RuntimeTarget<'_> {
+    fn target_description_xml(
+        &self,
+        _annex: &[u8],
+        offset: u64,
+        length: usize,
+        buf: &mut [u8],
+    ) -> TargetResult<usize, Self> {
+        let xml = self.target_desc.get_target_xml();
+
+        Ok(copy_range_to_buf(xml.as_bytes(), offset, length, buf))
+    }
+}
+
+impl section_offsets::SectionOffsets for RuntimeTarget<'_> {
+    fn get_section_offsets(&mut self) -> Result<Offsets<u64>, Self::Error> {
+        // Without configured offsets, the image is reported at its link address
+        let offsets = self.section_offsets.unwrap_or(SectionOffsets {
+            text: 0,
+            data: 0,
+            bss: Some(0),
+        });
+
+        Ok(Offsets::Sections {
+            text: offsets.text,
+            data: offsets.data,
+            bss: offsets.bss,
+        })
+    }
+}
+
+impl MemoryMap for RuntimeTarget<'_> {
+    fn memory_map_xml(
+        &self,
+        offset: u64,
+        length: usize,
+        buf: &mut [u8],
+    ) -> TargetResult<usize, Self> {
+        let xml = match &self.memory_map {
+            Some(memory_map) => self.target_result(user_memory_map(memory_map))?,
+            None => {
+                let session = self.session.lock().unwrap();
+
+                self.target_result(gdb_memory_map(&session, self.cores[0]))?
+            }
+        };
+
+        Ok(copy_range_to_buf(xml.as_bytes(), offset, length, buf))
+    }
+}
+
+/// Check whether an error is caused by the communication with the probe
+fn is_probe_error(e: &Error) -> bool {
+    matches!(
+        e,
+        Error::Probe(_)
+            | Error::Arm(ArmError::Probe(_))
+            | Error::Riscv(RiscvError::DebugProbe(_))
+            | Error::Xtensa(XtensaError::DebugProbe(_))
+    )
+}
+
+/// Format an error together with its causes
+pub(super) fn error_message(e: Error) -> String {
+    format!("{:#}", anyhow::Error::from(e))
+}
+
+/// Convert an error returned by the GDB stub state machine
+fn stub_error<E: std::fmt::Display>(e: E) -> Error {
+    anyhow::anyhow!("GDB stub error: {e}").into()
+}
@@ -0,0 +1,357 @@
+use super::{error_message, RuntimeTarget, SectionOffsets};
+
+use crate::Error;
+
+use gdbstub::target::ext::monitor_cmd::{outputln, ConsoleOutput, MonitorCmd};
+
+use std::time::Duration;
+
+impl MonitorCmd for RuntimeTarget<'_> {
+    fn handle_monitor_cmd(
+        &mut self,
+        cmd: &[u8],
+        mut out: ConsoleOutput<'_>,
+    ) -> Result<(), Self::Error> {
+        let cmd = String::from_utf8_lossy(cmd);
+        let args = cmd.split_whitespace().collect::<Vec<_>>();
+
+        match args[..] {
+            ["help"] => {
+                outputln!(out, "probe-rs monitor commands:");
+                outputln!(
+                    out,
+                    "  reset [halt]                 - reset the target and halt it at the reset vector"
+                );
+                outputln!(
+                    out,
+                    "  reset run                    - reset the target and let it run"
+                );
+                outputln!(
+                    out,
+                    "  reset init                   - reset and halt the target, then run the target specific initialization, e.g. freeze the watchdogs"
+                );
+                outputln!(
+                    out,
+                    "  mass_erase                   - erase the entire flash memory, destroying all its contents"
+                );
+                outputln!(
+                    out,
+                    "  rtt                          - show the address of the RTT control block, if GDB knows it"
+                );
+                outputln!(
+                    out,
+                    "  semihosting [enable|disable] - show or change whether semihosting console output is serviced"
+                );
+                outputln!(
+                    out,
+                    "                                 or reported as a trap, also available as \"arm semihosting\""
+                );
+                outputln!(
+                    out,
+                    "  speed [<khz>]                - show or change the speed of the debug protocol"
+                );
+                outputln!(
+                    out,
+                    "  verify <on|off>              - read back and compare memory after every write"
+                );
+                outputln!(
+                    out,
+                    "  offsets [<text> [<data>]]    - show or set the section offsets of a relocated image"
+                );
+                outputln!(
+                    out,
+                    "  offsets clear                - report the image at its link address"
+                );
+            }
+            ["reset"] | ["reset", "halt"] => self.reset(&mut out, ResetMode::Halt)?,
+            ["reset", "run"] => self.reset(&mut out, ResetMode::Run)?,
+            ["reset", "init"] => self.reset(&mut out, ResetMode::Init)?,
+            ["mass_erase"] => {
+                outputln!(out, "Erasing the entire flash memory");
+
+                let mut session = self.session.lock().unwrap();
+
+                match session.mass_erase() {
+                    Ok(()) => {
+                        for core_id in &self.cores {
+                            session.core(*core_id)?.halt(Duration::from_millis(100))?;
+                        }
+
+                        self.resync(&mut session)?;
+
+                        outputln!(out, "Done");
+                    }
+                    Err(e) => outputln!(out, "Mass erase failed: {}", error_message(e)),
+                }
+            }
+            ["rtt"] => match self.rtt_address {
+                Some(address) => outputln!(out, "RTT control block at {:#010x}", address),
+                None => outputln!(
+                    out,
+                    "RTT control block unknown, GDB did not provide the _SEGGER_RTT symbol"
+                ),
+            },
+            // OpenOCD names this command `arm semihosting`
+            ["semihosting"] | ["arm", "semihosting"] => {
+                let state = if self.semihosting {
+                    "enabled"
+                } else {
+                    "disabled"
+                };
+
+                outputln!(out, "Semihosting console output {}", state);
+            }
+            ["semihosting", "enable"] | ["arm", "semihosting", "enable"] => {
+                self.semihosting = true;
+
+                outputln!(out, "Semihosting console output enabled");
+            }
+            ["semihosting", "disable"] | ["arm", "semihosting", "disable"] => {
+                self.semihosting = false;
+
+                outputln!(out, "Semihosting console output disabled");
+            }
+            ["speed"] => {
+                let session = self.session.lock().unwrap();
+
+                if session.speed_is_approximate() {
+                    outputln!(out, "Speed: up to {} kHz", session.speed_khz());
+                } else {
+                    outputln!(out, "Speed: {} kHz", session.speed_khz());
+                }
+            }
+            ["speed", speed_khz] => match speed_khz.parse() {
+                Ok(speed_khz) => match self.session.lock().unwrap().set_speed(speed_khz) {
+                    Ok(speed_khz) => outputln!(out, "Speed set to {} kHz", speed_khz),
+                    Err(e) => outputln!(out, "Failed to change the speed: {}", error_message(e)),
+                },
+                Err(_) => outputln!(out, "Invalid speed, expected a number in kHz"),
+            },
+            ["verify", "on"] => {
+                self.verify = true;
+
+                outputln!(out, "Memory write verification enabled");
+            }
+            ["verify", "off"] => {
+                self.verify = false;
+
+                outputln!(out, "Memory write verification disabled");
+            }
+            ["offsets"] => match self.section_offsets {
+                Some(offsets) => outputln!(
+                    out,
+                    "Section offsets: text {:#x}, data {:#x}",
+                    offsets.text,
+                    offsets.data
+                ),
+                None => outputln!(out, "No section offsets configured"),
+            },
+            ["offsets", "clear"] => {
+                self.section_offsets = None;
+
+                outputln!(out, "Section offsets cleared");
+            }
+            ["offsets", text] | ["offsets", text, _] => {
+                let text = parse_number(text);
+                let data = match args.get(2) {
+                    Some(data) => parse_number(data),
+                    None => text,
+                };
+
+                match (text, data) {
+                    (Some(text), Some(data)) => {
+                        self.section_offsets = Some(SectionOffsets {
+                            text,
+                            data,
+                            bss: Some(data),
+                        });
+
+                        // GDB only asks for the offsets when connecting
+                        outputln!(
+                            out,
+                            "Section offsets set to text {:#x}, data {:#x}. Reconnect GDB to apply them.",
+                            text,
+                            data
+                        );
+                    }
+                    _ => outputln!(
+                        out,
+                        "Invalid offset, expected a decimal or 0x-prefixed hex number"
+                    ),
+                }
+            }
+            _ => {
+                outputln!(out, "Unknown command \"{}\"", cmd);
+                outputln!(out, "Enter \"monitor help\" for a list of commands");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Variants of the `monitor reset` command
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ResetMode {
+    /// Halt the cores at the reset vector
+    Halt,
+    /// Let the cores run after the reset
+    Run,
+    /// Halt the cores at the reset vector and run the target specific initialization
+    Init,
+}
+
+impl RuntimeTarget<'_> {
+    /// Reset all cores and report their program counter
+    fn reset(&mut self, out: &mut ConsoleOutput<'_>, mode: ResetMode) -> Result<(), Error> {
+        outputln!(out, "Resetting target");
+
+        let mut session = self.session.lock().unwrap();
+
+        // Without breakpoints there is nothing to program before the cores run, the target
+        // is reset once and all cores are left running
+        if mode == ResetMode::Run
+            && self.hw_breakpoints.is_empty()
+            && self.hw_watchpoints.is_empty()
+        {
+            session.reset_and_run()?;
+
+            outputln!(out, "Target running");
+
+            return Ok(());
+        }
+
+        // The reset affects the whole target, so it is issued once, through the first core.
+        // The other cores halt at the reset vector because of their reset catch.
+        let timeout = Duration::from_millis(500);
+        let (&first, others) = self
+            .cores
+            .split_first()
+            .expect("a GDB target has at least one core");
+
+        for core_id in others {
+            session.core(*core_id)?.reset_catch_set()?;
+        }
+
+        let mut core = session.core(first)?;
+        let info = match mode {
+            ResetMode::Init => core.reset_and_init(timeout)?,
+            ResetMode::Halt | ResetMode::Run => core.reset_and_halt(timeout)?,
+        };
+        let mut halted_at = vec![(first, info.pc)];
+
+        for core_id in others {
+            let mut core = session.core(*core_id)?;
+
+            core.wait_for_core_halted(timeout)?;
+            core.reset_catch_clear()?;
+
+            if mode == ResetMode::Init {
+                core.reset_init()?;
+            }
+
+            halted_at.push((*core_id, core.read_core_reg(core.program_counter())?));
+        }
+
+        if mode != ResetMode::Run {
+            for (core_id, pc) in halted_at {
+                outputln!(out, "Core {} halted at {:#010x}", core_id, pc);
+            }
+        }
+
+        // The reset cleared the breakpoints, so they have to be set before the cores run
+        self.resync(&mut session)?;
+
+        if mode == ResetMode::Run {
+            for core_id in &self.cores {
+                session.core(*core_id)?.run()?;
+            }
+
+            // GDB still considers the target stopped, until it is continued or interrupted
+            outputln!(out, "Target running");
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse a decimal or `0x`-prefixed hexadecimal number
+fn parse_number(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::architecture::arm::armv7m::Aircr;
+    use crate::gdb_server::target::conn::test::process_packets;
+    use crate::gdb_server::target::RuntimeTarget;
+    use crate::gdb_server::GdbInstanceConfiguration;
+    use crate::probe::fake_probe::FakeProbe;
+    use crate::{MemoryMappedRegister, Permissions, Session};
+
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// Send a monitor command to a GDB target serving both cores of a mocked STM32H745,
+    /// returning the number of system resets
+    fn system_resets(packet: &[u8]) -> (Session, usize) {
+        let mut fake_probe = FakeProbe::with_mocked_core();
+
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let recorded_writes = writes.clone();
+        fake_probe.set_memory_write_handler(Box::new(move |address, value| {
+            recorded_writes.lock().unwrap().push((address, value));
+        }));
+
+        let session = fake_probe
+            .into_probe()
+            .attach("STM32H745BGTx", Permissions::default())
+            .unwrap();
+        let instance = GdbInstanceConfiguration::from_session(&session, Some("127.0.0.1:0"))
+            .unwrap()
+            .remove(0);
+        assert_eq!(instance.cores, [0, 1]);
+
+        let session = Mutex::new(session);
+        let mut target =
+            RuntimeTarget::new(&session, &instance, false, Duration::from_millis(10)).unwrap();
+
+        writes.lock().unwrap().clear();
+        process_packets(&mut target, packet);
+        drop(target);
+
+        let resets = writes
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|&&(address, value)| {
+                address == Aircr::ADDRESS_OFFSET && Aircr::from(value).sysresetreq()
+            })
+            .count();
+
+        (session.into_inner().unwrap(), resets)
+    }
+
+    #[test]
+    fn reset_run_resets_the_target_once() {
+        // `monitor reset run`
+        let (mut session, resets) = system_resets(b"$qRcmd,72657365742072756e#09");
+
+        assert_eq!(resets, 1);
+        assert!(!session.core(0).unwrap().core_halted().unwrap());
+        assert!(!session.core(1).unwrap().core_halted().unwrap());
+    }
+
+    #[test]
+    fn reset_halt_resets_the_target_once() {
+        // `monitor reset`
+        let (mut session, resets) = system_resets(b"$qRcmd,7265736574#37");
+
+        assert_eq!(resets, 1);
+        assert!(session.core(0).unwrap().core_halted().unwrap());
+        assert!(session.core(1).unwrap().core_halted().unwrap());
+    }
+}
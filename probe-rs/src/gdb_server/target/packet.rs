@@ -0,0 +1,94 @@
+/// Keeps track of the packet currently being received from GDB
+///
+/// This allows inspecting packets which gdbstub does not fully handle, before the
+/// bytes are handed to the GDB stub state machine.
+#[derive(Debug, Default)]
+pub(crate) struct PacketSniffer {
+    state: SnifferState,
+    body: Vec<u8>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum SnifferState {
+    /// Waiting for the start of a packet
+    #[default]
+    Idle,
+    /// Receiving the packet body
+    Body,
+    /// Receiving the checksum, with the number of remaining checksum characters
+    Checksum(u8),
+}
+
+impl PacketSniffer {
+    /// Process the next byte received from GDB
+    ///
+    /// Returns the packet body if `byte` is the last byte of a packet.
+    pub fn push(&mut self, byte: u8) -> Option<&[u8]> {
+        match (self.state, byte) {
+            // A packet start always resets the state, e.g. after a dropped packet
+            (_, b'$') => {
+                self.body.clear();
+                self.state = SnifferState::Body;
+            }
+            (SnifferState::Body, b'#') => self.state = SnifferState::Checksum(2),
+            (SnifferState::Body, byte) => self.body.push(byte),
+            (SnifferState::Checksum(1), _) => {
+                self.state = SnifferState::Idle;
+
+                return Some(&self.body);
+            }
+            (SnifferState::Checksum(n), _) => self.state = SnifferState::Checksum(n - 1),
+            (SnifferState::Idle, _) => {}
+        }
+
+        None
+    }
+}
+
+/// Parse the optional resume address of the legacy `c<addr>` and `s<addr>` packets
+pub(crate) fn parse_resume_address(body: &[u8]) -> Option<u64> {
+    let (b'c' | b's', addr) = body.split_first()? else {
+        return None;
+    };
+
+    if addr.is_empty() {
+        return None;
+    }
+
+    u64::from_str_radix(std::str::from_utf8(addr).ok()?, 16).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn feed(sniffer: &mut PacketSniffer, data: &[u8]) -> Option<Vec<u8>> {
+        let mut last = None;
+
+        for byte in data {
+            last = sniffer.push(*byte).map(|body| body.to_vec());
+        }
+
+        last
+    }
+
+    #[test]
+    fn sniff_packet_body() {
+        let mut sniffer = PacketSniffer::default();
+
+        assert_eq!(
+            feed(&mut sniffer, b"+$s8000100#c5"),
+            Some(b"s8000100".to_vec())
+        );
+        assert_eq!(feed(&mut sniffer, b"+"), None);
+    }
+
+    #[test]
+    fn resume_address() {
+        assert_eq!(parse_resume_address(b"c8000100"), Some(0x800_0100));
+        assert_eq!(parse_resume_address(b"s20"), Some(0x20));
+        assert_eq!(parse_resume_address(b"c"), None);
+        assert_eq!(parse_resume_address(b"qSupported"), None);
+        assert_eq!(parse_resume_address(b"swbreak"), None);
+    }
+}
@@ -0,0 +1,314 @@
+use super::{ResumeAction, RuntimeTarget};
+
+use crate::{Core, CoreStatus, Error, HaltReason};
+
+use gdbstub::common::{Signal, Tid};
+use gdbstub::target::ext::base::multithread::{
+    MultiThreadRangeStepping, MultiThreadRangeSteppingOps, MultiThreadResume,
+    MultiThreadSchedulerLocking, MultiThreadSchedulerLockingOps, MultiThreadSingleStep,
+    MultiThreadSingleStepOps,
+};
+
+/// Maximum number of instructions executed by a single range step
+///
+/// The server can't process any other requests while stepping, so a range which is never
+/// left, e.g. a tight loop, is reported as a stop within the range after this many steps.
+const MAX_RANGE_STEPS: usize = 10_000;
+
+impl MultiThreadResume for RuntimeTarget<'_> {
+    fn resume(&mut self) -> Result<(), Self::Error> {
+        if !self.scheduler_locked {
+            let cores = self.active_cores();
+
+            continue_other_cores(&mut self.resume_actions, &cores);
+        }
+
+        let mut session = self.session.lock().unwrap();
+
+        if let Some(address) = self.resume_address.take() {
+            if let Some(&(core_id, _)) = self.resume_actions.first() {
+                let mut core = session.core(core_id)?;
+
+                core.write_core_reg(core.program_counter(), address)?;
+            }
+        }
+
+        // Start the running cores first, so they keep running while the other cores are stepped
+        for &(core_id, action) in &self.resume_actions {
+            if let ResumeAction::Resume = action {
+                session.core(core_id)?.run()?;
+            }
+        }
+
+        for &(core_id, action) in &self.resume_actions {
+            match action {
+                ResumeAction::Resume => {}
+                ResumeAction::Step => {
+                    session.core(core_id)?.step()?;
+                }
+                ResumeAction::RangeStep { start, end } => {
+                    let mut core = session.core(core_id)?;
+
+                    range_step(&mut core, start..end, &self.breakpoint_addresses())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn clear_resume_actions(&mut self) -> Result<(), Self::Error> {
+        self.resume_actions.clear();
+        self.scheduler_locked = false;
+
+        Ok(())
+    }
+
+    fn set_resume_action_continue(
+        &mut self,
+        tid: Tid,
+        signal: Option<Signal>,
+    ) -> Result<(), Self::Error> {
+        ignore_signal(signal);
+
+        self.set_resume_action(tid, ResumeAction::Resume);
+
+        Ok(())
+    }
+
+    fn support_single_step(&mut self) -> Option<MultiThreadSingleStepOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_range_step(&mut self) -> Option<MultiThreadRangeSteppingOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_scheduler_locking(&mut self) -> Option<MultiThreadSchedulerLockingOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl MultiThreadSingleStep for RuntimeTarget<'_> {
+    fn set_resume_action_step(
+        &mut self,
+        tid: Tid,
+        signal: Option<Signal>,
+    ) -> Result<(), Self::Error> {
+        ignore_signal(signal);
+
+        self.set_resume_action(tid, ResumeAction::Step);
+
+        Ok(())
+    }
+}
+
+impl MultiThreadRangeStepping for RuntimeTarget<'_> {
+    fn set_resume_action_range_step(
+        &mut self,
+        tid: Tid,
+        start: u64,
+        end: u64,
+    ) -> Result<(), Self::Error> {
+        self.set_resume_action(tid, ResumeAction::RangeStep { start, end });
+
+        Ok(())
+    }
+}
+
+impl MultiThreadSchedulerLocking for RuntimeTarget<'_> {
+    fn set_resume_action_scheduler_lock(&mut self) -> Result<(), Self::Error> {
+        self.scheduler_locked = true;
+
+        Ok(())
+    }
+}
+
+impl RuntimeTarget<'_> {
+    /// Set the resume action of the core of thread `tid`
+    fn set_resume_action(&mut self, tid: Tid, action: ResumeAction) {
+        push_resume_action(&mut self.resume_actions, tid, action);
+    }
+}
+
+/// Add the resume action of the core of thread `tid` to `actions`
+///
+/// Each thread takes the first action which applies to it, e.g. `vCont;s:2;c:2` steps the
+/// second core.
+pub(super) fn push_resume_action(
+    actions: &mut Vec<(usize, ResumeAction)>,
+    tid: Tid,
+    action: ResumeAction,
+) {
+    let core_id = tid.get() - 1;
+
+    if !actions.iter().any(|(id, _)| *id == core_id) {
+        actions.push((core_id, action));
+    }
+}
+
+/// Continue all `cores` which don't have a resume action in `actions`
+///
+/// gdbstub doesn't pass on the default continue action of a `vCont` packet, e.g. the `c` of
+/// `vCont;s:2;c`, it is implied for all threads unless the scheduler is locked.
+pub(super) fn continue_other_cores(actions: &mut Vec<(usize, ResumeAction)>, cores: &[usize]) {
+    for &core_id in cores {
+        if !actions.iter().any(|(id, _)| *id == core_id) {
+            actions.push((core_id, ResumeAction::Resume));
+        }
+    }
+}
+
+/// Signals sent with `vCont;C` or `vCont;S` can't be delivered to a bare-metal target, so the
+/// action is handled like a plain continue or step.
+fn ignore_signal(signal: Option<Signal>) {
+    if let Some(signal) = signal {
+        tracing::debug!("Ignoring signal {:?} requested by GDB", signal);
+    }
+}
+
+/// Step `core` until its program counter leaves `range`
+///
+/// The core is stepped at least once, an empty range is a single step. Stepping stops early
+/// when the core halts for another reason than the step, e.g. on a breakpoint instruction or
+/// a fault, when it reaches one of the `breakpoints`, or after [MAX_RANGE_STEPS] steps.
+/// In all cases the core is left halted, and the stop is reported when the halt is polled.
+fn range_step(
+    core: &mut Core<'_>,
+    range: std::ops::Range<u64>,
+    breakpoints: &[u64],
+) -> Result<(), Error> {
+    for _ in 0..MAX_RANGE_STEPS {
+        let pc = core.step()?.pc;
+
+        if !range.contains(&pc) || breakpoints.contains(&pc) {
+            return Ok(());
+        }
+
+        // Cortex-M cores report a step as a halt request
+        match core.status()? {
+            CoreStatus::Halted(HaltReason::Step | HaltReason::Request) => {}
+            _ => return Ok(()),
+        }
+    }
+
+    tracing::debug!(
+        "Range step {:#010x}..{:#010x} stopped after {} steps",
+        range.start,
+        range.end,
+        MAX_RANGE_STEPS
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::gdb_server::target::conn::test::{connection, process_packets, StubTarget};
+    use crate::gdb_server::GdbInstanceConfiguration;
+    use crate::probe::fake_probe::test::attach_mocked_core;
+    use crate::Session;
+
+    use gdbstub::stub::state_machine::GdbStubStateMachine;
+    use gdbstub::stub::GdbStub;
+
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// Feed the resume `packet` into gdbstub, and return the resume actions of the cores
+    fn resume_actions(packet: &[u8]) -> Vec<(usize, ResumeAction)> {
+        let mut target = StubTarget::new(2);
+        let stub = GdbStub::new(connection(packet));
+        let mut state = stub.run_state_machine(&mut target).unwrap();
+
+        loop {
+            state = match state {
+                GdbStubStateMachine::Idle(mut idle) => {
+                    let byte = idle
+                        .borrow_conn()
+                        .read_byte()
+                        .unwrap()
+                        .expect("the target was not resumed");
+
+                    idle.incoming_data(&mut target, byte).unwrap()
+                }
+                GdbStubStateMachine::Running(_) => return target.resumed.unwrap(),
+                _ => panic!("unexpected gdbstub state"),
+            };
+        }
+    }
+
+    #[test]
+    fn continue_resumes_all_cores() {
+        assert_eq!(
+            resume_actions(b"$vCont;c#a8"),
+            [(0, ResumeAction::Resume), (1, ResumeAction::Resume)]
+        );
+    }
+
+    #[test]
+    fn default_continue_resumes_other_cores() {
+        assert_eq!(
+            resume_actions(b"$vCont;s:2;c#c2"),
+            [(1, ResumeAction::Step), (0, ResumeAction::Resume)]
+        );
+    }
+
+    #[test]
+    fn locked_scheduler_keeps_other_cores_halted() {
+        assert_eq!(resume_actions(b"$vCont;s:2#24"), [(1, ResumeAction::Step)]);
+    }
+
+    /// Feed `packet` to a GDB stub for a mocked core with its program counter at `pc`
+    ///
+    /// The mocked core executes one 16 bit instruction per step.
+    fn process_on_mocked_core(pc: u32, packet: &[u8]) -> Session {
+        let (mut session, _writes) = attach_mocked_core(false);
+
+        let mut core = session.core(0).unwrap();
+        core.write_core_reg(core.program_counter(), pc).unwrap();
+        drop(core);
+
+        let instance = GdbInstanceConfiguration::from_session(&session, Some("127.0.0.1:0"))
+            .unwrap()
+            .remove(0);
+        let session = Mutex::new(session);
+        let mut target =
+            RuntimeTarget::new(&session, &instance, false, Duration::from_millis(10)).unwrap();
+
+        process_packets(&mut target, packet);
+        drop(target);
+
+        session.into_inner().unwrap()
+    }
+
+    #[test]
+    fn continue_with_signal_resumes_the_core() {
+        let mut session = process_on_mocked_core(0x1000, b"$vCont;C04:1#57");
+        let mut core = session.core(0).unwrap();
+
+        assert!(!core.core_halted().unwrap());
+    }
+
+    #[test]
+    fn step_with_signal_steps_the_core() {
+        let mut session = process_on_mocked_core(0x1000, b"$vCont;S05:1#68");
+        let mut core = session.core(0).unwrap();
+
+        let pc: u32 = core.read_core_reg(core.program_counter()).unwrap();
+        assert_eq!(pc, 0x1002);
+        assert!(core.core_halted().unwrap());
+    }
+
+    #[test]
+    fn range_step_steps_until_the_range_is_left() {
+        let mut session = process_on_mocked_core(0x1000, b"$vCont;r1000,1008:1#d8");
+        let mut core = session.core(0).unwrap();
+
+        let pc: u32 = core.read_core_reg(core.program_counter()).unwrap();
+        assert_eq!(pc, 0x1008);
+        assert!(core.core_halted().unwrap());
+    }
+}
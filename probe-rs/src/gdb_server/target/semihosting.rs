@@ -0,0 +1,104 @@
+use super::conn::GdbConnection;
+use crate::{Core, Error, MemoryInterface, SemihostingCommand};
+
+use gdbstub::conn::Connection;
+
+use std::net::TcpStream;
+
+/// Upper bound for a single console write, to avoid reading unbounded memory for a missing string terminator
+const MAX_CONSOLE_WRITE: usize = 4096;
+
+/// Maximum number of output bytes sent in a single `O` packet
+const MAX_OUTPUT_CHUNK: usize = 256;
+
+/// Service a semihosting console output request
+///
+/// Returns the text the target wants to print, or `None` if `command` is not a console output request.
+/// The return value of the semihosting call is updated so the core can be resumed afterwards.
+pub(crate) fn service_console_output(
+    core: &mut Core,
+    command: SemihostingCommand,
+) -> Result<Option<Vec<u8>>, Error> {
+    let text = match command {
+        SemihostingCommand::WriteChar { address } => vec![core.read_word_8(address as u64)?],
+        SemihostingCommand::WriteString { address } => read_string(core, address as u64)?,
+        SemihostingCommand::Write { parameter_block } => {
+            // The parameter block contains the file handle, the buffer address and the buffer length
+            let mut block = [0u32; 3];
+            core.read_32(parameter_block as u64, &mut block)?;
+
+            let [_handle, address, length] = block;
+
+            let mut text = vec![0u8; (length as usize).min(MAX_CONSOLE_WRITE)];
+            core.read(address as u64, &mut text)?;
+
+            // SYS_WRITE returns the number of bytes which were not written
+            let not_written = length - text.len() as u32;
+            core.write_core_reg(core.registers().result_register(0), not_written)?;
+
+            text
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(text))
+}
+
+/// Send console output to GDB, split into `O` packets
+pub(crate) fn send_console_output(
+    conn: &mut GdbConnection<TcpStream>,
+    text: &[u8],
+) -> Result<(), Error> {
+    for chunk in text.chunks(MAX_OUTPUT_CHUNK) {
+        let mut packet = String::with_capacity(chunk.len() * 2 + 5);
+
+        packet.push('O');
+        for byte in chunk {
+            packet.push_str(&format!("{byte:02x}"));
+        }
+
+        let checksum = packet.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+
+        conn.write_all(format!("${packet}#{checksum:02x}").as_bytes())
+            .map_err(|e| anyhow::anyhow!("Failed to send console output: {e}"))?;
+    }
+
+    conn.flush()
+        .map_err(|e| anyhow::anyhow!("Failed to send console output: {e}"))?;
+
+    Ok(())
+}
+
+/// Send an empty `O` packet, to show GDB front-ends waiting for a stop reply that the
+/// connection is still alive. GDB doesn't print anything for it.
+pub(crate) fn send_keep_alive(conn: &mut GdbConnection<TcpStream>) -> Result<(), Error> {
+    conn.write_all(b"$O#4f")
+        .map_err(|e| anyhow::anyhow!("Failed to send keep-alive: {e}"))?;
+
+    conn.flush()
+        .map_err(|e| anyhow::anyhow!("Failed to send keep-alive: {e}"))?;
+
+    Ok(())
+}
+
+/// Read a NUL-terminated string from target memory
+fn read_string(core: &mut Core, mut address: u64) -> Result<Vec<u8>, Error> {
+    let mut text = Vec::new();
+    let mut chunk = [0u8; 64];
+
+    while text.len() < MAX_CONSOLE_WRITE {
+        core.read(address, &mut chunk)?;
+
+        match chunk.iter().position(|&b| b == 0) {
+            Some(end) => {
+                text.extend_from_slice(&chunk[..end]);
+                break;
+            }
+            None => text.extend_from_slice(&chunk),
+        }
+
+        address += chunk.len() as u64;
+    }
+
+    Ok(text)
+}
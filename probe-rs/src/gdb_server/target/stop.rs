@@ -0,0 +1,235 @@
+use crate::{BreakpointCause, HaltReason};
+
+use gdbstub::common::{Signal, Tid};
+use gdbstub::stub::MultiThreadStopReason;
+use gdbstub::target::ext::breakpoints::WatchKind;
+
+/// Build the stop reply reported to GDB for a core which halted
+///
+/// # Arguments
+///
+/// * tid - The thread ID of the halted core
+/// * reason - Why the core halted
+/// * on_hw_breakpoint - Whether the core halted at the address of a breakpoint set by GDB
+/// * watchpoint - Address and kind of the watchpoint set by GDB which triggered, if known
+///
+/// GDB relies on `hwbreak` to decide whether it hit one of its breakpoints, so it is only
+/// reported if the halt reason is certain. GDB sets all its breakpoints with `Z1`, even the
+/// ones written as breakpoint instructions, so `swbreak` is never reported: gdbstub rejects
+/// it as a fatal error without software breakpoint support. Other breakpoint instructions
+/// and halts GDB can't attribute are reported as `SIGTRAP`, halt requests as `SIGINT` and
+/// exceptions as `SIGSEGV`.
+pub(crate) fn stop_reason(
+    tid: Tid,
+    reason: HaltReason,
+    on_hw_breakpoint: bool,
+    watchpoint: Option<(u64, WatchKind)>,
+) -> MultiThreadStopReason<u64> {
+    let signal = match reason {
+        HaltReason::Breakpoint(BreakpointCause::Hardware) => {
+            return MultiThreadStopReason::HwBreak(tid)
+        }
+        // Some cores, e.g. Cortex-M, don't tell breakpoint instructions and comparators apart.
+        // A step which lands on a breakpoint is reported as hitting the breakpoint as well.
+        HaltReason::Breakpoint(BreakpointCause::Software | BreakpointCause::Unknown)
+        | HaltReason::Step
+        | HaltReason::Multiple
+            if on_hw_breakpoint =>
+        {
+            return MultiThreadStopReason::HwBreak(tid)
+        }
+        HaltReason::Watchpoint => match watchpoint {
+            Some((addr, kind)) => return MultiThreadStopReason::Watch { tid, kind, addr },
+            None => Signal::SIGTRAP,
+        },
+        HaltReason::Request | HaltReason::External => Signal::SIGINT,
+        // Vector catch, e.g. a HardFault or BusFault
+        HaltReason::Exception => Signal::SIGSEGV,
+        // Steps are reported as a T packet instead of `DoneStep`, so the expedited registers can be included
+        HaltReason::Breakpoint(_)
+        | HaltReason::Step
+        | HaltReason::Multiple
+        | HaltReason::Unknown => Signal::SIGTRAP,
+    };
+
+    MultiThreadStopReason::SignalWithThread { tid, signal }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::gdb_server::target::conn::test::{connection, take_output, FakeStream, StubTarget};
+    use crate::gdb_server::target::conn::GdbConnection;
+    use crate::SemihostingCommand;
+
+    use gdbstub::stub::state_machine::GdbStubStateMachine;
+    use gdbstub::stub::GdbStub;
+
+    fn tid() -> Tid {
+        Tid::new(1).unwrap()
+    }
+
+    fn signal(signal: Signal) -> MultiThreadStopReason<u64> {
+        MultiThreadStopReason::SignalWithThread { tid: tid(), signal }
+    }
+
+    /// Continue the target, report the stop of a core halted for `reason` through gdbstub,
+    /// and return the stop reply sent to GDB
+    fn stop_reply(
+        reason: HaltReason,
+        on_hw_breakpoint: bool,
+        watchpoint: Option<(u64, WatchKind)>,
+    ) -> Vec<u8> {
+        let mut target = StubTarget::new(1);
+        let stub = GdbStub::new(connection(b"$vCont;c#a8"));
+        let mut state = stub.run_state_machine(&mut target).unwrap();
+
+        let running = loop {
+            state = match state {
+                GdbStubStateMachine::Idle(mut idle) => {
+                    let byte = idle
+                        .borrow_conn()
+                        .read_byte()
+                        .unwrap()
+                        .expect("the target was not resumed");
+
+                    idle.incoming_data(&mut target, byte).unwrap()
+                }
+                GdbStubStateMachine::Running(running) => break running,
+                _ => panic!("unexpected gdbstub state"),
+            };
+        };
+
+        let reason = stop_reason(tid(), reason, on_hw_breakpoint, watchpoint);
+
+        match running.report_stop(&mut target, reason).unwrap() {
+            GdbStubStateMachine::Idle(mut idle) => take_stop_reply(idle.borrow_conn()),
+            _ => panic!("unexpected gdbstub state"),
+        }
+    }
+
+    /// The last packet sent on `conn`, without its checksum
+    fn take_stop_reply(conn: &mut GdbConnection<FakeStream>) -> Vec<u8> {
+        let output = take_output(conn);
+        let start = output.iter().rposition(|&b| b == b'$').unwrap();
+        let end = output.iter().rposition(|&b| b == b'#').unwrap();
+
+        output[start + 1..end].to_vec()
+    }
+
+    #[test]
+    fn breakpoints_are_reported_through_gdbstub() {
+        assert_eq!(
+            stop_reply(
+                HaltReason::Breakpoint(BreakpointCause::Software),
+                false,
+                None
+            ),
+            b"T05thread:01;"
+        );
+        assert_eq!(
+            stop_reply(
+                HaltReason::Breakpoint(BreakpointCause::Software),
+                true,
+                None
+            ),
+            b"T05thread:01;hwbreak:;"
+        );
+        assert_eq!(
+            stop_reply(
+                HaltReason::Breakpoint(BreakpointCause::Hardware),
+                false,
+                None
+            ),
+            b"T05thread:01;hwbreak:;"
+        );
+        assert_eq!(
+            stop_reply(HaltReason::Breakpoint(BreakpointCause::Unknown), true, None),
+            b"T05thread:01;hwbreak:;"
+        );
+        assert_eq!(
+            stop_reply(
+                HaltReason::Breakpoint(BreakpointCause::Unknown),
+                false,
+                None
+            ),
+            b"T05thread:01;"
+        );
+        assert_eq!(
+            stop_reply(
+                HaltReason::Breakpoint(BreakpointCause::Semihosting(SemihostingCommand::Unknown {
+                    operation: 0x100
+                })),
+                false,
+                None
+            ),
+            b"T05thread:01;"
+        );
+        assert_eq!(
+            stop_reply(
+                HaltReason::Watchpoint,
+                false,
+                Some((0x2000_0000, WatchKind::Write))
+            ),
+            b"T05thread:01;watch:20000000;"
+        );
+    }
+
+    #[test]
+    fn steps() {
+        assert_eq!(
+            stop_reason(tid(), HaltReason::Step, false, None),
+            signal(Signal::SIGTRAP)
+        );
+        assert_eq!(
+            stop_reason(tid(), HaltReason::Step, true, None),
+            MultiThreadStopReason::HwBreak(tid())
+        );
+        assert_eq!(
+            stop_reason(tid(), HaltReason::Multiple, true, None),
+            MultiThreadStopReason::HwBreak(tid())
+        );
+    }
+
+    #[test]
+    fn watchpoints() {
+        assert_eq!(
+            stop_reason(
+                tid(),
+                HaltReason::Watchpoint,
+                false,
+                Some((0x2000_0000, WatchKind::ReadWrite))
+            ),
+            MultiThreadStopReason::Watch {
+                tid: tid(),
+                kind: WatchKind::ReadWrite,
+                addr: 0x2000_0000
+            }
+        );
+        assert_eq!(
+            stop_reason(tid(), HaltReason::Watchpoint, false, None),
+            signal(Signal::SIGTRAP)
+        );
+    }
+
+    #[test]
+    fn other_halts() {
+        assert_eq!(
+            stop_reason(tid(), HaltReason::Request, false, None),
+            signal(Signal::SIGINT)
+        );
+        assert_eq!(
+            stop_reason(tid(), HaltReason::External, false, None),
+            signal(Signal::SIGINT)
+        );
+        assert_eq!(
+            stop_reason(tid(), HaltReason::Exception, false, None),
+            signal(Signal::SIGSEGV)
+        );
+        assert_eq!(
+            stop_reason(tid(), HaltReason::Unknown, false, None),
+            signal(Signal::SIGTRAP)
+        );
+    }
+}
@@ -0,0 +1,406 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Instant;
+
+/// Size after which no more packets are written to a traffic log
+const MAX_LOG_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Packets are shortened to this many characters in the log
+const MAX_PACKET_TEXT: usize = 256;
+
+/// Direction of a logged packet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Direction {
+    /// Sent by GDB
+    Inbound,
+    /// Sent to GDB
+    Outbound,
+}
+
+/// Log of the packets exchanged with GDB, for debugging the GDB server
+///
+/// Every packet is written on its own line with the time since the log was opened, its
+/// direction, its text with binary payloads elided, and a short description. Once the log
+/// reaches [MAX_LOG_SIZE], further packets are dropped.
+pub(crate) struct TrafficLog {
+    writer: Box<dyn Write + Send>,
+    start: Instant,
+    written: u64,
+    max_size: u64,
+}
+
+impl TrafficLog {
+    /// Log to `writer`
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Box::new(writer),
+            start: Instant::now(),
+            written: 0,
+            max_size: MAX_LOG_SIZE,
+        }
+    }
+
+    /// Log to the file at `path`
+    ///
+    /// An existing log is appended to, e.g. when GDB reconnects, and counts towards the size limit.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let written = file.metadata()?.len();
+
+        let mut log = Self::new(file);
+        log.written = written;
+
+        Ok(log)
+    }
+
+    /// Log a note which is not a packet, e.g. a new connection
+    pub fn note(&mut self, note: &str) {
+        self.line(format!("{note}\n"));
+    }
+
+    /// Log the body of a packet, the part between `$` and `#`
+    pub fn packet(&mut self, direction: Direction, body: &[u8]) {
+        let summary = match direction {
+            Direction::Inbound => describe_request(body),
+            Direction::Outbound => describe_reply(body),
+        };
+
+        self.entry(direction, &packet_text(body), &summary);
+    }
+
+    /// Log a GDB interrupt request, which is sent outside of a packet
+    pub fn interrupt(&mut self) {
+        self.entry(Direction::Inbound, "\\x03", "interrupt");
+    }
+
+    fn entry(&mut self, direction: Direction, text: &str, summary: &str) {
+        let arrow = match direction {
+            Direction::Inbound => "<-",
+            Direction::Outbound => "->",
+        };
+
+        self.line(format!(
+            "{:>10.3} {arrow} {text:<40} {summary}\n",
+            self.start.elapsed().as_secs_f64()
+        ));
+    }
+
+    fn line(&mut self, mut line: String) {
+        if self.written >= self.max_size {
+            return;
+        }
+
+        if self.written + line.len() as u64 >= self.max_size {
+            line = "Traffic log size limit reached, further packets are not logged\n".to_string();
+            self.written = self.max_size;
+        } else {
+            self.written += line.len() as u64;
+        }
+
+        // The log is only a debugging aid, so the connection is kept up if it can't be written
+        if let Err(e) = self
+            .writer
+            .write_all(line.as_bytes())
+            .and_then(|_| self.writer.flush())
+        {
+            tracing::warn!("Failed to write GDB traffic log, disabling it: {e}");
+            self.written = self.max_size;
+        }
+    }
+}
+
+/// Printable text of a packet body, with binary payloads replaced by their length
+fn packet_text(body: &[u8]) -> String {
+    // Binary memory and flash writes
+    let binary_prefix = if body.starts_with(b"X") {
+        body.iter().position(|&b| b == b':')
+    } else if let Some(args) = body.strip_prefix(b"vFlashWrite:") {
+        args.iter()
+            .position(|&b| b == b':')
+            .map(|pos| pos + b"vFlashWrite:".len())
+    } else {
+        None
+    };
+
+    let (text, payload) = match binary_prefix {
+        Some(pos) => body.split_at(pos + 1),
+        None => (body, &[][..]),
+    };
+
+    let mut text = text.escape_ascii().to_string();
+
+    if text.len() > MAX_PACKET_TEXT {
+        let mut end = MAX_PACKET_TEXT;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        text = format!("{}... ({} bytes)", &text[..end], body.len());
+    }
+
+    if !payload.is_empty() {
+        text.push_str(&format!("<{} bytes>", payload.len()));
+    }
+
+    text
+}
+
+/// Human readable description of a packet sent by GDB
+fn describe_request(body: &[u8]) -> String {
+    let text = String::from_utf8_lossy(body);
+    let mut chars = text.chars();
+    let Some(command) = chars.next() else {
+        return "empty packet".to_string();
+    };
+    let args = chars.as_str();
+
+    match command {
+        '?' => "query halt reason".to_string(),
+        'g' => "read registers".to_string(),
+        'G' => "write registers".to_string(),
+        'p' => format!("read register {}", hex_arg(args)),
+        'P' => format!(
+            "write register {}",
+            hex_arg(args.split('=').next().unwrap_or(""))
+        ),
+        'm' => describe_memory_access("read memory", args),
+        'M' | 'X' => describe_memory_access("write memory", args.split(':').next().unwrap_or("")),
+        'c' if args.is_empty() => "continue".to_string(),
+        'c' => format!("continue at {}", hex_arg(args)),
+        's' if args.is_empty() => "step".to_string(),
+        's' => format!("step at {}", hex_arg(args)),
+        'Z' | 'z' => describe_breakpoint(command == 'Z', args),
+        'H' => format!("select thread {args}"),
+        'T' => format!("query if thread {args} is alive"),
+        'D' => "detach".to_string(),
+        'k' => "kill".to_string(),
+        'R' | 'r' => "restart".to_string(),
+        'q' | 'Q' | 'v' => describe_query(&text),
+        _ => format!("unknown packet '{command}'"),
+    }
+}
+
+/// Description of an address and length, as used by the memory access packets
+fn describe_memory_access(action: &str, args: &str) -> String {
+    match args.split_once(',') {
+        Some((addr, len)) => format!("{action} {} len {}", hex_arg(addr), hex_len(len)),
+        None => action.to_string(),
+    }
+}
+
+/// Description of a `Z` or `z` packet
+fn describe_breakpoint(insert: bool, args: &str) -> String {
+    let action = if insert { "insert" } else { "remove" };
+
+    let mut parts = args.split(',');
+    let kind = match parts.next() {
+        Some("0") => "software breakpoint",
+        Some("1") => "hardware breakpoint",
+        Some("2") => "write watchpoint",
+        Some("3") => "read watchpoint",
+        Some("4") => "access watchpoint",
+        _ => "breakpoint",
+    };
+
+    match parts.next() {
+        Some(addr) => format!("{action} {kind} at {}", hex_arg(addr)),
+        None => format!("{action} {kind}"),
+    }
+}
+
+/// Description of the general query and `v` packets, which are named instead of using a single letter
+fn describe_query(text: &str) -> String {
+    let name = text.split(&[':', ';', ','][..]).next().unwrap_or(text);
+
+    match name {
+        "qSupported" => "negotiate features".to_string(),
+        "QStartNoAckMode" => "start no-ack mode".to_string(),
+        "qAttached" => "query if attached to an existing process".to_string(),
+        "qC" => "query current thread".to_string(),
+        "qfThreadInfo" | "qsThreadInfo" => "list threads".to_string(),
+        "qOffsets" => "query section offsets".to_string(),
+        "qSymbol" => "symbol lookup".to_string(),
+        "qCRC" => describe_memory_access("compute CRC of", text.trim_start_matches("qCRC:")),
+        "qTStatus" => "query trace status".to_string(),
+        "qRcmd" => {
+            let command = text
+                .split_once(',')
+                .and_then(|(_, hex)| hex_decode(hex))
+                .unwrap_or_default();
+
+            format!("monitor {command}")
+        }
+        "qXfer" => {
+            let mut parts = text.split(':').skip(1);
+            match (parts.next(), parts.next()) {
+                (Some("features"), Some("read")) => "read target description".to_string(),
+                (Some("memory-map"), Some("read")) => "read memory map".to_string(),
+                (Some(object), Some(action)) => format!("{action} {object}"),
+                _ => "transfer".to_string(),
+            }
+        }
+        "vCont?" => "query supported resume actions".to_string(),
+        "vCont" => format!("resume ({})", text.trim_start_matches("vCont;")),
+        "vAttach" => format!("attach to process {}", text.trim_start_matches("vAttach;")),
+        "vKill" => "kill".to_string(),
+        "vRun" => "run".to_string(),
+        "vMustReplyEmpty" => "check handling of unknown packets".to_string(),
+        "vFlashErase" => describe_memory_access(
+            "erase flash",
+            text.strip_prefix("vFlashErase:").unwrap_or(""),
+        ),
+        "vFlashWrite" => {
+            let addr = text
+                .strip_prefix("vFlashWrite:")
+                .and_then(|args| args.split(':').next())
+                .unwrap_or("");
+
+            format!("write flash at {}", hex_arg(addr))
+        }
+        "vFlashDone" => "finish flash programming".to_string(),
+        name => format!("query {name}"),
+    }
+}
+
+/// Human readable description of a reply sent to GDB
+fn describe_reply(body: &[u8]) -> String {
+    match body {
+        b"" => "not supported".to_string(),
+        b"OK" => "ok".to_string(),
+        [b'E', code @ ..] if code.len() == 2 => {
+            format!("error {}", String::from_utf8_lossy(code))
+        }
+        [b'S' | b'T', signal @ ..] if signal.len() >= 2 => {
+            format!(
+                "stopped, signal 0x{}",
+                String::from_utf8_lossy(&signal[..2])
+            )
+        }
+        [b'W', ..] => "exited".to_string(),
+        [b'O', output @ ..] if output.is_empty() => "keep-alive".to_string(),
+        [b'O', output @ ..] => format!("console output, {} bytes", output.len() / 2),
+        [b'F', ..] => "file I/O request".to_string(),
+        _ => format!("reply, {} bytes", body.len()),
+    }
+}
+
+/// Format a hexadecimal argument as `0x` prefixed number
+fn hex_arg(arg: &str) -> String {
+    match u64::from_str_radix(arg, 16) {
+        Ok(value) => format!("{value:#x}"),
+        Err(_) => arg.to_string(),
+    }
+}
+
+/// Format a hexadecimal length as decimal number
+fn hex_len(arg: &str) -> String {
+    match u64::from_str_radix(arg, 16) {
+        Ok(value) => value.to_string(),
+        Err(_) => arg.to_string(),
+    }
+}
+
+/// Decode a hex encoded string, as used by `qRcmd`
+fn hex_decode(hex: &str) -> Option<String> {
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    /// A writer which can be inspected while the log owns it
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn requests() {
+        assert_eq!(
+            describe_request(b"m20000000,40"),
+            "read memory 0x20000000 len 64"
+        );
+        assert_eq!(
+            describe_request(b"X20000000,4:\x01\x02\x03\x04"),
+            "write memory 0x20000000 len 4"
+        );
+        assert_eq!(describe_request(b"c"), "continue");
+        assert_eq!(describe_request(b"p19"), "read register 0x19");
+        assert_eq!(
+            describe_request(b"Z1,8000100,2"),
+            "insert hardware breakpoint at 0x8000100"
+        );
+        assert_eq!(
+            describe_request(b"qXfer:features:read:target.xml:0,fff"),
+            "read target description"
+        );
+        assert_eq!(describe_request(b"qRcmd,7265736574"), "monitor reset");
+        assert_eq!(describe_request(b"vCont;c"), "resume (c)");
+        assert_eq!(describe_request(b"qFoo"), "query qFoo");
+    }
+
+    #[test]
+    fn replies() {
+        assert_eq!(describe_reply(b""), "not supported");
+        assert_eq!(describe_reply(b"OK"), "ok");
+        assert_eq!(describe_reply(b"E14"), "error 14");
+        assert_eq!(describe_reply(b"T05thread:1;"), "stopped, signal 0x05");
+        assert_eq!(describe_reply(b"O"), "keep-alive");
+        assert_eq!(describe_reply(b"00112233"), "reply, 8 bytes");
+    }
+
+    #[test]
+    fn binary_payload_is_elided() {
+        assert_eq!(
+            packet_text(b"X20000000,4:\x01\x02\x03\x04"),
+            "X20000000,4:<4 bytes>"
+        );
+        assert_eq!(
+            packet_text(b"vFlashWrite:8000000:\x00\xff"),
+            "vFlashWrite:8000000:<2 bytes>"
+        );
+        assert_eq!(packet_text(b"m0,4"), "m0,4");
+    }
+
+    #[test]
+    fn long_packet_is_shortened() {
+        let text = packet_text(&[b'a'; 1000]);
+
+        assert!(text.ends_with("... (1000 bytes)"));
+        assert!(text.len() < 300);
+    }
+
+    #[test]
+    fn log_is_size_capped() {
+        let buffer = SharedBuffer::default();
+        let mut log = TrafficLog::new(buffer.clone());
+        log.max_size = 200;
+
+        for _ in 0..10 {
+            log.packet(Direction::Inbound, b"g");
+        }
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+
+        assert!(output.lines().next().unwrap().contains(" <- g "));
+        assert!(output.ends_with("further packets are not logged\n"));
+        assert!(output.len() < 300);
+    }
+}
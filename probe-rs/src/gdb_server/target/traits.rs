@@ -0,0 +1,39 @@
+use crate::Error;
+
+use gdbstub::target::TargetError;
+
+/// Extension trait for converting probe-rs results into gdbstub target results
+pub(crate) trait GdbErrorExt<T> {
+    /// Convert the result into a non-fatal [TargetError], which is reported to GDB as an error packet
+    fn into_target_result_non_fatal(self) -> Result<T, TargetError<Error>>;
+}
+
+impl<T, E> GdbErrorExt<T> for Result<T, E>
+where
+    E: Into<Error>,
+{
+    fn into_target_result_non_fatal(self) -> Result<T, TargetError<Error>> {
+        self.map_err(|e| {
+            let e: Error = e.into();
+
+            tracing::debug!("Returning non-fatal error to GDB: {:?}", e);
+
+            TargetError::NonFatal
+        })
+    }
+}
+
+/// Extension trait for converting arbitrary results into probe-rs results
+pub(crate) trait ProbeRsErrorExt<T> {
+    /// Convert the error into an [Error]
+    fn into_error(self) -> Result<T, Error>;
+}
+
+impl<T, E> ProbeRsErrorExt<T> for Result<T, E>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    fn into_error(self) -> Result<T, Error> {
+        self.map_err(|e| anyhow::Error::from(e).into())
+    }
+}
@@ -0,0 +1,153 @@
+use crate::config::MemoryRegion;
+use crate::gdb_server::{GdbMemoryKind, GdbMemoryMap};
+use crate::{Error, Session};
+
+use std::fmt::Write;
+use std::ops::Range;
+
+/// Copy the `offset..offset + length` window of `data` into `buf`, as used by the `qXfer` family of packets
+///
+/// Returns the number of bytes copied, which is zero once `offset` is past the end of `data`.
+pub(crate) fn copy_range_to_buf(data: &[u8], offset: u64, length: usize, buf: &mut [u8]) -> usize {
+    let offset = match usize::try_from(offset) {
+        Ok(offset) if offset < data.len() => offset,
+        _ => return 0,
+    };
+
+    let len = length.min(buf.len()).min(data.len() - offset);
+
+    buf[..len].copy_from_slice(&data[offset..offset + len]);
+
+    len
+}
+
+/// Build the GDB memory map XML for the memory regions visible to the given core
+pub(crate) fn gdb_memory_map(session: &Session, core_id: usize) -> Result<String, Error> {
+    let target = session.target();
+    let core_name = &target.cores[core_id].name;
+
+    let regions = target
+        .memory_map
+        .iter()
+        .filter(|region| region.cores().contains(core_name))
+        .map(|region| match region {
+            MemoryRegion::Ram(region) => ("ram", &region.range),
+            MemoryRegion::Generic(region) => ("ram", &region.range),
+            MemoryRegion::Nvm(region) => ("rom", &region.range),
+        });
+
+    memory_map_xml(regions)
+}
+
+/// Build the GDB memory map XML for a memory map supplied by the user
+pub(crate) fn user_memory_map(memory_map: &GdbMemoryMap) -> Result<String, Error> {
+    let regions = memory_map.regions().iter().map(|region| match region.kind {
+        GdbMemoryKind::Ram => ("ram", &region.range),
+        GdbMemoryKind::Flash => ("rom", &region.range),
+    });
+
+    memory_map_xml(regions)
+}
+
+/// Build the GDB memory map XML from the memory type and address range of each region
+fn memory_map_xml<'a>(
+    regions: impl Iterator<Item = (&'static str, &'a Range<u64>)>,
+) -> Result<String, Error> {
+    let mut xml = String::from(
+        r#"<?xml version="1.0"?>
+<!DOCTYPE memory-map PUBLIC "+//IDN gnu.org//DTD GDB Memory Map V1.0//EN" "http://sourceware.org/gdb/gdb-memory-map.dtd">
+<memory-map>
+"#,
+    );
+
+    for (kind, range) in regions {
+        writeln!(
+            xml,
+            r#"<memory type="{}" start="{:#x}" length="{:#x}"/>"#,
+            kind,
+            range.start,
+            range.end - range.start
+        )
+        .map_err(|e| anyhow::anyhow!(e))?;
+    }
+
+    xml.push_str("</memory-map>");
+
+    Ok(xml)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{copy_range_to_buf, user_memory_map};
+    use crate::gdb_server::{GdbMemoryMap, GdbMemoryRegion};
+
+    /// Read a whole document the way GDB does, one window at a time, until an empty reply (`l`) is returned
+    fn read_document(data: &[u8], window: usize) -> (Vec<u8>, usize) {
+        let mut document = Vec::new();
+        let mut requests = 0;
+
+        loop {
+            let mut buf = vec![0u8; window];
+            let len = copy_range_to_buf(data, document.len() as u64, window, &mut buf);
+            requests += 1;
+
+            if len == 0 {
+                return (document, requests);
+            }
+
+            document.extend_from_slice(&buf[..len]);
+        }
+    }
+
+    #[test]
+    fn document_below_window_size() {
+        let data = b"<memory-map/>";
+
+        assert_eq!(read_document(data, 64), (data.to_vec(), 2));
+    }
+
+    #[test]
+    fn document_at_window_size() {
+        let data = [0x55u8; 64];
+
+        assert_eq!(read_document(&data, 64), (data.to_vec(), 2));
+    }
+
+    #[test]
+    fn document_above_window_size() {
+        let data = (0..=255u8).cycle().take(1000).collect::<Vec<_>>();
+
+        assert_eq!(read_document(&data, 64), (data.clone(), 17));
+    }
+
+    #[test]
+    fn window_is_limited_by_buffer() {
+        let data = [1u8; 32];
+        let mut buf = [0u8; 8];
+
+        assert_eq!(copy_range_to_buf(&data, 30, 16, &mut buf), 2);
+        assert_eq!(copy_range_to_buf(&data, 0, 16, &mut buf), 8);
+        assert_eq!(copy_range_to_buf(&data, 32, 16, &mut buf), 0);
+        assert_eq!(copy_range_to_buf(&data, u64::MAX, 16, &mut buf), 0);
+    }
+
+    #[test]
+    fn user_memory_map_xml() {
+        let memory_map = GdbMemoryMap::new(vec![
+            "ram:0x20000000:0x8000".parse::<GdbMemoryRegion>().unwrap(),
+            "flash:0x08000000:0x20000"
+                .parse::<GdbMemoryRegion>()
+                .unwrap(),
+        ])
+        .unwrap();
+
+        let xml = user_memory_map(&memory_map).unwrap();
+
+        assert!(xml.ends_with(
+            "<memory-map>\n\
+             <memory type=\"rom\" start=\"0x8000000\" length=\"0x20000\"/>\n\
+             <memory type=\"ram\" start=\"0x20000000\" length=\"0x8000\"/>\n\
+             </memory-map>"
+        ));
+    }
+}
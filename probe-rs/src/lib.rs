@@ -92,20 +92,21 @@ mod session;
 #[cfg(test)]
 mod test;
 
-pub use crate::config::{CoreType, InstructionSet, Target};
+pub use crate::config::{AccessWidth, CoreType, InstructionSet, Target};
 pub use crate::core::{
     exception_handler_for_core, Architecture, BreakpointCause, Core, CoreDump, CoreDumpError,
-    CoreInformation, CoreInterface, CoreRegister, CoreRegisters, CoreState, CoreStatus, HaltReason,
-    MemoryMappedRegister, RegisterId, RegisterRole, RegisterValue, SemihostingCommand,
-    SpecificCoreState, VectorCatchCondition,
+    CoreInformation, CoreInterface, CoreRegister, CoreRegisters, CoreSnapshot, CoreState,
+    CoreStatus, HaltReason, MemoryMappedRegister, RegisterId, RegisterRole, RegisterValue,
+    SemihostingCommand, SpecificCoreState, VectorCatchCondition, WatchpointKind,
 };
 pub use crate::error::Error;
-pub use crate::memory::MemoryInterface;
+pub use crate::memory::{MemoryInterface, MemoryTransfer, ReadIter, TransferQueue};
 pub use crate::probe::{
-    fake_probe::FakeProbe, list::Lister, AttachMethod, DebugProbe, DebugProbeError, DebugProbeInfo,
-    DebugProbeSelector, DebugProbeType, Probe, ProbeCreationError, WireProtocol,
+    fake_probe::FakeProbe, list::Lister, AttachMethod, AttachOptions, DebugProbe, DebugProbeError,
+    DebugProbeInfo, DebugProbeSelector, DebugProbeType, Probe, ProbeCreationError, ProbeDetails,
+    WireProtocol,
 };
-pub use crate::session::{Permissions, Session};
+pub use crate::session::{DetachMode, Permissions, Session};
 
 // Exports only used in tests
 #[cfg(feature = "test")]
@@ -1,4 +1,4 @@
-use crate::error::Error;
+use crate::{config::AccessWidth, error::Error};
 
 use anyhow::{anyhow, Result};
 use scroll::Pread;
@@ -122,6 +122,16 @@ pub trait MemoryInterface {
     /// Returns [`Error::MemoryNotAligned`] if this does not hold true.
     fn write_word_32(&mut self, address: u64, data: u32) -> Result<(), Error>;
 
+    /// Write a 16bit word at `address`.
+    ///
+    /// The address where the write should be performed at has to be halfword aligned.
+    /// Returns [`Error::MemoryNotAligned`] if this does not hold true.
+    ///
+    /// Not all interfaces support 16 bit accesses, these return [`Error::NotImplemented`].
+    fn write_word_16(&mut self, _address: u64, _data: u16) -> Result<(), Error> {
+        Err(Error::NotImplemented("16-bit memory access"))
+    }
+
     /// Write an 8bit word at `address`.
     fn write_word_8(&mut self, address: u64, data: u8) -> Result<(), Error>;
 
@@ -233,6 +243,87 @@ pub trait MemoryInterface {
         Ok(())
     }
 
+    /// Write a block of bytes to `address`, using only accesses of the given width.
+    ///
+    /// In contrast to [`MemoryInterface::write`], accesses are never combined or split,
+    /// which is required for device memory like peripheral registers. The accesses are
+    /// done in ascending address order.
+    ///
+    /// Both `address` and the length of `data` have to be a multiple of the access width.
+    /// Returns [`Error::MemoryNotAligned`] if this does not hold true.
+    fn write_block_with_width(
+        &mut self,
+        address: u64,
+        data: &[u8],
+        width: AccessWidth,
+    ) -> Result<(), Error> {
+        let size = width.bytes();
+
+        if address % size as u64 != 0 || data.len() % size != 0 {
+            return Err(Error::MemoryNotAligned {
+                address,
+                alignment: size,
+            });
+        }
+
+        for (offset, chunk) in (0..).step_by(size).zip(data.chunks_exact(size)) {
+            let address = address + offset;
+
+            match width {
+                AccessWidth::U8 => self.write_word_8(address, chunk[0])?,
+                AccessWidth::U16 => {
+                    self.write_word_16(address, u16::from_le_bytes([chunk[0], chunk[1]]))?
+                }
+                AccessWidth::U32 => self.write_word_32(
+                    address,
+                    u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]),
+                )?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read `len` bytes starting at `address`, in chunks of at most `chunk_size` bytes.
+    ///
+    /// The chunks are read lazily with [`MemoryInterface::read`] and yielded together with
+    /// their start address, so large regions can be scanned without buffering them completely.
+    /// After a failed read, the error is yielded and the iteration ends.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    fn read_iter(&mut self, address: u64, len: u64, chunk_size: usize) -> ReadIter<'_, Self>
+    where
+        Self: Sized,
+    {
+        assert!(chunk_size > 0, "The chunk size must not be zero");
+
+        ReadIter {
+            memory: self,
+            address,
+            remaining: len,
+            chunk_size,
+        }
+    }
+
+    /// Execute a batch of 32 bit reads and writes, in order.
+    ///
+    /// The values which are read are stored in the `value` field of the transfers.
+    /// Interfaces which support it send all transfers to the probe at once,
+    /// see also [`TransferQueue`].
+    fn transfer_batch_32(&mut self, transfers: &mut [MemoryTransfer]) -> Result<(), Error> {
+        for transfer in transfers.iter_mut() {
+            if transfer.write {
+                self.write_word_32(transfer.address, transfer.value)?;
+            } else {
+                transfer.value = self.read_word_32(transfer.address)?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Returns whether the current platform supports native 8bit transfers.
     fn supports_8bit_transfers(&self) -> Result<bool, Error>;
 
@@ -289,6 +380,10 @@ where
         (*self).write_word_32(address, data)
     }
 
+    fn write_word_16(&mut self, address: u64, data: u16) -> Result<(), Error> {
+        (*self).write_word_16(address, data)
+    }
+
     fn write_word_8(&mut self, address: u64, data: u8) -> Result<(), Error> {
         (*self).write_word_8(address, data)
     }
@@ -309,6 +404,19 @@ where
         (*self).write(address, data)
     }
 
+    fn write_block_with_width(
+        &mut self,
+        address: u64,
+        data: &[u8],
+        width: AccessWidth,
+    ) -> Result<(), Error> {
+        (*self).write_block_with_width(address, data, width)
+    }
+
+    fn transfer_batch_32(&mut self, transfers: &mut [MemoryTransfer]) -> Result<(), Error> {
+        (*self).transfer_batch_32(transfers)
+    }
+
     fn supports_8bit_transfers(&self) -> Result<bool, Error> {
         MemoryInterface::supports_8bit_transfers(*self)
     }
@@ -318,6 +426,137 @@ where
     }
 }
 
+/// A single 32 bit memory access in a batch, see [`MemoryInterface::transfer_batch_32`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryTransfer {
+    /// The address of the word, has to be 4 byte aligned.
+    pub address: u64,
+    /// `true` if the word is written, `false` if it is read.
+    pub write: bool,
+    /// The value to write, or the value which was read once the batch is executed.
+    pub value: u32,
+}
+
+impl MemoryTransfer {
+    /// Read the word at `address`.
+    pub fn read(address: u64) -> Self {
+        Self {
+            address,
+            write: false,
+            value: 0,
+        }
+    }
+
+    /// Write `value` to the word at `address`.
+    pub fn write(address: u64, value: u32) -> Self {
+        Self {
+            address,
+            write: true,
+            value,
+        }
+    }
+}
+
+/// Collects independent 32 bit reads and writes, to execute them with a single probe command.
+///
+/// Each access to target memory usually needs a round trip to the probe. Queued transfers are
+/// sent to the probe as one batch by [`TransferQueue::execute`], which returns the values read
+/// in the order the reads were queued.
+///
+/// ```no_run
+/// # use probe_rs::{MemoryInterface, TransferQueue};
+/// # fn example(core: &mut impl MemoryInterface) -> Result<(), probe_rs::Error> {
+/// let mut queue = TransferQueue::new();
+/// queue.write_32(0x2000_0000, 0x1234_5678);
+/// queue.read_32(0x2000_0000);
+/// queue.read_32(0x2000_0004);
+///
+/// let values = queue.execute(core)?;
+/// assert_eq!(values.len(), 2);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TransferQueue {
+    transfers: Vec<MemoryTransfer>,
+}
+
+impl TransferQueue {
+    /// Create an empty queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a read of the 32 bit word at `address`.
+    pub fn read_32(&mut self, address: u64) {
+        self.transfers.push(MemoryTransfer::read(address));
+    }
+
+    /// Queue a write of `value` to the 32 bit word at `address`.
+    pub fn write_32(&mut self, address: u64, value: u32) {
+        self.transfers.push(MemoryTransfer::write(address, value));
+    }
+
+    /// Number of queued transfers.
+    pub fn len(&self) -> usize {
+        self.transfers.len()
+    }
+
+    /// Returns `true` if no transfers are queued.
+    pub fn is_empty(&self) -> bool {
+        self.transfers.is_empty()
+    }
+
+    /// Execute all queued transfers on `memory`, and return the values read, in order.
+    ///
+    /// The queue is empty afterwards, also if a transfer failed.
+    pub fn execute(&mut self, memory: &mut impl MemoryInterface) -> Result<Vec<u32>, Error> {
+        let mut transfers = std::mem::take(&mut self.transfers);
+
+        memory.transfer_batch_32(&mut transfers)?;
+
+        Ok(transfers
+            .iter()
+            .filter(|transfer| !transfer.write)
+            .map(|transfer| transfer.value)
+            .collect())
+    }
+}
+
+/// Iterator over the chunks of a memory region, see [`MemoryInterface::read_iter`].
+#[derive(Debug)]
+pub struct ReadIter<'memory, M> {
+    memory: &'memory mut M,
+    address: u64,
+    remaining: u64,
+    chunk_size: usize,
+}
+
+impl<M: MemoryInterface> Iterator for ReadIter<'_, M> {
+    type Item = Result<(u64, Vec<u8>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let address = self.address;
+        let len = self.remaining.min(self.chunk_size as u64) as usize;
+
+        let mut chunk = vec![0; len];
+
+        if let Err(error) = self.memory.read(address, &mut chunk) {
+            self.remaining = 0;
+            return Some(Err(error));
+        }
+
+        self.address += len as u64;
+        self.remaining -= len as u64;
+
+        Some(Ok((address, chunk)))
+    }
+}
+
 // Helper functions to validate address space constraints
 
 /// Validate that an input address is valid for 32-bit only systems
@@ -328,3 +567,45 @@ pub(crate) fn valid_32bit_address(address: u64) -> Result<u32, Error> {
 
     Ok(address)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{MemoryInterface, TransferQueue};
+    use crate::test::MockMemory;
+
+    #[test]
+    fn read_iter_chunks() {
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, (0..12).collect());
+
+        let chunks = memory
+            .read_iter(0x2000_0000, 10, 4)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(
+            chunks,
+            vec![
+                (0x2000_0000, vec![0, 1, 2, 3]),
+                (0x2000_0004, vec![4, 5, 6, 7]),
+                (0x2000_0008, vec![8, 9]),
+            ]
+        );
+    }
+
+    #[test]
+    fn transfer_queue_reads_in_order() {
+        let mut memory = MockMemory::new();
+        memory.add_range(0x2000_0000, (0..8).collect());
+
+        let mut queue = TransferQueue::new();
+        queue.read_32(0x2000_0004);
+        queue.read_32(0x2000_0000);
+        assert_eq!(queue.len(), 2);
+
+        let values = queue.execute(&mut memory).unwrap();
+
+        assert_eq!(values, vec![0x0706_0504, 0x0302_0100]);
+        assert!(queue.is_empty());
+    }
+}
@@ -11,7 +11,7 @@ pub(crate) mod list;
 pub(crate) mod stlink;
 pub(crate) mod wlink;
 
-use crate::architecture::arm::ArmError;
+use crate::architecture::arm::{ArmError, DpAddress};
 use crate::architecture::riscv::communication_interface::RiscvError;
 use crate::architecture::xtensa::communication_interface::XtensaCommunicationInterface;
 use crate::error::Error;
@@ -34,6 +34,7 @@ use crate::{Lister, Session};
 use probe_rs_target::ScanChainElement;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use std::{convert::TryFrom, fmt};
 
 /// Used to log warnings when the measured target voltage is
@@ -194,11 +195,50 @@ pub enum ProbeCreationError {
     /// Something else happened.
     #[error("{0}")]
     Other(&'static str),
+    /// None of the attached probes matches the selector.
+    #[error(
+        "No probe matches '{selector}'. {}",
+        format_available_probes(available)
+    )]
+    NoMatchingProbe {
+        /// The selector given by the user.
+        selector: String,
+        /// All probes which are attached.
+        available: Vec<DebugProbeInfo>,
+    },
+    /// No probe was selected, but more than one probe is attached.
+    #[error(
+        "Multiple probes were found, please select one. {}",
+        format_available_probes(available)
+    )]
+    MultipleProbesFound {
+        /// All probes which are attached.
+        available: Vec<DebugProbeInfo>,
+    },
+}
+
+fn format_available_probes(probes: &[DebugProbeInfo]) -> String {
+    if probes.is_empty() {
+        return "No probes were found.".to_string();
+    }
+
+    let mut list = String::from("Available probes:");
+    for (index, probe) in probes.iter().enumerate() {
+        list.push_str(&format!("\n  [{index}]: {probe:?}"));
+    }
+
+    list
 }
 
 /// The Probe struct is a generic wrapper over the different
 /// probes supported.
 ///
+/// # Thread safety
+///
+/// A `Probe` owns the USB handle of its probe exclusively, and shares no mutable state with
+/// other probes. It is [`Send`], so multiple probes can be opened and used in parallel, each
+/// from its own thread. Listing and opening probes is serialized within the process.
+///
 /// # Examples
 ///
 /// ## Open the first probe found
@@ -236,6 +276,20 @@ impl Probe {
         }
     }
 
+    /// List all attached debug probes which are supported by one of the built-in drivers.
+    ///
+    /// This is a shorthand for [`Lister::list_all`] with the default [`Lister`].
+    pub fn list_all() -> Vec<DebugProbeInfo> {
+        Lister::new().list_all()
+    }
+
+    /// Open the attached debug probe with the given USB serial number.
+    ///
+    /// This is a shorthand for [`Lister::open_by_serial`] with the default [`Lister`].
+    pub fn open_by_serial(serial_number: &str) -> Result<Self, DebugProbeError> {
+        Lister::new().open_by_serial(serial_number)
+    }
+
     /// Same as [`Probe::new`] but without automatic boxing in case you already have a box.
     pub fn from_specific_probe(probe: Box<dyn DebugProbe>) -> Self {
         Probe {
@@ -259,7 +313,25 @@ impl Probe {
         target: impl Into<TargetSelector>,
         permissions: Permissions,
     ) -> Result<Session, Error> {
-        Session::new(self, target.into(), AttachMethod::Normal, permissions)
+        Session::new(self, target.into(), AttachMethod::Normal, permissions, None)
+    }
+
+    /// Attach to a running target without resetting or halting it.
+    ///
+    /// Use this to inspect a device in the field without disturbing the program running on it.
+    /// See [`AttachMethod::HotAttach`] for what attaching changes on the target.
+    pub fn hot_attach(
+        self,
+        target: impl Into<TargetSelector>,
+        permissions: Permissions,
+    ) -> Result<Session, Error> {
+        Session::new(
+            self,
+            target.into(),
+            AttachMethod::HotAttach,
+            permissions,
+            None,
+        )
     }
 
     /// Attach to a target without knowing what target you have at hand.
@@ -297,14 +369,58 @@ impl Probe {
         permissions: Permissions,
     ) -> Result<Session, Error> {
         // The session will de-assert reset after connecting to the debug interface.
-        Session::new(self, target.into(), AttachMethod::UnderReset, permissions).map_err(|e| {
-            if matches!(e, Error::Arm(ArmError::Timeout) | Error::Riscv(RiscvError::Timeout)) {
-                Error::Other(
-                anyhow::anyhow!("Timeout while attaching to target under reset. This can happen if the target is not responding to the reset sequence. Ensure the chip's reset pin is connected, or try attaching without reset (`connectUnderReset = false` for DAP Clients, or remove `connect-under-reset` option from CLI options.)."))
-            } else {
-                e
+        Session::new(
+            self,
+            target.into(),
+            AttachMethod::UnderReset,
+            permissions,
+            None,
+        )
+        .map_err(under_reset_error)
+    }
+
+    /// Attach to the chip, with the given [`AttachOptions`].
+    ///
+    /// Unlike [`Probe::attach`] and [`Probe::attach_under_reset`], this leaves all cores of the
+    /// target in a defined state: halted if [`AttachOptions::halt_on_attach`] is set, and running
+    /// otherwise.
+    pub fn attach_with_options(
+        self,
+        target: impl Into<TargetSelector>,
+        permissions: Permissions,
+        options: AttachOptions,
+    ) -> Result<Session, Error> {
+        let mut session = if options.under_reset {
+            Session::new(
+                self,
+                target.into(),
+                AttachMethod::UnderReset,
+                permissions,
+                options.dp,
+            )
+            .map_err(under_reset_error)?
+        } else {
+            Session::new(
+                self,
+                target.into(),
+                AttachMethod::Normal,
+                permissions,
+                options.dp,
+            )?
+        };
+
+        for (core_id, _) in session.list_cores() {
+            let mut core = session.core(core_id)?;
+
+            if options.halt_on_attach {
+                core.halt(Duration::from_millis(100))?;
+            } else if core.core_halted()? {
+                // Attaching under reset catches the core at the reset vector
+                core.run()?;
             }
-        })
+        }
+
+        Ok(session)
     }
 
     /// Selects the transport protocol to be used by the debug probe.
@@ -353,6 +469,68 @@ impl Probe {
         self.inner.target_reset_deassert()
     }
 
+    /// Asserts the reset of the target for `duration`, and deasserts it again.
+    /// This is always the hard reset which means the reset wire has to be connected to work.
+    ///
+    /// This is not supported on all probes, see [`Probe::has_reset_pin`].
+    pub fn target_reset_pulse(&mut self, duration: Duration) -> Result<(), DebugProbeError> {
+        self.target_reset_assert()?;
+        std::thread::sleep(duration);
+        self.target_reset_deassert()
+    }
+
+    /// Check if the probe can drive the reset pin of the target.
+    ///
+    /// If not, [`Probe::target_reset_assert`] and [`Probe::target_reset_deassert`] return an
+    /// error, and attaching under reset is not possible.
+    pub fn has_reset_pin(&self) -> bool {
+        self.inner.has_reset_pin()
+    }
+
+    /// Clock an arbitrary bit sequence onto SWDIO/TMS, corresponding to DAP_SWJ_Sequence.
+    ///
+    /// The first `bit_count` bits of `data` are sent, starting with the least significant bit
+    /// of the first byte. This is used for patterns outside of the normal SWD framing, e.g. to
+    /// switch protocols, wake a target from the dormant state or for vendor-specific unlock
+    /// sequences.
+    ///
+    /// This is only supported by probes which give raw access to the DAP, other probes
+    /// return [`DebugProbeError::CommandNotSupportedByProbe`].
+    pub fn swj_sequence(&mut self, bit_count: usize, data: &[u8]) -> Result<(), DebugProbeError> {
+        if bit_count > data.len() * 8 {
+            return Err(DebugProbeError::Other(anyhow::anyhow!(
+                "SWJ sequence of {bit_count} bits needs {} bytes of data, but only {} were given",
+                (bit_count + 7) / 8,
+                data.len()
+            )));
+        }
+
+        let probe = self
+            .inner
+            .try_as_dap_probe()
+            .ok_or(DebugProbeError::CommandNotSupportedByProbe("swj_sequence"))?;
+
+        // The probes accept at most 64 bits at once.
+        for (index, chunk) in data[..(bit_count + 7) / 8].chunks(8).enumerate() {
+            let mut bits = [0u8; 8];
+            bits[..chunk.len()].copy_from_slice(chunk);
+
+            let bit_len = (bit_count - index * 64).min(64);
+
+            probe.swj_sequence(bit_len as u8, u64::from_le_bytes(bits))?;
+        }
+
+        Ok(())
+    }
+
+    /// Perform an SWD line reset, i.e. more than 50 clock cycles with SWDIO high followed by
+    /// idle cycles.
+    ///
+    /// This is not supported on all probes, see [`Probe::swj_sequence`].
+    pub fn swd_line_reset(&mut self) -> Result<(), DebugProbeError> {
+        self.swj_sequence(SWD_LINE_RESET_BITS, &SWD_LINE_RESET.to_le_bytes())
+    }
+
     /// Configure protocol speed to use in kHz
     pub fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
         if !self.attached {
@@ -385,6 +563,23 @@ impl Probe {
         self.inner.speed_khz()
     }
 
+    /// Check whether [`Probe::speed_khz`] is only an upper bound for the speed, see
+    /// [`DebugProbe::speed_is_approximate`].
+    pub fn speed_is_approximate(&self) -> bool {
+        self.inner.speed_is_approximate()
+    }
+
+    /// Configure how often a transfer is retried after a WAIT or FAULT response.
+    ///
+    /// See [`DebugProbe::set_transfer_retries`] for more information.
+    pub fn set_transfer_retries(
+        &mut self,
+        wait_retries: usize,
+        fault_retries: usize,
+    ) -> Result<(), DebugProbeError> {
+        self.inner.set_transfer_retries(wait_retries, fault_retries)
+    }
+
     /// Check if the probe has an interface to
     /// debug Xtensa chips.
     pub fn has_xtensa_interface(&self) -> bool {
@@ -480,8 +675,24 @@ impl Probe {
     pub fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
         self.inner.get_target_voltage()
     }
+
+    /// Get details about the probe, like its firmware version and capabilities.
+    ///
+    /// This is useful to report which probe and firmware is used, e.g. in bug reports.
+    pub fn info(&mut self) -> ProbeDetails {
+        self.inner.details()
+    }
 }
 
+/// Length of an SWD line reset including the idle cycles, see [`SWD_LINE_RESET`].
+pub(crate) const SWD_LINE_RESET_BITS: usize = 54;
+
+/// SWD line reset: 51 cycles with SWDIO high, followed by 3 idle cycles.
+pub(crate) const SWD_LINE_RESET: u64 = 0x0007_FFFF_FFFF_FFFF;
+
+// Probes are moved into the threads using them.
+static_assertions::assert_impl_all!(Probe: Send);
+
 /// An abstraction over general debug probe functionality.
 ///
 /// This trait has to be implemented by ever debug probe driver.
@@ -518,6 +729,31 @@ pub trait DebugProbe: Send + fmt::Debug {
     ///
     fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError>;
 
+    /// Check whether [`DebugProbe::speed_khz`] is only an upper bound for the speed.
+    ///
+    /// This is the case for probes which don't report the speed they actually use, and
+    /// whose clock divider is unknown.
+    fn speed_is_approximate(&self) -> bool {
+        false
+    }
+
+    /// Configure how often a transfer is retried after a WAIT or FAULT response from the target.
+    ///
+    /// WAIT responses are retried with an increasing number of idle cycles in between.
+    /// On a FAULT response caused by a protocol error, the sticky error flags are cleared
+    /// before retrying. More retries help with unreliable connections, e.g. long wires.
+    ///
+    /// Not all probes support this, and some probes handle retries in their firmware.
+    fn set_transfer_retries(
+        &mut self,
+        _wait_retries: usize,
+        _fault_retries: usize,
+    ) -> Result<(), DebugProbeError> {
+        Err(DebugProbeError::CommandNotSupportedByProbe(
+            "set_transfer_retries",
+        ))
+    }
+
     /// Set the JTAG scan chain information for the target under debug.
     ///
     /// This allows the probe to know which TAPs are in the scan chain and their
@@ -558,6 +794,14 @@ pub trait DebugProbe: Send + fmt::Debug {
     /// This should deassert the reset pin of the target via debug probe.
     fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError>;
 
+    /// Check if the probe can drive the reset pin of the target.
+    ///
+    /// Drivers for probes without a reset pin, or where it is not wired to the debug
+    /// connector, return `false`.
+    fn has_reset_pin(&self) -> bool {
+        true
+    }
+
     /// Selects the transport protocol to be used by the debug probe.
     fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError>;
 
@@ -642,6 +886,35 @@ pub trait DebugProbe: Send + fmt::Debug {
     fn get_target_voltage(&mut self) -> Result<Option<f32>, DebugProbeError> {
         Ok(None)
     }
+
+    /// Get details about the probe, like its firmware version and capabilities.
+    ///
+    /// Drivers which don't know more about the probe only report whether SWO is supported.
+    fn details(&mut self) -> ProbeDetails {
+        ProbeDetails {
+            swo_supported: self.get_swo_interface().is_some(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Details about a debug probe, see [`Probe::info`].
+///
+/// Values which the probe doesn't report are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProbeDetails {
+    /// The firmware version of the probe.
+    pub firmware_version: Option<String>,
+    /// The version of the CMSIS-DAP protocol, only reported by CMSIS-DAP probes.
+    pub protocol_version: Option<String>,
+    /// The wire protocols supported by the probe, empty if unknown.
+    pub protocols: Vec<WireProtocol>,
+    /// The maximum size of a command packet in bytes.
+    pub packet_size: Option<usize>,
+    /// The number of command packets the probe can buffer.
+    pub packet_count: Option<usize>,
+    /// Whether the probe can capture SWO data.
+    pub swo_supported: bool,
 }
 
 /// Denotes the type of a given [`DebugProbe`].
@@ -756,6 +1029,10 @@ pub struct DebugProbeSelector {
     pub product_id: u16,
     /// The the serial number of the debug probe to be used.
     pub serial_number: Option<String>,
+    /// Use the CMSIS-DAP v1 (HID) interface, even if the probe supports CMSIS-DAP v2.
+    ///
+    /// This helps with probe firmware which has a broken v2 implementation. Other probes ignore this.
+    pub cmsisdap_v1: bool,
 }
 
 impl TryFrom<&str> for DebugProbeSelector {
@@ -767,6 +1044,7 @@ impl TryFrom<&str> for DebugProbeSelector {
                 vendor_id: u16::from_str_radix(split[0], 16)?,
                 product_id: u16::from_str_radix(split[1], 16)?,
                 serial_number: None,
+                cmsisdap_v1: false,
             }
         } else {
             return Err(DebugProbeSelectorParseError::Format);
@@ -800,6 +1078,7 @@ impl From<DebugProbeInfo> for DebugProbeSelector {
             vendor_id: selector.vendor_id,
             product_id: selector.product_id,
             serial_number: selector.serial_number,
+            cmsisdap_v1: false,
         }
     }
 }
@@ -810,6 +1089,7 @@ impl From<&DebugProbeInfo> for DebugProbeSelector {
             vendor_id: selector.vendor_id,
             product_id: selector.product_id,
             serial_number: selector.serial_number.clone(),
+            cmsisdap_v1: false,
         }
     }
 }
@@ -1076,6 +1356,35 @@ impl std::hash::Hash for DeferredResultIndex {
     }
 }
 
+/// Explain a timeout while attaching under reset, which is usually caused by an unconnected reset pin.
+fn under_reset_error(error: Error) -> Error {
+    if matches!(
+        error,
+        Error::Arm(ArmError::Timeout) | Error::Riscv(RiscvError::Timeout)
+    ) {
+        Error::Other(
+            anyhow::anyhow!("Timeout while attaching to target under reset. This can happen if the target is not responding to the reset sequence. Ensure the chip's reset pin is connected, or try attaching without reset (`connectUnderReset = false` for DAP Clients, or remove `connect-under-reset` option from CLI options.)."))
+    } else {
+        error
+    }
+}
+
+/// Options controlling how to attach to a target, see [`Probe::attach_with_options`].
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Default)]
+pub struct AttachOptions {
+    /// Halt all cores after attaching, e.g. to debug startup code.
+    ///
+    /// Otherwise the cores are left running, to attach to a live system without disturbing it.
+    pub halt_on_attach: bool,
+    /// Attach to the target while it is in reset, see [`AttachMethod::UnderReset`].
+    pub under_reset: bool,
+    /// The debug port to use on a SWD multi-drop bus, see [`DpAddress::multidrop`].
+    ///
+    /// This is used for the cores which the target description doesn't assign to a particular
+    /// debug port, and for the auto-detection of the target.
+    pub dp: Option<DpAddress>,
+}
+
 /// The method that should be used for attaching.
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
 pub enum AttachMethod {
@@ -1085,4 +1394,28 @@ pub enum AttachMethod {
     ///
     /// This is required on targets that can remap SWD pins or disable the SWD interface in sleep.
     UnderReset,
+    /// Attach to a running target without disturbing the program, see [`Probe::hot_attach`].
+    ///
+    /// Only ARM targets are supported. Attaching touches the target as follows:
+    ///
+    /// - The debug port is initialized and powered up, and the access ports are read.
+    /// - The chip specific unlock sequence runs. For most chips, this only reads the protection
+    ///   state of an unlocked device. Some sequences also configure the chip for debugging:
+    ///   on STM32 chips, the debug connection is kept alive in low power modes with the
+    ///   DBG_SLEEP, DBG_STOP and DBG_STANDBY bits of DBGMCU_CR, and on the STM32H7 the clocks
+    ///   of the debug components are enabled. This increases the power consumption in low
+    ///   power modes, but doesn't affect the running program.
+    /// - Vector catches left in DEMCR by an earlier debug session are cleared, if halting debug
+    ///   is still disabled. They would halt the core once halting debug is enabled.
+    /// - Halting debug is enabled by setting DHCSR.C_DEBUGEN, without setting C_HALT, C_STEP or
+    ///   C_MASKINTS. This does not affect the running program.
+    /// - The sticky bits in DFSR are cleared, which are only used by the debugger.
+    ///
+    /// The reset pin is not asserted, AIRCR is not written, the cores are not halted and hardware
+    /// breakpoints left by an earlier debug session are not cleared.
+    ///
+    /// The session uses [`DetachMode::Untouched`](crate::DetachMode::Untouched), so the
+    /// breakpoints, debug registers and cores are not touched when it ends either. Use
+    /// [`Session::set_detach_mode`] to restore the debug state instead.
+    HotAttach,
 }
@@ -8,9 +8,9 @@ use crate::{
         dp::{Abort, Ctrl, RdBuff, DPIDR},
         ArmError, DapError, DpAddress, Pins, PortType, RawDapAccess, Register,
     },
-    probe::common::bits_to_byte,
+    probe::common::{bits_to_byte, trace_dap_transfer, DapAck},
     probe::JTAGAccess,
-    DebugProbe, DebugProbeError,
+    DebugProbe, DebugProbeError, WireProtocol,
 };
 
 #[derive(Debug)]
@@ -26,6 +26,14 @@ pub struct SwdSettings {
     /// is received.
     pub num_retries_after_wait: usize,
 
+    /// How often a SWD transfer is retried when a FAULT response
+    /// is received because of a protocol error.
+    ///
+    /// Before retrying, the sticky error flags are cleared using the
+    /// `ABORT` register. Faults caused by a failed AP transaction,
+    /// e.g. an access to invalid memory, are never retried.
+    pub num_retries_after_fault: usize,
+
     /// When a SWD transfer is retried due to a WAIT response, the idle
     /// cycle amount is doubled every time as a backoff. This sets a maximum
     /// cap to the cycle amount.
@@ -58,6 +66,7 @@ impl Default for SwdSettings {
         Self {
             num_idle_cycles_between_writes: 2,
             num_retries_after_wait: 1000,
+            num_retries_after_fault: 2,
             max_retry_idle_cycles_after_wait: 128,
             idle_cycles_before_write_verify: 8,
             idle_cycles_after_transfer: 8,
@@ -291,7 +300,7 @@ fn perform_jtag_transfers<P: JTAGAccess + RawProtocolIo>(
 
             // Mark OK/FAULT transactions as failed
             // The caller will reset the sticky flag and retry if needed
-            for transfer in transfers {
+            for transfer in transfers.iter_mut() {
                 if transfer.status == TransferStatus::Ok {
                     transfer.status = TransferStatus::Failed(DapError::FaultResponse);
                 }
@@ -299,6 +308,10 @@ fn perform_jtag_transfers<P: JTAGAccess + RawProtocolIo>(
         }
     }
 
+    for transfer in transfers.iter() {
+        transfer.trace();
+    }
+
     Ok(())
 }
 
@@ -348,6 +361,8 @@ fn perform_swd_transfers<P: RawProtocolIo>(
             }
         }
 
+        transfer.trace();
+
         read_index += response_length(response_direction);
 
         read_index += additional_idle_cycles_after;
@@ -588,6 +603,25 @@ impl DapTransfer {
             && self.address == RdBuff::ADDRESS
             && self.direction == TransferDirection::Read
     }
+
+    /// Log the transfer and its acknowledge, see [trace_dap_transfer].
+    fn trace(&self) {
+        let ack = match &self.status {
+            // The acknowledge was OK, only the data was corrupted.
+            TransferStatus::Ok | TransferStatus::Failed(DapError::IncorrectParity) => DapAck::Ok,
+            TransferStatus::Failed(DapError::WaitResponse) => DapAck::Wait,
+            TransferStatus::Failed(DapError::FaultResponse) => DapAck::Fault,
+            TransferStatus::Failed(_) | TransferStatus::Pending => DapAck::NoAck,
+        };
+
+        trace_dap_transfer(
+            self.port,
+            self.address,
+            self.direction == TransferDirection::Read,
+            self.value,
+            ack,
+        );
+    }
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -823,6 +857,18 @@ fn parse_swd_response(response: &[bool], direction: TransferDirection) -> Result
     }
 }
 
+/// Address of the DP TARGETSEL register
+const TARGETSEL_ADDRESS: u8 = 0xC;
+
+/// Selection alert and SWD activation code to leave the dormant state, followed by a line reset
+/// and two idle cycles, see ADIv5.2 B5.3.4.
+///
+/// The bits are sent LSB first.
+const DORMANT_TO_SWD: [u8; 28] = [
+    0xff, 0x92, 0xf3, 0x09, 0x62, 0x95, 0x2d, 0x85, 0x86, 0xe9, 0xaf, 0xdd, 0xe3, 0xa2, 0x0e, 0xbc,
+    0x19, 0xa0, 0xf1, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00,
+];
+
 pub trait RawProtocolIo {
     fn jtag_shift_tms<M>(&mut self, tms: M, tdi: bool) -> Result<(), DebugProbeError>
     where
@@ -881,20 +927,91 @@ fn line_reset<P: RawProtocolIo + JTAGAccess + RawDapAccess>(this: &mut P) -> Res
     result
 }
 
+/// Clear the sticky error flags after a FAULT response.
+///
+/// Returns `true` if the fault was caused by a protocol error, i.e. an overrun or
+/// a write data error. In that case, the faulted transfer was not performed and can
+/// be retried. A sticky error is caused by a failed AP transaction, e.g. an access
+/// to invalid memory, and retrying would hide that error.
+fn clear_sticky_errors<P: RawDapAccess>(this: &mut P) -> Result<bool, ArmError> {
+    let response = RawDapAccess::raw_read_register(this, PortType::DebugPort, Ctrl::ADDRESS)?;
+    let ctrl = Ctrl::try_from(response)?;
+
+    tracing::debug!("DAP FAULT, Ctrl/Stat register value is: {:#?}", ctrl);
+
+    if ctrl.sticky_orun() || ctrl.w_data_err() || ctrl.sticky_err() {
+        let mut abort = Abort(0);
+
+        abort.set_orunerrclr(ctrl.sticky_orun());
+        abort.set_wderrclr(ctrl.w_data_err());
+        abort.set_stkerrclr(ctrl.sticky_err());
+
+        RawDapAccess::raw_write_register(this, PortType::DebugPort, Abort::ADDRESS, abort.into())?;
+    }
+
+    Ok(!ctrl.sticky_err())
+}
+
 impl<Probe: DebugProbe + RawProtocolIo + JTAGAccess + 'static> RawDapAccess for Probe {
     fn select_dp(&mut self, dp: DpAddress) -> Result<(), ArmError> {
         match dp {
             DpAddress::Default => Ok(()), // nop
-            DpAddress::Multidrop(_) => Err(DebugProbeError::ProbeSpecific(
-                anyhow::anyhow!("No support for multidrop SWD yet").into(),
-            )
-            .into()),
+            DpAddress::Multidrop(targetsel) => {
+                if self.active_protocol() != Some(WireProtocol::Swd) {
+                    return Err(DebugProbeError::ProbeSpecific(
+                        anyhow::anyhow!("Multidrop is only supported with SWD").into(),
+                    )
+                    .into());
+                }
+
+                let idle_cycles =
+                    std::cmp::max(1, self.swd_settings().num_idle_cycles_between_writes);
+
+                for _ in 0..5 {
+                    for chunk in DORMANT_TO_SWD.chunks(8) {
+                        let mut bits = [0; 8];
+                        bits[..chunk.len()].copy_from_slice(chunk);
+
+                        self.swj_sequence(chunk.len() as u8 * 8, u64::from_le_bytes(bits))?;
+                    }
+
+                    // No target drives the ACK of a TARGETSEL write, so the response is ignored.
+                    let sequence = build_swd_transfer(
+                        PortType::DebugPort,
+                        TransferType::Write(targetsel),
+                        TARGETSEL_ADDRESS,
+                    );
+                    self.swd_io(
+                        sequence.direction_bits().to_owned(),
+                        sequence.io_bits().to_owned(),
+                    )?;
+
+                    // "A write to the TARGETSEL register must always be followed by a read of the DPIDR register or a line reset. If the
+                    // response to the DPIDR read is incorrect, or there is no response, the host must start the sequence again."
+                    let mut transfers = [DapTransfer::read(PortType::DebugPort, DPIDR::ADDRESS)];
+                    perform_transfers(self, &mut transfers, idle_cycles)?;
+
+                    match &transfers[0].status {
+                        TransferStatus::Ok => {
+                            tracing::debug!("DPIDR read {:08x}", transfers[0].value);
+                            return Ok(());
+                        }
+                        status => {
+                            tracing::debug!("DPIDR read failed, retrying. Status: {:?}", status)
+                        }
+                    }
+                }
+
+                tracing::warn!("Giving up on TARGETSEL, too many retries.");
+                Err(DapError::NoAcknowledge.into())
+            }
         }
     }
 
     fn raw_read_register(&mut self, port: PortType, address: u8) -> Result<u32, ArmError> {
         let dap_wait_retries = self.swd_settings().num_retries_after_wait;
         let mut idle_cycles = std::cmp::max(1, self.swd_settings().num_idle_cycles_between_writes);
+        let mut fault_retries = 0;
 
         // Now we try to issue the request until it fails or succeeds.
         // If we timeout we retry a maximum of 5 times.
@@ -948,41 +1065,23 @@ impl<Probe: DebugProbe + RawProtocolIo + JTAGAccess + 'static> RawDapAccess for
                     // if we are *not* currently reading the ctrl register, otherwise
                     // this could end up being an endless recursion.
 
-                    if address != Ctrl::ADDRESS {
-                        let response = RawDapAccess::raw_read_register(
-                            self,
-                            PortType::DebugPort,
-                            Ctrl::ADDRESS,
-                        )?;
-                        let ctrl = Ctrl::try_from(response)?;
-                        tracing::debug!(
-                            "Reading DAP register failed. Ctrl/Stat register value is: {:#?}",
-                            ctrl
+                    if address == Ctrl::ADDRESS {
+                        tracing::warn!(
+                            "Error reading CTRL/STAT register. This should not happen..."
                         );
 
-                        // Check the reason for the fault
-                        // Other fault reasons than overrun or write error are not handled yet.
-                        if ctrl.sticky_orun() || ctrl.sticky_err() {
-                            // We did not handle a WAIT state properly
+                        return Err(DapError::FaultResponse.into());
+                    }
 
-                            // Because we use overrun detection, we now have to clear the overrun error
-                            let mut abort = Abort(0);
+                    if !clear_sticky_errors(self)? {
+                        return Err(DapError::StickyError { port, address }.into());
+                    }
 
-                            // Clear sticky error flags
-                            abort.set_orunerrclr(ctrl.sticky_orun());
-                            abort.set_stkerrclr(ctrl.sticky_err());
+                    if fault_retries < self.swd_settings().num_retries_after_fault {
+                        fault_retries += 1;
+                        tracing::debug!("Retrying read after protocol fault.");
 
-                            RawDapAccess::raw_write_register(
-                                self,
-                                PortType::DebugPort,
-                                Abort::ADDRESS,
-                                abort.into(),
-                            )?;
-                        }
-                    } else {
-                        tracing::warn!(
-                            "Error reading CTRL/STAT register. This should not happen..."
-                        );
+                        continue;
                     }
 
                     return Err(DapError::FaultResponse.into());
@@ -1017,6 +1116,7 @@ impl<Probe: DebugProbe + RawProtocolIo + JTAGAccess + 'static> RawDapAccess for
         let mut succesful_transfers = 0;
 
         let mut idle_cycles = std::cmp::max(1, self.swd_settings().num_idle_cycles_between_writes);
+        let mut fault_retries = 0;
 
         'transfer: for _ in 0..self.swd_settings().num_retries_after_wait {
             if succesful_transfers == values.len() {
@@ -1068,6 +1168,25 @@ impl<Probe: DebugProbe + RawProtocolIo + JTAGAccess + 'static> RawDapAccess for
 
                             continue 'transfer;
                         }
+
+                        if err == &DapError::FaultResponse {
+                            if !clear_sticky_errors(self)? {
+                                return Err(DapError::StickyError { port, address }.into());
+                            }
+
+                            if fault_retries >= self.swd_settings().num_retries_after_fault {
+                                return Err(err.clone().into());
+                            }
+
+                            fault_retries += 1;
+
+                            tracing::debug!(
+                                "Retrying access {} after protocol fault",
+                                index_offset + index + 1
+                            );
+
+                            continue 'transfer;
+                        }
                         return Err(err.clone().into());
                     }
                     TransferStatus::Pending => {
@@ -1089,6 +1208,7 @@ impl<Probe: DebugProbe + RawProtocolIo + JTAGAccess + 'static> RawDapAccess for
     ) -> Result<(), ArmError> {
         let dap_wait_retries = self.swd_settings().num_retries_after_wait;
         let mut idle_cycles = std::cmp::max(1, self.swd_settings().num_idle_cycles_between_writes);
+        let mut fault_retries = 0;
 
         // Now we try to issue the request until it fails or succeeds.
         // If we timeout we retry a maximum of 5 times.
@@ -1136,36 +1256,15 @@ impl<Probe: DebugProbe + RawProtocolIo + JTAGAccess + 'static> RawDapAccess for
                     tracing::debug!("DAP FAULT");
                     // A fault happened during operation.
 
-                    // To get a clue about the actual fault we read the ctrl register,
-                    // which will have the fault status flags set.
-
-                    let response =
-                        RawDapAccess::raw_read_register(self, PortType::DebugPort, Ctrl::ADDRESS)?;
-
-                    let ctrl = Ctrl::try_from(response)?;
-                    tracing::trace!(
-                        "Writing DAP register failed. Ctrl/Stat register value is: {:#?}",
-                        ctrl
-                    );
-
-                    // Check the reason for the fault
-                    // Other fault reasons than overrun or write error are not handled yet.
-                    if ctrl.sticky_orun() || ctrl.sticky_err() {
-                        // We did not handle a WAIT state properly
-
-                        // Because we use overrun detection, we now have to clear the overrun error
-                        let mut abort = Abort(0);
+                    if !clear_sticky_errors(self)? {
+                        return Err(DapError::StickyError { port, address }.into());
+                    }
 
-                        // Clear sticky error flags
-                        abort.set_orunerrclr(ctrl.sticky_orun());
-                        abort.set_stkerrclr(ctrl.sticky_err());
+                    if fault_retries < self.swd_settings().num_retries_after_fault {
+                        fault_retries += 1;
+                        tracing::debug!("Retrying write after protocol fault.");
 
-                        RawDapAccess::raw_write_register(
-                            self,
-                            PortType::DebugPort,
-                            Abort::ADDRESS,
-                            abort.into(),
-                        )?;
+                        continue;
                     }
 
                     return Err(DapError::FaultResponse.into());
@@ -1200,6 +1299,7 @@ impl<Probe: DebugProbe + RawProtocolIo + JTAGAccess + 'static> RawDapAccess for
         let mut succesful_transfers = 0;
 
         let mut idle_cycles = std::cmp::max(1, self.swd_settings().num_idle_cycles_between_writes);
+        let mut fault_retries = 0;
 
         'transfer: for _ in 0..self.swd_settings().num_retries_after_wait {
             if succesful_transfers == values.len() {
@@ -1254,6 +1354,25 @@ impl<Probe: DebugProbe + RawProtocolIo + JTAGAccess + 'static> RawDapAccess for
                             continue 'transfer;
                         }
 
+                        if err == &DapError::FaultResponse {
+                            if !clear_sticky_errors(self)? {
+                                return Err(DapError::StickyError { port, address }.into());
+                            }
+
+                            if fault_retries >= self.swd_settings().num_retries_after_fault {
+                                return Err(err.clone().into());
+                            }
+
+                            fault_retries += 1;
+
+                            tracing::debug!(
+                                "Retrying access {} after protocol fault",
+                                index_offset + index + 1
+                            );
+
+                            continue 'transfer;
+                        }
+
                         return Err(err.clone().into());
                     }
                     TransferStatus::Pending => {
@@ -1808,6 +1927,64 @@ mod test {
         assert_eq!(result, read_value);
     }
 
+    #[test]
+    fn read_register_with_overrun_fault() {
+        let read_value = 47;
+        let mut mock = MockJaylink::new();
+
+        mock.add_read_response(DapAcknowledge::Ok, 0);
+        mock.add_read_response(DapAcknowledge::Fault, 0);
+        mock.add_idle_cycles(mock.swd_settings.idle_cycles_after_transfer);
+
+        // The CTRL/STAT register is read to find out the reason for the fault, STICKYORUN is set
+        mock.add_transfer();
+        mock.add_read_response(DapAcknowledge::Ok, 0x2);
+        mock.add_idle_cycles(mock.swd_settings.idle_cycles_after_transfer);
+
+        // The overrun flag is cleared using the ABORT register
+        mock.add_transfer();
+        mock.add_write_response(
+            DapAcknowledge::Ok,
+            mock.swd_settings.num_idle_cycles_between_writes,
+        );
+        mock.add_idle_cycles(mock.swd_settings.idle_cycles_after_transfer);
+
+        // The read is retried
+        mock.add_transfer();
+        mock.add_read_response(DapAcknowledge::Ok, 0);
+        mock.add_read_response(DapAcknowledge::Ok, read_value);
+        mock.add_idle_cycles(mock.swd_settings.idle_cycles_after_transfer);
+
+        let result = mock.raw_read_register(PortType::AccessPort, 4).unwrap();
+
+        assert_eq!(result, read_value);
+    }
+
+    #[test]
+    fn read_register_with_sticky_error_is_not_retried() {
+        let mut mock = MockJaylink::new();
+
+        mock.add_read_response(DapAcknowledge::Ok, 0);
+        mock.add_read_response(DapAcknowledge::Fault, 0);
+        mock.add_idle_cycles(mock.swd_settings.idle_cycles_after_transfer);
+
+        // The CTRL/STAT register is read to find out the reason for the fault, STICKYERR is set
+        mock.add_transfer();
+        mock.add_read_response(DapAcknowledge::Ok, 0x20);
+        mock.add_idle_cycles(mock.swd_settings.idle_cycles_after_transfer);
+
+        // The error flag is cleared using the ABORT register
+        mock.add_transfer();
+        mock.add_write_response(
+            DapAcknowledge::Ok,
+            mock.swd_settings.num_idle_cycles_between_writes,
+        );
+        mock.add_idle_cycles(mock.swd_settings.idle_cycles_after_transfer);
+
+        mock.raw_read_register(PortType::AccessPort, 4)
+            .expect_err("A failed AP transaction must not be retried");
+    }
+
     #[test]
     fn read_register_with_wait_response_jtag() {
         let read_value = 47;
@@ -1845,6 +2022,23 @@ mod test {
         assert_eq!(result, read_value);
     }
 
+    #[test]
+    fn targetsel_write_request() {
+        use super::{build_swd_transfer, TransferType, TARGETSEL_ADDRESS};
+
+        let sequence = build_swd_transfer(
+            PortType::DebugPort,
+            TransferType::Write(0x0100_2927),
+            TARGETSEL_ADDRESS,
+        );
+
+        // Two idle cycles, followed by the request, which is 0x99 sent LSB first
+        assert_eq!(
+            &sequence.io_bits()[2..10],
+            &[true, false, false, true, true, false, false, true]
+        );
+    }
+
     #[test]
     fn write_register() {
         let mut mock = MockJaylink::new();
@@ -142,7 +142,7 @@ impl Request for StatusRequest {
 pub struct TraceStatus {
     pub(crate) _active: bool,
     pub(crate) error: bool,
-    pub(crate) _overrun: bool,
+    pub(crate) overrun: bool,
 }
 
 impl From<u8> for TraceStatus {
@@ -150,7 +150,7 @@ impl From<u8> for TraceStatus {
         Self {
             _active: value & (1 << 0) != 0,
             error: value & (1 << 6) != 0,
-            _overrun: value & (1 << 7) != 0,
+            overrun: value & (1 << 7) != 0,
         }
     }
 }
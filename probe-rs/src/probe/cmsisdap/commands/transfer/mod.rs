@@ -2,6 +2,7 @@ pub mod configure;
 
 use super::{CommandId, Request, SendError};
 use crate::architecture::arm::PortType;
+use crate::probe::common::{trace_dap_transfer, DapAck};
 use scroll::{Pread, Pwrite, LE};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -183,13 +184,7 @@ impl Request for TransferRequest {
         }
 
         let last_transfer_response = LastTransferResponse {
-            ack: match buffer[1] & 0x7 {
-                1 => Ack::Ok,
-                2 => Ack::Wait,
-                4 => Ack::Fault,
-                7 => Ack::NoAck,
-                _ => Ack::NoAck,
-            },
+            ack: Ack::from_response(buffer[1]),
             protocol_error: buffer[1] & 0x8 > 1,
             value_missmatch: buffer[1] & 0x10 > 1,
         };
@@ -206,7 +201,16 @@ impl Request for TransferRequest {
 
         for (i, ack) in xfer_count_and_ack {
             let req = &self.transfers[i];
-            let (resp, len) = InnerTransferResponse::from_bytes(req, ack, buffer)?;
+            let (resp, len) = InnerTransferResponse::from_bytes(req, ack.clone(), buffer)?;
+
+            trace_dap_transfer(
+                req.APnDP,
+                u8::from(req.A2) << 2 | u8::from(req.A3) << 3,
+                req.RnW == RW::R,
+                resp.data.or(req.data).unwrap_or_default(),
+                ack.into(),
+            );
+
             transfers.push(resp);
             buffer = &buffer[len..];
         }
@@ -230,6 +234,29 @@ pub enum Ack {
     NoAck = 7,
 }
 
+impl Ack {
+    /// Decode the acknowledge from the response byte of a transfer command.
+    fn from_response(response: u8) -> Self {
+        match response & 0x7 {
+            1 => Ack::Ok,
+            2 => Ack::Wait,
+            4 => Ack::Fault,
+            _ => Ack::NoAck,
+        }
+    }
+}
+
+impl From<Ack> for DapAck {
+    fn from(ack: Ack) -> Self {
+        match ack {
+            Ack::Ok => DapAck::Ok,
+            Ack::Wait => DapAck::Wait,
+            Ack::Fault => DapAck::Fault,
+            Ack::NoAck => DapAck::NoAck,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct LastTransferResponse {
     pub ack: Ack,
@@ -323,6 +350,28 @@ impl Request for TransferBlockRequest {
             }
         }
 
+        // Only the last executed transfer can have failed.
+        for i in 0..transfer_count as usize {
+            let ack = if i + 1 == transfer_count as usize {
+                Ack::from_response(transfer_response)
+            } else {
+                Ack::Ok
+            };
+
+            let request = &self.transfer_request;
+
+            trace_dap_transfer(
+                request.ap_n_dp,
+                u8::from(request.a2) << 2 | u8::from(request.a3) << 3,
+                request.r_n_w == RW::R,
+                data.get(i)
+                    .or(self.transfer_data.get(i))
+                    .copied()
+                    .unwrap_or_default(),
+                ack.into(),
+            );
+        }
+
         Ok(TransferBlockResponse {
             _transfer_count: transfer_count,
             transfer_response,
@@ -7,17 +7,20 @@ use crate::{
         communication_interface::UninitializedArmProbe,
         dp::{Abort, Ctrl},
         swo::poll_interval_from_buf_size,
-        ArmCommunicationInterface, ArmError, DapError, DpAddress, Pins, PortType, RawDapAccess,
-        Register, SwoAccess, SwoConfig, SwoMode,
+        ArmCommunicationInterface, ArmError, DapError, DapTransfer, DpAddress, Pins, PortType,
+        RawDapAccess, Register, SwoAccess, SwoConfig, SwoMode,
     },
     probe::{
         cmsisdap::commands::{
-            general::info::{CapabilitiesCommand, PacketCountCommand, SWOTraceBufferSizeCommand},
+            general::info::{
+                CapabilitiesCommand, FirmwareVersionCommand, PacketCountCommand,
+                ProductFirmwareVersionCommand, ProductIdCommand, SWOTraceBufferSizeCommand,
+            },
             CmsisDapError,
         },
         BatchCommand,
     },
-    CoreStatus, DebugProbe, DebugProbeError, DebugProbeSelector, WireProtocol,
+    CoreStatus, DebugProbe, DebugProbeError, DebugProbeSelector, ProbeDetails, WireProtocol,
 };
 
 use commands::{
@@ -68,9 +71,14 @@ pub struct CmsisDap {
     packet_size: u16,
     packet_count: u8,
     capabilities: Capabilities,
+    /// Version of the CMSIS-DAP protocol implemented by the probe.
+    protocol_version: Option<String>,
+    firmware_version: Option<String>,
     swo_buffer_size: Option<usize>,
     swo_active: bool,
     swo_streaming: bool,
+    /// The probe dropped SWO data since the last call to [`SwoAccess::take_swo_overrun`].
+    swo_overrun: bool,
     connected: bool,
 
     /// Speed in kHz
@@ -93,6 +101,8 @@ impl std::fmt::Debug for CmsisDap {
             .field("packet_size", &self.packet_size)
             .field("packet_count", &self.packet_count)
             .field("capabilities", &self.capabilities)
+            .field("protocol_version", &self.protocol_version)
+            .field("firmware_version", &self.firmware_version)
             .field("swo_buffer_size", &self.swo_buffer_size)
             .field("swo_active", &self.swo_active)
             .field("swo_streaming", &self.swo_streaming)
@@ -115,6 +125,25 @@ impl CmsisDap {
         let packet_count = commands::send_command(&mut device, PacketCountCommand {})?;
         let caps: Capabilities = commands::send_command(&mut device, CapabilitiesCommand {})?;
         tracing::debug!("Detected probe capabilities: {:?}", caps);
+
+        // These strings are optional, and older probes don't know the product firmware version.
+        let product_name = commands::send_command(&mut device, ProductIdCommand {})
+            .ok()
+            .flatten();
+        let protocol_version = commands::send_command(&mut device, FirmwareVersionCommand {})
+            .ok()
+            .flatten();
+        let firmware_version =
+            commands::send_command(&mut device, ProductFirmwareVersionCommand {})
+                .ok()
+                .flatten();
+        tracing::debug!(
+            "Probe {:?}, protocol version {:?}, firmware version {:?}",
+            product_name,
+            protocol_version,
+            firmware_version
+        );
+
         let mut swo_buffer_size = None;
         if caps.swo_uart_implemented || caps.swo_manchester_implemented {
             let swo_size = commands::send_command(&mut device, SWOTraceBufferSizeCommand {})?;
@@ -130,9 +159,12 @@ impl CmsisDap {
             packet_count,
             packet_size,
             capabilities: caps,
+            protocol_version,
+            firmware_version,
             swo_buffer_size,
             swo_active: false,
             swo_streaming: false,
+            swo_overrun: false,
             connected: false,
             speed_khz: 1_000,
             scan_chain: None,
@@ -504,10 +536,12 @@ impl CmsisDap {
 
                         tracing::trace!("Ctrl/Stat register value is: {:?}", ctrl);
 
-                        if ctrl.sticky_err() {
+                        if ctrl.sticky_orun() || ctrl.w_data_err() || ctrl.sticky_err() {
                             let mut abort = Abort(0);
 
                             // Clear sticky error flags.
+                            abort.set_orunerrclr(ctrl.sticky_orun());
+                            abort.set_wderrclr(ctrl.w_data_err());
                             abort.set_stkerrclr(ctrl.sticky_err());
 
                             RawDapAccess::raw_write_register(
@@ -518,6 +552,23 @@ impl CmsisDap {
                             )?;
                         }
 
+                        // A failed AP transaction is reported instead of retried,
+                        // otherwise the error would go unnoticed.
+                        if ctrl.sticky_err() {
+                            let error = match batch.get(count) {
+                                Some(BatchCommand::Read(port, address))
+                                | Some(BatchCommand::Write(port, address, _)) => {
+                                    DapError::StickyError {
+                                        port: *port,
+                                        address: *address as u8,
+                                    }
+                                }
+                                None => DapError::FaultResponse,
+                            };
+
+                            return Err(error.into());
+                        }
+
                         tracing::trace!("draining {:?} and retries left {:?}", count, retry);
                         batch.drain(0..count);
                         continue;
@@ -544,6 +595,111 @@ impl CmsisDap {
         Err(DapError::FaultResponse.into())
     }
 
+    /// Execute `transfers` using as few DAP_Transfer commands as possible.
+    ///
+    /// Each command is filled up to the packet size, both for the request and the response.
+    fn transfer_batch(&mut self, transfers: &mut [DapTransfer]) -> Result<(), ArmError> {
+        // Pending writes are executed first, so their errors are not attributed to the batch.
+        self.process_batch()?;
+
+        let packet_size = self.packet_size as usize;
+        let mut start = 0;
+
+        while start < transfers.len() {
+            let end = start + chunk_len(&transfers[start..], packet_size);
+
+            self.transfer_chunk(&mut transfers[start..end])
+                .map_err(|(index, source)| ArmError::BatchTransfer {
+                    index: start + index,
+                    source: Box::new(source),
+                })?;
+
+            start = end;
+        }
+
+        Ok(())
+    }
+
+    /// Execute `transfers` with a single DAP_Transfer command.
+    ///
+    /// Errors are returned together with the index of the failed transfer.
+    fn transfer_chunk(&mut self, transfers: &mut [DapTransfer]) -> Result<(), (usize, ArmError)> {
+        let response = commands::send_command(&mut self.device, transfer_request(transfers))
+            .map_err(|error| {
+                (
+                    0,
+                    ArmError::from(DebugProbeError::from(CmsisDapError::from(error))),
+                )
+            })?;
+
+        for (transfer, result) in transfers.iter_mut().zip(&response.transfers) {
+            if let Some(data) = result.data {
+                transfer.value = data;
+            }
+        }
+
+        // The transfer count includes the failed transfer.
+        let failed = (response.transfer_count as usize).saturating_sub(1);
+
+        if response.last_transfer_response.protocol_error {
+            return Err((failed, DapError::SwdProtocol.into()));
+        }
+
+        match response.last_transfer_response.ack {
+            Ack::Ok if response.transfer_count as usize == transfers.len() => Ok(()),
+            Ack::Ok => Err((
+                response.transfer_count as usize,
+                DapError::FaultResponse.into(),
+            )),
+            Ack::NoAck => Err((failed, DapError::NoAcknowledge.into())),
+            Ack::Wait => {
+                let mut abort = Abort(0);
+                abort.set_dapabort(true);
+
+                RawDapAccess::raw_write_register(
+                    self,
+                    PortType::DebugPort,
+                    Abort::ADDRESS,
+                    abort.into(),
+                )
+                .map_err(|error| (failed, error))?;
+
+                Err((failed, DapError::WaitResponse.into()))
+            }
+            Ack::Fault => {
+                let ctrl = self.read_ctrl_register().map_err(|error| (failed, error))?;
+
+                let mut abort = Abort(0);
+
+                // Clear sticky error flags.
+                abort.set_orunerrclr(ctrl.sticky_orun());
+                abort.set_wderrclr(ctrl.w_data_err());
+                abort.set_stkerrclr(ctrl.sticky_err());
+
+                RawDapAccess::raw_write_register(
+                    self,
+                    PortType::DebugPort,
+                    Abort::ADDRESS,
+                    abort.into(),
+                )
+                .and_then(|_| self.process_batch())
+                .map_err(|error| (failed, error))?;
+
+                let transfer = &transfers[failed];
+                let error = if ctrl.sticky_err() {
+                    DapError::StickyError {
+                        port: transfer.port,
+                        address: transfer.address,
+                    }
+                } else {
+                    DapError::FaultResponse
+                };
+
+                Err((failed, error.into()))
+            }
+        }
+    }
+
     /// Add a BatchCommand to our current batch.
     ///
     /// If the BatchCommand is a Read, this will immediately process the batch
@@ -658,6 +814,11 @@ impl CmsisDap {
 
                 let response: swo::DataResponse =
                     commands::send_command(&mut self.device, swo::DataRequest { max_count: n })?;
+                if response.status.overrun {
+                    tracing::warn!("The SWO trace buffer of the probe overflowed, data was lost");
+                    self.swo_overrun = true;
+                }
+
                 if response.status.error {
                     Err(CmsisDapError::SwoTraceStreamError.into())
                 } else {
@@ -722,14 +883,41 @@ impl DebugProbe for CmsisDap {
         self.speed_khz
     }
 
+    /// Neither DAP_SWJ_Clock nor DAP_Info report the clock divider chosen by the firmware.
+    fn speed_is_approximate(&self) -> bool {
+        true
+    }
+
     /// For CMSIS-DAP, we can set the maximum speed. The actual speed
     /// used by the probe cannot be determined, but it will not be
     /// higher than this value.
+    ///
+    /// If the probe rejects the speed, it is clamped to the fastest or
+    /// slowest speed the probe accepts, and that speed is returned.
     fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
-        self.set_swj_clock(speed_khz * 1_000)?;
-        self.speed_khz = speed_khz;
+        // The clock is sent in Hz
+        let requested_khz = speed_khz.clamp(1, u32::MAX / 1_000);
+
+        let clock_khz = clamp_speed(requested_khz, |clock_khz| {
+            match self.set_swj_clock(clock_khz * 1_000) {
+                Ok(()) => Ok(true),
+                Err(CmsisDapError::ErrorResponse) => Ok(false),
+                Err(error) => Err(error),
+            }
+        })?
+        .ok_or(DebugProbeError::UnsupportedSpeed(speed_khz))?;
+
+        if clock_khz != speed_khz {
+            tracing::warn!(
+                "Speed of {} kHz is not supported by the probe, using {} kHz instead",
+                speed_khz,
+                clock_khz
+            );
+        }
 
-        Ok(speed_khz)
+        self.speed_khz = clock_khz;
+
+        Ok(clock_khz)
     }
 
     fn set_scan_chain(&mut self, scan_chain: Vec<ScanChainElement>) -> Result<(), DebugProbeError> {
@@ -844,6 +1032,28 @@ impl DebugProbe for CmsisDap {
         Some(self as _)
     }
 
+    fn details(&mut self) -> ProbeDetails {
+        let mut protocols = Vec::new();
+
+        if self.capabilities.swd_implemented {
+            protocols.push(WireProtocol::Swd);
+        }
+
+        if self.capabilities.jtag_implemented {
+            protocols.push(WireProtocol::Jtag);
+        }
+
+        ProbeDetails {
+            firmware_version: self.firmware_version.clone(),
+            protocol_version: self.protocol_version.clone(),
+            protocols,
+            packet_size: Some(self.packet_size as usize),
+            packet_count: Some(self.packet_count as usize),
+            swo_supported: self.capabilities.swo_uart_implemented
+                || self.capabilities.swo_manchester_implemented,
+        }
+    }
+
     fn get_swo_interface_mut(&mut self) -> Option<&mut dyn SwoAccess> {
         Some(self as _)
     }
@@ -943,6 +1153,10 @@ impl RawDapAccess for CmsisDap {
             .map(|_| ())
     }
 
+    fn raw_transfer_batch(&mut self, transfers: &mut [DapTransfer]) -> Result<(), ArmError> {
+        self.transfer_batch(transfers)
+    }
+
     fn raw_write_block(
         &mut self,
         port: PortType,
@@ -1146,6 +1360,7 @@ impl SwoAccess for CmsisDap {
         self.start_swo_capture()?;
 
         self.swo_active = true;
+        self.swo_overrun = false;
         Ok(())
     }
 
@@ -1194,6 +1409,10 @@ impl SwoAccess for CmsisDap {
     fn swo_buffer_size(&mut self) -> Option<usize> {
         self.swo_buffer_size
     }
+
+    fn take_swo_overrun(&mut self) -> bool {
+        std::mem::take(&mut self.swo_overrun)
+    }
 }
 
 impl Drop for CmsisDap {
@@ -1212,6 +1431,97 @@ impl Drop for CmsisDap {
     }
 }
 
+/// Speed in kHz which is supported by practically all probes, used to find out whether a
+/// rejected speed is too high or too low.
+const REFERENCE_SPEED_KHZ: u32 = 1_000;
+
+/// The speed closest to `speed_khz` which is accepted by the probe, in kHz.
+///
+/// CMSIS-DAP doesn't report the range of supported clocks, so a rejected speed is clamped to the
+/// limit of the probe by searching between it and [`REFERENCE_SPEED_KHZ`]. `try_speed` sets a
+/// speed and returns whether the probe accepted it, the returned speed is the one set last.
+/// Returns `None` if the reference speed is rejected as well.
+fn clamp_speed<E>(
+    speed_khz: u32,
+    mut try_speed: impl FnMut(u32) -> Result<bool, E>,
+) -> Result<Option<u32>, E> {
+    if try_speed(speed_khz)? {
+        return Ok(Some(speed_khz));
+    }
+
+    if speed_khz == REFERENCE_SPEED_KHZ || !try_speed(REFERENCE_SPEED_KHZ)? {
+        return Ok(None);
+    }
+
+    // The limit of the probe is between the accepted and the rejected speed
+    let mut accepted = REFERENCE_SPEED_KHZ;
+    let mut rejected = speed_khz;
+    let mut last_accepted = true;
+
+    while accepted.abs_diff(rejected) > 1 {
+        let middle = accepted.min(rejected) + accepted.abs_diff(rejected) / 2;
+
+        last_accepted = try_speed(middle)?;
+
+        if last_accepted {
+            accepted = middle;
+        } else {
+            rejected = middle;
+        }
+    }
+
+    if !last_accepted && !try_speed(accepted)? {
+        return Ok(None);
+    }
+
+    Ok(Some(accepted))
+}
+
+/// The number of transfers from the start of `transfers` which fit into a single DAP_Transfer
+/// command, for both the request and the response.
+fn chunk_len(transfers: &[DapTransfer], packet_size: usize) -> usize {
+    // Command, DAP index and transfer count, and command, count and response.
+    let mut request_size = 3;
+    let mut response_size = 3;
+
+    for (len, transfer) in transfers.iter().enumerate() {
+        let (request, response) = if transfer.write { (5, 0) } else { (1, 4) };
+
+        if len == u8::MAX as usize
+            || request_size + request > packet_size
+            || response_size + response > packet_size
+        {
+            return len;
+        }
+
+        request_size += request;
+        response_size += response;
+    }
+
+    transfers.len()
+}
+
+/// The DAP_Transfer command executing `transfers`.
+fn transfer_request(transfers: &[DapTransfer]) -> TransferRequest {
+    let requests: Vec<InnerTransferRequest> = transfers
+        .iter()
+        .map(|transfer| {
+            if transfer.write {
+                InnerTransferRequest::new(
+                    transfer.port,
+                    RW::W,
+                    transfer.address,
+                    Some(transfer.value),
+                )
+            } else {
+                InnerTransferRequest::new(transfer.port, RW::R, transfer.address, None)
+            }
+        })
+        .collect();
+
+    TransferRequest::new(&requests)
+}
+
 impl From<ScanChainError> for CmsisDapError {
     fn from(error: ScanChainError) -> Self {
         match error {
@@ -1220,3 +1530,110 @@ impl From<ScanChainError> for CmsisDapError {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{chunk_len, clamp_speed, transfer_request};
+    use crate::architecture::arm::{DapTransfer, PortType};
+    use crate::probe::cmsisdap::commands::Request;
+
+    #[test]
+    fn chunks_fill_the_packet_size() {
+        // A write takes 5 bytes of the request, 3 bytes are used by the header
+        let writes = [DapTransfer::write(PortType::AccessPort, 0x0C, 0); 20];
+        assert_eq!(chunk_len(&writes, 64), 12);
+
+        // A read takes 4 bytes of the response
+        let reads = [DapTransfer::read(PortType::AccessPort, 0x0C); 20];
+        assert_eq!(chunk_len(&reads, 64), 15);
+
+        let mixed = [
+            DapTransfer::write(PortType::AccessPort, 0x04, 0),
+            DapTransfer::read(PortType::AccessPort, 0x0C),
+        ]
+        .repeat(10);
+        assert_eq!(chunk_len(&mixed, 64), 20);
+        assert_eq!(chunk_len(&mixed, 32), 9);
+
+        assert_eq!(chunk_len(&[], 64), 0);
+    }
+
+    #[test]
+    fn chunks_are_limited_to_255_transfers() {
+        let reads = [DapTransfer::read(PortType::AccessPort, 0x0C); 300];
+
+        assert_eq!(chunk_len(&reads, 1024), 255);
+        assert_eq!(chunk_len(&reads[255..], 1024), 45);
+    }
+
+    #[test]
+    fn transfer_request_bytes() {
+        // Read a word from memory: write CSW and TAR, read DRW
+        let transfers = [
+            DapTransfer::write(PortType::AccessPort, 0x00, 0x2300_0052),
+            DapTransfer::write(PortType::AccessPort, 0x04, 0x2000_0000),
+            DapTransfer::read(PortType::AccessPort, 0x0C),
+            DapTransfer::read(PortType::DebugPort, 0x0C),
+        ];
+
+        let mut buffer = [0; 64];
+        let len = transfer_request(&transfers).to_bytes(&mut buffer).unwrap();
+
+        // The DAP_Transfer command as sent to the probe, after the command ID 0x05
+        #[rustfmt::skip]
+        let expected = [
+            0x00, 0x04,
+            0x01, 0x52, 0x00, 0x00, 0x23,
+            0x05, 0x00, 0x00, 0x00, 0x20,
+            0x0F,
+            0x0E,
+        ];
+        assert_eq!(buffer[..len], expected);
+    }
+
+    /// Clamp `speed_khz` for a probe accepting the speeds in `supported`, and return the
+    /// clamped speed together with the speed set last
+    fn clamp_for(
+        supported: std::ops::RangeInclusive<u32>,
+        speed_khz: u32,
+    ) -> (Option<u32>, Option<u32>) {
+        let mut current = None;
+
+        let clamped = clamp_speed(speed_khz, |clock_khz| {
+            let accepted = supported.contains(&clock_khz);
+            if accepted {
+                current = Some(clock_khz);
+            }
+
+            Ok::<_, ()>(accepted)
+        })
+        .unwrap();
+
+        (clamped, current)
+    }
+
+    #[test]
+    fn supported_speed_is_used() {
+        assert_eq!(clamp_for(100..=4_000, 2_500), (Some(2_500), Some(2_500)));
+    }
+
+    #[test]
+    fn speed_is_clamped_to_the_maximum() {
+        assert_eq!(clamp_for(100..=4_000, 8_000), (Some(4_000), Some(4_000)));
+        assert_eq!(
+            clamp_for(100..=4_000, u32::MAX / 1_000),
+            (Some(4_000), Some(4_000))
+        );
+    }
+
+    #[test]
+    fn speed_is_clamped_to_the_minimum() {
+        assert_eq!(clamp_for(100..=4_000, 10), (Some(100), Some(100)));
+        assert_eq!(clamp_for(100..=4_000, 1), (Some(100), Some(100)));
+    }
+
+    #[test]
+    fn speed_is_not_clamped_without_a_supported_reference() {
+        assert_eq!(clamp_for(2_000..=4_000, 8_000), (None, None));
+    }
+}
@@ -251,8 +251,16 @@ fn device_matches(
     }
 }
 
+/// Environment variable which disables the CMSIS-DAP v2 interface when set to `1`.
+///
+/// This is a fallback for tools which don't expose [`DebugProbeSelector::cmsisdap_v1`].
+const FORCE_V1_VARIABLE: &str = "PROBE_RS_CMSISDAP_FORCE_V1";
+
 /// Attempt to open the given DebugProbeInfo in CMSIS-DAP v2 mode if possible,
 /// otherwise in v1 mode.
+///
+/// v1 mode is always used if [`DebugProbeSelector::cmsisdap_v1`] is set, or the
+/// `PROBE_RS_CMSISDAP_FORCE_V1` environment variable is set to `1`.
 pub fn open_device_from_selector(
     selector: impl Into<DebugProbeSelector>,
 ) -> Result<CmsisDapDevice, ProbeCreationError> {
@@ -269,6 +277,16 @@ pub fn open_device_from_selector(
     // try to open that.
     let mut hid_device_info = None;
 
+    let force_v1 = if selector.cmsisdap_v1 {
+        tracing::debug!("CMSIS-DAP v1 requested, not using CMSIS-DAP v2");
+        true
+    } else if std::env::var(FORCE_V1_VARIABLE).is_ok_and(|value| value == "1") {
+        tracing::debug!("{FORCE_V1_VARIABLE} is set, not using CMSIS-DAP v2");
+        true
+    } else {
+        false
+    };
+
     // Try using rusb to open a v2 device. This might fail if
     // the device does not support v2 operation or due to driver
     // or permission issues with opening bulk devices.
@@ -284,6 +302,13 @@ pub fn open_device_from_selector(
                 }
             };
 
+            // Don't open other devices, they may be probes in use by another session
+            if d_desc.vendor_id() != selector.vendor_id
+                || d_desc.product_id() != selector.product_id
+            {
+                continue;
+            }
+
             let handle = match device.open() {
                 Ok(handle) => handle,
                 Err(err) => {
@@ -312,7 +337,7 @@ pub fn open_device_from_selector(
             if device_matches(d_desc, &selector, sn_str) {
                 hid_device_info = get_cmsisdap_info(&device);
 
-                if hid_device_info.is_some() {
+                if hid_device_info.is_some() && !force_v1 {
                     // If the VID, PID, and potentially SN all match,
                     // and the device is a valid CMSIS-DAP probe,
                     // attempt to open the device in v2 mode.
@@ -1,5 +1,7 @@
 //! Crate-public structures and utilities to be shared between probes.
 
+use crate::architecture::arm::PortType;
+
 use bitfield::bitfield;
 use bitvec::prelude::*;
 
@@ -15,6 +17,83 @@ pub(crate) fn bits_to_byte(bits: impl IntoIterator<Item = bool>) -> u32 {
     bit_val
 }
 
+/// Acknowledge of a single DP or AP transfer, see [`trace_dap_transfer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DapAck {
+    Ok,
+    Wait,
+    Fault,
+    /// No response from the target, or a response violating the protocol.
+    NoAck,
+}
+
+impl std::fmt::Display for DapAck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DapAck::Ok => "OK",
+            DapAck::Wait => "WAIT",
+            DapAck::Fault => "FAULT",
+            DapAck::NoAck => "NO ACK",
+        })
+    }
+}
+
+/// Log a DP or AP transfer on the wire at trace level, to debug the communication with a target.
+///
+/// This is only compiled in with the `trace` feature, and does nothing unless trace logging
+/// is enabled for this module. The bank of the register is not known on the wire, but writes
+/// to SELECT are logged with the decoded APSEL, APBANKSEL and DPBANKSEL fields.
+#[cfg(feature = "trace")]
+pub(crate) fn trace_dap_transfer(port: PortType, address: u8, read: bool, value: u32, ack: DapAck) {
+    if !tracing::enabled!(tracing::Level::TRACE) {
+        return;
+    }
+
+    let rnw = if read { 'R' } else { 'W' };
+
+    match port {
+        PortType::DebugPort => {
+            let register = match (address & 0xC, read) {
+                (0x0, true) => "DPIDR",
+                (0x0, false) => "ABORT",
+                (0x4, _) => "CTRL/STAT",
+                (0x8, true) => "RESEND",
+                (0x8, false) => "SELECT",
+                (0xC, true) => "RDBUFF",
+                _ => "TARGETSEL",
+            };
+
+            if register == "SELECT" {
+                tracing::trace!(
+                    "DP {rnw} {register} ({address:#04x}) data={value:#010x} ack={ack} \
+                     (APSEL={:#04x} APBANKSEL={:#x} DPBANKSEL={:#x})",
+                    value >> 24,
+                    (value >> 4) & 0xF,
+                    value & 0xF,
+                );
+            } else {
+                tracing::trace!(
+                    "DP {rnw} {register} ({address:#04x}) data={value:#010x} ack={ack}"
+                );
+            }
+        }
+        PortType::AccessPort => {
+            tracing::trace!("AP {rnw} {address:#04x} data={value:#010x} ack={ack}");
+        }
+    }
+}
+
+#[cfg(not(feature = "trace"))]
+#[inline(always)]
+pub(crate) fn trace_dap_transfer(
+    _port: PortType,
+    _address: u8,
+    _read: bool,
+    _value: u32,
+    _ack: DapAck,
+) {
+}
+
 bitfield! {
     /// A JTAG IDCODE.
     /// Identifies a particular Test Access Port (TAP) on the JTAG scan chain.
@@ -462,11 +462,11 @@ impl DebugProbe for EspUsbJtag {
         self.protocol.base_speed_khz / self.protocol.div_min as u32
     }
 
-    fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+    fn set_speed(&mut self, _speed_khz: u32) -> Result<u32, DebugProbeError> {
         // TODO:
         // can only go lower, base speed is max of 40000khz
 
-        Ok(speed_khz)
+        Ok(self.speed_khz())
     }
 
     fn set_scan_chain(&mut self, scan_chain: Vec<ScanChainElement>) -> Result<(), DebugProbeError> {
@@ -1,15 +1,22 @@
 #![allow(missing_docs)] // Don't require docs for test code
-use std::{cell::RefCell, collections::VecDeque, fmt::Debug, sync::Arc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    sync::Arc,
+};
 
 use probe_rs_target::ScanChainElement;
 
 use crate::{
     architecture::arm::{
         ap::{memory_ap::mock::MockMemoryAp, AccessPort, MemoryAp},
+        armv7m::{Aircr, Dcrdr, Demcr, FpCtrl},
         armv8m::Dhcsr,
         communication_interface::{
-            ArmDebugState, Initialized, SwdSequence, Uninitialized, UninitializedArmProbe,
+            ArmDebugState, DapProbe, Initialized, SwdSequence, Uninitialized, UninitializedArmProbe,
         },
+        core::{cortex_m::Dcrsr, Dfsr},
         dp::DebugPortError,
         memory::adi_v5_memory_interface::{ADIMemoryInterface, ArmProbe},
         sequences::ArmDebugSequence,
@@ -25,6 +32,8 @@ use crate::{
 pub struct FakeProbe {
     protocol: WireProtocol,
     speed: u32,
+    /// Highest speed accepted by [`DebugProbe::set_speed`]
+    max_speed: u32,
     scan_chain: Option<Vec<ScanChainElement>>,
 
     dap_register_read_handler: Option<Box<dyn Fn(PortType, u8) -> Result<u32, ArmError> + Send>>,
@@ -35,6 +44,9 @@ pub struct FakeProbe {
     operations: RefCell<VecDeque<Operation>>,
 
     memory_ap: MockedAp,
+
+    /// SWO data returned by the next reads, one chunk per read
+    swo_data: VecDeque<Vec<u8>>,
 }
 
 enum MockedAp {
@@ -44,11 +56,41 @@ enum MockedAp {
     Core(MockCore),
 }
 
+/// Start of the system region of the mocked core, memory below it behaves like RAM
+const SYSTEM_REGION_START: u64 = 0xE000_0000;
+
+#[allow(clippy::type_complexity)]
 struct MockCore {
     dhcsr: Dhcsr,
 
     /// Is the core halted?
     is_halted: bool,
+
+    /// Was the core reset since DHCSR was last read?
+    reset_since_dhcsr_read: bool,
+
+    dfsr: Dfsr,
+
+    demcr: Demcr,
+
+    /// Values of the core registers, by their DCRSR register selector, unwritten registers read as 0
+    core_registers: HashMap<u32, u32>,
+
+    dcrdr: u32,
+
+    /// Is the breakpoint unit enabled?
+    fpb_enabled: bool,
+
+    /// Values of the breakpoint comparators, the number of comparators
+    /// reported in FP_CTRL is the length of this list.
+    fpb_comparators: Vec<u32>,
+
+    /// Bytes written below [SYSTEM_REGION_START], unwritten bytes read as 0
+    ram: HashMap<u64, u8>,
+
+    memory_write_handler: Option<Box<dyn Fn(u64, u32) + Send>>,
+
+    memory_error_handler: Option<Box<dyn Fn(u64) -> Option<ArmError> + Send>>,
 }
 
 impl MockCore {
@@ -56,8 +98,62 @@ impl MockCore {
         Self {
             dhcsr: Dhcsr(0),
             is_halted: false,
+            reset_since_dhcsr_read: false,
+            dfsr: Dfsr::from(0),
+            demcr: Demcr::from(0),
+            core_registers: HashMap::new(),
+            dcrdr: 0,
+            fpb_enabled: false,
+            fpb_comparators: Vec::new(),
+            ram: HashMap::new(),
+            memory_write_handler: None,
+            memory_error_handler: None,
+        }
+    }
+
+    /// The error of a failed 32 bit access to `address`, if the access fails
+    fn memory_error(&self, address: u64) -> Result<(), ArmError> {
+        match self
+            .memory_error_handler
+            .as_ref()
+            .and_then(|handler| handler(address))
+        {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// The index of the breakpoint comparator at `address`, if there is one
+    fn fpb_comparator(&self, address: u64) -> Option<usize> {
+        let offset = address.checked_sub(FpCtrl::ADDRESS_OFFSET + 8)?;
+        let index = (offset / 4) as usize;
+
+        (offset % 4 == 0 && index < self.fpb_comparators.len()).then_some(index)
+    }
+
+    fn read_ram(&self, address: u64, data: &mut [u8]) {
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = self.ram.get(&(address + i as u64)).copied().unwrap_or(0);
+        }
+    }
+
+    fn write_ram(&mut self, address: u64, data: &[u8]) {
+        for (i, byte) in data.iter().enumerate() {
+            self.ram.insert(address + i as u64, *byte);
         }
     }
+
+    /// Reset the core, which clears the breakpoint unit and halts the core
+    /// if a reset vector catch is set.
+    fn reset(&mut self) {
+        println!("MockCore: Reset");
+
+        self.reset_since_dhcsr_read = true;
+        self.is_halted = self.demcr.vc_corereset();
+        self.dfsr.set_vcatch(self.is_halted);
+        self.fpb_enabled = false;
+        self.fpb_comparators.fill(0);
+    }
 }
 
 impl SwdSequence for &mut MockCore {
@@ -76,13 +172,20 @@ impl SwdSequence for &mut MockCore {
 }
 
 impl ArmProbe for &mut MockCore {
-    fn read_8(&mut self, _address: u64, _data: &mut [u8]) -> Result<(), ArmError> {
-        todo!()
+    fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), ArmError> {
+        if address + data.len() as u64 > SYSTEM_REGION_START {
+            todo!()
+        }
+
+        self.read_ram(address, data);
+
+        Ok(())
     }
 
     fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
         for (i, val) in data.iter_mut().enumerate() {
             let address = address + (i as u64 * 4);
+            self.memory_error(address)?;
 
             match address {
                 // DHCSR
@@ -97,10 +200,36 @@ impl ArmProbe for &mut MockCore {
                     // be read.
                     dhcsr |= 1 << 16;
 
+                    // S_RESET_ST is cleared by reading DHCSR
+                    if std::mem::take(&mut self.reset_since_dhcsr_read) {
+                        dhcsr |= 1 << 25;
+                    }
+
                     *val = dhcsr;
                     println!("Read  DHCSR: {:#x} = {:#x}", address, val);
                 }
+                Dfsr::ADDRESS_OFFSET => *val = self.dfsr.into(),
+                Dcrdr::ADDRESS_OFFSET => *val = self.dcrdr,
+                Demcr::ADDRESS_OFFSET => *val = self.demcr.into(),
+                FpCtrl::ADDRESS_OFFSET => {
+                    let num_code = self.fpb_comparators.len() as u32;
+
+                    *val = ((num_code >> 4) << 12) | ((num_code & 0xf) << 4);
+
+                    if self.fpb_enabled {
+                        *val |= 1;
+                    }
+                }
+                address if self.fpb_comparator(address).is_some() => {
+                    *val = self.fpb_comparators[self.fpb_comparator(address).unwrap()];
+                }
+
+                address if address < SYSTEM_REGION_START => {
+                    let mut bytes = [0; 4];
+                    self.read_ram(address, &mut bytes);
 
+                    *val = u32::from_le_bytes(bytes);
+                }
                 _ => {
                     *val = 0;
                     println!("Read {:#010x} = 0", address);
@@ -111,17 +240,39 @@ impl ArmProbe for &mut MockCore {
         Ok(())
     }
 
-    fn read_64(&mut self, _address: u64, _data: &mut [u64]) -> Result<(), ArmError> {
-        todo!()
+    fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), ArmError> {
+        if address + data.len() as u64 * 8 > SYSTEM_REGION_START {
+            todo!()
+        }
+
+        for (i, val) in data.iter_mut().enumerate() {
+            let mut bytes = [0; 8];
+            self.read_ram(address + i as u64 * 8, &mut bytes);
+
+            *val = u64::from_le_bytes(bytes);
+        }
+
+        Ok(())
     }
 
-    fn write_8(&mut self, _address: u64, _data: &[u8]) -> Result<(), ArmError> {
-        todo!()
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), ArmError> {
+        if address + data.len() as u64 > SYSTEM_REGION_START {
+            todo!()
+        }
+
+        self.write_ram(address, data);
+
+        Ok(())
     }
 
     fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
         for (i, word) in data.iter().enumerate() {
             let address = address + (i as u64 * 4);
+            self.memory_error(address)?;
+
+            if let Some(handler) = &self.memory_write_handler {
+                handler(address, *word);
+            }
 
             match address {
                 // DHCSR
@@ -140,9 +291,53 @@ impl ArmProbe for &mut MockCore {
                         if !self.dhcsr.c_halt() && self.dhcsr.c_debugen() && self.dhcsr.c_step() {
                             tracing::debug!("MockCore: Single step requested, setting s_halt");
                             self.is_halted = true;
+
+                            // Every instruction is a 16 bit instruction which doesn't branch
+                            let pc = self.core_registers.entry(15).or_default();
+                            *pc = pc.wrapping_add(2);
+                        }
+
+                        if self.is_halted {
+                            self.dfsr.set_halted(true);
                         }
                     }
                 }
+                Dfsr::ADDRESS_OFFSET => {
+                    // Bits are cleared by writing 1 to them
+                    self.dfsr = Dfsr::from(u32::from(self.dfsr) & !*word);
+                }
+                Dcrdr::ADDRESS_OFFSET => self.dcrdr = *word,
+                Dcrsr::ADDRESS_OFFSET => {
+                    let register = *word & 0x7f;
+
+                    if *word & (1 << 16) != 0 {
+                        self.core_registers.insert(register, self.dcrdr);
+                    } else {
+                        self.dcrdr = self.core_registers.get(&register).copied().unwrap_or(0);
+                    }
+                }
+                Aircr::ADDRESS_OFFSET => {
+                    let aircr = Aircr::from(*word);
+
+                    if aircr.get_vectkeystat() == 0x05fa && aircr.sysresetreq() {
+                        self.reset();
+                    }
+                }
+                Demcr::ADDRESS_OFFSET => self.demcr = Demcr::from(*word),
+                FpCtrl::ADDRESS_OFFSET => {
+                    // Writes without the KEY bit are ignored
+                    if *word & 0b10 != 0 {
+                        self.fpb_enabled = *word & 1 != 0;
+                    }
+                }
+                address if self.fpb_comparator(address).is_some() => {
+                    let index = self.fpb_comparator(address).unwrap();
+
+                    self.fpb_comparators[index] = *word;
+                }
+                address if address < SYSTEM_REGION_START => {
+                    self.write_ram(address, &word.to_le_bytes());
+                }
                 _ => println!("Write {:#010x} = {:#010x}", address, word),
             }
         }
@@ -163,7 +358,7 @@ impl ArmProbe for &mut MockCore {
     }
 
     fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
-        todo!()
+        Ok(true)
     }
 
     fn ap(&mut self) -> MemoryAp {
@@ -189,6 +384,11 @@ pub enum Operation {
         address: u8,
         result: u32,
     },
+    WriteRawApRegister {
+        ap: ApAddress,
+        address: u8,
+        value: u32,
+    },
 }
 
 impl Debug for FakeProbe {
@@ -206,6 +406,7 @@ impl FakeProbe {
         FakeProbe {
             protocol: WireProtocol::Swd,
             speed: 1000,
+            max_speed: u32::MAX,
             scan_chain: None,
 
             dap_register_read_handler: None,
@@ -214,6 +415,8 @@ impl FakeProbe {
             operations: RefCell::new(VecDeque::new()),
 
             memory_ap: MockedAp::MemoryAp(MockMemoryAp::with_pattern()),
+
+            swo_data: VecDeque::new(),
         }
     }
 
@@ -222,6 +425,7 @@ impl FakeProbe {
         FakeProbe {
             protocol: WireProtocol::Swd,
             speed: 1000,
+            max_speed: u32::MAX,
             scan_chain: None,
 
             dap_register_read_handler: None,
@@ -230,6 +434,8 @@ impl FakeProbe {
             operations: RefCell::new(VecDeque::new()),
 
             memory_ap: MockedAp::Core(MockCore::new()),
+
+            swo_data: VecDeque::new(),
         }
     }
 
@@ -251,6 +457,49 @@ impl FakeProbe {
         self.dap_register_write_handler = Some(handler);
     }
 
+    /// This sets the handler for memory writes to the mocked core.
+    /// Can be used to check which registers of the core are written.
+    ///
+    /// Only has an effect on a probe created with [`FakeProbe::with_mocked_core`].
+    pub fn set_memory_write_handler(&mut self, handler: Box<dyn Fn(u64, u32) + Send>) {
+        if let MockedAp::Core(core) = &mut self.memory_ap {
+            core.memory_write_handler = Some(handler);
+        }
+    }
+
+    /// This sets the handler for failed memory accesses of the mocked core, it returns the
+    /// error of an access to the given address. Can be used to simulate a lost connection
+    /// to the target.
+    ///
+    /// Only has an effect on a probe created with [`FakeProbe::with_mocked_core`].
+    pub fn set_memory_error_handler(
+        &mut self,
+        handler: Box<dyn Fn(u64) -> Option<ArmError> + Send>,
+    ) {
+        if let MockedAp::Core(core) = &mut self.memory_ap {
+            core.memory_error_handler = Some(handler);
+        }
+    }
+
+    /// Queue SWO data, which is returned by a single read of the SWO interface.
+    pub fn add_swo_data(&mut self, data: Vec<u8>) {
+        self.swo_data.push_back(data);
+    }
+
+    /// This sets the number of breakpoint comparators of the mocked core, which has none by default.
+    ///
+    /// Only has an effect on a probe created with [`FakeProbe::with_mocked_core`].
+    pub fn set_breakpoint_units(&mut self, units: usize) {
+        if let MockedAp::Core(core) = &mut self.memory_ap {
+            core.fpb_comparators = vec![0; units];
+        }
+    }
+
+    /// Limit the speed of the probe, higher speeds are rejected as unsupported.
+    pub fn set_max_speed(&mut self, speed_khz: u32) {
+        self.max_speed = speed_khz;
+    }
+
     /// Makes a generic probe out of the [`FakeProbe`]
     pub fn into_probe(self) -> Probe {
         Probe::from_specific_probe(Box::new(self))
@@ -279,7 +528,28 @@ impl FakeProbe {
                 Ok(result)
             }
             None => panic!("No more operations expected, but got read_raw_ap_register ap={expected_ap:?}, address:{expected_address}"),
-            //other => panic!("Unexpected operation: {:?}", other),
+            Some(other) => panic!("Expected {other:?}, but got read_raw_ap_register ap={expected_ap:?}, address:{expected_address}"),
+        }
+    }
+
+    fn write_raw_ap_register(
+        &mut self,
+        expected_ap: ApAddress,
+        expected_address: u8,
+        expected_value: u32,
+    ) -> Result<(), ArmError> {
+        let operation = self.next_operation();
+
+        match operation {
+            Some(Operation::WriteRawApRegister { ap, address, value }) => {
+                assert_eq!(ap, expected_ap);
+                assert_eq!(address, expected_address);
+                assert_eq!(value, expected_value);
+
+                Ok(())
+            }
+            None => panic!("No more operations expected, but got write_raw_ap_register ap={expected_ap:?}, address:{expected_address}, value:{expected_value:#x}"),
+            Some(other) => panic!("Expected {other:?}, but got write_raw_ap_register ap={expected_ap:?}, address:{expected_address}, value:{expected_value:#x}"),
         }
     }
 
@@ -319,6 +589,10 @@ impl DebugProbe for FakeProbe {
     }
 
     fn set_speed(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        if speed_khz > self.max_speed {
+            return Err(DebugProbeError::UnsupportedSpeed(speed_khz));
+        }
+
         self.speed = speed_khz;
 
         Ok(speed_khz)
@@ -373,11 +647,14 @@ impl DebugProbe for FakeProbe {
 }
 
 impl RawDapAccess for FakeProbe {
-    fn select_dp(&mut self, _dp: DpAddress) -> Result<(), ArmError> {
-        Err(DebugPortError::Unsupported(
-            "Fake debug probe does not support DP selection.".to_string(),
-        )
-        .into())
+    fn select_dp(&mut self, dp: DpAddress) -> Result<(), ArmError> {
+        match dp {
+            DpAddress::Default => Ok(()),
+            DpAddress::Multidrop(_) => Err(DebugPortError::Unsupported(
+                "Fake debug probe does not support multidrop DP selection.".to_string(),
+            )
+            .into()),
+        }
     }
 
     /// Reads the DAP register on the specified port and address
@@ -399,7 +676,7 @@ impl RawDapAccess for FakeProbe {
     }
 
     fn swj_sequence(&mut self, _bit_len: u8, _bits: u64) -> Result<(), DebugProbeError> {
-        todo!()
+        Ok(())
     }
 
     fn swj_pins(
@@ -420,6 +697,8 @@ impl RawDapAccess for FakeProbe {
     }
 }
 
+impl DapProbe for FakeProbe {}
+
 #[derive(Debug)]
 struct FakeArmInterface<S: ArmDebugState> {
     probe: Box<FakeProbe>,
@@ -544,6 +823,10 @@ impl ArmProbeInterface for FakeArmInterface<Initialized> {
         Ok(None)
     }
 
+    fn speed_khz(&self) -> u32 {
+        self.probe.speed_khz()
+    }
+
     fn close(self: Box<Self>) -> Probe {
         Probe::from_attached_probe(self.probe)
     }
@@ -562,7 +845,7 @@ impl SwoAccess for FakeArmInterface<Initialized> {
     }
 
     fn read_swo_timeout(&mut self, _timeout: std::time::Duration) -> Result<Vec<u8>, ArmError> {
-        unimplemented!()
+        Ok(self.probe.swo_data.pop_front().unwrap_or_default())
     }
 }
 
@@ -577,7 +860,8 @@ impl DapAccess for FakeArmInterface<Initialized> {
         _address: u8,
         _value: u32,
     ) -> Result<(), ArmError> {
-        todo!()
+        // Accept writes like the debug port power down when a session ends
+        Ok(())
     }
 
     fn read_raw_ap_register(&mut self, _ap: ApAddress, _address: u8) -> Result<u32, ArmError> {
@@ -595,11 +879,11 @@ impl DapAccess for FakeArmInterface<Initialized> {
 
     fn write_raw_ap_register(
         &mut self,
-        _ap: ApAddress,
-        _address: u8,
-        _value: u32,
+        ap: ApAddress,
+        address: u8,
+        value: u32,
     ) -> Result<(), ArmError> {
-        todo!()
+        self.probe.write_raw_ap_register(ap, address, value)
     }
 
     fn write_raw_ap_register_repeated(
@@ -613,9 +897,77 @@ impl DapAccess for FakeArmInterface<Initialized> {
 }
 
 #[cfg(test)]
-mod test {
-    use super::FakeProbe;
-    use crate::Permissions;
+pub(crate) mod test {
+    use std::io::Read;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::{FakeProbe, Operation};
+    use crate::{
+        architecture::arm::{
+            armv6m::Aircr,
+            armv7m::{Demcr, FpCtrl},
+            armv8m::Dhcsr,
+            ApAddress, ArmError, DapError,
+        },
+        flashing::{FileDownloadError, FlashError},
+        DebugProbeError, DetachMode, Error, MemoryMappedRegister, Permissions, Session,
+    };
+
+    /// Attach to a mocked nRF52833 core, recording all memory writes to it
+    pub(crate) fn attach_mocked_core(hot_attach: bool) -> (Session, Arc<Mutex<Vec<(u64, u32)>>>) {
+        attach_configured_core(hot_attach, |_| {})
+    }
+
+    /// Like [attach_mocked_core], with the probe configured by `configure` before attaching
+    pub(crate) fn attach_configured_core(
+        hot_attach: bool,
+        configure: impl FnOnce(&mut FakeProbe),
+    ) -> (Session, Arc<Mutex<Vec<(u64, u32)>>>) {
+        attach_mocked_target("nrf52833_xxaa", hot_attach, |probe| {
+            configure(probe);
+
+            // Indicate that the core is unlocked
+            probe.expect_operation(Operation::ReadRawApRegister {
+                ap: ApAddress::with_default_dp(1),
+                address: 0xC,
+                result: 1,
+            });
+        })
+    }
+
+    /// Attach to a mocked core of the chip `target`, recording all memory writes to it
+    fn attach_mocked_target(
+        target: &str,
+        hot_attach: bool,
+        configure: impl FnOnce(&mut FakeProbe),
+    ) -> (Session, Arc<Mutex<Vec<(u64, u32)>>>) {
+        let mut fake_probe = FakeProbe::with_mocked_core();
+        configure(&mut fake_probe);
+
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let recorded_writes = writes.clone();
+        fake_probe.set_memory_write_handler(Box::new(move |address, value| {
+            recorded_writes.lock().unwrap().push((address, value));
+        }));
+
+        let probe = fake_probe.into_probe();
+        let session = if hot_attach {
+            probe.hot_attach(target, Permissions::default())
+        } else {
+            probe.attach(target, Permissions::default())
+        };
+
+        (session.unwrap(), writes)
+    }
+
+    /// Whether one of the writes resumes the core
+    fn resumes_core(writes: &[(u64, u32)]) -> bool {
+        writes
+            .iter()
+            .any(|&(address, value)| address == Dhcsr::ADDRESS_OFFSET && !Dhcsr(value).c_halt())
+    }
 
     #[test]
     fn create_session_with_fake_probe() {
@@ -627,4 +979,414 @@ mod test {
             .attach("nrf51822_xxAC", Permissions::default())
             .unwrap();
     }
+
+    /// Whether the first comparator of the breakpoint unit was cleared
+    fn clears_breakpoint(writes: &[(u64, u32)]) -> bool {
+        writes
+            .iter()
+            .any(|&(address, value)| address == FpCtrl::ADDRESS_OFFSET + 8 && value & 1 == 0)
+    }
+
+    /// Attach with a breakpoint set and the core halted
+    fn attach_halted_with_breakpoint() -> (Session, Arc<Mutex<Vec<(u64, u32)>>>) {
+        let (mut session, writes) =
+            attach_configured_core(false, |probe| probe.set_breakpoint_units(2));
+
+        let mut core = session.core(0).unwrap();
+        core.halt(Duration::from_millis(100)).unwrap();
+        core.set_hw_breakpoint(0x1000).unwrap();
+        drop(core);
+        writes.lock().unwrap().clear();
+
+        (session, writes)
+    }
+
+    #[test]
+    fn drop_keeps_halted_core_halted() {
+        let (session, writes) = attach_halted_with_breakpoint();
+
+        drop(session);
+
+        let writes = writes.lock().unwrap();
+        assert!(clears_breakpoint(&writes));
+        assert!(!resumes_core(&writes));
+    }
+
+    #[test]
+    fn drop_with_resume_on_detach_resumes_halted_core() {
+        let (mut session, writes) = attach_halted_with_breakpoint();
+        session.set_resume_on_detach(true);
+
+        drop(session);
+
+        let writes = writes.lock().unwrap();
+        assert!(clears_breakpoint(&writes));
+        assert!(resumes_core(&writes));
+    }
+
+    #[test]
+    fn drop_with_leave_halted_keeps_core_halted() {
+        let (mut session, writes) = attach_halted_with_breakpoint();
+        session.set_detach_mode(DetachMode::LeaveHalted);
+        // Only applies to DetachMode::Restore
+        session.set_resume_on_detach(true);
+
+        drop(session);
+
+        // Breakpoints are still cleared and debugging is stopped
+        let writes = writes.lock().unwrap();
+        assert!(clears_breakpoint(&writes));
+        assert!(writes
+            .iter()
+            .any(|&(address, value)| address == Demcr::ADDRESS_OFFSET && value == 0));
+        assert!(!resumes_core(&writes));
+    }
+
+    #[test]
+    fn set_speed_enables_debugging_when_the_speed_is_rejected() {
+        let (mut session, writes) =
+            attach_configured_core(false, |probe| probe.set_max_speed(4000));
+        writes.lock().unwrap().clear();
+
+        assert!(matches!(
+            session.set_speed(8000),
+            Err(Error::Probe(DebugProbeError::UnsupportedSpeed(8000)))
+        ));
+        assert_eq!(session.speed_khz(), 1000);
+
+        // The probe was re-attached anyway, so debugging has to be enabled again
+        assert!(writes
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|&(address, value)| address == Dhcsr::ADDRESS_OFFSET && Dhcsr(value).c_debugen()));
+    }
+
+    #[test]
+    fn swo_reader_keeps_unread_data_in_order() {
+        let (mut session, _writes) = attach_configured_core(false, |probe| {
+            probe.add_swo_data((0..6).collect());
+            probe.add_swo_data((6..12).collect());
+        });
+
+        let mut reader = session.swo_reader().unwrap().with_capacity(8);
+        let mut buf = [0; 8];
+
+        assert_eq!(reader.read(&mut buf[..4]).unwrap(), 4);
+        assert_eq!(buf[..4], [0, 1, 2, 3]);
+        assert_eq!(reader.buffered_len(), 2);
+
+        // The second chunk fills the buffer to its capacity, past the end of the first chunk
+        assert_eq!(reader.read(&mut buf).unwrap(), 8);
+        assert_eq!(buf, [4, 5, 6, 7, 8, 9, 10, 11]);
+        assert_eq!(reader.buffered_len(), 0);
+        assert_eq!(reader.overflow_count(), 0);
+
+        // No more data available
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn swo_reader_discards_oldest_data_on_overflow() {
+        let (mut session, _writes) = attach_configured_core(false, |probe| {
+            probe.add_swo_data((0..6).collect());
+            probe.add_swo_data((6..9).collect());
+        });
+
+        let mut reader = session.swo_reader().unwrap().with_capacity(4);
+        let mut buf = [0; 8];
+
+        assert_eq!(reader.read(&mut buf[..2]).unwrap(), 2);
+        assert_eq!(buf[..2], [2, 3]);
+        assert_eq!(reader.overflow_count(), 2);
+
+        assert_eq!(reader.read(&mut buf).unwrap(), 4);
+        assert_eq!(buf[..4], [5, 6, 7, 8]);
+        assert_eq!(reader.overflow_count(), 3);
+    }
+
+    #[test]
+    fn read_into_and_write_from_unaligned_values() {
+        let (mut session, _writes) = attach_mocked_core(false);
+        let mut core = session.core(0).unwrap();
+
+        core.write_from(0x2000_0000, 0x0807_0605_0403_0201u64)
+            .unwrap();
+
+        // Reads crossing a word boundary, with lengths which are not a multiple of 4
+        assert_eq!(core.read_into::<u8>(0x2000_0005).unwrap(), 0x06);
+        assert_eq!(core.read_into::<u16>(0x2000_0003).unwrap(), 0x0504);
+        assert_eq!(core.read_into::<u32>(0x2000_0002).unwrap(), 0x0605_0403);
+        assert_eq!(
+            core.read_into::<u64>(0x2000_0000).unwrap(),
+            0x0807_0605_0403_0201
+        );
+
+        // Unaligned writes only change the bytes of the value
+        core.write_from(0x2000_0003, 0xbbaau16).unwrap();
+        core.write_from(0x2000_0006, 0xccu8).unwrap();
+        assert_eq!(
+            core.read_into::<u64>(0x2000_0000).unwrap(),
+            0x08cc_06bb_aa03_0201
+        );
+
+        // Values ending in the next word, e.g. a 32 bit value at an unaligned address
+        core.write_from(0x2000_0006, 0x4433_2211u32).unwrap();
+        assert_eq!(core.read_into::<u16>(0x2000_0008).unwrap(), 0x4433);
+        assert_eq!(core.read_into::<u32>(0x2000_0005).unwrap(), 0x3322_1106);
+    }
+
+    #[test]
+    fn read_stack_registers_applies_to_later_core_handles() {
+        let (mut session, _writes) = attach_mocked_core(false);
+
+        let mut core = session.core(0).unwrap();
+        let info = core.halt(Duration::from_millis(100)).unwrap();
+        assert_eq!((info.sp, info.lr), (None, None));
+
+        core.set_read_stack_registers(true);
+        drop(core);
+
+        let info = session
+            .core(0)
+            .unwrap()
+            .halt(Duration::from_millis(100))
+            .unwrap();
+        assert_eq!((info.sp, info.lr), (Some(0), Some(0)));
+    }
+
+    /// Check that attaching neither reset nor halted the core, and that nothing is written
+    /// when the session ends. Returns the writes while attaching.
+    fn check_hot_attach(session: Session, writes: &Mutex<Vec<(u64, u32)>>) -> Vec<(u64, u32)> {
+        let attach_writes = std::mem::take(&mut *writes.lock().unwrap());
+
+        assert!(!attach_writes.is_empty());
+        for &(address, value) in &attach_writes {
+            assert_ne!(
+                address,
+                Aircr::ADDRESS_OFFSET,
+                "AIRCR written during hot attach"
+            );
+
+            if address == Dhcsr::ADDRESS_OFFSET {
+                assert!(
+                    !Dhcsr(value).c_halt(),
+                    "DHCSR.C_HALT set during hot attach: {value:#010x}"
+                );
+            }
+        }
+
+        // Breakpoints, DHCSR and DEMCR are left as they are when the session ends
+        drop(session);
+
+        assert!(writes.lock().unwrap().is_empty());
+
+        attach_writes
+    }
+
+    #[test]
+    fn hot_attach_does_not_reset_or_halt() {
+        let (session, writes) = attach_mocked_core(true);
+
+        check_hot_attach(session, &writes);
+    }
+
+    #[test]
+    fn hot_attach_to_stm32_does_not_reset_or_halt() {
+        let (session, writes) = attach_mocked_target("STM32F411RETx", true, |_| {});
+
+        let attach_writes = check_hot_attach(session, &writes);
+
+        // The unlock sequence keeps the debug connection alive in low power modes,
+        // by setting DBG_SLEEP, DBG_STOP and DBG_STANDBY in DBGMCU_CR
+        assert!(attach_writes.contains(&(0xE004_2004, 0b111)));
+    }
+
+    #[test]
+    fn download_bin_beyond_flash_is_rejected() {
+        let (mut session, writes) = attach_mocked_core(false);
+        writes.lock().unwrap().clear();
+
+        let path =
+            std::env::temp_dir().join(format!("probe-rs-download-bin-{}.bin", std::process::id()));
+        std::fs::write(&path, [0x55; 0x200]).unwrap();
+
+        // The flash of the nRF52833 ends at 0x8_0000
+        let result = session.download_bin(&path, 0x7_ff00);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(FileDownloadError::Flash(FlashError::NoSuitableNvm {
+                start: 0x7_ff00,
+                end: 0x8_0100,
+                ..
+            }))
+        ));
+        assert!(writes.lock().unwrap().is_empty());
+    }
+
+    /// Ping the core of a mocked nRF52833 whose reads of DHCSR fail with `error` after attaching
+    fn ping_with_dhcsr_error(error: Option<DapError>) -> Result<bool, Error> {
+        let dhcsr_error = Arc::new(Mutex::new(None));
+        let handler_error = dhcsr_error.clone();
+
+        let (mut session, _writes) = attach_configured_core(false, |probe| {
+            probe.set_memory_error_handler(Box::new(move |address| {
+                let error = handler_error.lock().unwrap().clone();
+
+                error
+                    .filter(|_| address == Dhcsr::get_mmio_address())
+                    .map(ArmError::Dap)
+            }));
+        });
+
+        let mut core = session.core(0).unwrap();
+        *dhcsr_error.lock().unwrap() = error;
+        let result = core.ping();
+
+        // Let the session detach cleanly
+        *dhcsr_error.lock().unwrap() = None;
+
+        result
+    }
+
+    #[test]
+    fn ping_responding_core() {
+        assert!(ping_with_dhcsr_error(None).unwrap());
+    }
+
+    #[test]
+    fn ping_unresponsive_core() {
+        assert!(!ping_with_dhcsr_error(Some(DapError::NoAcknowledge)).unwrap());
+    }
+
+    #[test]
+    fn ping_returns_fault_response() {
+        assert!(matches!(
+            ping_with_dhcsr_error(Some(DapError::FaultResponse)),
+            Err(Error::Arm(ArmError::Dap(DapError::FaultResponse)))
+        ));
+    }
+
+    /// Attach with a breakpoint set, memory accesses for which `fails` returns `true`
+    /// are not acknowledged by the target
+    fn attach_with_breakpoint(
+        auto_reattach: bool,
+        fails: impl Fn(u64) -> bool + Send + 'static,
+    ) -> (Session, Arc<Mutex<Vec<(u64, u32)>>>) {
+        let (mut session, writes) = attach_configured_core(false, |probe| {
+            probe.set_breakpoint_units(2);
+            probe.set_memory_error_handler(Box::new(move |address| {
+                fails(address).then_some(ArmError::Dap(DapError::NoAcknowledge))
+            }));
+        });
+
+        session.set_auto_reattach(auto_reattach);
+        session.core(0).unwrap().set_hw_breakpoint(0x1000).unwrap();
+        writes.lock().unwrap().clear();
+
+        (session, writes)
+    }
+
+    /// Fails the given number of memory accesses, starting with the next one
+    fn fail_next_accesses(failures: &Arc<AtomicUsize>) -> impl Fn(u64) -> bool + Send + 'static {
+        let failures = failures.clone();
+
+        move |_| {
+            failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok()
+        }
+    }
+
+    /// Whether the breakpoint unit was enabled and the first comparator programmed
+    fn sets_breakpoint(writes: &[(u64, u32)]) -> bool {
+        let enables_unit = writes
+            .iter()
+            .any(|&(address, value)| address == FpCtrl::ADDRESS_OFFSET && value & 0b11 == 0b11);
+        let sets_comparator = writes
+            .iter()
+            .any(|&(address, value)| address == FpCtrl::ADDRESS_OFFSET + 8 && value & 1 == 1);
+
+        enables_unit && sets_comparator
+    }
+
+    #[test]
+    fn auto_reattach_retries_failed_operation() {
+        let failures = Arc::new(AtomicUsize::new(0));
+        let (mut session, writes) = attach_with_breakpoint(true, fail_next_accesses(&failures));
+
+        // The target was power cycled, the first transfer isn't acknowledged
+        failures.store(1, Ordering::SeqCst);
+
+        let mut attempts = 0;
+        let value = session.with_core(0, |core| {
+            attempts += 1;
+            core.read_word_32(0x2000_0000)
+        });
+
+        assert_eq!(value.unwrap(), 0);
+        assert_eq!(attempts, 2);
+        assert!(sets_breakpoint(&writes.lock().unwrap()));
+    }
+
+    #[test]
+    fn auto_reattach_returns_error_of_repeated_operation() {
+        let (mut session, writes) = attach_with_breakpoint(true, |address| address == 0x2000_0000);
+
+        let mut attempts = 0;
+        let value = session.with_core(0, |core| {
+            attempts += 1;
+            core.read_word_32(0x2000_0000)
+        });
+
+        assert!(matches!(
+            value,
+            Err(Error::Arm(ArmError::Dap(DapError::NoAcknowledge)))
+        ));
+        // Re-attached once, not for the second failure
+        assert_eq!(attempts, 2);
+        assert!(sets_breakpoint(&writes.lock().unwrap()));
+    }
+
+    #[test]
+    fn auto_reattach_returns_error_if_reattaching_fails() {
+        let failures = Arc::new(AtomicUsize::new(0));
+        let (mut session, writes) = attach_with_breakpoint(true, fail_next_accesses(&failures));
+
+        // The operation and enabling debugging while re-attaching fail
+        failures.store(2, Ordering::SeqCst);
+
+        let mut attempts = 0;
+        let value = session.with_core(0, |core| {
+            attempts += 1;
+            core.read_word_32(0x2000_0000)
+        });
+
+        assert!(matches!(
+            value,
+            Err(Error::Arm(ArmError::Dap(DapError::NoAcknowledge)))
+        ));
+        assert_eq!(attempts, 1);
+        assert!(!sets_breakpoint(&writes.lock().unwrap()));
+    }
+
+    #[test]
+    fn no_reattach_without_auto_reattach() {
+        let failures = Arc::new(AtomicUsize::new(0));
+        let (mut session, writes) = attach_with_breakpoint(false, fail_next_accesses(&failures));
+
+        failures.store(1, Ordering::SeqCst);
+
+        let mut attempts = 0;
+        let value = session.with_core(0, |core| {
+            attempts += 1;
+            core.read_word_32(0x2000_0000)
+        });
+
+        assert!(value.is_err());
+        assert_eq!(attempts, 1);
+        assert!(writes.lock().unwrap().is_empty());
+    }
 }
@@ -458,6 +458,10 @@ impl DebugProbe for FtdiProbe {
         Ok(speed_khz)
     }
 
+    fn speed_is_approximate(&self) -> bool {
+        true
+    }
+
     fn set_scan_chain(&mut self, scan_chain: Vec<ScanChainElement>) -> Result<(), DebugProbeError> {
         tracing::info!("Setting scan chain to {:?}", scan_chain);
         self.scan_chain = Some(scan_chain);
@@ -507,18 +511,23 @@ impl DebugProbe for FtdiProbe {
     }
 
     fn target_reset(&mut self) -> Result<(), DebugProbeError> {
-        tracing::error!("FTDI target_reset");
-        unimplemented!()
+        Err(DebugProbeError::CommandNotSupportedByProbe("target_reset"))
     }
 
     fn target_reset_assert(&mut self) -> Result<(), DebugProbeError> {
-        tracing::error!("FTDI target_reset_assert");
-        unimplemented!()
+        Err(DebugProbeError::CommandNotSupportedByProbe(
+            "target_reset_assert",
+        ))
     }
 
     fn target_reset_deassert(&mut self) -> Result<(), DebugProbeError> {
-        tracing::error!("FTDI target_reset_deassert");
-        unimplemented!()
+        Err(DebugProbeError::CommandNotSupportedByProbe(
+            "target_reset_deassert",
+        ))
+    }
+
+    fn has_reset_pin(&self) -> bool {
+        false
     }
 
     fn select_protocol(&mut self, protocol: WireProtocol) -> Result<(), DebugProbeError> {
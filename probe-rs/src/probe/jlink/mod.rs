@@ -21,7 +21,8 @@ use crate::{
     },
     probe::{
         arm_jtag::{ProbeStatistics, RawProtocolIo, SwdSettings},
-        DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeType, JTAGAccess, WireProtocol,
+        DebugProbe, DebugProbeError, DebugProbeInfo, DebugProbeType, JTAGAccess, ProbeDetails,
+        WireProtocol,
     },
     DebugProbeSelector,
 };
@@ -437,7 +438,9 @@ impl DebugProbe for JLink {
             return Err(DebugProbeError::UnsupportedSpeed(speed_khz));
         }
 
-        if let Ok(speeds) = self.handle.read_speeds() {
+        let speeds = self.handle.read_speeds().ok();
+
+        if let Some(speeds) = &speeds {
             tracing::debug!("Supported speeds: {:?}", speeds);
 
             let max_speed_khz = speeds.max_speed_hz() / 1000;
@@ -449,12 +452,32 @@ impl DebugProbe for JLink {
 
         if let Some(expected_speed) = SpeedConfig::khz(speed_khz as u16) {
             self.handle.set_speed(expected_speed)?;
-            self.speed_khz = speed_khz;
         } else {
             return Err(DebugProbeError::UnsupportedSpeed(speed_khz));
         }
 
-        Ok(speed_khz)
+        // The probe divides its base frequency by an integer, rounding the speed down.
+        self.speed_khz = speeds.map_or(speed_khz, |speeds| {
+            let speed_hz = speed_khz * 1000;
+            let divider = ((speeds.base_freq() + speed_hz - 1) / speed_hz)
+                .max(u32::from(speeds.min_div()))
+                .max(1);
+
+            speeds.base_freq() / divider / 1000
+        });
+
+        Ok(self.speed_khz)
+    }
+
+    fn set_transfer_retries(
+        &mut self,
+        wait_retries: usize,
+        fault_retries: usize,
+    ) -> Result<(), DebugProbeError> {
+        self.swd_settings.num_retries_after_wait = wait_retries;
+        self.swd_settings.num_retries_after_fault = fault_retries;
+
+        Ok(())
     }
 
     fn attach(&mut self) -> Result<(), super::DebugProbeError> {
@@ -589,6 +612,15 @@ impl DebugProbe for JLink {
         Some(self as _)
     }
 
+    fn details(&mut self) -> ProbeDetails {
+        ProbeDetails {
+            firmware_version: self.handle.read_firmware_version().ok(),
+            protocols: self.supported_protocols.clone(),
+            swo_supported: self.handle.capabilities().contains(Capability::Swo),
+            ..Default::default()
+        }
+    }
+
     fn get_swo_interface_mut(&mut self) -> Option<&mut dyn SwoAccess> {
         Some(self as _)
     }
@@ -12,6 +12,22 @@ use super::{
 #[cfg(feature = "ftdi")]
 use super::ftdi;
 
+use std::sync::{Mutex, MutexGuard};
+
+/// Serializes listing and opening probes within the process.
+///
+/// Some drivers briefly open USB devices to read their serial numbers, which fails or disturbs
+/// another thread opening the same device at the time. Opened probes are not affected by this
+/// lock, every [`Probe`] owns its USB handle exclusively.
+static PROBE_ENUMERATION: Mutex<()> = Mutex::new(());
+
+fn lock_probe_enumeration() -> MutexGuard<'static, ()> {
+    // The lock protects no data, so a panic while holding it leaves nothing inconsistent
+    PROBE_ENUMERATION
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
 /// Struct to list all attached debug probes
 #[derive(Debug)]
 pub struct Lister {
@@ -40,6 +56,103 @@ impl Lister {
     pub fn list_all(&self) -> Vec<DebugProbeInfo> {
         self.lister.list_all()
     }
+
+    /// Open the only attached debug probe
+    ///
+    /// If no probe is attached, [`ProbeCreationError::NotFound`] is returned. If multiple
+    /// probes are attached, [`ProbeCreationError::MultipleProbesFound`] lists them, so the
+    /// user can select one.
+    pub fn open_single(&self) -> Result<Probe, DebugProbeError> {
+        self.open(self.select_single()?)
+    }
+
+    /// Find the only attached debug probe, without opening it
+    ///
+    /// See [`Lister::open_single`] for the returned errors.
+    pub fn select_single(&self) -> Result<DebugProbeInfo, DebugProbeError> {
+        let mut probes = self.list_all();
+
+        match probes.len() {
+            0 => Err(ProbeCreationError::NotFound.into()),
+            1 => Ok(probes.remove(0)),
+            _ => Err(ProbeCreationError::MultipleProbesFound { available: probes }.into()),
+        }
+    }
+
+    /// Open the probe with the given USB serial number
+    ///
+    /// This tells apart probes with the same VID and PID, e.g. two identical development
+    /// boards. If no probe matches, the returned error lists all available probes.
+    pub fn open_by_serial(&self, serial_number: &str) -> Result<Probe, DebugProbeError> {
+        let probes = self.list_all();
+
+        match probes
+            .iter()
+            .find(|info| info.serial_number.as_deref() == Some(serial_number))
+        {
+            Some(info) => self.open(info),
+            None => Err(DebugProbeError::ProbeCouldNotBeCreated(
+                ProbeCreationError::NoMatchingProbe {
+                    selector: serial_number.to_string(),
+                    available: probes,
+                },
+            )),
+        }
+    }
+
+    /// Try to open a probe selected by a user provided string
+    ///
+    /// The string can be one of
+    /// - `VID:PID` or `VID:PID:Serial`, see [`DebugProbeSelector`],
+    /// - the serial number of a probe,
+    /// - the index of a probe in the list returned by [`Lister::list_all`].
+    ///
+    /// If no probe matches, the returned error lists all available probes.
+    pub fn open_matching(&self, selector: &str) -> Result<Probe, DebugProbeError> {
+        self.open(self.select_matching(selector)?)
+    }
+
+    /// Find the probe selected by a user provided string, without opening it
+    ///
+    /// See [`Lister::open_matching`] for the accepted strings.
+    pub fn select_matching(&self, selector: &str) -> Result<DebugProbeInfo, DebugProbeError> {
+        let probes = self.list_all();
+
+        match select_probe(&probes, selector) {
+            Some(info) => Ok(info.clone()),
+            None => Err(DebugProbeError::ProbeCouldNotBeCreated(
+                ProbeCreationError::NoMatchingProbe {
+                    selector: selector.to_string(),
+                    available: probes,
+                },
+            )),
+        }
+    }
+}
+
+/// Find the probe matching `selector`, see [`Lister::open_matching`]
+///
+/// A serial number takes precedence over an index, so probes with short numeric
+/// serial numbers can always be selected.
+fn select_probe<'a>(probes: &'a [DebugProbeInfo], selector: &str) -> Option<&'a DebugProbeInfo> {
+    if let Ok(selector) = DebugProbeSelector::try_from(selector) {
+        return probes.iter().find(|info| {
+            info.vendor_id == selector.vendor_id
+                && info.product_id == selector.product_id
+                && (selector.serial_number.is_none()
+                    || info.serial_number == selector.serial_number)
+        });
+    }
+
+    probes
+        .iter()
+        .find(|info| info.serial_number.as_deref() == Some(selector))
+        .or_else(|| {
+            selector
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| probes.get(index))
+        })
 }
 
 impl Default for Lister {
@@ -85,6 +198,8 @@ impl AllProbesLister {
 
     fn open(selector: impl Into<DebugProbeSelector>) -> Result<Probe, DebugProbeError> {
         let selector = selector.into();
+        let _enumeration = lock_probe_enumeration();
+
         match cmsisdap::CmsisDap::new_from_selector(selector.clone()) {
             Ok(link) => return Ok(Probe::from_specific_probe(link)),
             Err(DebugProbeError::ProbeCouldNotBeCreated(ProbeCreationError::NotFound)) => {}
@@ -123,6 +238,8 @@ impl AllProbesLister {
     }
 
     fn list_all() -> Vec<DebugProbeInfo> {
+        let _enumeration = lock_probe_enumeration();
+
         let mut list = cmsisdap::tools::list_cmsisdap_devices();
         #[cfg(feature = "ftdi")]
         {
@@ -139,3 +256,132 @@ impl AllProbesLister {
         list
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{select_probe, Lister, ProbeLister};
+    use crate::{
+        DebugProbeError, DebugProbeInfo, DebugProbeSelector, DebugProbeType, FakeProbe, Probe,
+        ProbeCreationError,
+    };
+
+    #[derive(Debug)]
+    struct FixedLister(Vec<DebugProbeInfo>);
+
+    impl ProbeLister for FixedLister {
+        fn open(&self, _selector: &DebugProbeSelector) -> Result<Probe, DebugProbeError> {
+            Ok(Probe::new(FakeProbe::new()))
+        }
+
+        fn list_all(&self) -> Vec<DebugProbeInfo> {
+            self.0.clone()
+        }
+    }
+
+    fn probes() -> Vec<DebugProbeInfo> {
+        vec![
+            DebugProbeInfo::new(
+                "CMSIS-DAP",
+                0x0d28,
+                0x0204,
+                Some("ABC123".to_string()),
+                DebugProbeType::CmsisDap,
+                None,
+            ),
+            DebugProbeInfo::new(
+                "J-Link",
+                0x1366,
+                0x0105,
+                Some("2".to_string()),
+                DebugProbeType::JLink,
+                None,
+            ),
+            DebugProbeInfo::new(
+                "ST-Link",
+                0x0483,
+                0x374b,
+                None,
+                DebugProbeType::StLink,
+                None,
+            ),
+        ]
+    }
+
+    #[test]
+    fn select_by_vid_pid() {
+        let probes = probes();
+
+        assert_eq!(select_probe(&probes, "1366:0105"), Some(&probes[1]));
+        assert_eq!(select_probe(&probes, "0d28:0204:ABC123"), Some(&probes[0]));
+        assert_eq!(select_probe(&probes, "0d28:0204:XYZ"), None);
+    }
+
+    #[test]
+    fn select_by_serial() {
+        let probes = probes();
+
+        assert_eq!(select_probe(&probes, "ABC123"), Some(&probes[0]));
+    }
+
+    #[test]
+    fn serial_takes_precedence_over_index() {
+        let probes = probes();
+
+        assert_eq!(select_probe(&probes, "0"), Some(&probes[0]));
+        assert_eq!(select_probe(&probes, "2"), Some(&probes[1]));
+        assert_eq!(select_probe(&probes, "3"), None);
+    }
+
+    #[test]
+    fn open_single_probe() {
+        let lister = Lister::with_lister(Box::new(FixedLister(probes()[..1].to_vec())));
+        assert!(lister.open_single().is_ok());
+
+        let lister = Lister::with_lister(Box::new(FixedLister(Vec::new())));
+        assert!(matches!(
+            lister.open_single(),
+            Err(DebugProbeError::ProbeCouldNotBeCreated(
+                ProbeCreationError::NotFound
+            ))
+        ));
+
+        let lister = Lister::with_lister(Box::new(FixedLister(probes())));
+        assert!(matches!(
+            lister.open_single(),
+            Err(DebugProbeError::ProbeCouldNotBeCreated(
+                ProbeCreationError::MultipleProbesFound { available }
+            )) if available.len() == 3
+        ));
+    }
+
+    #[test]
+    fn select_without_opening() {
+        let lister = Lister::with_lister(Box::new(FixedLister(probes())));
+
+        assert_eq!(lister.select_matching("ABC123").unwrap(), probes()[0]);
+        assert!(matches!(
+            lister.select_single(),
+            Err(DebugProbeError::ProbeCouldNotBeCreated(
+                ProbeCreationError::MultipleProbesFound { .. }
+            ))
+        ));
+
+        // The selector built from a probe uses CMSIS-DAP v2 unless requested otherwise
+        let selector = DebugProbeSelector::from(lister.select_matching("0").unwrap());
+        assert!(!selector.cmsisdap_v1);
+        assert_eq!(selector.to_string(), "0d28:0204:ABC123");
+    }
+
+    #[test]
+    fn open_by_serial_number() {
+        let lister = Lister::with_lister(Box::new(FixedLister(probes())));
+
+        assert!(lister.open_by_serial("ABC123").is_ok());
+        assert!(matches!(
+            lister.open_by_serial("XYZ"),
+            Err(DebugProbeError::ProbeCouldNotBeCreated(
+                ProbeCreationError::NoMatchingProbe { selector, available }
+            )) if selector == "XYZ" && available.len() == 3
+        ));
+    }
+}
@@ -3,7 +3,7 @@ pub mod tools;
 mod usb_interface;
 
 use self::usb_interface::{StLinkUsb, StLinkUsbDevice};
-use super::{DebugProbe, DebugProbeError, ProbeCreationError, WireProtocol};
+use super::{DebugProbe, DebugProbeError, ProbeCreationError, ProbeDetails, WireProtocol};
 use crate::architecture::arm::memory::adi_v5_memory_interface::ArmProbe;
 use crate::architecture::arm::{valid_32bit_arm_address, ArmError};
 use crate::{
@@ -275,6 +275,16 @@ impl DebugProbe for StLink<StLinkUsbDevice> {
         Some(self as _)
     }
 
+    fn details(&mut self) -> ProbeDetails {
+        ProbeDetails {
+            // ST names firmware versions like V2J37, the SWIM and MSC versions are left out.
+            firmware_version: Some(format!("V{}J{}", self.hw_version, self.jtag_version)),
+            protocols: vec![WireProtocol::Swd, WireProtocol::Jtag],
+            swo_supported: true,
+            ..Default::default()
+        }
+    }
+
     fn get_swo_interface_mut(&mut self) -> Option<&mut dyn SwoAccess> {
         Some(self as _)
     }
@@ -1068,6 +1078,50 @@ impl<D: StLinkUsb> StLink<D> {
         Ok(())
     }
 
+    /// Write a single halfword with a 16 bit access.
+    ///
+    /// The command is available from firmware V2J26, the oldest firmware we support.
+    fn write_mem_16bit(
+        &mut self,
+        address: u32,
+        data: u16,
+        apsel: u8,
+    ) -> Result<(), DebugProbeError> {
+        self.select_ap(apsel)?;
+
+        tracing::trace!("write_mem_16bit");
+
+        if address % 2 != 0 {
+            return Err(DebugProbeError::from(StlinkError::UnalignedAddress));
+        }
+
+        let addbytes = address.to_le_bytes();
+        let lenbytes = 2u16.to_le_bytes();
+        let data = data.to_le_bytes();
+        retry_on_wait(|| {
+            self.device.write(
+                &[
+                    commands::JTAG_COMMAND,
+                    commands::JTAG_WRITEMEM_16BIT,
+                    addbytes[0],
+                    addbytes[1],
+                    addbytes[2],
+                    addbytes[3],
+                    lenbytes[0],
+                    lenbytes[1],
+                    apsel,
+                ],
+                &data,
+                &mut [],
+                TIMEOUT,
+            )?;
+
+            self.get_last_rw_status()
+        })?;
+
+        Ok(())
+    }
+
     fn _read_debug_reg(&mut self, address: u32) -> Result<u32, DebugProbeError> {
         tracing::trace!("Read debug reg {:08x}", address);
         let mut buff = [0u8; 8];
@@ -1381,6 +1435,10 @@ impl ArmProbeInterface for StlinkArmDebug {
         Ok(self.ap_information.len())
     }
 
+    fn speed_khz(&self) -> u32 {
+        self.probe.speed_khz()
+    }
+
     fn close(self: Box<Self>) -> Probe {
         Probe::from_attached_probe(self.probe)
     }
@@ -1554,6 +1612,16 @@ impl ArmProbe for StLinkMemoryInterface<'_> {
         Ok(())
     }
 
+    fn write_word_16(&mut self, address: u64, data: u16) -> Result<(), ArmError> {
+        let address = valid_32bit_arm_address(address)?;
+
+        self.probe
+            .probe
+            .write_mem_16bit(address, data, self.current_ap.ap_address().ap)?;
+
+        Ok(())
+    }
+
     fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), ArmError> {
         let address = valid_32bit_arm_address(address)?;
 
@@ -1762,8 +1830,10 @@ mod test {
                     Ok(())
                 }
                 commands::JTAG_COMMAND => {
-                    // Return a status of OK for JTAG commands
-                    read_data[0] = 0x80;
+                    // Return a status of OK for JTAG commands which expect a response
+                    if let Some(status) = read_data.first_mut() {
+                        *status = 0x80;
+                    }
 
                     Ok(())
                 }
@@ -1856,6 +1926,30 @@ mod test {
             .expect("Selecting AP other than AP 0 should work");
     }
 
+    #[test]
+    fn write_mem_16bit_alignment() {
+        let usb_mock = MockUsb {
+            hw_version: 2,
+            jtag_version: 26,
+            swim_version: 0,
+            target_voltage_a0: 1.0,
+            _target_voltage_a1: 2.0,
+        };
+
+        let mut probe = usb_mock.build();
+
+        probe.init().expect("Init function failed");
+
+        probe
+            .write_mem_16bit(0x2000_0002, 0x1234, 0)
+            .expect("Aligned 16 bit write failed");
+
+        assert!(matches!(
+            probe.write_mem_16bit(0x2000_0001, 0x1234, 0),
+            Err(DebugProbeError::ProbeSpecific(_))
+        ));
+    }
+
     #[test]
     fn test_is_wait_error() {
         assert!(!is_wait_error(&StlinkError::BanksNotAllowedOnDPRegister));
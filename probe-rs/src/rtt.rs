@@ -111,6 +111,9 @@ pub struct Rtt {
 impl Rtt {
     const RTT_ID: [u8; 16] = *b"SEGGER RTT\0\0\0\0\0\0";
 
+    // Number of bytes read at once while scanning for the control block
+    const SCAN_CHUNK_SIZE: usize = 0x1_0000;
+
     // Minimum size of the ControlBlock struct in target memory in bytes with empty arrays
     const MIN_SIZE: usize = Self::O_CHANNEL_ARRAYS;
 
@@ -265,56 +268,25 @@ impl Rtt {
         let mut instances = ranges
             .into_iter()
             .filter_map(|range| {
-                let range_len = match range.end.checked_sub(range.start) {
-                    Some(v) => if v < (Self::MIN_SIZE as u64) {
-                        return None;
-                    } else {
-                        v
-                    },
-                    None => return None,
-                };
+                let range_len = range.end.checked_sub(range.start)?;
 
-                let range_len_usize: usize = match range_len.try_into() {
+                if range_len < Self::MIN_SIZE as u64 {
+                    return None;
+                }
+
+                let target_ptr = Self::find_id(core, range.start, range_len)?;
+                let target_ptr: u32 = match target_ptr.try_into() {
                     Ok(v) => v,
                     Err(_) => {
-                        // FIXME: This is not ideal because it means that we
-                        // won't consider a >4GiB region if probe-rs is running
-                        // on a 32-bit host, but it would be relatively unusual
-                        // to use a 32-bit host to debug a 64-bit target.
-                        tracing::warn!("ignoring region of length {} because it is too long to buffer in host memory", range_len);
+                        // FIXME: The RTT API currently supports only
+                        // 32-bit addresses, and so it can't accept
+                        // an RTT block at an address >4GiB.
+                        tracing::warn!("can't use RTT block at {:#010x}; must be at a location reachable by 32-bit addressing", target_ptr);
                         return None;
                     }
                 };
 
-                let mut mem = vec![0; range_len_usize];
-                {
-                    core.read(range.start, mem.as_mut()).ok()?;
-                }
-
-                match kmp::kmp_find(&Self::RTT_ID, mem.as_slice()) {
-                    Some(offset) => {
-                        let target_ptr = range.start + (offset as u64);
-                        let target_ptr: u32 = match target_ptr.try_into() {
-                            Ok(v) => v,
-                            Err(_) => {
-                                // FIXME: The RTT API currently supports only
-                                // 32-bit addresses, and so it can't accept
-                                // an RTT block at an address >4GiB.
-                                tracing::warn!("can't use RTT block at {:#010x}; must be at a location reachable by 32-bit addressing", target_ptr);
-                                return None;
-                            },
-                        };
-
-                        Rtt::from(
-                            core,
-                            memory_map,
-                            target_ptr,
-                            Some(&mem[offset..]),
-                        )
-                        .transpose()
-                    },
-                    None => None,
-                }
+                Rtt::from(core, memory_map, target_ptr, None).transpose()
             })
             .collect::<Result<Vec<_>, _>>()?;
 
@@ -331,6 +303,30 @@ impl Rtt {
         Ok(instances.remove(0))
     }
 
+    /// Returns the address of the first RTT ID in the `len` bytes starting at `start`.
+    ///
+    /// The memory is read in chunks, so large regions don't have to be buffered completely.
+    /// Returns `None` if no ID is found, or if the memory can't be read.
+    fn find_id(core: &mut Core, start: u64, len: u64) -> Option<u64> {
+        // The end of the previous chunk is kept, to find an ID which crosses a chunk boundary.
+        let overlap = Self::RTT_ID.len() - 1;
+        let mut window = Vec::with_capacity(Self::SCAN_CHUNK_SIZE + overlap);
+
+        for chunk in core.read_iter(start, len, Self::SCAN_CHUNK_SIZE) {
+            let (address, chunk) = chunk.ok()?;
+
+            let keep = window.len().min(overlap);
+            window.drain(..window.len() - keep);
+            window.extend_from_slice(&chunk);
+
+            if let Some(offset) = kmp::kmp_find(&Self::RTT_ID, &window) {
+                return Some(address - keep as u64 + offset as u64);
+            }
+        }
+
+        None
+    }
+
     /// Returns the memory address of the control block in target memory.
     pub fn ptr(&self) -> u32 {
         self.ptr
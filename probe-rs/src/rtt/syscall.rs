@@ -1,14 +1,20 @@
 use crate::SemihostingCommand;
 
-/// Decode a semihosting syscall. Only SYS_EXIT is supported at the moment
+/// Decode a semihosting syscall. Only SYS_EXIT and the console output operations are supported at the moment
 pub fn decode_semihosting_syscall(operation: u32, parameter: u32) -> SemihostingCommand {
     // This is defined by the ARM Semihosting Specification:
     // <https://github.com/ARM-software/abi-aa/blob/main/semihosting/semihosting.rst#semihosting-operations>
+    const SYS_WRITEC: u32 = 0x03;
+    const SYS_WRITE0: u32 = 0x04;
+    const SYS_WRITE: u32 = 0x05;
     const SYS_EXIT: u32 = 0x18;
     const SYS_EXIT_ADP_STOPPED_APPLICATIONEXIT: u32 = 0x20026;
     match (operation, parameter) {
         (SYS_EXIT, SYS_EXIT_ADP_STOPPED_APPLICATIONEXIT) => SemihostingCommand::ExitSuccess,
         (SYS_EXIT, code) => SemihostingCommand::ExitError { code: code as u64 },
+        (SYS_WRITEC, address) => SemihostingCommand::WriteChar { address },
+        (SYS_WRITE0, address) => SemihostingCommand::WriteString { address },
+        (SYS_WRITE, parameter_block) => SemihostingCommand::Write { parameter_block },
         _ => {
             tracing::warn!(
                 "Unknown semihosting operation={operation:04x} parameter={parameter:04x}"
@@ -17,3 +23,24 @@ pub fn decode_semihosting_syscall(operation: u32, parameter: u32) -> Semihosting
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode_console_output() {
+        assert_eq!(
+            decode_semihosting_syscall(0x04, 0x2000_0100),
+            SemihostingCommand::WriteString {
+                address: 0x2000_0100
+            }
+        );
+        assert_eq!(
+            decode_semihosting_syscall(0x05, 0x2000_0200),
+            SemihostingCommand::Write {
+                parameter_block: 0x2000_0200
+            }
+        );
+    }
+}
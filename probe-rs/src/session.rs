@@ -1,22 +1,33 @@
 use crate::architecture::arm::component::get_arm_components;
 use crate::architecture::arm::sequences::{ArmDebugSequence, DefaultArmSequence};
-use crate::architecture::arm::{ArmError, DpAddress};
+use crate::architecture::arm::{
+    ap::{scan_access_ports, AccessPort, ApInfo, MemoryAp},
+    ApAddress, ArmError, DapStatistics, DpAddress, Pins,
+};
 use crate::architecture::riscv::communication_interface::RiscvError;
-use crate::config::{ChipInfo, CoreExt, RegistryError, Target, TargetSelector};
-use crate::core::{Architecture, CombinedCoreState};
+use crate::config::{
+    ChipDetectionInfo, CoreExt, RegistryError, ResetCause, Target, TargetSelector,
+};
+use crate::core::{is_transfer_error, Architecture, CombinedCoreState, CoreAccessOptions};
+use crate::flashing::{
+    download_file, BinOptions, FileDownloadError, FlashError, FlashPlan, Format,
+};
 use crate::probe::fake_probe::FakeProbe;
 use crate::{
     architecture::{
         arm::{
-            communication_interface::ArmProbeInterface, component::TraceSink,
-            memory::CoresightComponent, SwoReader,
+            communication_interface::{ArmProbeInterface, SwdSequence},
+            component::TraceSink,
+            memory::{CoresightComponent, DiscoveredComponent},
+            SwoReader,
         },
         riscv::communication_interface::RiscvCommunicationInterface,
     },
     config::DebugSequence,
 };
-use crate::{AttachMethod, Core, CoreType, Error, Lister, Probe};
+use crate::{AttachMethod, Core, CoreType, DebugProbeError, Error, Lister, MemoryInterface, Probe};
 use std::ops::DerefMut;
+use std::path::Path;
 use std::{fmt, sync::Arc, time::Duration};
 
 /// The `Session` struct represents an active debug session.
@@ -34,15 +45,56 @@ use std::{fmt, sync::Arc, time::Duration};
 ///
 /// If you do so, make sure that both threads sleep in between tasks such that other stakeholders may take their turn.
 ///
+/// Sessions created from different probes are independent of each other, so multiple targets
+/// can be debugged or flashed in parallel, e.g. with one thread per session. A `Session` is
+/// [`Send`] but not [`Sync`].
+///
 /// To get access to a single [Core] from the `Session`, the [Session::core()] method can be used.
 /// Please see the [Session::core()] method for more usage guidelines.
 ///
+/// When the session is dropped, the debug state of the target is restored, see [DetachMode].
 #[derive(Debug)]
 pub struct Session {
     target: Target,
     interface: ArchitectureInterface,
     cores: Vec<CombinedCoreState>,
     configured_trace_sink: Option<TraceSink>,
+    detach_mode: DetachMode,
+    /// Resume halted cores when the session ends
+    resume_on_detach: bool,
+    /// Re-attach automatically after the connection to the target was lost
+    auto_reattach: bool,
+    /// Speed of the debug protocol in kHz, as reported by the probe
+    speed_khz: u32,
+    /// The probe only knows an upper bound for the speed
+    speed_is_approximate: bool,
+}
+
+/// What happens to the target when a [Session] ends, see [Session::detach()]
+///
+/// Halted cores stay halted in all modes, unless [Session::set_resume_on_detach()] is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DetachMode {
+    /// Restore the debug state of the target to how it was before attaching.
+    ///
+    /// Breakpoints and watchpoints are removed, the debug registers changed while attaching
+    /// (DHCSR and DEMCR on Cortex-M) are restored, and the debug port is powered down.
+    /// Otherwise the firmware may behave differently until a power cycle, e.g. faults would
+    /// halt the core instead of resetting it. Halting debug stays enabled on halted cores,
+    /// as disabling it would resume them.
+    #[default]
+    Restore,
+    /// Remove breakpoints and watchpoints and power down the debug port, but leave the debug
+    /// registers as they are, e.g. to keep the vector catches for another tool.
+    ///
+    /// Halted cores are not resumed, even with [Session::set_resume_on_detach()].
+    LeaveHalted,
+    /// Leave the target exactly as it is, without removing breakpoints or powering down the
+    /// debug port.
+    ///
+    /// This is the default for sessions attached with
+    /// [AttachMethod::HotAttach](crate::AttachMethod::HotAttach).
+    Untouched,
 }
 
 pub(crate) enum ArchitectureInterface {
@@ -88,12 +140,26 @@ impl ArchitectureInterface {
 impl Session {
     /// Open a new session with a given debug target.
     pub(crate) fn new(
-        probe: Probe,
+        mut probe: Probe,
         target: TargetSelector,
         attach_method: AttachMethod,
         permissions: Permissions,
+        dp: Option<DpAddress>,
     ) -> Result<Self, Error> {
-        let (probe, target) = get_target_from_selector(target, attach_method, probe)?;
+        log_target_voltage(&mut probe);
+
+        let (probe, mut target) = get_target_from_selector(target, attach_method, probe, dp)?;
+
+        // Cores which the target description doesn't assign to a DP use the selected one
+        if let Some(DpAddress::Multidrop(targetsel)) = dp {
+            for core in &mut target.cores {
+                if let CoreAccessOptions::Arm(options) = &mut core.core_access_options {
+                    if options.psel == 0 {
+                        options.psel = targetsel;
+                    }
+                }
+            }
+        }
 
         let cores = target
             .cores
@@ -118,7 +184,25 @@ impl Session {
             }
         };
 
-        session.clear_all_hw_breakpoints()?;
+        // Breakpoints are only cleared when the target may be halted anyway, firmware can use the
+        // flash patch unit of the FPB while running. For the same reason, a hot attached target
+        // is left as it is when the session ends.
+        if attach_method == AttachMethod::HotAttach {
+            session.detach_mode = DetachMode::Untouched;
+        } else {
+            session.clear_all_hw_breakpoints()?;
+        }
+
+        tracing::info!(
+            "Attached to {} at {}{} kHz",
+            session.target.name,
+            if session.speed_is_approximate {
+                "up to "
+            } else {
+                ""
+            },
+            session.speed_khz
+        );
 
         Ok(session)
     }
@@ -128,7 +212,7 @@ impl Session {
         target: Target,
         attach_method: AttachMethod,
         permissions: Permissions,
-        cores: Vec<CombinedCoreState>,
+        mut cores: Vec<CombinedCoreState>,
     ) -> Result<Self, Error> {
         let default_core = target.default_core();
 
@@ -157,7 +241,7 @@ impl Session {
                     probe.get_name()
                 );
                 tracing::info!("Falling back to standard probe reset.");
-                probe.target_reset_assert()?;
+                assert_reset_for_attach(&mut probe)?;
             }
         }
 
@@ -166,6 +250,9 @@ impl Session {
         }
         probe.attach_to_unspecified()?;
 
+        let speed_khz = probe.speed_khz();
+        let speed_is_approximate = probe.speed_is_approximate();
+
         let interface = probe.try_into_arm_interface().map_err(|(_, err)| err)?;
 
         let mut interface = interface
@@ -182,13 +269,16 @@ impl Session {
             Ok(()) => (),
             // In case this happens after unlock. Try to re-attach the probe once.
             Err(ArmError::ReAttachRequired) => {
-                Self::reattach_arm_interface(&mut interface, &sequence_handle)?;
+                Self::reattach_arm_interface(&mut interface, &sequence_handle, None)?;
             }
             Err(e) => return Err(Error::Arm(e)),
         }
 
         // For each core, setup debugging
-        for core in &cores {
+        for core in &mut cores {
+            if attach_method == AttachMethod::HotAttach {
+                core.clear_stale_vector_catch(&mut *interface)?;
+            }
             core.enable_arm_debug(&mut *interface)?;
         }
 
@@ -220,6 +310,11 @@ impl Session {
                 interface: ArchitectureInterface::Arm(interface),
                 cores,
                 configured_trace_sink: None,
+                detach_mode: DetachMode::default(),
+                resume_on_detach: false,
+                auto_reattach: false,
+                speed_khz,
+                speed_is_approximate,
             };
 
             {
@@ -243,6 +338,11 @@ impl Session {
                 interface: ArchitectureInterface::Arm(interface),
                 cores,
                 configured_trace_sink: None,
+                detach_mode: DetachMode::default(),
+                resume_on_detach: false,
+                auto_reattach: false,
+                speed_khz,
+                speed_is_approximate,
             })
         }
     }
@@ -250,12 +350,17 @@ impl Session {
     fn attach_riscv(
         mut probe: Probe,
         target: Target,
-        _attach_method: AttachMethod,
+        attach_method: AttachMethod,
         _permissions: Permissions,
         cores: Vec<CombinedCoreState>,
     ) -> Result<Self, Error> {
         // TODO: Handle attach under reset
 
+        // The core is halted below, and the connect sequence may write to the target
+        if attach_method == AttachMethod::HotAttach {
+            return Err(Error::NotImplemented("hot attach to RISC-V targets"));
+        }
+
         let sequence_handle = match &target.debug_sequence {
             DebugSequence::Riscv(sequence) => sequence.clone(),
             DebugSequence::Arm(_) => {
@@ -269,6 +374,9 @@ impl Session {
 
         probe.attach_to_unspecified()?;
 
+        let speed_khz = probe.speed_khz();
+        let speed_is_approximate = probe.speed_is_approximate();
+
         let interface = probe
             .try_into_riscv_interface()
             .map_err(|(_probe, err)| err)?;
@@ -278,6 +386,11 @@ impl Session {
             interface: ArchitectureInterface::Riscv(Box::new(interface)),
             cores,
             configured_trace_sink: None,
+            detach_mode: DetachMode::default(),
+            resume_on_detach: false,
+            auto_reattach: false,
+            speed_khz,
+            speed_is_approximate,
         };
 
         {
@@ -364,19 +477,35 @@ impl Session {
             }
 
             TraceSink::TraceMemory => {
-                let components = self.get_arm_components(DpAddress::Default)?;
+                let components = self.get_arm_components(self.arm_debug_port())?;
                 let interface = self.get_arm_interface()?;
                 crate::architecture::arm::component::read_trace_memory(interface, &components)
             }
         }
     }
 
+    /// Append the SWO data received since the last read to `buf`.
+    ///
+    /// Returns `true` if the probe dropped data before it, because its trace buffer
+    /// overflowed. This is only detected by some probes, e.g. CMSIS-DAP probes using the
+    /// polled SWO transport.
+    ///
+    /// SWO has to be configured with [Session::setup_tracing] first.
+    pub fn read_swo(&mut self, buf: &mut Vec<u8>) -> Result<bool, ArmError> {
+        let interface = self.get_arm_interface()?;
+
+        buf.extend(interface.read_swo()?);
+
+        Ok(interface.take_swo_overrun())
+    }
+
     /// Returns an implementation of [std::io::Read] that wraps [SwoAccess::read_swo].
     ///
     /// The implementation buffers all available bytes from
     /// [SwoAccess::read_swo] on each [std::io::Read::read],
     /// minimizing the chance of a target-side overflow event on which
-    /// trace packets are lost.
+    /// trace packets are lost. The bytes are kept in a bounded ring buffer,
+    /// bytes discarded on overflow are reported by [SwoReader::overflow_count].
     ///
     /// [SwoAccess::read_swo]: crate::architecture::arm::swo::SwoAccess
     pub fn swo_reader(&mut self) -> Result<SwoReader, Error> {
@@ -394,6 +523,68 @@ impl Session {
         Ok(interface)
     }
 
+    /// Returns how often ARM debug port transfers were retried or recovered from errors.
+    ///
+    /// Returns `None` for non-ARM targets, and for probes which don't track it.
+    pub fn dap_statistics(&mut self) -> Option<DapStatistics> {
+        self.get_arm_interface().ok()?.dap_statistics()
+    }
+
+    /// Read a register of the debug port of the default core.
+    ///
+    /// The upper 4 bits of `address` select the DPBANKSEL bank, SELECT is written as needed.
+    /// This is meant for chip bring-up and vendor-specific debug features, the debug port
+    /// is normally accessed by the higher layers.
+    ///
+    /// Only supported on ARM targets.
+    pub fn read_dp_register(&mut self, address: u8) -> Result<u32, ArmError> {
+        let dp = self.arm_debug_port();
+
+        self.get_arm_interface()?.read_raw_dp_register(dp, address)
+    }
+
+    /// Write a register of the debug port of the default core.
+    ///
+    /// The upper 4 bits of `address` select the DPBANKSEL bank, SELECT is written as needed.
+    /// SELECT itself can be written as well, the interface then uses the written banks for
+    /// the following accesses. Writing other registers which the higher layers rely on, e.g.
+    /// CTRL/STAT, can break the communication with the target.
+    ///
+    /// Only supported on ARM targets.
+    pub fn write_dp_register(&mut self, address: u8, value: u32) -> Result<(), ArmError> {
+        let dp = self.arm_debug_port();
+
+        self.get_arm_interface()?
+            .write_raw_dp_register(dp, address, value)
+    }
+
+    /// Read a register of an access port, e.g. a vendor-specific control AP.
+    ///
+    /// The upper 4 bits of `address` select the APBANKSEL bank, SELECT is written as needed
+    /// and kept in sync with the higher layers.
+    ///
+    /// Only supported on ARM targets.
+    pub fn read_ap_register(&mut self, ap: ApAddress, address: u8) -> Result<u32, ArmError> {
+        self.get_arm_interface()?.read_raw_ap_register(ap, address)
+    }
+
+    /// Write a register of an access port, e.g. a vendor-specific control AP.
+    ///
+    /// The upper 4 bits of `address` select the APBANKSEL bank, SELECT is written as needed
+    /// and kept in sync with the higher layers. Memory APs are configured again by the higher
+    /// layers on each [Core] access, so writing their CSW or TAR doesn't affect them.
+    ///
+    /// Only supported on ARM targets.
+    pub fn write_ap_register(
+        &mut self,
+        ap: ApAddress,
+        address: u8,
+        value: u32,
+    ) -> Result<(), ArmError> {
+        self.get_arm_interface()?
+            .write_raw_ap_register(ap, address, value)
+    }
+
     /// Get the RISC-V probe interface.
     pub fn get_riscv_interface(&mut self) -> Result<&mut RiscvCommunicationInterface, RiscvError> {
         let interface = match &mut self.interface {
@@ -404,11 +595,16 @@ impl Session {
         Ok(interface)
     }
 
+    /// Re-attach the probe, optionally changing the speed of the debug protocol while detached.
+    ///
+    /// Returns the speed used after re-attaching. If the speed can't be changed, the probe is still
+    /// re-attached before the error is returned.
     #[tracing::instrument(skip_all)]
     fn reattach_arm_interface(
         interface: &mut Box<dyn ArmProbeInterface>,
         debug_sequence: &Arc<dyn ArmDebugSequence>,
-    ) -> Result<(), Error> {
+        speed_khz: Option<u32>,
+    ) -> Result<u32, Error> {
         use crate::DebugProbe;
 
         // In order to re-attach we need an owned instance to the interface
@@ -425,6 +621,12 @@ impl Session {
         tracing::debug!("Re-attaching Probe");
         let mut probe = tmp_interface.close();
         probe.detach()?;
+
+        let speed_result = match speed_khz {
+            Some(speed_khz) => probe.set_speed(speed_khz),
+            None => Ok(probe.speed_khz()),
+        };
+
         probe.attach_to_unspecified()?;
 
         let new_interface = probe.try_into_arm_interface().map_err(|(_, err)| err)?;
@@ -436,9 +638,325 @@ impl Session {
         std::mem::swap(interface, &mut tmp_interface);
 
         tracing::debug!("Probe re-attached");
+
+        Ok(speed_result?)
+    }
+
+    /// Re-attach the probe to the target, and set up debugging on all cores again.
+    ///
+    /// This can be used to continue a session after the connection to the target was lost,
+    /// e.g. because the target was power cycled. The breakpoints, watchpoints and vector
+    /// catches set through [Core] are applied again. Other state of the cores, e.g. whether
+    /// they are halted, and tracing are not restored.
+    ///
+    /// [Core] handles borrow the session, so they are never invalidated by re-attaching.
+    ///
+    /// This is only supported for ARM targets.
+    pub fn reattach(&mut self) -> Result<(), Error> {
+        let ArchitectureInterface::Arm(interface) = &mut self.interface else {
+            return Err(Error::NotImplemented("re-attaching to non-ARM targets"));
+        };
+
+        let DebugSequence::Arm(debug_sequence) = &self.target.debug_sequence else {
+            unreachable!("This should never happen. Please file a bug if it does.")
+        };
+
+        Self::reattach_arm_interface(interface, debug_sequence, None)?;
+
+        // For re-setup debugging on all cores
+        for core_state in &mut self.cores {
+            core_state.enable_arm_debug(interface.deref_mut())?;
+        }
+
+        for core_state in &mut self.cores {
+            self.interface
+                .attach(core_state)?
+                .restore_debug_settings()?;
+        }
+
+        Ok(())
+    }
+
+    /// Re-attach automatically after the connection to the target was lost, e.g. because the
+    /// target was power cycled.
+    ///
+    /// This applies to operations run with [Session::with_core()]. This is only supported for
+    /// ARM targets.
+    pub fn set_auto_reattach(&mut self, enabled: bool) {
+        self.auto_reattach = enabled;
+    }
+
+    /// Run `operation` on the core with the index `core_index`.
+    ///
+    /// If [Session::set_auto_reattach()] is enabled and a transfer of the operation fails,
+    /// the session re-attaches with [Session::reattach()] and runs the operation once more, so
+    /// the operation has to be safe to repeat. If re-attaching fails, the error of the first
+    /// attempt is returned.
+    pub fn with_core<T>(
+        &mut self,
+        core_index: usize,
+        mut operation: impl FnMut(&mut Core<'_>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let error = match operation(&mut self.core(core_index)?) {
+            Err(error) if self.auto_reattach && is_transfer_error(&error) => error,
+            result => return result,
+        };
+
+        tracing::warn!("Transfer failed: {error}, re-attaching");
+
+        if let Err(reattach_error) = self.reattach() {
+            tracing::warn!("Re-attaching failed: {reattach_error}");
+
+            return Err(error);
+        }
+
+        operation(&mut self.core(core_index)?)
+    }
+
+    /// Get the speed of the debug protocol in kHz, as reported by the probe when attaching or
+    /// changing the speed with [`Session::set_speed`].
+    ///
+    /// This can be lower than the requested speed. If the probe does not report the speed it
+    /// uses, this is the upper bound, see [`Session::speed_is_approximate`].
+    pub fn speed_khz(&self) -> u32 {
+        self.speed_khz
+    }
+
+    /// Check whether [`Session::speed_khz`] is only an upper bound for the speed, see
+    /// [`Probe::speed_is_approximate`].
+    pub fn speed_is_approximate(&self) -> bool {
+        self.speed_is_approximate
+    }
+
+    /// Change the speed of the debug protocol while attached, see [`Probe::set_speed`].
+    ///
+    /// The probe is re-attached to the target to apply the new speed, the state of the cores is
+    /// kept. Returns the speed actually used by the probe, in kHz.
+    ///
+    /// This is only supported for ARM targets.
+    pub fn set_speed(&mut self, speed_khz: u32) -> Result<u32, Error> {
+        let ArchitectureInterface::Arm(interface) = &mut self.interface else {
+            return Err(Error::NotImplemented(
+                "changing the speed of non-ARM targets",
+            ));
+        };
+
+        let DebugSequence::Arm(debug_sequence) = &self.target.debug_sequence else {
+            unreachable!("This should never happen. Please file a bug if it does.")
+        };
+
+        let speed_result = Self::reattach_arm_interface(interface, debug_sequence, Some(speed_khz));
+
+        // The probe is re-attached even if it rejected the speed, so debugging has to be enabled
+        // again before the error is returned.
+        let enable_result = self
+            .cores
+            .iter_mut()
+            .try_for_each(|core_state| core_state.enable_arm_debug(interface.deref_mut()));
+
+        self.speed_khz = speed_result?;
+        enable_result?;
+
+        Ok(self.speed_khz)
+    }
+
+    /// Assert the reset pin of the target, see [`Probe::target_reset_assert`].
+    ///
+    /// The target is held in reset until [Session::target_reset_deassert()] is called, most
+    /// accesses to the cores fail in the meantime.
+    pub fn target_reset_assert(&mut self) -> Result<(), Error> {
+        self.drive_reset_pin(true)
+    }
+
+    /// Deassert the reset pin of the target, see [`Probe::target_reset_deassert`].
+    pub fn target_reset_deassert(&mut self) -> Result<(), Error> {
+        self.drive_reset_pin(false)
+    }
+
+    /// Reset the target with the reset pin, holding it in reset for `duration`.
+    ///
+    /// Unlike [Core::reset()], this also works if the firmware disabled the system reset
+    /// request. Depending on the chip, the reset pin also resets the debug logic, use
+    /// [Session::reattach()] afterwards to restore the connection in that case.
+    pub fn target_reset_pulse(&mut self, duration: Duration) -> Result<(), Error> {
+        self.target_reset_assert()?;
+        std::thread::sleep(duration);
+        self.target_reset_deassert()
+    }
+
+    fn drive_reset_pin(&mut self, asserted: bool) -> Result<(), Error> {
+        match &mut self.interface {
+            ArchitectureInterface::Arm(interface) => {
+                // DAP_SWJ_Pins, the reset pin is active low
+                let mut n_reset = Pins(0);
+                n_reset.set_nreset(true);
+                let n_reset = n_reset.0 as u32;
+
+                interface.swj_pins(if asserted { 0 } else { n_reset }, n_reset, 0)?;
+            }
+            ArchitectureInterface::Riscv(interface) => {
+                if asserted {
+                    interface.target_reset_assert()?;
+                } else {
+                    interface.target_reset_deassert()?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read the cause of the last reset from the reset status register of the target.
+    ///
+    /// The register and its flags are taken from the target description. If `clear` is set,
+    /// the flags are cleared after reading them, so the cause of the next reset is not mixed
+    /// up with this one.
+    pub fn reset_cause(&mut self, clear: bool) -> Result<ResetCause, Error> {
+        let Some(register) = self.target.reset_cause.clone() else {
+            return Err(Error::Other(anyhow::anyhow!(
+                "The reset cause register of {} is not known",
+                self.target.name
+            )));
+        };
+
+        let mut core = self.core(0)?;
+        let value = core.read_word_32(register.address)?;
+
+        if clear {
+            core.write_word_32(register.address, register.clear_value(value))?;
+        }
+
+        Ok(register.decode(value))
+    }
+
+    /// Reset the target and let it run from the reset vector, e.g. to restart the firmware.
+    ///
+    /// The reset catch of all cores is cleared first, so none of them stops at the reset
+    /// vector. The reset affects the whole target, so it is issued only once, through the
+    /// first core, see [`Core::reset_and_run`]. The other cores are resumed if they are
+    /// still halted afterwards.
+    pub fn reset_and_run(&mut self) -> Result<(), Error> {
+        for core_index in 1..self.cores.len() {
+            match self.core(core_index)?.reset_catch_clear() {
+                Ok(()) | Err(Error::Riscv(RiscvError::ResetHaltRequestNotSupported)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.core(0)?.reset_and_run()?;
+
+        for core_index in 1..self.cores.len() {
+            let mut core = self.core(core_index)?;
+
+            if core.core_halted()? {
+                core.run()?;
+            }
+        }
+
         Ok(())
     }
 
+    /// Reset the device into its built-in bootloader, e.g. the DFU bootloader of STM32 devices.
+    ///
+    /// The boot source is configured by vendor specific means, like the option bytes, and stays
+    /// configured until it is changed back. This is only supported on some devices, others
+    /// return [`DebugProbeError::NotImplemented`](crate::DebugProbeError::NotImplemented).
+    pub fn reset_to_bootloader(&mut self) -> Result<(), Error> {
+        let result = self.run_arm_sequence(
+            "Resetting into the bootloader",
+            |sequence, interface, ap| sequence.reset_to_bootloader(interface, ap),
+        );
+
+        match result {
+            // The device reset the debug connection as well
+            Err(Error::Arm(ArmError::ReAttachRequired)) => self.reattach(),
+            result => result,
+        }
+    }
+
+    /// Read the option bytes of the device, as the raw values of its option registers.
+    ///
+    /// The layout is device specific, e.g. for the STM32G0 this is the `FLASH_OPTR` register.
+    /// This is only supported on some devices, others return
+    /// [`DebugProbeError::NotImplemented`](crate::DebugProbeError::NotImplemented).
+    pub fn read_option_bytes(&mut self) -> Result<Vec<u32>, Error> {
+        self.run_arm_sequence("Reading option bytes", |sequence, interface, ap| {
+            sequence.read_option_bytes(interface, ap)
+        })
+    }
+
+    /// Program the option bytes of the device, in the layout returned by [`Session::read_option_bytes`].
+    ///
+    /// The device is reset to load the new option bytes. Lowering the read protection level
+    /// erases the flash memory. Settings which permanently lock the device, like read protection
+    /// level 2, are refused unless `force` is set.
+    pub fn write_option_bytes(&mut self, option_bytes: &[u32], force: bool) -> Result<(), Error> {
+        let result = self.run_arm_sequence("Writing option bytes", |sequence, interface, ap| {
+            sequence.write_option_bytes(interface, ap, option_bytes, force)
+        });
+
+        match result {
+            // Loading the option bytes reset the debug connection as well
+            Err(Error::Arm(ArmError::ReAttachRequired)) => self.reattach(),
+            result => result,
+        }
+    }
+
+    /// Run a vendor specific debug sequence on the default memory AP of an ARM target
+    fn run_arm_sequence<T>(
+        &mut self,
+        name: &'static str,
+        sequence: impl FnOnce(
+            &dyn ArmDebugSequence,
+            &mut dyn ArmProbeInterface,
+            MemoryAp,
+        ) -> Result<T, ArmError>,
+    ) -> Result<T, Error> {
+        let ArchitectureInterface::Arm(interface) = &mut self.interface else {
+            return Err(Error::Probe(crate::DebugProbeError::NotImplemented(name)));
+        };
+
+        let DebugSequence::Arm(debug_sequence) = &self.target.debug_sequence else {
+            unreachable!("This should never happen. Please file a bug if it does.")
+        };
+
+        let default_core = self.target.default_core();
+        let default_memory_ap = default_core.memory_ap().ok_or_else(|| {
+            Error::Other(anyhow::anyhow!(
+                "Unable to connect to core {default_core:?}, no memory AP configured"
+            ))
+        })?;
+
+        sequence(
+            debug_sequence.as_ref(),
+            interface.deref_mut(),
+            default_memory_ap,
+        )
+        .map_err(Error::from)
+    }
+
+    /// Recover a locked device by erasing it, and set up debugging on all cores again.
+    ///
+    /// This runs the device specific recovery sequence, e.g. an ERASEALL through the CTRL-AP
+    /// on nRF devices, which also removes the APPROTECT read protection. Devices which are
+    /// locked while attaching are only unlocked if [`Permissions::allow_erase_all`] is given,
+    /// this can be used to recover a device which locks itself again afterwards.
+    ///
+    /// **This destroys all contents of the flash memory, and possibly other non-volatile settings.**
+    ///
+    /// This is only supported for ARM targets.
+    pub fn recover(&mut self) -> Result<(), Error> {
+        let result = self.run_arm_sequence("device recovery", |sequence, interface, ap| {
+            sequence.debug_device_recover(interface, ap)
+        });
+
+        match result {
+            // The erase resets the device, so the cores need to be set up again in any case.
+            Ok(()) | Err(Error::Arm(ArmError::ReAttachRequired)) => self.reattach(),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Check if the connected device has a debug erase sequence defined
     pub fn has_sequence_erase_all(&self) -> bool {
         match &self.target.debug_sequence {
@@ -484,19 +1002,29 @@ impl Session {
         match erase_result {
             Ok(()) => (),
             // In case this happens after unlock. Try to re-attach the probe once.
-            Err(ArmError::ReAttachRequired) => {
-                Self::reattach_arm_interface(interface, &debug_sequence)?;
-                // For re-setup debugging on all cores
-                for core_state in &self.cores {
-                    core_state.enable_arm_debug(interface.deref_mut())?;
-                }
-            }
+            Err(ArmError::ReAttachRequired) => self.reattach()?,
             Err(e) => return Err(Error::Arm(e)),
         }
         tracing::info!("Device Erased Successfully");
         Ok(())
     }
 
+    /// Erase the entire flash memory of the device and wait until the erase is complete.
+    ///
+    /// This uses the chip's mass erase command instead of erasing sector by sector where
+    /// possible: the device's debug erase sequence if it has one, which can also recover
+    /// devices that are locked or read protected, otherwise the chip erase function of the
+    /// flash algorithms.
+    ///
+    /// **This destroys all contents of the flash memory.**
+    pub fn mass_erase(&mut self) -> Result<(), Error> {
+        if self.has_sequence_erase_all() {
+            return self.sequence_erase_all();
+        }
+
+        crate::flashing::erase_all(self, None).map_err(|e| Error::Other(e.into()))
+    }
+
     /// Reads all the available ARM CoresightComponents of the currently attached target.
     ///
     /// This will recursively parse the Romtable of the attached target
@@ -510,11 +1038,51 @@ impl Session {
         get_arm_components(interface, dp)
     }
 
+    /// Scans the access ports of the debug port of the default core of an ARM target.
+    ///
+    /// The IDR register of every access port is read, gaps in the AP numbers are skipped.
+    pub fn list_access_ports(&mut self) -> Result<Vec<ApInfo>, ArmError> {
+        let dp = self.arm_debug_port();
+        let interface = self.get_arm_interface()?;
+
+        Ok(scan_access_ports(interface, dp))
+    }
+
+    /// Walks the ROM tables of all access ports of an ARM target, and lists the components found.
+    ///
+    /// The components of the debug port of the default core are listed, with their base address
+    /// and part number. Trace components like the TPIU, ETM or MTB can be located with this,
+    /// instead of assuming their addresses.
+    pub fn discover_components(&mut self) -> Result<Vec<DiscoveredComponent>, ArmError> {
+        let components = self.get_arm_components(self.arm_debug_port())?;
+
+        Ok(components
+            .iter()
+            .flat_map(CoresightComponent::discover)
+            .collect())
+    }
+
+    /// The debug port of the default core, used to access the trace components.
+    fn arm_debug_port(&self) -> DpAddress {
+        self.debug_port().unwrap_or(DpAddress::Default)
+    }
+
     /// Get the target description of the connected target.
     pub fn target(&self) -> &Target {
         &self.target
     }
 
+    /// The debug port used to access the default core of an ARM target.
+    ///
+    /// This is [`DpAddress::Multidrop`] if the target is accessed through a SWD multi-drop bus.
+    /// Returns `None` for other architectures.
+    pub fn debug_port(&self) -> Option<DpAddress> {
+        self.target
+            .default_core()
+            .memory_ap()
+            .map(|ap| ap.ap_address().dp)
+    }
+
     /// Configure the target and probe for serial wire view (SWV) tracing.
     pub fn setup_tracing(
         &mut self,
@@ -534,7 +1102,7 @@ impl Session {
             }
         };
 
-        let components = self.get_arm_components(DpAddress::Default)?;
+        let components = self.get_arm_components(self.arm_debug_port())?;
         let interface = self.get_arm_interface()?;
 
         // Configure SWO on the probe when the trace sink is configured for a serial output. Note
@@ -565,7 +1133,7 @@ impl Session {
 
     /// Begin tracing a memory address over SWV.
     pub fn add_swv_data_trace(&mut self, unit: usize, address: u32) -> Result<(), ArmError> {
-        let components = self.get_arm_components(DpAddress::Default)?;
+        let components = self.get_arm_components(self.arm_debug_port())?;
         let interface = self.get_arm_interface()?;
         crate::architecture::arm::component::add_swv_data_trace(
             interface,
@@ -577,11 +1145,26 @@ impl Session {
 
     /// Stop tracing from a given SWV unit
     pub fn remove_swv_data_trace(&mut self, unit: usize) -> Result<(), ArmError> {
-        let components = self.get_arm_components(DpAddress::Default)?;
+        let components = self.get_arm_components(self.arm_debug_port())?;
         let interface = self.get_arm_interface()?;
         crate::architecture::arm::component::remove_swv_data_trace(interface, &components, unit)
     }
 
+    /// Enable or disable the exception trace over SWV.
+    ///
+    /// [Session::setup_tracing()] enables the exception trace, use this to turn it off if the
+    /// trace bandwidth is needed for other data. The trace can be decoded with the
+    /// [ExceptionTraceDecoder](crate::architecture::arm::swo::exception_trace::ExceptionTraceDecoder).
+    pub fn set_swv_exception_trace(&mut self, enabled: bool) -> Result<(), ArmError> {
+        let components = self.get_arm_components(self.arm_debug_port())?;
+        let interface = self.get_arm_interface()?;
+        crate::architecture::arm::component::set_swv_exception_trace(
+            interface,
+            &components,
+            enabled,
+        )
+    }
+
     /// Return the `Architecture` of the currently connected chip.
     pub fn architecture(&self) -> Architecture {
         match self.interface {
@@ -590,6 +1173,119 @@ impl Session {
         }
     }
 
+    /// Set what happens to the target when this session ends, see [DetachMode].
+    ///
+    /// This applies to both [Session::detach()] and dropping the session.
+    pub fn set_detach_mode(&mut self, mode: DetachMode) {
+        self.detach_mode = mode;
+    }
+
+    /// Resume halted cores when this session ends, which is disabled by default.
+    ///
+    /// This only applies to [DetachMode::Restore], the other modes leave halted cores halted.
+    pub fn set_resume_on_detach(&mut self, resume: bool) {
+        self.resume_on_detach = resume;
+    }
+
+    /// End the session, releasing the target as configured with [Session::set_detach_mode()].
+    ///
+    /// Dropping a session does the same, but can only log failures. All steps are attempted
+    /// even if one of them fails, the first error is returned.
+    pub fn detach(mut self) -> Result<(), Error> {
+        self.release_target()
+    }
+
+    /// Restore the debug state of the target according to the detach mode
+    ///
+    /// The target is only released once, afterwards it is left as it is.
+    fn release_target(&mut self) -> Result<(), Error> {
+        let mode = std::mem::replace(&mut self.detach_mode, DetachMode::Untouched);
+        if mode == DetachMode::Untouched {
+            return Ok(());
+        }
+
+        let mut result = Ok(());
+        let mut check = |what: &str, step: Result<(), Error>| {
+            if let Err(err) = step {
+                tracing::warn!("Failed to {what} while detaching: {err:?}");
+
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+        };
+
+        for i in 0..self.cores.len() {
+            check(
+                "clear hardware breakpoints",
+                self.core(i)
+                    .and_then(|mut core| core.clear_all_hw_breakpoints()),
+            );
+            check(
+                "clear hardware watchpoints",
+                self.core(i)
+                    .and_then(|mut core| core.clear_all_hw_watchpoints()),
+            );
+
+            if mode == DetachMode::Restore && self.resume_on_detach {
+                check(
+                    "resume the core",
+                    self.core(i).and_then(|mut core| {
+                        if core.core_halted()? {
+                            core.run()?;
+                        }
+                        Ok(())
+                    }),
+                );
+            }
+
+            // Call any necessary deconfiguration/shutdown hooks.
+            check(
+                "deconfigure the device",
+                self.core(i).and_then(|mut core| core.debug_core_stop()),
+            );
+
+            if mode == DetachMode::Restore {
+                let halted = self.core(i).and_then(|mut core| core.core_halted());
+
+                match (halted, &mut self.interface) {
+                    (Ok(halted), ArchitectureInterface::Arm(interface)) => check(
+                        "restore the debug registers",
+                        self.cores[i].restore_arm_debug_state(&mut **interface, halted),
+                    ),
+                    (Ok(_), ArchitectureInterface::Riscv(_)) => {}
+                    (Err(err), _) => check("restore the debug registers", Err(err)),
+                }
+            }
+        }
+
+        // Power down the debug ports, so the target can enter low power modes after we detach.
+        if let (ArchitectureInterface::Arm(interface), DebugSequence::Arm(sequence)) =
+            (&mut self.interface, &self.target.debug_sequence)
+        {
+            let mut dps = Vec::new();
+            for core in &self.target.cores {
+                if let Some(ap) = core.memory_ap() {
+                    let dp = ap.ap_address().dp;
+                    if !dps.contains(&dp) {
+                        dps.push(dp);
+                    }
+                }
+            }
+
+            for dp in dps {
+                check(
+                    "power down the debug port",
+                    sequence
+                        .debug_port_stop(&mut **interface, dp)
+                        .map_err(Error::from),
+                );
+            }
+        }
+
+        result
+    }
+
     /// Clears all hardware breakpoints on all cores
     pub fn clear_all_hw_breakpoints(&mut self) -> Result<(), Error> {
         { 0..self.cores.len() }.try_for_each(|n| {
@@ -597,6 +1293,51 @@ impl Session {
                 .and_then(|mut core| core.clear_all_hw_breakpoints())
         })
     }
+
+    /// Plan writing `data` to flash at the `base` address, without touching the target.
+    ///
+    /// The returned [FlashPlan] lists the sectors which would be erased and the pages which would
+    /// be programmed, and whether any of the data falls outside of flash, e.g. into RAM. This
+    /// allows showing a summary and asking for confirmation before the data is written.
+    pub fn plan_flash(&self, base: u64, data: &[u8]) -> Result<FlashPlan, FlashError> {
+        FlashPlan::new(&self.target, base, data)
+    }
+
+    /// Program a raw binary file to flash, starting at the `base` address.
+    ///
+    /// The data may span multiple flash sectors, which are erased and programmed as needed.
+    /// If the data does not fit into the flash region containing `base`, a
+    /// [FlashError::NoSuitableNvm](crate::flashing::FlashError::NoSuitableNvm) error is returned
+    /// before anything is written.
+    ///
+    /// For other file formats and more options, see [download_file_with_options](crate::flashing::download_file_with_options).
+    pub fn download_bin(
+        &mut self,
+        path: impl AsRef<Path>,
+        base: u32,
+    ) -> Result<(), FileDownloadError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)?;
+
+        // The loader would write data outside of flash to RAM, so the bounds are checked here
+        if !self.plan_flash(base as u64, &data)?.is_within_flash() {
+            return Err(FlashError::NoSuitableNvm {
+                start: base as u64,
+                end: base as u64 + data.len() as u64,
+                description_source: self.target.source().clone(),
+            }
+            .into());
+        }
+
+        download_file(
+            self,
+            path,
+            Format::Bin(BinOptions {
+                base_address: Some(base as u64),
+                skip: 0,
+            }),
+        )
+    }
 }
 
 // This test ensures that [Session] is fully [Send] + [Sync].
@@ -606,20 +1347,45 @@ static_assertions::assert_impl_all!(Session: Send);
 impl Drop for Session {
     #[tracing::instrument(name = "session_drop", skip(self))]
     fn drop(&mut self) {
-        if let Err(err) = { 0..self.cores.len() }.try_for_each(|i| {
-            self.core(i)
-                .and_then(|mut core| core.clear_all_hw_breakpoints())
-        }) {
-            tracing::warn!("Could not clear all hardware breakpoints: {:?}", err);
-        }
+        // Failures are logged by `release_target`
+        let _ = self.release_target();
+    }
+}
 
-        // Call any necessary deconfiguration/shutdown hooks.
-        if let Err(err) = { 0..self.cores.len() }
-            .try_for_each(|i| self.core(i).and_then(|mut core| core.debug_core_stop()))
-        {
-            tracing::warn!("Failed to deconfigure device during shutdown: {err:?}");
-        }
+/// Below this voltage the target is most likely not powered, or not connected to the probe
+const MIN_TARGET_VOLTAGE: f32 = 1.0;
+
+/// Log the target voltage if the probe can measure it, and warn if it is too low to attach
+fn log_target_voltage(probe: &mut Probe) {
+    match probe.get_target_voltage() {
+        Ok(Some(voltage)) if voltage < MIN_TARGET_VOLTAGE => tracing::warn!(
+            "The target voltage is {voltage:.2} V, check that the target is powered and connected to the probe"
+        ),
+        Ok(Some(voltage)) => tracing::info!("Target voltage: {voltage:.2} V"),
+        Ok(None) => {}
+        Err(e) => tracing::debug!("Failed to read the target voltage: {e}"),
+    }
+}
+
+/// Assert the reset pin to attach under reset, with a clear error for probes without reset control
+fn assert_reset_for_attach(probe: &mut Probe) -> Result<(), Error> {
+    let name = probe.get_name();
+    let no_reset_pin = || {
+        Error::Other(anyhow::anyhow!(
+            "Unable to attach under reset, the {name} probe can't control the reset pin of the target"
+        ))
+    };
+
+    if !probe.has_reset_pin() {
+        return Err(no_reset_pin());
     }
+
+    probe.target_reset_assert().map_err(|error| match error {
+        DebugProbeError::CommandNotSupportedByProbe(_) | DebugProbeError::NotImplemented(_) => {
+            no_reset_pin()
+        }
+        error => error.into(),
+    })
 }
 
 /// Determine the [Target] from a [TargetSelector].
@@ -631,6 +1397,7 @@ fn get_target_from_selector(
     target: TargetSelector,
     attach_method: AttachMethod,
     probe: Probe,
+    dp: Option<DpAddress>,
 ) -> Result<(Probe, Target), Error> {
     let mut probe = probe;
 
@@ -644,7 +1411,7 @@ fn get_target_from_selector(
             // Thus, we try just using a normal reset for target detection if we want to do so under reset.
             // This can of course fail, but target detection is a best effort, not a guarantee!
             if AttachMethod::UnderReset == attach_method {
-                probe.target_reset_assert()?;
+                assert_reset_for_attach(&mut probe)?;
             }
             probe.attach_to_unspecified()?;
 
@@ -655,8 +1422,7 @@ fn get_target_from_selector(
                             .initialize(DefaultArmSequence::create())
                             .map_err(|(_probe, err)| err)?;
 
-                        // TODO:
-                        let dp = DpAddress::Default;
+                        let dp = dp.unwrap_or(DpAddress::Default);
 
                         let found_arm_chip = interface
                             .read_chip_info_from_rom_table(dp)
@@ -665,7 +1431,7 @@ fn get_target_from_selector(
                                 None
                             });
 
-                        found_chip = found_arm_chip.map(ChipInfo::from);
+                        found_chip = found_arm_chip.map(ChipDetectionInfo::from);
 
                         probe = interface.close();
                     }
@@ -0,0 +1,58 @@
+use probe_rs::{
+    flashing::{download_file, Format},
+    Lister, Permissions,
+};
+
+use std::thread;
+
+/// Flash two targets in parallel, each from its own thread and session.
+///
+/// This needs two probes with the same kind of target attached, and is configured with
+/// the following environment variables:
+///
+/// - `PROBE_RS_TEST_SERIALS`: serial numbers of the two probes, separated by a comma
+/// - `PROBE_RS_TEST_CHIP`: name of the target chip
+/// - `PROBE_RS_TEST_ELF`: path of the ELF file to flash
+///
+/// Run it with `cargo test --test concurrent_sessions -- --ignored`.
+#[test]
+#[ignore = "requires two probes with attached targets"]
+fn flash_two_probes_concurrently() {
+    let serials = std::env::var("PROBE_RS_TEST_SERIALS").expect("PROBE_RS_TEST_SERIALS not set");
+    let chip = std::env::var("PROBE_RS_TEST_CHIP").expect("PROBE_RS_TEST_CHIP not set");
+    let elf = std::env::var("PROBE_RS_TEST_ELF").expect("PROBE_RS_TEST_ELF not set");
+
+    let serials = serials.split(',').map(str::to_string).collect::<Vec<_>>();
+    assert_eq!(
+        serials.len(),
+        2,
+        "PROBE_RS_TEST_SERIALS needs two serial numbers"
+    );
+
+    let threads = serials
+        .into_iter()
+        .map(|serial| {
+            let chip = chip.clone();
+            let elf = elf.clone();
+
+            thread::spawn(move || {
+                let lister = Lister::new();
+
+                let probe = lister
+                    .open_by_serial(&serial)
+                    .unwrap_or_else(|e| panic!("Failed to open probe {serial}: {e}"));
+
+                let mut session = probe
+                    .attach(chip, Permissions::default())
+                    .unwrap_or_else(|e| panic!("Failed to attach with probe {serial}: {e}"));
+
+                download_file(&mut session, &elf, Format::Elf)
+                    .unwrap_or_else(|e| panic!("Failed to flash with probe {serial}: {e}"));
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for thread in threads {
+        thread.join().expect("Flashing thread panicked");
+    }
+}
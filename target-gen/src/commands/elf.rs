@@ -85,6 +85,8 @@ pub fn cmd_elf(
                         psel: 0,
                         debug_base: None,
                         cti_base: None,
+                        fpb_base: None,
+                        dwt_base: None,
                     }),
                 }],
                 part: None,
@@ -107,6 +109,7 @@ pub fn cmd_elf(
                 rtt_scan_ranges: None,
                 scan_chain: None,
                 default_binary_format: None,
+                reset_cause: None,
             }],
             flash_algorithms: vec![algorithm],
             source: BuiltIn,
@@ -163,6 +163,7 @@ where
             rtt_scan_ranges: None,
             scan_chain: None, // TODO, parse from sdf
             default_binary_format: None,
+            reset_cause: None,
         });
     }
 
@@ -184,6 +185,8 @@ fn create_core(processor: &Processor) -> Result<ProbeCore> {
                 psel: 0,
                 debug_base: None,
                 cti_base: None,
+                fpb_base: None,
+                dwt_base: None,
             }),
             Architecture::Riscv => CoreAccessOptions::Riscv(RiscvCoreAccessOptions {}),
         },
@@ -556,6 +559,7 @@ pub(crate) fn get_mem_map(device: &Device, cores: &[probe_rs_target::Core]) -> V
                     name: Some(region.name.clone()),
                     range: region.memory_start..region.memory_end,
                     cores,
+                    access_width: None,
                     }));
                 }
             },